@@ -0,0 +1 @@
+ALTER TABLE mods ADD COLUMN monetization_status_updated timestamptz NULL;
@@ -0,0 +1 @@
+ALTER TABLE payouts ADD COLUMN withheld numeric(40, 20) NULL;
@@ -0,0 +1 @@
+ALTER TABLE mods ADD COLUMN payout_hold boolean NOT NULL DEFAULT FALSE;
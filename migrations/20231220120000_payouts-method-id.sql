@@ -0,0 +1 @@
+ALTER TABLE payouts ADD COLUMN method_id text NULL;
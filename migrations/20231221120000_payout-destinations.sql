@@ -0,0 +1,7 @@
+CREATE TABLE payout_destinations (
+    user_id bigint REFERENCES users NOT NULL,
+    method text NOT NULL,
+    method_id text NOT NULL,
+    percentage numeric(5, 2) NOT NULL,
+    PRIMARY KEY (user_id, method_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE payouts ADD COLUMN retry_of bigint NULL REFERENCES payouts;
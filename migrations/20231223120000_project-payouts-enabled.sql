@@ -0,0 +1 @@
+ALTER TABLE mods ADD COLUMN payouts_enabled boolean NOT NULL DEFAULT TRUE;
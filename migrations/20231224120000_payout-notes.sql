@@ -0,0 +1 @@
+ALTER TABLE payouts ADD COLUMN note text NULL;
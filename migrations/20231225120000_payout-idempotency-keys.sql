@@ -0,0 +1,4 @@
+ALTER TABLE payouts ADD COLUMN idempotency_key text NULL;
+
+CREATE UNIQUE INDEX payouts_user_idempotency_key ON payouts (user_id, idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
@@ -0,0 +1,15 @@
+-- Views and downloads payouts are split by, for deployments reading them from Postgres rather
+-- than ClickHouse. user_id is 0 for anonymous events, as in ClickHouse.
+CREATE TABLE analytics_views (
+    recorded timestamptz NOT NULL,
+    project_id bigint NOT NULL,
+    user_id bigint NOT NULL
+);
+CREATE INDEX analytics_views_recorded ON analytics_views (recorded);
+
+CREATE TABLE analytics_downloads (
+    recorded timestamptz NOT NULL,
+    project_id bigint NOT NULL,
+    user_id bigint NOT NULL
+);
+CREATE INDEX analytics_downloads_recorded ON analytics_downloads (recorded);
@@ -0,0 +1,11 @@
+CREATE TABLE payout_audit_log (
+    id bigserial PRIMARY KEY,
+    created timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    actor varchar(255) NOT NULL,
+    action varchar(64) NOT NULL,
+    target varchar(255) NULL,
+    details jsonb NOT NULL DEFAULT '{}'::jsonb
+);
+
+CREATE INDEX payout_audit_log_created ON payout_audit_log (created);
+CREATE INDEX payout_audit_log_target ON payout_audit_log (target, created);
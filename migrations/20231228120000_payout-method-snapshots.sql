@@ -0,0 +1,7 @@
+CREATE TABLE payout_method_snapshots (
+    id bigserial PRIMARY KEY,
+    created timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    methods jsonb NOT NULL
+);
+
+CREATE INDEX payout_method_snapshots_created ON payout_method_snapshots (created);
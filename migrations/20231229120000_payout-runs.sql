@@ -0,0 +1,14 @@
+CREATE TABLE payout_runs (
+    id bigserial PRIMARY KEY,
+    created timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    day timestamptz NOT NULL,
+    budget numeric(96, 48) NOT NULL,
+    distributed numeric(96, 48) NOT NULL,
+    projects integer NOT NULL,
+    users integer NOT NULL,
+    duration_ms bigint NOT NULL,
+    outcome varchar(64) NOT NULL,
+    forced boolean NOT NULL
+);
+
+CREATE INDEX payout_runs_created ON payout_runs (created);
@@ -0,0 +1 @@
+CREATE INDEX payouts_values_user_created ON payouts_values (user_id, created DESC, id DESC);
@@ -0,0 +1,4 @@
+CREATE TABLE payout_preferred_methods (
+    user_id bigint PRIMARY KEY REFERENCES users,
+    method_id text NOT NULL
+);
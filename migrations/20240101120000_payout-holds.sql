@@ -0,0 +1,6 @@
+CREATE TABLE payout_holds (
+    user_id bigint PRIMARY KEY REFERENCES users,
+    reason text NOT NULL,
+    created timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires timestamptz NOT NULL
+);
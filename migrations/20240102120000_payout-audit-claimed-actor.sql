@@ -0,0 +1 @@
+ALTER TABLE payout_audit_log ADD COLUMN claimed_actor varchar(255) NULL;
@@ -11,6 +11,12 @@ pub struct ReturnIntervals {
     pub total: u64,
 }
 
+#[derive(clickhouse::Row, Serialize, Deserialize, Clone, Debug)]
+pub struct ReturnDailyTotal {
+    pub time: u32,
+    pub total: u64,
+}
+
 #[derive(clickhouse::Row, Serialize, Deserialize, Clone, Debug)]
 pub struct ReturnCountry {
     pub country: String,
@@ -162,3 +168,43 @@ pub async fn fetch_countries_views(
 
     Ok(query.fetch_all().await?)
 }
+
+// Fetches the payout-eligible views and downloads across all projects per day, in the same way
+// they are counted by payouts
+pub async fn fetch_daily_payout_totals(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    client: Arc<clickhouse::Client>,
+) -> Result<(Vec<ReturnDailyTotal>, Vec<ReturnDailyTotal>), ApiError> {
+    let views = client
+        .query(
+            "
+            SELECT
+                toUnixTimestamp(toStartOfDay(recorded)) AS time,
+                count(1) AS total
+            FROM views
+            WHERE recorded BETWEEN ? AND ? AND project_id != 0
+            GROUP BY time
+            ",
+        )
+        .bind(start_date.timestamp())
+        .bind(end_date.timestamp())
+        .fetch_all();
+
+    let downloads = client
+        .query(
+            "
+            SELECT
+                toUnixTimestamp(toStartOfDay(recorded)) AS time,
+                count(1) AS total
+            FROM downloads
+            WHERE recorded BETWEEN ? AND ? AND user_id != 0
+            GROUP BY time
+            ",
+        )
+        .bind(start_date.timestamp())
+        .bind(end_date.timestamp())
+        .fetch_all();
+
+    Ok(futures::future::try_join(views, downloads).await?)
+}
@@ -5,6 +5,15 @@ mod fetch;
 
 pub use fetch::*;
 
+use crate::queue::payouts::PayoutAnalyticsSource;
+
+/// Whether ClickHouse is used. Deployments whose payouts read analytics from Postgres, see
+/// [`PayoutAnalyticsSource`], can go without it by leaving `CLICKHOUSE_URL` unset.
+pub fn clickhouse_enabled() -> bool {
+    dotenvy::var("CLICKHOUSE_URL").is_ok()
+        || PayoutAnalyticsSource::from_env() != PayoutAnalyticsSource::Postgres
+}
+
 pub async fn init_client() -> clickhouse::error::Result<clickhouse::Client> {
     init_client_with_database(&dotenvy::var("CLICKHOUSE_DATABASE").unwrap()).await
 }
@@ -13,7 +13,13 @@ pub mod oauth_client_item;
 pub mod oauth_token_item;
 pub mod organization_item;
 pub mod pat_item;
+pub mod payout_audit_item;
+pub mod payout_history_item;
+pub mod payout_hold_item;
 pub mod payout_item;
+pub mod payout_method_snapshot_item;
+pub mod payout_preference_item;
+pub mod payout_run_item;
 pub mod project_item;
 pub mod report_item;
 pub mod session_item;
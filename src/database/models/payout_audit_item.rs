@@ -0,0 +1,77 @@
+use crate::models::payouts::{PayoutAuditAction, PayoutAuditEntry};
+use chrono::{DateTime, Utc};
+
+use super::DatabaseError;
+
+/// The audit log of administrative payout actions
+pub struct PayoutAuditLog;
+
+impl PayoutAuditLog {
+    /// Records an administrative payout action in the audit log
+    pub async fn insert(
+        entry: &PayoutAuditEntry,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO payout_audit_log (created, actor, claimed_actor, action, target, details)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+            entry.created,
+            entry.actor,
+            entry.claimed_actor,
+            entry.action.as_str(),
+            entry.target,
+            entry.details,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The audit log entries from `start` to `end`, newest first, optionally only those on
+    /// `target`, by `actor` or claimed to be by `claimed_actor`
+    pub async fn get_entries(
+        target: Option<&str>,
+        actor: Option<&str>,
+        claimed_actor: Option<&str>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: i64,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<PayoutAuditEntry>, DatabaseError> {
+        let results = sqlx::query!(
+            "
+            SELECT created, actor, claimed_actor, action, target, details
+            FROM payout_audit_log
+            WHERE ($1::varchar IS NULL OR target = $1)
+                AND ($2::varchar IS NULL OR actor = $2)
+                AND ($3::varchar IS NULL OR claimed_actor = $3)
+                AND created BETWEEN $4 AND $5
+            ORDER BY created DESC, id DESC
+            LIMIT $6
+            ",
+            target,
+            actor,
+            claimed_actor,
+            start,
+            end,
+            limit,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|r| PayoutAuditEntry {
+                created: r.created,
+                actor: r.actor,
+                claimed_actor: r.claimed_actor,
+                action: PayoutAuditAction::from_string(&r.action),
+                target: r.target,
+                details: r.details,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::models::payouts::{PayoutHistoryEntry, PayoutHistoryPage};
+use chrono::{DateTime, Utc};
+
+use super::{DatabaseError, ProjectId, UserId};
+
+/// The amounts credited to users by payout runs
+pub struct PayoutHistory;
+
+impl PayoutHistory {
+    /// A page of the amounts credited to the user from `start` to `end`, optionally only those
+    /// earned by `project_id`, newest first
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_page<'a, E>(
+        user_id: UserId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        project_id: Option<ProjectId>,
+        offset: i64,
+        limit: i64,
+        exec: E,
+    ) -> Result<PayoutHistoryPage, DatabaseError>
+    where
+        E: sqlx::Executor<'a, Database = sqlx::Postgres> + Copy,
+    {
+        let total = sqlx::query!(
+            r#"
+            SELECT COUNT(*) "count!"
+            FROM payouts_values
+            WHERE user_id = $1 AND created BETWEEN $2 AND $3
+                AND ($4::bigint IS NULL OR mod_id = $4)
+            "#,
+            user_id.0,
+            start,
+            end,
+            project_id.map(|x| x.0),
+        )
+        .fetch_one(exec)
+        .await?
+        .count;
+
+        let entries = sqlx::query!(
+            "
+            SELECT mod_id, amount, created
+            FROM payouts_values
+            WHERE user_id = $1 AND created BETWEEN $2 AND $3
+                AND ($4::bigint IS NULL OR mod_id = $4)
+            ORDER BY created DESC, id DESC
+            OFFSET $5
+            LIMIT $6
+            ",
+            user_id.0,
+            start,
+            end,
+            project_id.map(|x| x.0),
+            offset,
+            limit,
+        )
+        .fetch_all(exec)
+        .await?
+        .into_iter()
+        .map(|r| PayoutHistoryEntry {
+            project_id: r.mod_id.map(|x| ProjectId(x).into()),
+            amount: r.amount,
+            created: r.created,
+        })
+        .collect();
+
+        Ok(PayoutHistoryPage { total, entries })
+    }
+}
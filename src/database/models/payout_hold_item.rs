@@ -0,0 +1,106 @@
+use crate::models::payouts::UserPayoutHold;
+use chrono::{DateTime, Utc};
+
+use super::{DatabaseError, UserId};
+
+/// Holds placed on users' payouts
+pub struct PayoutHold;
+
+impl PayoutHold {
+    /// The hold on the user's payouts, if they have one which has not expired
+    pub async fn get_active(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<UserPayoutHold>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT reason, created, expires
+            FROM payout_holds
+            WHERE user_id = $1 AND expires > NOW()
+            ",
+            user_id.0
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(result.map(|r| UserPayoutHold {
+            reason: r.reason,
+            created: r.created,
+            expires: r.expires,
+        }))
+    }
+
+    /// Places a hold on the user's payouts until `expires`, replacing any they already have
+    pub async fn set(
+        user_id: UserId,
+        reason: &str,
+        expires: DateTime<Utc>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<UserPayoutHold, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            INSERT INTO payout_holds (user_id, reason, expires)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE
+            SET reason = EXCLUDED.reason, created = CURRENT_TIMESTAMP, expires = EXCLUDED.expires
+            RETURNING reason, created, expires
+            ",
+            user_id.0,
+            reason,
+            expires,
+        )
+        .fetch_one(exec)
+        .await?;
+
+        Ok(UserPayoutHold {
+            reason: result.reason,
+            created: result.created,
+            expires: result.expires,
+        })
+    }
+
+    /// Extends the user's hold to `expires`. Holds which already expired are not renewed.
+    pub async fn renew(
+        user_id: UserId,
+        expires: DateTime<Utc>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<UserPayoutHold>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE payout_holds
+            SET expires = $2
+            WHERE user_id = $1 AND expires > NOW()
+            RETURNING reason, created, expires
+            ",
+            user_id.0,
+            expires,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(result.map(|r| UserPayoutHold {
+            reason: r.reason,
+            created: r.created,
+            expires: r.expires,
+        }))
+    }
+
+    /// Lifts the hold on the user's payouts, returning whether an active one was lifted
+    pub async fn lift(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            DELETE FROM payout_holds
+            WHERE user_id = $1
+            RETURNING expires > NOW() active
+            ",
+            user_id.0
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(result.and_then(|r| r.active).unwrap_or(false))
+    }
+}
@@ -14,9 +14,17 @@ pub struct Payout {
     pub amount: Decimal,
 
     pub fee: Option<Decimal>,
+    /// Tax withheld from the payout, on top of the fee
+    pub withheld: Option<Decimal>,
     pub method: Option<PayoutMethodType>,
+    /// The `PayoutMethod` withdrawn with, ex: a Tremendous product id
+    pub method_id: Option<String>,
     pub method_address: Option<String>,
     pub platform_id: Option<String>,
+    /// The user's note for the payout, see [`crate::queue::payouts::sanitize_payout_note`]
+    pub note: Option<String>,
+    /// The key the withdrawal was made with, unique per user so it is never made twice
+    pub idempotency_key: Option<String>,
 }
 
 impl Payout {
@@ -27,20 +35,25 @@ impl Payout {
         sqlx::query!(
             "
             INSERT INTO payouts (
-                id, amount, fee, user_id, status, method, method_address, platform_id
+                id, amount, fee, withheld, user_id, status, method, method_id, method_address,
+                platform_id, note, idempotency_key
             )
             VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12
             )
             ",
             self.id.0,
             self.amount,
             self.fee,
+            self.withheld,
             self.user_id.0,
             self.status.as_str(),
             self.method.map(|x| x.as_str()),
+            self.method_id,
             self.method_address,
             self.platform_id,
+            self.note,
+            self.idempotency_key,
         )
         .execute(&mut **transaction)
         .await?;
@@ -68,7 +81,8 @@ impl Payout {
 
         let results = sqlx::query!(
             "
-            SELECT id, user_id, created, amount, status, method, method_address, platform_id, fee
+            SELECT id, user_id, created, amount, status, method, method_id, method_address,
+                platform_id, fee, withheld, note, idempotency_key
             FROM payouts
             WHERE id = ANY($1)
             ",
@@ -83,9 +97,13 @@ impl Payout {
                 status: PayoutStatus::from_string(&r.status),
                 amount: r.amount,
                 method: r.method.map(|x| PayoutMethodType::from_string(&x)),
+                method_id: r.method_id,
                 method_address: r.method_address,
                 platform_id: r.platform_id,
                 fee: r.fee,
+                withheld: r.withheld,
+                note: r.note,
+                idempotency_key: r.idempotency_key,
             }))
         })
         .try_collect::<Vec<Payout>>()
@@ -114,4 +132,129 @@ impl Payout {
             .map(|r| PayoutId(r.id))
             .collect::<Vec<_>>())
     }
+
+    /// The user's withdrawals which are still on their way, or which failed and have not been
+    /// retried yet
+    pub async fn get_unsettled_for_user(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<PayoutId>, DatabaseError> {
+        let results = sqlx::query!(
+            "
+            SELECT id
+            FROM payouts p
+            WHERE user_id = $1 AND (
+                status = ANY($2)
+                OR (status = $3 AND NOT EXISTS (SELECT 1 FROM payouts r WHERE r.retry_of = p.id))
+            )
+            ORDER BY created DESC
+            ",
+            user_id.0,
+            &PayoutStatus::PENDING
+                .iter()
+                .map(|x| x.as_str().to_string())
+                .collect::<Vec<_>>(),
+            PayoutStatus::Failed.as_str(),
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(results.into_iter().map(|r| PayoutId(r.id)).collect())
+    }
+
+    /// The withdrawal a user made with `idempotency_key`, if any
+    pub async fn get_id_by_idempotency_key(
+        user_id: UserId,
+        idempotency_key: &str,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<PayoutId>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT id
+            FROM payouts
+            WHERE user_id = $1 AND idempotency_key = $2
+            ",
+            user_id.0,
+            idempotency_key,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(result.map(|r| PayoutId(r.id)))
+    }
+
+    /// The withdrawal a payment provider knows as `platform_id`, if any
+    pub async fn get_id_by_platform_id(
+        platform_id: &str,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<PayoutId>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT id
+            FROM payouts
+            WHERE platform_id = $1
+            ",
+            platform_id,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(result.map(|r| PayoutId(r.id)))
+    }
+
+    /// Derives a user's balance from the ledger: all credited payout values, minus every
+    /// withdrawal (including its fee and withheld tax) which has not been cancelled or failed.
+    pub async fn get_ledger_balance(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Decimal, DatabaseError> {
+        let result = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE((SELECT SUM(amount) FROM payouts_values WHERE user_id = $1), 0)
+                - COALESCE((
+                    SELECT SUM(amount + COALESCE(fee, 0) + COALESCE(withheld, 0))
+                    FROM payouts
+                    WHERE user_id = $1 AND NOT (status = ANY($2))
+                ), 0) "balance!"
+            "#,
+            user_id.0,
+            &[
+                PayoutStatus::Cancelled.as_str().to_string(),
+                PayoutStatus::Failed.as_str().to_string(),
+            ][..]
+        )
+        .fetch_one(exec)
+        .await?;
+
+        Ok(result.balance)
+    }
+
+    /// What a user's withdrawals which have not completed yet took from their balance, and
+    /// everything ever credited to them, as `(pending, lifetime_earnings)`
+    pub async fn get_pending_and_lifetime(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(Decimal, Decimal), DatabaseError> {
+        let result = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE((
+                    SELECT SUM(amount + COALESCE(fee, 0) + COALESCE(withheld, 0))
+                    FROM payouts
+                    WHERE user_id = $1 AND status = ANY($2)
+                ), 0) "pending!",
+                COALESCE((SELECT SUM(amount) FROM payouts_values WHERE user_id = $1), 0) "lifetime_earnings!"
+            "#,
+            user_id.0,
+            &PayoutStatus::PENDING
+                .iter()
+                .map(|x| x.as_str().to_string())
+                .collect::<Vec<_>>(),
+        )
+        .fetch_one(exec)
+        .await?;
+
+        Ok((result.pending, result.lifetime_earnings))
+    }
 }
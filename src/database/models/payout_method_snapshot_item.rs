@@ -0,0 +1,75 @@
+use crate::models::payouts::PayoutMethodSnapshot;
+use chrono::{DateTime, Utc};
+
+use super::DatabaseError;
+
+/// The payout methods as they were listed at points in time
+pub struct PayoutMethodSnapshots;
+
+impl PayoutMethodSnapshots {
+    /// Records the payout methods as they are currently listed
+    pub async fn insert(
+        snapshot: &PayoutMethodSnapshot,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO payout_method_snapshots (created, methods)
+            VALUES ($1, $2)
+            ",
+            snapshot.created,
+            serde_json::to_value(&snapshot.methods)?,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// When the payout methods were last snapshotted, if ever
+    pub async fn get_last_time(
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<DateTime<Utc>>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT MAX(created) last FROM payout_method_snapshots
+            ",
+        )
+        .fetch_one(exec)
+        .await?;
+
+        Ok(result.last)
+    }
+
+    /// The payout method snapshot taken closest to `at`, before or after it
+    pub async fn get_near(
+        at: DateTime<Utc>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<PayoutMethodSnapshot>, DatabaseError> {
+        let result = sqlx::query!(
+            r#"
+            SELECT created "created!", methods "methods!"
+            FROM (
+                (SELECT id, created, methods FROM payout_method_snapshots
+                WHERE created <= $1 ORDER BY created DESC, id DESC LIMIT 1)
+                UNION ALL
+                (SELECT id, created, methods FROM payout_method_snapshots
+                WHERE created > $1 ORDER BY created, id LIMIT 1)
+            ) nearest
+            ORDER BY ABS(EXTRACT(EPOCH FROM created - $1)), created DESC
+            LIMIT 1
+            "#,
+            at,
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(match result {
+            Some(r) => Some(PayoutMethodSnapshot {
+                created: r.created,
+                methods: serde_json::from_value(r.methods)?,
+            }),
+            None => None,
+        })
+    }
+}
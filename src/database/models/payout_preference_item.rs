@@ -0,0 +1,131 @@
+use crate::models::payouts::{PayoutDestination, PayoutMethodType};
+
+use super::{DatabaseError, UserId};
+
+/// How users prefer to withdraw their payouts
+pub struct PayoutPreferences;
+
+impl PayoutPreferences {
+    /// The id of the payout method the user prefers, if they have chosen one
+    pub async fn get_preferred_method(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Option<String>, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            SELECT method_id
+            FROM payout_preferred_methods
+            WHERE user_id = $1
+            ",
+            user_id.0
+        )
+        .fetch_optional(exec)
+        .await?;
+
+        Ok(result.map(|r| r.method_id))
+    }
+
+    /// Sets the payout method the user prefers, or clears it with `None`
+    pub async fn set_preferred_method(
+        user_id: UserId,
+        method_id: Option<&str>,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        match method_id {
+            Some(method_id) => {
+                sqlx::query!(
+                    "
+                    INSERT INTO payout_preferred_methods (user_id, method_id)
+                    VALUES ($1, $2)
+                    ON CONFLICT (user_id) DO UPDATE SET method_id = EXCLUDED.method_id
+                    ",
+                    user_id.0,
+                    method_id,
+                )
+                .execute(exec)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    "
+                    DELETE FROM payout_preferred_methods
+                    WHERE user_id = $1
+                    ",
+                    user_id.0
+                )
+                .execute(exec)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The destinations a user's split withdrawals are sent to, largest share first
+    pub async fn get_destinations(
+        user_id: UserId,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<PayoutDestination>, DatabaseError> {
+        let results = sqlx::query!(
+            "
+            SELECT method, method_id, percentage
+            FROM payout_destinations
+            WHERE user_id = $1
+            ORDER BY percentage DESC, method_id
+            ",
+            user_id.0
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|r| PayoutDestination {
+                method: PayoutMethodType::from_string(&r.method),
+                method_id: r.method_id,
+                percentage: r.percentage,
+            })
+            .collect())
+    }
+
+    /// Replaces all of a user's split withdrawal destinations
+    pub async fn set_destinations(
+        user_id: UserId,
+        destinations: &[PayoutDestination],
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            DELETE FROM payout_destinations
+            WHERE user_id = $1
+            ",
+            user_id.0
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query!(
+            "
+            INSERT INTO payout_destinations (user_id, method, method_id, percentage)
+            SELECT $1, * FROM UNNEST($2::text[], $3::text[], $4::numeric[])
+            ",
+            user_id.0,
+            &destinations
+                .iter()
+                .map(|x| x.method.as_str().to_string())
+                .collect::<Vec<_>>(),
+            &destinations
+                .iter()
+                .map(|x| x.method_id.clone())
+                .collect::<Vec<_>>(),
+            &destinations
+                .iter()
+                .map(|x| x.percentage)
+                .collect::<Vec<_>>(),
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::models::payouts::PayoutRun;
+
+use super::DatabaseError;
+
+/// The summaries of past payout runs
+pub struct PayoutRunHistory;
+
+impl PayoutRunHistory {
+    /// Records the summary of a payout run
+    pub async fn insert(
+        run: &PayoutRun,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            INSERT INTO payout_runs (
+                created, day, budget, distributed, projects, users, duration_ms, outcome, forced
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ",
+            run.created,
+            run.day,
+            run.budget,
+            run.distributed,
+            run.projects,
+            run.users,
+            run.duration_ms,
+            run.outcome,
+            run.forced,
+        )
+        .execute(exec)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The most recent `limit` payout runs, newest first
+    pub async fn get_recent(
+        limit: i64,
+        exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    ) -> Result<Vec<PayoutRun>, DatabaseError> {
+        let results = sqlx::query!(
+            "
+            SELECT created, day, budget, distributed, projects, users, duration_ms, outcome, forced
+            FROM payout_runs
+            ORDER BY created DESC, id DESC
+            LIMIT $1
+            ",
+            limit,
+        )
+        .fetch_all(exec)
+        .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|r| PayoutRun {
+                created: r.created,
+                day: r.day,
+                budget: r.budget,
+                distributed: r.distributed,
+                projects: r.projects,
+                users: r.users,
+                duration_ms: r.duration_ms,
+                outcome: r.outcome,
+                forced: r.forced,
+            })
+            .collect())
+    }
+}
@@ -304,6 +304,50 @@ impl Project {
         Ok(())
     }
 
+    /// Holds back or releases the project's payouts, ex: while it is under review by moderation.
+    /// Held projects are left out of payouts even if monetized. Returns whether the project exists.
+    pub async fn set_payout_hold(
+        id: ProjectId,
+        hold: bool,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE mods
+            SET payout_hold = $1
+            WHERE id = $2
+            ",
+            hold,
+            id as ProjectId
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Opts the project in or out of payouts at its team's request. Opted out projects are left
+    /// out of payouts even if monetized, and their share goes to other projects.
+    pub async fn set_payouts_enabled(
+        id: ProjectId,
+        enabled: bool,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "
+            UPDATE mods
+            SET payouts_enabled = $1
+            WHERE id = $2
+            ",
+            enabled,
+            id as ProjectId
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn remove(
         id: ProjectId,
         transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
@@ -395,6 +395,28 @@ impl User {
         Ok(codes)
     }
 
+    /// Takes `amount` out of the user's balance if it is at least that much, returning whether
+    /// it was. The user's row stays locked until the transaction ends.
+    pub async fn withdraw_balance(
+        id: UserId,
+        amount: Decimal,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<bool, DatabaseError> {
+        let result = sqlx::query!(
+            "
+            UPDATE users
+            SET balance = balance - $1
+            WHERE id = $2 AND balance >= $1
+            ",
+            amount,
+            id as UserId,
+        )
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn clear_caches(
         user_ids: &[(UserId, Option<String>)],
         redis: &RedisPool,
@@ -551,6 +573,16 @@ impl User {
             .execute(&mut **transaction)
             .await?;
 
+            sqlx::query!(
+                "
+                DELETE FROM payout_destinations
+                WHERE user_id = $1
+                ",
+                id as UserId,
+            )
+            .execute(&mut **transaction)
+            .await?;
+
             sqlx::query!(
                 r#"
                 UPDATE threads_messages
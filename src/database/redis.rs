@@ -140,6 +140,31 @@ impl RedisConnection {
         Ok(redis_execute(&mut cmd, &mut self.connection).await?)
     }
 
+    /// Increments a counter, returning its new value. The counter expires `expiry` seconds after
+    /// its first increment, so it counts events within a fixed window.
+    pub async fn increment(
+        &mut self,
+        namespace: &str,
+        id: &str,
+        expiry: i64,
+    ) -> Result<i64, DatabaseError> {
+        let key = format!("{}_{}:{}", self.meta_namespace, namespace, id);
+        let (count,): (i64,) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(&key)
+            .arg(0)
+            .arg("EX")
+            .arg(expiry)
+            .arg("NX")
+            .ignore()
+            .cmd("INCR")
+            .arg(&key)
+            .query_async(&mut self.connection)
+            .await?;
+        Ok(count)
+    }
+
     pub async fn delete<T1>(&mut self, namespace: &str, id: T1) -> Result<(), DatabaseError>
     where
         T1: Display,
@@ -15,7 +15,10 @@ use clickhouse_crate::Client;
 use util::cors::default_cors;
 
 use crate::{
-    queue::payouts::process_payout,
+    queue::payouts::{
+        catch_up_payouts, payout_config_errors, payout_method_snapshot_interval,
+        payout_run_interval, reconcile_failed_payouts, snapshot_payout_methods,
+    },
     search::indexing::index_projects,
     util::env::{parse_strings_from_var, parse_var},
 };
@@ -209,14 +212,14 @@ pub fn app_setup(
         let pool_ref = pool.clone();
         let redis_ref = redis_pool.clone();
         let client_ref = clickhouse.clone();
-        scheduler.run(std::time::Duration::from_secs(60 * 60 * 6), move || {
+        scheduler.run(payout_run_interval(), move || {
             let pool_ref = pool_ref.clone();
             let redis_ref = redis_ref.clone();
             let client_ref = client_ref.clone();
 
             async move {
                 info!("Started running payouts");
-                let result = process_payout(&pool_ref, &redis_ref, &client_ref).await;
+                let result = catch_up_payouts(&pool_ref, &redis_ref, &client_ref, None).await;
                 if let Err(e) = result {
                     warn!("Payouts run failed: {:?}", e);
                 }
@@ -225,11 +228,59 @@ pub fn app_setup(
         });
     }
 
+    // Payout methods are shared between instances through Redis unless disabled
+    let payouts_queue = if parse_var("PAYOUTS_SHARED_METHODS_CACHE").unwrap_or(true) {
+        PayoutsQueue::new().with_redis(redis_pool.clone())
+    } else {
+        PayoutsQueue::new()
+    };
+    let payouts_queue = web::Data::new(payouts_queue);
+    payouts_queue.log_provider_modes();
+    payouts_queue.log_config_summary();
+
+    {
+        let pool_ref = pool.clone();
+        let redis_ref = redis_pool.clone();
+        let payouts_queue_ref = payouts_queue.clone();
+        scheduler.run(std::time::Duration::from_secs(60 * 60), move || {
+            let pool_ref = pool_ref.clone();
+            let redis_ref = redis_ref.clone();
+            let payouts_queue_ref = payouts_queue_ref.clone();
+
+            async move {
+                info!("Reconciling failed payouts");
+                let result =
+                    reconcile_failed_payouts(&pool_ref, &redis_ref, &payouts_queue_ref).await;
+                if let Err(e) = result {
+                    warn!("Reconciling failed payouts failed: {:?}", e);
+                }
+                info!("Done reconciling failed payouts");
+            }
+        });
+    }
+
+    {
+        let pool_ref = pool.clone();
+        let payouts_queue_ref = payouts_queue.clone();
+        scheduler.run(payout_method_snapshot_interval(), move || {
+            let pool_ref = pool_ref.clone();
+            let payouts_queue_ref = payouts_queue_ref.clone();
+
+            async move {
+                info!("Snapshotting payout methods");
+                let result = snapshot_payout_methods(&pool_ref, &payouts_queue_ref).await;
+                if let Err(e) = result {
+                    warn!("Snapshotting payout methods failed: {:?}", e);
+                }
+                info!("Done snapshotting payout methods");
+            }
+        });
+    }
+
     let ip_salt = Pepper {
         pepper: models::ids::Base62Id(models::ids::random_base62(11)).to_string(),
     };
 
-    let payouts_queue = web::Data::new(PayoutsQueue::new());
     let active_sockets = web::Data::new(RwLock::new(ActiveSockets::default()));
 
     LabrinthConfig {
@@ -364,14 +415,10 @@ pub fn check_env_vars() -> bool {
     failed |= check_var::<String>("GOOGLE_CLIENT_SECRET");
     failed |= check_var::<String>("STEAM_API_KEY");
 
-    failed |= check_var::<String>("TREMENDOUS_API_URL");
-    failed |= check_var::<String>("TREMENDOUS_API_KEY");
-    failed |= check_var::<String>("TREMENDOUS_PRIVATE_KEY");
-
-    failed |= check_var::<String>("PAYPAL_API_URL");
-    failed |= check_var::<String>("PAYPAL_WEBHOOK_ID");
-    failed |= check_var::<String>("PAYPAL_CLIENT_ID");
-    failed |= check_var::<String>("PAYPAL_CLIENT_SECRET");
+    for error in payout_config_errors() {
+        warn!("{error}");
+        failed |= true;
+    }
 
     failed |= check_var::<String>("TURNSTILE_SECRET");
 
@@ -392,10 +439,12 @@ pub fn check_env_vars() -> bool {
         failed |= true;
     }
 
-    failed |= check_var::<String>("CLICKHOUSE_URL");
-    failed |= check_var::<String>("CLICKHOUSE_USER");
-    failed |= check_var::<String>("CLICKHOUSE_PASSWORD");
-    failed |= check_var::<String>("CLICKHOUSE_DATABASE");
+    if clickhouse::clickhouse_enabled() {
+        failed |= check_var::<String>("CLICKHOUSE_URL");
+        failed |= check_var::<String>("CLICKHOUSE_USER");
+        failed |= check_var::<String>("CLICKHOUSE_PASSWORD");
+        failed |= check_var::<String>("CLICKHOUSE_DATABASE");
+    }
 
     failed |= check_var::<String>("MAXMIND_LICENSE_KEY");
 
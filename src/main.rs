@@ -8,7 +8,7 @@ use labrinth::ratelimit::memory::{MemoryStore, MemoryStoreActor};
 use labrinth::ratelimit::middleware::RateLimiter;
 use labrinth::util::env::parse_var;
 use labrinth::{check_env_vars, clickhouse, database, file_hosting, queue};
-use log::{error, info};
+use log::{error, info, warn};
 
 use std::sync::Arc;
 
@@ -81,8 +81,13 @@ async fn main() -> std::io::Result<()> {
         _ => panic!("Invalid storage backend specified. Aborting startup!"),
     };
 
-    info!("Initializing clickhouse connection");
-    let mut clickhouse = clickhouse::init_client().await.unwrap();
+    let mut clickhouse = if clickhouse::clickhouse_enabled() {
+        info!("Initializing clickhouse connection");
+        clickhouse::init_client().await.unwrap()
+    } else {
+        warn!("ClickHouse is not configured, analytics are only recorded in Postgres");
+        ::clickhouse::Client::default()
+    };
 
     let maxmind_reader = Arc::new(queue::maxmind::MaxMindIndexer::new().await.unwrap());
 
@@ -103,8 +108,14 @@ async fn main() -> std::io::Result<()> {
         maxmind_reader.clone(),
     );
 
+    labrinth_config
+        .payouts_queue
+        .register_metrics(&prometheus.registry)
+        .expect("Failed to register payouts metrics");
+    let payouts_queue = labrinth_config.payouts_queue.clone();
+
     // Init App
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
             .wrap(prometheus.clone())
             .wrap(actix_web::middleware::Compress::default())
@@ -133,6 +144,41 @@ async fn main() -> std::io::Result<()> {
             .configure(|cfg| labrinth::app_config(cfg, labrinth_config.clone()))
     })
     .bind(dotenvy::var("BIND_ADDR").unwrap())?
-    .run()
-    .await
+    .disable_signals()
+    .run();
+
+    // Signals are handled here instead of by actix, so in-flight payouts are drained while the
+    // server is still running them, and only then is it stopped
+    let handle = server.handle();
+    actix_rt::spawn(async move {
+        shutdown_signal().await;
+
+        info!("Waiting for in-flight payouts to finish");
+        if !payouts_queue
+            .drain(queue::payouts::payout_shutdown_timeout())
+            .await
+        {
+            warn!("Some payouts were still in flight at shutdown and will be rolled back");
+        }
+
+        handle.stop(true).await;
+    });
+
+    server.await
+}
+
+/// Resolves once the process is asked to stop, by Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = Box::pin(actix_rt::signal::ctrl_c());
+
+    #[cfg(unix)]
+    {
+        use actix_rt::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        futures::future::select(ctrl_c, Box::pin(terminate.recv())).await;
+    }
+
+    #[cfg(not(unix))]
+    let _ = ctrl_c.await;
 }
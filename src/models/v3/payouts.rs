@@ -1,6 +1,6 @@
-use crate::models::ids::{Base62Id, UserId};
+use crate::models::ids::{Base62Id, ProjectId, UserId};
 use chrono::{DateTime, Utc};
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Debug)]
@@ -14,15 +14,25 @@ pub struct Payout {
     pub user_id: UserId,
     pub status: PayoutStatus,
     pub created: DateTime<Utc>,
-    #[serde(with = "rust_decimal::serde::float")]
+    #[serde(with = "money")]
     pub amount: Decimal,
 
-    #[serde(with = "rust_decimal::serde::float_option")]
+    #[serde(with = "money::option")]
     pub fee: Option<Decimal>,
+    /// tax withheld from the payout based on the recipient's country
+    #[serde(with = "money::option")]
+    pub withheld: Option<Decimal>,
     pub method: Option<PayoutMethodType>,
     /// the address this payout was sent to: ex: email, paypal email, venmo handle
     pub method_address: Option<String>,
     pub platform_id: Option<String>,
+    /// the user's note for the payout, also sent to PayPal and Venmo as the payout's note.
+    /// Tremendous orders have no note, so it is only kept for the user's records there.
+    pub note: Option<String>,
+    /// ISO 4217 code of the currency `amount`, `fee` and `withheld` are in
+    pub currency: String,
+    /// The number of decimal places amounts in `currency` are rounded and formatted to
+    pub decimal_places: u32,
 }
 
 impl Payout {
@@ -34,19 +44,42 @@ impl Payout {
             created: data.created,
             amount: data.amount,
             fee: data.fee,
+            withheld: data.withheld,
             method: data.method,
             method_address: data.method_address,
             platform_id: data.platform_id,
+            note: data.note,
+            currency: BALANCE_CURRENCY.to_string(),
+            decimal_places: currency_decimal_places(BALANCE_CURRENCY),
         }
     }
 }
 
+/// The currency user balances and payouts are kept in
+pub const BALANCE_CURRENCY: &str = "USD";
+
+/// The number of decimal places (ISO 4217 minor units) amounts in `currency` are written with
+pub fn currency_decimal_places(currency: &str) -> u32 {
+    match &*currency.to_uppercase() {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+/// The provider a payout method is sent through. Clients depend on each variant's wire string,
+/// so they are pinned individually rather than derived from the variant names, and must match
+/// [`PayoutMethodType::as_str`].
 #[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
-#[serde(rename_all = "lowercase")]
 pub enum PayoutMethodType {
+    #[serde(rename = "venmo")]
     Venmo,
+    #[serde(rename = "paypal")]
     PayPal,
+    #[serde(rename = "tremendous")]
     Tremendous,
+    #[serde(rename = "unknown")]
     Unknown,
 }
 
@@ -81,6 +114,9 @@ impl PayoutMethodType {
 pub enum PayoutStatus {
     Success,
     InTransit,
+    /// Held for manual review before being sent to the provider, see
+    /// [`crate::queue::payouts::review_threshold`]
+    PendingReview,
     Cancelled,
     Cancelling,
     Failed,
@@ -98,6 +134,7 @@ impl PayoutStatus {
         match self {
             PayoutStatus::Success => "success",
             PayoutStatus::InTransit => "in-transit",
+            PayoutStatus::PendingReview => "pending-review",
             PayoutStatus::Cancelled => "cancelled",
             PayoutStatus::Cancelling => "cancelling",
             PayoutStatus::Failed => "failed",
@@ -105,10 +142,19 @@ impl PayoutStatus {
         }
     }
 
+    /// Statuses of withdrawals which have been taken from the balance but not completed yet,
+    /// ex: because they are still being sent or await review
+    pub const PENDING: [PayoutStatus; 3] = [
+        PayoutStatus::InTransit,
+        PayoutStatus::PendingReview,
+        PayoutStatus::Cancelling,
+    ];
+
     pub fn from_string(string: &str) -> PayoutStatus {
         match string {
             "success" => PayoutStatus::Success,
             "in-transit" => PayoutStatus::InTransit,
+            "pending-review" => PayoutStatus::PendingReview,
             "cancelled" => PayoutStatus::Cancelled,
             "cancelling" => PayoutStatus::Cancelling,
             "failed" => PayoutStatus::Failed,
@@ -117,19 +163,116 @@ impl PayoutStatus {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// A way of withdrawing a balance, as listed by `GET /v3/payout/methods`.
+///
+/// Clients depend on this JSON shape, so changes to it must be additive: fields are never
+/// removed or renamed, and fields added later are `#[serde(default)]` so JSON without them still
+/// deserializes. Unknown fields are ignored. The shape is pinned by snapshots in
+/// `tests/payouts.rs`, which should only ever gain fields.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct PayoutMethod {
     pub id: String,
     #[serde(rename = "type")]
     pub type_: PayoutMethodType,
     pub name: String,
     pub supported_countries: Vec<String>,
+    /// ISO 4217 codes of the currencies the method pays out in
+    #[serde(default)]
+    pub supported_currencies: Vec<String>,
     pub image_url: Option<String>,
     pub interval: PayoutInterval,
     pub fee: PayoutMethodFee,
+    /// The interval and fee in US dollars, so methods in other currencies can be compared.
+    /// `None` if there is no exchange rate for the method's currency.
+    #[serde(default)]
+    pub usd_equivalent: Option<PayoutUsdEquivalent>,
+    /// The method's position in the server's ordering, increasing down the list
+    #[serde(default)]
+    pub order: u32,
+    /// Whether new withdrawals can currently be made with this method
+    #[serde(default = "default_available")]
+    pub available: bool,
+    /// ISO 4217 code of the currency `interval` and `fee` are in
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// The number of decimal places amounts in `currency` are rounded and formatted to
+    #[serde(default = "default_decimal_places")]
+    pub decimal_places: u32,
+    /// What the method is, as described by its provider
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Terms from the provider which must be shown to users before they redeem the method
+    #[serde(default)]
+    pub disclosure: Option<String>,
+    /// The scheduled maintenance the method is currently unavailable for, if any
+    #[serde(default)]
+    pub maintenance: Option<PayoutMaintenance>,
+    /// The least that can be withdrawn with the method, in `currency`: the smallest amount of
+    /// its interval, raised to the minimum withdrawal converted to `currency` if that is higher.
+    /// `None` if there is no exchange rate to convert the minimum withdrawal with.
+    #[serde(default, with = "rust_decimal::serde::float_option")]
+    pub minimum: Option<Decimal>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// Why a payout method is temporarily unavailable, and when it is expected back
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutMaintenance {
+    pub reason: String,
+    pub until: DateTime<Utc>,
+}
+
+fn default_available() -> bool {
+    true
+}
+
+fn default_decimal_places() -> u32 {
+    2
+}
+
+impl PayoutMethod {
+    /// The amounts of a balance, in US dollars, which can be withdrawn with this method: its
+    /// interval, or the US dollar equivalent of it if it pays out in another currency
+    pub fn balance_interval(&self) -> &PayoutInterval {
+        self.usd_equivalent
+            .as_ref()
+            .map_or(&self.interval, |x| &x.interval)
+    }
+
+    /// The fee for withdrawing from a balance with this method, in US dollars: its fee, or the
+    /// US dollar equivalent of it if it pays out in another currency
+    pub fn balance_fee(&self) -> &PayoutMethodFee {
+        self.usd_equivalent.as_ref().map_or(&self.fee, |x| &x.fee)
+    }
+
+    /// Fills in `currency` and `decimal_places` from the currencies the method pays out in. Its
+    /// interval and fee are in US dollars if it supports them, otherwise in the first currency
+    /// it pays out in.
+    pub fn annotate_currency(&mut self) {
+        let currency = if self
+            .supported_currencies
+            .iter()
+            .any(|x| x == BALANCE_CURRENCY)
+        {
+            Some(BALANCE_CURRENCY)
+        } else {
+            self.supported_currencies.first().map(|x| &**x)
+        };
+        self.currency = currency.map(|x| x.to_string());
+        self.decimal_places = self
+            .currency
+            .as_deref()
+            .map_or_else(default_decimal_places, currency_decimal_places);
+    }
+}
+
+/// A payout method's interval and fee converted to US dollars
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutUsdEquivalent {
+    pub interval: PayoutInterval,
+    pub fee: PayoutMethodFee,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct PayoutMethodFee {
     #[serde(with = "rust_decimal::serde::float")]
     pub percentage: Decimal,
@@ -139,7 +282,239 @@ pub struct PayoutMethodFee {
     pub max: Option<Decimal>,
 }
 
-#[derive(Clone)]
+impl PayoutMethodFee {
+    /// The fee charged for withdrawing `amount`, before rounding
+    pub fn calculate(&self, amount: Gross) -> Fee {
+        Fee(std::cmp::min(
+            std::cmp::max(self.min, self.percentage * amount.0),
+            self.max.unwrap_or(Decimal::MAX),
+        ))
+    }
+
+    /// This fee with its amounts multiplied by an exchange `rate`, rounded to cents
+    pub fn convert(&self, rate: Decimal) -> Self {
+        PayoutMethodFee {
+            percentage: self.percentage,
+            min: (self.min * rate).round_dp(2),
+            max: self.max.map(|x| (x * rate).round_dp(2)),
+        }
+    }
+}
+
+/// An amount withdrawn from a balance, before the fee is taken out of it. Only a [`Fee`] can be
+/// subtracted from it, which leaves the [`Net`] amount sent.
+///
+/// ```compile_fail
+/// use labrinth::models::payouts::{Gross, PayoutMethodFee};
+/// use rust_decimal::Decimal;
+///
+/// // A net amount is not a fee
+/// let gross = Gross::new(Decimal::ONE_HUNDRED);
+/// let net = gross
+///     - PayoutMethodFee {
+///         percentage: Decimal::ZERO,
+///         min: Decimal::ONE,
+///         max: None,
+///     }
+///     .calculate(gross);
+/// let net = gross - net;
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Gross(Decimal);
+
+impl Gross {
+    pub fn new(amount: Decimal) -> Self {
+        Gross(amount)
+    }
+
+    pub fn amount(self) -> Decimal {
+        self.0
+    }
+}
+
+impl std::ops::Sub<Fee> for Gross {
+    type Output = Net;
+
+    fn sub(self, fee: Fee) -> Net {
+        Net(self.0 - fee.0)
+    }
+}
+
+/// The fee charged for withdrawing a [`Gross`] amount, see [`PayoutMethodFee::calculate`]
+///
+/// ```compile_fail
+/// use labrinth::models::payouts::{Fee, Gross, PayoutMethodFee};
+/// use rust_decimal::Decimal;
+///
+/// // Fees are only charged on gross amounts
+/// let fee: Fee = PayoutMethodFee {
+///     percentage: Decimal::ZERO,
+///     min: Decimal::ONE,
+///     max: None,
+/// }
+/// .calculate(Decimal::ONE_HUNDRED);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Fee(Decimal);
+
+impl Fee {
+    pub fn amount(self) -> Decimal {
+        self.0
+    }
+
+    /// This fee, lowered to `cap` if it is more
+    pub fn capped(self, cap: Decimal) -> Self {
+        Fee(self.0.min(cap))
+    }
+
+    /// This fee rounded to cents with `strategy`
+    pub fn round_to_cents(self, strategy: RoundingStrategy) -> Self {
+        Fee(self.0.round_dp_with_strategy(2, strategy))
+    }
+}
+
+/// What is left of a [`Gross`] amount once its [`Fee`] is taken out, and is sent to the user
+/// once any [`Withheld`] tax is taken out too
+///
+/// ```compile_fail
+/// use labrinth::models::payouts::{Gross, PayoutMethodFee};
+/// use rust_decimal::Decimal;
+///
+/// // A fee can only be taken out of a gross amount, and only once
+/// let gross = Gross::new(Decimal::ONE_HUNDRED);
+/// let fee = PayoutMethodFee {
+///     percentage: Decimal::ZERO,
+///     min: Decimal::ONE,
+///     max: None,
+/// }
+/// .calculate(gross);
+/// let net = gross - fee - fee;
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Net(Decimal);
+
+impl Net {
+    pub fn amount(self) -> Decimal {
+        self.0
+    }
+
+    /// The tax withheld from this amount at `rate`, ex: `0.3` for 30%
+    pub fn withhold(self, rate: Decimal) -> Withheld {
+        Withheld(self.0 * rate)
+    }
+
+    /// This amount rounded to cents with `strategy`
+    pub fn round_to_cents(self, strategy: RoundingStrategy) -> Self {
+        Net(self.0.round_dp_with_strategy(2, strategy))
+    }
+}
+
+impl std::ops::Sub<Withheld> for Net {
+    type Output = Net;
+
+    fn sub(self, withheld: Withheld) -> Net {
+        Net(self.0 - withheld.0)
+    }
+}
+
+/// Tax withheld from a [`Net`] amount, see [`Net::withhold`]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Withheld(Decimal);
+
+impl Withheld {
+    pub fn amount(self) -> Decimal {
+        self.0
+    }
+
+    /// This amount rounded to cents with `strategy`
+    pub fn round_to_cents(self, strategy: RoundingStrategy) -> Self {
+        Withheld(self.0.round_dp_with_strategy(2, strategy))
+    }
+}
+
+/// Serializes monetary amounts as JSON strings, ex: `"0.25"` or `"100000"`, so no client reads
+/// them through a float. Amounts are normalized, so equal amounts are always written the same
+/// way. Numbers are still accepted when deserializing, as older clients send them.
+///
+/// Payout methods keep their amounts as numbers (see [`PayoutDecimal`]), as clients build their
+/// amount selectors from that shape.
+pub mod money {
+    use rust_decimal::Decimal;
+    use serde::{de, Deserializer, Serializer};
+    use std::fmt;
+    use std::str::FromStr;
+
+    pub fn serialize<S>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&value.normalize())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+
+    struct MoneyVisitor;
+
+    impl<'de> de::Visitor<'de> for MoneyVisitor {
+        type Value = Decimal;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a decimal amount, as a string or number")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+            Decimal::from_str(value.trim()).map_err(E::custom)
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+            Ok(Decimal::from(value))
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+            Ok(Decimal::from(value))
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+            // Display writes the shortest digits that read back as the same float, ex: `0.1`
+            // rather than its exact binary value
+            self.visit_str(&value.to_string())
+        }
+    }
+
+    /// [`money`](self) for optional amounts
+    pub mod option {
+        use rust_decimal::Decimal;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        #[derive(Deserialize)]
+        struct Money(#[serde(with = "super")] Decimal);
+
+        pub fn serialize<S>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(Option::<Money>::deserialize(deserializer)?.map(|x| x.0))
+        }
+    }
+}
+
+/// A decimal amount, serialized as a JSON number
+#[derive(Clone, Debug, PartialEq)]
 pub struct PayoutDecimal(pub Decimal);
 
 impl Serialize for PayoutDecimal {
@@ -161,16 +536,693 @@ impl<'de> Deserialize<'de> for PayoutDecimal {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// The amounts that can be withdrawn with a payout method. Clients build their amount selectors
+/// from this, so its JSON shape must not change. It is externally tagged, with amounts in USD as
+/// JSON numbers:
+///
+/// - `{"standard": {"min": 0.01, "max": 5000.0}}`
+/// - `{"fixed": {"values": [5.0, 10.0, 25.0]}}`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum PayoutInterval {
+    /// Any amount between `min` and `max`, inclusive
     Standard {
         #[serde(with = "rust_decimal::serde::float")]
         min: Decimal,
         #[serde(with = "rust_decimal::serde::float")]
         max: Decimal,
     },
-    Fixed {
-        values: Vec<PayoutDecimal>,
-    },
+    /// Only the listed amounts, in ascending order
+    Fixed { values: Vec<PayoutDecimal> },
+}
+
+impl PayoutInterval {
+    /// The smallest amount which can be withdrawn
+    pub fn min(&self) -> Decimal {
+        match self {
+            PayoutInterval::Standard { min, .. } => *min,
+            PayoutInterval::Fixed { values } => {
+                values.iter().map(|x| x.0).min().unwrap_or(Decimal::ZERO)
+            }
+        }
+    }
+
+    /// Whether `amount` can be withdrawn
+    pub fn contains(&self, amount: Decimal) -> bool {
+        match self {
+            PayoutInterval::Standard { min, max } => *min <= amount && amount <= *max,
+            PayoutInterval::Fixed { values } => values.iter().any(|x| x.0 == amount),
+        }
+    }
+
+    /// Whether a balance of `amount` is enough to withdraw with this interval: it is within a
+    /// standard interval, or at least one fixed value
+    pub fn accommodates(&self, amount: Decimal) -> bool {
+        match self {
+            PayoutInterval::Standard { .. } => self.contains(amount),
+            PayoutInterval::Fixed { values } => values.iter().any(|x| x.0 <= amount),
+        }
+    }
+
+    /// The largest amount which can be withdrawn from a balance of `balance`: the balance
+    /// rounded down to the cent and limited to the maximum of a standard interval, or the
+    /// largest fixed value it covers. `None` if the balance is too small for any amount.
+    pub fn max_withdrawable(&self, balance: Decimal) -> Option<Decimal> {
+        match self {
+            PayoutInterval::Standard { min, max } => {
+                let amount = std::cmp::min(
+                    balance.round_dp_with_strategy(2, RoundingStrategy::ToZero),
+                    *max,
+                );
+                (amount >= *min && amount > Decimal::ZERO).then_some(amount)
+            }
+            PayoutInterval::Fixed { values } => {
+                values.iter().map(|x| x.0).filter(|x| *x <= balance).max()
+            }
+        }
+    }
+
+    /// This interval with its amounts multiplied by an exchange `rate`, rounded to cents
+    pub fn convert(&self, rate: Decimal) -> Self {
+        match self {
+            PayoutInterval::Standard { min, max } => PayoutInterval::Standard {
+                min: (min * rate).round_dp(2),
+                max: (max * rate).round_dp(2),
+            },
+            PayoutInterval::Fixed { values } => PayoutInterval::Fixed {
+                values: values
+                    .iter()
+                    .map(|x| PayoutDecimal((x.0 * rate).round_dp(2)))
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Why a payout method cannot currently be used by a user
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutIneligibility {
+    /// The method is not offered in the user's country
+    CountryUnsupported,
+    /// Payouts to the user's country are blocked for compliance reasons, or their country is
+    /// unknown while any are
+    CountryBlocked,
+    /// The user's balance is below the method's minimum withdrawal
+    UnderThreshold,
+    /// The user has not verified the account the method pays out to
+    AccountUnverified,
+    /// The method or its provider is currently unavailable
+    ProviderDown,
+    /// The method is unavailable for scheduled maintenance
+    Maintenance,
+    /// The user's payouts are on hold for compliance reasons
+    OnHold,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PayoutMethodEligibility {
+    pub method_id: String,
+    #[serde(rename = "type")]
+    pub type_: PayoutMethodType,
+    pub eligible: bool,
+    pub reasons: Vec<PayoutIneligibility>,
+    /// The maintenance the method is unavailable for, when it is one of the reasons
+    #[serde(default)]
+    pub maintenance: Option<PayoutMaintenance>,
+    /// Whether this is the user's preferred method. It is listed first while the user can
+    /// withdraw with it, and keeps its usual place otherwise.
+    #[serde(default)]
+    pub preferred: bool,
+    /// Why the method is not where the user expects it, ex: their preferred method listed in its
+    /// usual place as they can't withdraw with it
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// How far a user is through setting up withdrawals
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct PayoutOnboarding {
+    /// Whether the user has a PayPal account, Venmo handle or email to send withdrawals to
+    pub destination_set: bool,
+    /// Whether the user's country is known, which decides the methods offered to them
+    pub country_set: bool,
+    /// Whether the user has verified the account of at least one payout method
+    pub verification_complete: bool,
+    /// Whether the user can currently withdraw with at least one payout method
+    pub eligible: bool,
+}
+
+/// An administrative payout action, as recorded in the payout audit log
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutAuditAction {
+    RecomputeBalance,
+    ProcessPayout,
+    CatchUpPayouts,
+    ReconcilePayout,
+    PayoutHold,
+    ApprovePayout,
+    DenyPayout,
+    UserPayoutHold,
+    Unknown,
+}
+
+impl PayoutAuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutAuditAction::RecomputeBalance => "recompute_balance",
+            PayoutAuditAction::ProcessPayout => "process_payout",
+            PayoutAuditAction::CatchUpPayouts => "catch_up_payouts",
+            PayoutAuditAction::ReconcilePayout => "reconcile_payout",
+            PayoutAuditAction::PayoutHold => "payout_hold",
+            PayoutAuditAction::ApprovePayout => "approve_payout",
+            PayoutAuditAction::DenyPayout => "deny_payout",
+            PayoutAuditAction::UserPayoutHold => "user_payout_hold",
+            PayoutAuditAction::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_string(string: &str) -> PayoutAuditAction {
+        match string {
+            "recompute_balance" => PayoutAuditAction::RecomputeBalance,
+            "process_payout" => PayoutAuditAction::ProcessPayout,
+            "catch_up_payouts" => PayoutAuditAction::CatchUpPayouts,
+            "reconcile_payout" => PayoutAuditAction::ReconcilePayout,
+            "payout_hold" => PayoutAuditAction::PayoutHold,
+            "approve_payout" => PayoutAuditAction::ApprovePayout,
+            "deny_payout" => PayoutAuditAction::DenyPayout,
+            "user_payout_hold" => PayoutAuditAction::UserPayoutHold,
+            _ => PayoutAuditAction::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for PayoutAuditAction {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.as_str())
+    }
+}
+
+/// A record of an administrative payout action. Entries are only ever added, never changed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutAuditEntry {
+    pub created: DateTime<Utc>,
+    /// Who took the action, as authenticated. Admin requests are only authenticated by the
+    /// shared admin key, so their actions are all taken by `admin`.
+    pub actor: String,
+    /// Who the request said took the action, from its `Modrinth-Admin-Actor` header. Anyone with
+    /// the admin key can send any name, so it is unverified.
+    #[serde(default)]
+    pub claimed_actor: Option<String>,
+    pub action: PayoutAuditAction,
+    /// What the action was taken on, ex: a payout, user or project id, or a day
+    pub target: Option<String>,
+    /// The action's parameters and outcome
+    pub details: serde_json::Value,
+}
+
+/// An amount credited to a user for a day
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutHistoryEntry {
+    /// The project the amount was earned by, `None` for amounts not earned by a project
+    pub project_id: Option<ProjectId>,
+    #[serde(with = "money")]
+    pub amount: Decimal,
+    /// The start of the day the amount was credited for
+    pub created: DateTime<Utc>,
+}
+
+/// A page of a user's payout history, newest first
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutHistoryPage {
+    /// The number of entries matching the filters, across every page
+    pub total: i64,
+    pub entries: Vec<PayoutHistoryEntry>,
+}
+
+/// A compliance hold on all of a user's withdrawals. It stops applying once it expires, unless
+/// it is renewed first.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UserPayoutHold {
+    pub reason: String,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+}
+
+/// A summary of one payout run, as listed in the run history
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutRun {
+    pub created: DateTime<Utc>,
+    /// The start of the day paid out
+    pub day: DateTime<Utc>,
+    /// The day's share of the period budget
+    #[serde(with = "money")]
+    pub budget: Decimal,
+    /// Everything credited to team members for the day, not counting anything swept into the
+    /// reserve
+    #[serde(with = "money")]
+    pub distributed: Decimal,
+    /// The number of projects credited for the day
+    pub projects: i32,
+    /// The number of team members credited for the day, not counting the reserve
+    pub users: i32,
+    pub duration_ms: i64,
+    /// `paid`, or `zero_activity` if there were no views or downloads to pay out by
+    pub outcome: String,
+    /// Whether a day which was already paid out was reprocessed
+    pub forced: bool,
+}
+
+/// The payout methods as they were listed at a point in time, for investigating past payouts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutMethodSnapshot {
+    pub created: DateTime<Utc>,
+    pub methods: Vec<PayoutMethod>,
+}
+
+/// One of the payout methods a user's split withdrawals are sent to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutDestination {
+    pub method: PayoutMethodType,
+    pub method_id: String,
+    /// The percentage of each split withdrawal sent to this destination. A user's destinations
+    /// always add up to 100.
+    #[serde(with = "rust_decimal::serde::float")]
+    pub percentage: Decimal,
+}
+
+/// A user's balances at a glance, as returned by `GET /v3/payout/balance`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutBalance {
+    /// What can be withdrawn right now
+    #[serde(with = "money")]
+    pub available: Decimal,
+    /// Taken from the balance by withdrawals which have not completed yet, including their fees
+    /// and withheld tax
+    #[serde(with = "money")]
+    pub pending: Decimal,
+    /// Everything ever credited to the user, before any withdrawals
+    #[serde(with = "money")]
+    pub lifetime_earnings: Decimal,
+    /// What the user is estimated to be credited for the in-progress day. This is extrapolated
+    /// from partial analytics, like `GET /v3/analytics/revenue/estimate`.
+    #[serde(with = "money")]
+    pub next_payout_estimate: Decimal,
+}
+
+/// The longest idempotency key a withdrawal can be made with
+pub const IDEMPOTENCY_KEY_MAX_LENGTH: usize = 255;
+
+/// A withdrawal of part of the user's balance, as taken by `POST /v3/payout`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WithdrawalRequest {
+    #[serde(with = "money")]
+    pub amount: Decimal,
+    pub method: PayoutMethodType,
+    pub method_id: String,
+    /// A note for the user's records, also sent to PayPal and Venmo as the payout's note.
+    /// Tremendous orders have no note, so it is not sent with those.
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Chosen by the client so the withdrawal can be retried safely. Repeating a withdrawal with
+    /// the same key succeeds without making another, and using it for a withdrawal of another
+    /// amount or method is rejected.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+impl WithdrawalRequest {
+    /// Checks the withdrawal is well formed, before anything is looked up or taken for it. The
+    /// error names the offending field, ex: `amount: must be greater than zero`.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_withdrawal_amount(self.amount)?;
+
+        if self.method == PayoutMethodType::Unknown {
+            return Err("method: unknown payout method".to_string());
+        }
+
+        if self.method_id.trim().is_empty() {
+            return Err("method_id: must not be empty".to_string());
+        }
+
+        if let Some(key) = &self.idempotency_key {
+            validate_idempotency_key(key)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Sending a failed withdrawal again, as taken by `POST /v3/payout/{id}/retry`. The withdrawal
+/// is sent to the user's current address for its method, so a corrected email, PayPal account
+/// or Venmo handle is picked up.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WithdrawalRetryRequest {
+    /// Sends the withdrawal with another method instead, given along with `method_id`
+    #[serde(default)]
+    pub method: Option<PayoutMethodType>,
+    #[serde(default)]
+    pub method_id: Option<String>,
+    /// As for [`WithdrawalRequest::idempotency_key`]
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+impl WithdrawalRetryRequest {
+    /// Checks the retry is well formed, as [`WithdrawalRequest::validate`] does
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.method, &self.method_id) {
+            (Some(PayoutMethodType::Unknown), _) => {
+                return Err("method: unknown payout method".to_string())
+            }
+            (Some(_), Some(method_id)) if method_id.trim().is_empty() => {
+                return Err("method_id: must not be empty".to_string())
+            }
+            (Some(_), None) => return Err("method_id: must be given with method".to_string()),
+            (None, Some(_)) => return Err("method: must be given with method_id".to_string()),
+            _ => {}
+        }
+
+        if let Some(key) = &self.idempotency_key {
+            validate_idempotency_key(key)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks an amount to withdraw is positive, naming the `amount` field if not
+pub fn validate_withdrawal_amount(amount: Decimal) -> Result<(), String> {
+    if amount <= Decimal::ZERO {
+        return Err("amount: must be greater than zero".to_string());
+    }
+
+    Ok(())
+}
+
+/// Checks an idempotency key is short printable ASCII, naming the `idempotency_key` field if not
+pub fn validate_idempotency_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > IDEMPOTENCY_KEY_MAX_LENGTH {
+        return Err(format!(
+            "idempotency_key: must be between 1 and {IDEMPOTENCY_KEY_MAX_LENGTH} characters"
+        ));
+    }
+
+    if !key.chars().all(|x| x.is_ascii_graphic()) {
+        return Err("idempotency_key: must only contain printable ASCII characters".to_string());
+    }
+
+    Ok(())
+}
+
+/// A withdrawal as made by `POST /v3/payout/{id}/retry`, or by `POST /v3/payout` when it is sent
+/// with `Prefer: return=representation`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WithdrawalResponse {
+    pub id: PayoutId,
+    /// What is sent to the recipient, after the fee and any withheld tax
+    #[serde(with = "money")]
+    pub net: Decimal,
+    #[serde(with = "money")]
+    pub fee: Decimal,
+    /// The id the provider gave the payout, ex: a PayPal payout item id. `None` until it is sent,
+    /// ex: while it is held for review.
+    pub provider_reference: Option<String>,
+    pub status: PayoutStatus,
+}
+
+impl From<Payout> for WithdrawalResponse {
+    fn from(payout: Payout) -> Self {
+        Self {
+            id: payout.id,
+            net: payout.amount,
+            fee: payout.fee.unwrap_or_default(),
+            provider_reference: payout.platform_id,
+            status: payout.status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Amounts {
+        #[serde(with = "money")]
+        amount: Decimal,
+        #[serde(with = "money::option")]
+        fee: Option<Decimal>,
+    }
+
+    #[test]
+    fn money_is_written_as_normalized_strings() {
+        for (amount, json) in [
+            (Decimal::new(25, 2), "0.25"),
+            (Decimal::from(100000), "100000"),
+            (Decimal::new(1000, 2), "10"),
+        ] {
+            let value = serde_json::to_value(Amounts {
+                amount,
+                fee: Some(amount),
+            })
+            .unwrap();
+            assert_eq!(value, serde_json::json!({ "amount": json, "fee": json }));
+
+            let parsed: Amounts = serde_json::from_value(value).unwrap();
+            assert_eq!(parsed.amount, amount);
+            assert_eq!(parsed.fee, Some(amount));
+        }
+    }
+
+    #[test]
+    fn fee_is_calculated_on_the_gross_amount_and_taken_out_of_it() {
+        let schedule = PayoutMethodFee {
+            percentage: Decimal::new(2, 2),
+            min: Decimal::new(25, 2),
+            max: Some(Decimal::from(20)),
+        };
+
+        for (gross, expected_fee, expected_net) in [
+            (Decimal::from(5), Decimal::new(25, 2), Decimal::new(475, 2)),
+            (Decimal::from(100), Decimal::from(2), Decimal::from(98)),
+            (Decimal::from(5000), Decimal::from(20), Decimal::from(4980)),
+        ] {
+            let gross = Gross::new(gross);
+            let fee = schedule.calculate(gross);
+
+            assert_eq!(fee.amount(), expected_fee);
+            assert_eq!((gross - fee).amount(), expected_net);
+        }
+    }
+
+    #[test]
+    fn amounts_are_rounded_without_changing_their_kind() {
+        let gross = Gross::new(Decimal::new(12346, 3));
+        let fee = PayoutMethodFee {
+            percentage: Decimal::ZERO,
+            min: Decimal::new(6, 3),
+            max: None,
+        }
+        .calculate(gross)
+        .round_to_cents(RoundingStrategy::MidpointAwayFromZero);
+        assert_eq!(fee.amount(), Decimal::new(1, 2));
+
+        let net: Net = (gross - fee).round_to_cents(RoundingStrategy::MidpointAwayFromZero);
+        assert_eq!(net.amount(), Decimal::new(1234, 2));
+
+        let withheld: Withheld = net
+            .withhold(Decimal::new(5, 1))
+            .round_to_cents(RoundingStrategy::MidpointAwayFromZero);
+        assert_eq!(withheld.amount(), Decimal::new(617, 2));
+        assert_eq!((net - withheld).amount(), Decimal::new(617, 2));
+    }
+
+    #[test]
+    fn money_still_reads_numbers() {
+        let parsed: Amounts =
+            serde_json::from_value(serde_json::json!({ "amount": 0.1, "fee": null })).unwrap();
+        assert_eq!(
+            parsed,
+            Amounts {
+                amount: Decimal::new(1, 1),
+                fee: None,
+            }
+        );
+
+        let parsed: Amounts =
+            serde_json::from_value(serde_json::json!({ "amount": 100000, "fee": 2 })).unwrap();
+        assert_eq!(parsed.fee, Some(Decimal::from(2)));
+
+        assert!(
+            serde_json::from_value::<Amounts>(serde_json::json!({ "amount": "lots" })).is_err()
+        );
+    }
+
+    #[test]
+    fn payout_method_types_keep_their_wire_strings() {
+        for (type_, wire) in [
+            (PayoutMethodType::Venmo, "venmo"),
+            (PayoutMethodType::PayPal, "paypal"),
+            (PayoutMethodType::Tremendous, "tremendous"),
+            (PayoutMethodType::Unknown, "unknown"),
+        ] {
+            assert_eq!(serde_json::to_value(type_).unwrap(), wire);
+            assert_eq!(
+                serde_json::from_value::<PayoutMethodType>(serde_json::json!(wire)).unwrap(),
+                type_
+            );
+            // The database stores the same strings
+            assert_eq!(type_.as_str(), wire);
+            assert_eq!(PayoutMethodType::from_string(wire), type_);
+        }
+    }
+
+    #[test]
+    fn unknown_payout_method_types_are_rejected() {
+        for wire in ["bitcoin", "PayPal", "pay_pal", ""] {
+            let err = serde_json::from_value::<PayoutMethodType>(serde_json::json!(wire))
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("unknown variant"), "{wire}: {err}");
+        }
+    }
+
+    #[test]
+    fn fixed_intervals_accommodate_amounts_above_their_smallest_value() {
+        let fixed = PayoutInterval::Fixed {
+            values: vec![
+                PayoutDecimal(Decimal::from(25)),
+                PayoutDecimal(Decimal::from(50)),
+            ],
+        };
+        assert!(!fixed.accommodates(Decimal::from(7)));
+        assert!(fixed.accommodates(Decimal::from(25)));
+        assert!(fixed.accommodates(Decimal::from(30)));
+
+        let standard = PayoutInterval::Standard {
+            min: Decimal::ONE,
+            max: Decimal::from(10),
+        };
+        assert!(standard.accommodates(Decimal::from(7)));
+        assert!(!standard.accommodates(Decimal::from(11)));
+    }
+
+    #[test]
+    fn withdrawal_request_round_trips() {
+        let json = serde_json::json!({
+            "amount": "12.5",
+            "method": "paypal",
+            "method_id": "paypal_us",
+            "note": "For the server bills",
+            "idempotency_key": "7d9f0c1e-withdrawal",
+        });
+
+        let request: WithdrawalRequest = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(request.amount, Decimal::new(125, 1));
+        assert_eq!(request.method, PayoutMethodType::PayPal);
+        assert_eq!(
+            request.idempotency_key.as_deref(),
+            Some("7d9f0c1e-withdrawal")
+        );
+        assert_eq!(serde_json::to_value(&request).unwrap(), json);
+
+        // Older clients send amounts as numbers and leave out the optional fields
+        let request: WithdrawalRequest = serde_json::from_value(serde_json::json!({
+            "amount": 5,
+            "method": "venmo",
+            "method_id": "venmo",
+        }))
+        .unwrap();
+        assert_eq!(request.note, None);
+        assert_eq!(request.idempotency_key, None);
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn withdrawal_request_validation_names_the_field() {
+        let valid = WithdrawalRequest {
+            amount: Decimal::from(5),
+            method: PayoutMethodType::PayPal,
+            method_id: "paypal_us".to_string(),
+            note: None,
+            idempotency_key: Some("retry-1".to_string()),
+        };
+        assert!(valid.validate().is_ok());
+
+        for (request, field) in [
+            (
+                WithdrawalRequest {
+                    amount: Decimal::ZERO,
+                    ..valid.clone()
+                },
+                "amount:",
+            ),
+            (
+                WithdrawalRequest {
+                    method: PayoutMethodType::Unknown,
+                    ..valid.clone()
+                },
+                "method:",
+            ),
+            (
+                WithdrawalRequest {
+                    method_id: " ".to_string(),
+                    ..valid.clone()
+                },
+                "method_id:",
+            ),
+            (
+                WithdrawalRequest {
+                    idempotency_key: Some(String::new()),
+                    ..valid.clone()
+                },
+                "idempotency_key:",
+            ),
+            (
+                WithdrawalRequest {
+                    idempotency_key: Some("a".repeat(IDEMPOTENCY_KEY_MAX_LENGTH + 1)),
+                    ..valid.clone()
+                },
+                "idempotency_key:",
+            ),
+            (
+                WithdrawalRequest {
+                    idempotency_key: Some("has spaces".to_string()),
+                    ..valid.clone()
+                },
+                "idempotency_key:",
+            ),
+        ] {
+            assert!(request.validate().unwrap_err().starts_with(field));
+        }
+    }
+
+    #[test]
+    fn withdrawal_response_round_trips() {
+        let response = WithdrawalResponse {
+            id: PayoutId(1000),
+            net: Decimal::new(1175, 2),
+            fee: Decimal::new(25, 2),
+            provider_reference: None,
+            status: PayoutStatus::PendingReview,
+        };
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "id": "G8",
+                "net": "11.75",
+                "fee": "0.25",
+                "provider_reference": null,
+                "status": "pending-review",
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<WithdrawalResponse>(json).unwrap(),
+            response
+        );
+    }
 }
@@ -63,7 +63,7 @@ pub struct UserPayoutData {
     pub paypal_address: Option<String>,
     pub paypal_country: Option<String>,
     pub venmo_handle: Option<String>,
-    #[serde(with = "rust_decimal::serde::float")]
+    #[serde(with = "crate::models::payouts::money")]
     pub balance: Decimal,
 }
 
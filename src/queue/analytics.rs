@@ -1,6 +1,8 @@
+use crate::clickhouse::clickhouse_enabled;
 use crate::database::models::DatabaseError;
 use crate::database::redis::RedisPool;
 use crate::models::analytics::{Download, PageView, Playtime};
+use crate::queue::payouts::{record_postgres_events, AnalyticsEvent, PayoutAnalyticsSource};
 use crate::routes::ApiError;
 use dashmap::{DashMap, DashSet};
 use redis::cmd;
@@ -65,17 +67,40 @@ impl AnalyticsQueue {
         let playtime_queue = self.playtime_queue.clone();
         self.playtime_queue.clear();
 
-        if !views_queue.is_empty() {
-            let mut views = client.insert("views")?;
+        // Payouts read views and downloads from Postgres instead of ClickHouse when configured to,
+        // in which case ClickHouse may not be set up at all
+        let record_in_postgres =
+            PayoutAnalyticsSource::from_env() == PayoutAnalyticsSource::Postgres;
+        let record_in_clickhouse = clickhouse_enabled();
+
+        // The whole batch is recorded in Postgres at once, so a failure partway through doesn't
+        // leave its views counted without its downloads
+        let mut transaction = pool.begin().await?;
 
-            for view in views_queue {
-                views.write(&view).await?;
+        if !views_queue.is_empty() {
+            if record_in_postgres {
+                record_postgres_events(
+                    &mut transaction,
+                    AnalyticsEvent::Views,
+                    views_queue
+                        .iter()
+                        .map(|x| (x.recorded, x.project_id, x.user_id)),
+                )
+                .await?;
             }
 
-            views.end().await?;
+            if record_in_clickhouse {
+                let mut views = client.insert("views")?;
+
+                for view in views_queue {
+                    views.write(&view).await?;
+                }
+
+                views.end().await?;
+            }
         }
 
-        if !playtime_queue.is_empty() {
+        if !playtime_queue.is_empty() && record_in_clickhouse {
             let mut playtimes = client.insert("playtime")?;
 
             for playtime in playtime_queue {
@@ -141,11 +166,20 @@ impl AnalyticsQueue {
                 .map(|x| x.project_id as i64)
                 .collect::<Vec<_>>();
 
-            let mut transaction = pool.begin().await?;
-            let mut downloads = client.insert("downloads")?;
+            let raw_downloads = raw_downloads
+                .into_iter()
+                .map(|(_, download)| download)
+                .collect::<Vec<_>>();
 
-            for (_, download) in raw_downloads {
-                downloads.write(&download).await?;
+            if record_in_postgres {
+                record_postgres_events(
+                    &mut transaction,
+                    AnalyticsEvent::Downloads,
+                    raw_downloads
+                        .iter()
+                        .map(|x| (x.recorded, x.project_id, x.user_id)),
+                )
+                .await?;
             }
 
             sqlx::query!(
@@ -166,10 +200,19 @@ impl AnalyticsQueue {
             .execute(&mut *transaction)
             .await?;
 
-            transaction.commit().await?;
-            downloads.end().await?;
+            if record_in_clickhouse {
+                let mut downloads = client.insert("downloads")?;
+
+                for download in raw_downloads {
+                    downloads.write(&download).await?;
+                }
+
+                downloads.end().await?;
+            }
         }
 
+        transaction.commit().await?;
+
         Ok(())
     }
 }
@@ -1,759 +1,7039 @@
-use crate::models::ids::UserId;
+use crate::models::ids::base62_impl::{parse_base62, to_base62};
+use crate::models::ids::{PayoutId, UserId};
 use crate::models::payouts::{
-    PayoutDecimal, PayoutInterval, PayoutMethod, PayoutMethodFee, PayoutMethodType,
+    currency_decimal_places, Fee, Gross, Net, PayoutAuditEntry, PayoutDestination,
+    PayoutIneligibility, PayoutInterval, PayoutMaintenance, PayoutMethod, PayoutMethodEligibility,
+    PayoutMethodFee, PayoutMethodSnapshot, PayoutMethodType, PayoutOnboarding, PayoutRun,
+    PayoutStatus, Withheld, BALANCE_CURRENCY,
 };
 use crate::routes::ApiError;
-use crate::util::env::parse_var;
+use crate::util::currency::FxRates;
+use crate::util::env::{env_var, parse_code_decimals, parse_var, parse_var_with};
 use crate::{database::redis::RedisPool, models::projects::MonetizationStatus};
+use async_trait::async_trait;
 use base64::Engine;
+use bytes::Bytes;
 use chrono::{DateTime, Datelike, Duration, Utc, Weekday};
 use dashmap::DashMap;
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use prometheus::{
+    GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry,
+};
+use regex::Regex;
 use reqwest::Method;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::postgres::PgQueryResult;
 use sqlx::PgPool;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use std::time::Instant;
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
+use tokio_stream::wrappers::ReceiverStream;
+
+mod analytics;
+mod paypal;
+mod tremendous;
+
+pub use analytics::{
+    record_postgres_events, AnalyticsEvent, ClickHouseAnalytics, PayoutAnalytics,
+    PayoutAnalyticsSource, PostgresAnalytics, ProjectMultiplier,
+};
+pub use paypal::{PayPalProvider, PayPalRecipient};
+use tremendous::{tremendous_payout_method, TremendousProduct};
+pub use tremendous::{
+    TremendousIntervalOverride, TremendousIntervalOverrides, TremendousOrderValue,
+    TremendousProvider,
+};
 
 pub struct PayoutsQueue {
-    credential: RwLock<Option<PayPalCredentials>>,
+    credentials: RwLock<HashMap<String, PayPalCredentials>>,
     payout_options: RwLock<Option<PayoutMethods>>,
     payouts_locks: DashMap<UserId, Arc<Mutex<()>>>,
+    /// Set once the queue starts draining for shutdown, see [`PayoutsQueue::drain`]
+    draining: AtomicBool,
+    /// The payout locks taken by [`PayoutsQueue::drain`], held so no more withdrawals start
+    drained_locks: Mutex<Vec<OwnedMutexGuard<()>>>,
+    metrics: PayoutsMetrics,
+    providers: Vec<Box<dyn PayoutProvider>>,
+    /// Where payout methods are shared with other instances, see [`PayoutsQueue::with_redis`]
+    redis: Option<RedisPool>,
+    /// Overrides `PAYPAL_API_URL`, see [`PayoutsQueue::with_api_urls`]
+    paypal_api_url: Option<String>,
+    /// Overrides `TREMENDOUS_API_URL`, see [`PayoutsQueue::with_api_urls`]
+    tremendous_api_url: Option<String>,
 }
 
-#[derive(Clone)]
-struct PayPalCredentials {
-    access_token: String,
-    token_type: String,
-    expires: DateTime<Utc>,
+/// A withdrawal to be sent through a payout provider
+pub struct PayoutRequest {
+    pub id: PayoutId,
+    pub method: PayoutMethodType,
+    /// The id of the `PayoutMethod` being withdrawn with, ex: a Tremendous product id
+    pub method_id: String,
+    /// The amount to send, after fees and withholding
+    pub amount: Decimal,
+    /// Where the payout is sent: the PayPal id, Venmo handle or email address
+    pub address: String,
+    pub recipient_name: String,
+    /// Tags the provider requests made for this withdrawal, see [`new_correlation_id`]
+    pub correlation_id: String,
+    /// The user's note for the payout, already sanitized by [`sanitize_payout_note`]. Only PayPal
+    /// takes one, Tremendous orders are sent without it.
+    pub note: Option<String>,
+    /// What a Tremendous reward is ordered for, resolved by the caller from the product being
+    /// withdrawn with and its exchange rate, see [`TremendousOrderValue::for_method`]. Required
+    /// for Tremendous payouts, unused otherwise.
+    pub tremendous_value: Option<TremendousOrderValue>,
 }
 
-#[derive(Clone)]
-struct PayoutMethods {
-    options: Vec<PayoutMethod>,
-    expires: DateTime<Utc>,
+/// The most characters a payout note is kept to
+pub const PAYOUT_NOTE_MAX_LENGTH: usize = 255;
+
+/// Cleans up a user's payout note before it is stored or sent to a provider. Control characters
+/// and runs of whitespace become single spaces, and the note is cut to
+/// [`PAYOUT_NOTE_MAX_LENGTH`] characters. Notes left empty are dropped.
+pub fn sanitize_payout_note(note: &str) -> Option<String> {
+    let note = note
+        .split(|x: char| x.is_whitespace() || x.is_control())
+        .filter(|x| !x.is_empty())
+        .join(" ")
+        .chars()
+        .take(PAYOUT_NOTE_MAX_LENGTH)
+        .collect::<String>();
+    let note = note.trim_end();
+
+    (!note.is_empty()).then(|| note.to_string())
 }
 
-impl Default for PayoutsQueue {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Response header a withdrawal's correlation id is returned in
+pub const CORRELATION_ID_HEADER: &str = "Modrinth-Correlation-Id";
+
+/// PayPal's idempotency header, which their dashboard also shows alongside the request
+const PAYPAL_REQUEST_ID_HEADER: &str = "PayPal-Request-Id";
+
+/// Generates an id to trace a payment provider request by. It is logged with the request and,
+/// where the provider supports it, sent along so the request can be found in their dashboard.
+pub fn new_correlation_id() -> String {
+    uuid::Uuid::new_v4().to_string()
 }
-// Batches payouts and handles token refresh
-impl PayoutsQueue {
-    pub fn new() -> Self {
-        PayoutsQueue {
-            credential: RwLock::new(None),
-            payout_options: RwLock::new(None),
-            payouts_locks: DashMap::new(),
-        }
-    }
 
-    async fn refresh_token(&self) -> Result<PayPalCredentials, ApiError> {
-        let mut creds = self.credential.write().await;
-        let client = reqwest::Client::new();
+/// A payment provider which payouts can be sent through. Providers receive the queue so they
+/// can share its credentials and metrics.
+#[async_trait]
+pub trait PayoutProvider: Send + Sync {
+    fn name(&self) -> &'static str;
 
-        let combined_key = format!(
-            "{}:{}",
-            dotenvy::var("PAYPAL_CLIENT_ID")?,
-            dotenvy::var("PAYPAL_CLIENT_SECRET")?
-        );
-        let formatted_key = format!(
-            "Basic {}",
-            base64::engine::general_purpose::STANDARD.encode(combined_key)
-        );
+    /// Whether this provider is configured to send real money
+    fn mode(&self) -> PayoutProviderMode;
 
-        let mut form = HashMap::new();
-        form.insert("grant_type", "client_credentials");
+    /// The payout method types which are sent through this provider
+    fn supported_methods(&self) -> &'static [PayoutMethodType];
 
-        #[derive(Deserialize)]
-        struct PaypalCredential {
-            access_token: String,
-            token_type: String,
-            expires_in: i64,
-        }
+    /// Sends a payout, returning the provider's id for it if one was given
+    async fn create_payout(
+        &self,
+        queue: &PayoutsQueue,
+        request: &PayoutRequest,
+    ) -> Result<Option<String>, ApiError>;
 
-        let credential: PaypalCredential = client
-            .post(&format!("{}oauth2/token", dotenvy::var("PAYPAL_API_URL")?))
-            .header("Accept", "application/json")
-            .header("Accept-Language", "en_US")
-            .header("Authorization", formatted_key)
-            .form(&form)
-            .send()
-            .await
-            .map_err(|_| ApiError::Payments("Error while authenticating with PayPal".to_string()))?
-            .json()
-            .await
-            .map_err(|_| {
-                ApiError::Payments(
-                    "Error while authenticating with PayPal (deser error)".to_string(),
-                )
-            })?;
+    async fn get_status(
+        &self,
+        queue: &PayoutsQueue,
+        platform_id: &str,
+    ) -> Result<PayoutStatus, ApiError>;
 
-        let new_creds = PayPalCredentials {
-            access_token: credential.access_token,
-            token_type: credential.token_type,
-            expires: Utc::now() + Duration::seconds(credential.expires_in),
-        };
+    async fn cancel_payout(&self, queue: &PayoutsQueue, platform_id: &str) -> Result<(), ApiError>;
+}
+
+/// Whether a payout destination can be withdrawn to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DestinationValidation {
+    pub deliverable: bool,
+    /// Why the destination can't be withdrawn to
+    pub reason: Option<String>,
+}
 
-        *creds = Some(new_creds.clone());
+impl DestinationValidation {
+    /// Checks whether a withdrawal with `method` could be delivered to `address` by its format,
+    /// see [`validate_destination_format`]. No provider can confirm an account exists beforehand.
+    pub fn check(method: PayoutMethodType, address: &str) -> Self {
+        let reason = validate_destination_format(method, address).err();
 
-        Ok(new_creds)
+        DestinationValidation {
+            deliverable: reason.is_none(),
+            reason,
+        }
     }
+}
 
-    pub async fn make_paypal_request<T: Serialize, X: DeserializeOwned>(
-        &self,
-        method: Method,
-        path: &str,
-        body: Option<T>,
-        raw_text: Option<String>,
-        no_api_prefix: Option<bool>,
-    ) -> Result<X, ApiError> {
-        let read = self.credential.read().await;
-        let credentials = if let Some(credentials) = read.as_ref() {
-            if credentials.expires < Utc::now() {
-                drop(read);
-                self.refresh_token().await.map_err(|_| {
-                    ApiError::Payments("Error while authenticating with PayPal".to_string())
-                })?
-            } else {
-                credentials.clone()
-            }
-        } else {
-            drop(read);
-            self.refresh_token().await.map_err(|_| {
-                ApiError::Payments("Error while authenticating with PayPal".to_string())
-            })?
-        };
+lazy_static! {
+    static ref VENMO_HANDLE: Regex = Regex::new(r"^@?[A-Za-z0-9_-]{5,30}$").unwrap();
+}
 
-        let client = reqwest::Client::new();
-        let mut request = client
-            .request(
-                method,
-                if no_api_prefix.unwrap_or(false) {
-                    path.to_string()
-                } else {
-                    format!("{}{path}", dotenvy::var("PAYPAL_API_URL")?)
-                },
-            )
-            .header(
-                "Authorization",
-                format!("{} {}", credentials.token_type, credentials.access_token),
-            );
+/// Checks that `address` is in a format `method` can send to: an email address for gift cards,
+/// an email address or phone number for PayPal, and additionally a username for Venmo
+pub fn validate_destination_format(method: PayoutMethodType, address: &str) -> Result<(), String> {
+    let address = address.trim();
+    let is_email = validator::validate_email(address);
+    let is_phone = validator::validate_phone(address);
 
-        if let Some(body) = body {
-            request = request.json(&body);
-        } else if let Some(body) = raw_text {
-            request = request
-                .header(reqwest::header::CONTENT_TYPE, "application/json")
-                .body(body);
+    let valid = match method {
+        PayoutMethodType::Tremendous => is_email,
+        PayoutMethodType::PayPal => is_email || is_phone,
+        PayoutMethodType::Venmo => is_email || is_phone || VENMO_HANDLE.is_match(address),
+        PayoutMethodType::Unknown => return Err("Unknown payout method".to_string()),
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(match method {
+            PayoutMethodType::Tremendous => "Not a valid email address",
+            PayoutMethodType::PayPal => "Not a valid email address or phone number",
+            _ => "Not a valid email address, phone number or username",
         }
+        .to_string())
+    }
+}
 
-        let resp = request
-            .send()
-            .await
-            .map_err(|_| ApiError::Payments("could not communicate with PayPal".to_string()))?;
+/// Whether a payout provider is pointed at its sandbox or sends real money
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutProviderMode {
+    Sandbox,
+    Live,
+}
 
-        let status = resp.status();
+impl PayoutProviderMode {
+    /// Infers the mode from a provider's API URL, ex: `https://api-m.sandbox.paypal.com/v1/` or
+    /// `https://testflight.tremendous.com/api/v2/` are sandboxes
+    pub fn from_api_url(url: &str) -> Self {
+        let url = url.to_lowercase();
 
-        let value = resp.json::<Value>().await.map_err(|_| {
-            ApiError::Payments("could not retrieve PayPal response body".to_string())
-        })?;
+        if url.contains("sandbox") || url.contains("testflight") {
+            PayoutProviderMode::Sandbox
+        } else {
+            PayoutProviderMode::Live
+        }
+    }
 
-        if !status.is_success() {
-            #[derive(Deserialize)]
-            struct PayPalError {
-                pub name: String,
-                pub message: String,
-            }
+    /// Reads the mode from the `sandbox_var` flag if it is set, otherwise infers it from the URL
+    /// in `url_var`
+    fn from_env(sandbox_var: &'static str, url_var: &str) -> Self {
+        match parse_var::<bool>(sandbox_var) {
+            Some(true) => PayoutProviderMode::Sandbox,
+            Some(false) => PayoutProviderMode::Live,
+            None => Self::from_api_url(&dotenvy::var(url_var).unwrap_or_default()),
+        }
+    }
+}
 
-            #[derive(Deserialize)]
-            struct PayPalIdentityError {
-                pub error: String,
-                pub error_description: String,
-            }
+/// When to escalate a payment provider's failures to an error, configured by
+/// `PAYOUTS_FAILURE_ALERT_RATE` (the fraction of requests failing, 0.5 by default) over its last
+/// `PAYOUTS_FAILURE_ALERT_WINDOW` requests (20 by default). Only a full window can trip it, so
+/// a few failures after a quiet period are not escalated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FailureRateAlert {
+    pub rate: f64,
+    pub window: usize,
+}
 
-            if let Ok(error) = serde_json::from_value::<PayPalError>(value.clone()) {
-                return Err(ApiError::Payments(format!(
-                    "error name: {}, message: {}",
-                    error.name, error.message
-                )));
-            }
+impl Default for FailureRateAlert {
+    fn default() -> Self {
+        FailureRateAlert {
+            rate: 0.5,
+            window: 20,
+        }
+    }
+}
 
-            if let Ok(error) = serde_json::from_value::<PayPalIdentityError>(value) {
-                return Err(ApiError::Payments(format!(
-                    "error name: {}, message: {}",
-                    error.error, error.error_description
-                )));
-            }
+impl FailureRateAlert {
+    pub fn from_env() -> Self {
+        let default = Self::default();
 
-            return Err(ApiError::Payments(
-                "could not retrieve PayPal error body".to_string(),
-            ));
+        FailureRateAlert {
+            rate: parse_var("PAYOUTS_FAILURE_ALERT_RATE").unwrap_or(default.rate),
+            window: parse_var::<usize>("PAYOUTS_FAILURE_ALERT_WINDOW")
+                .unwrap_or(default.window)
+                .max(1),
         }
+    }
+}
+
+/// A failed provider request, with the category it is recorded under, see
+/// [`PayoutsMetrics::observe_error`]
+struct ProviderRequestError {
+    category: &'static str,
+    error: ApiError,
+}
 
-        Ok(serde_json::from_value(value)?)
+impl ProviderRequestError {
+    fn new(category: &'static str, error: impl Into<ApiError>) -> Self {
+        ProviderRequestError {
+            category,
+            error: error.into(),
+        }
     }
+}
 
-    pub async fn make_tremendous_request<T: Serialize, X: DeserializeOwned>(
-        &self,
-        method: Method,
-        path: &str,
-        body: Option<T>,
-    ) -> Result<X, ApiError> {
-        let client = reqwest::Client::new();
-        let mut request = client
-            .request(
-                method,
-                format!("{}{path}", dotenvy::var("TREMENDOUS_API_URL")?),
-            )
-            .header(
-                "Authorization",
-                format!("Bearer {}", dotenvy::var("TREMENDOUS_API_KEY")?),
-            );
+/// Prometheus metrics for the payout method cache and payment provider requests
+struct PayoutsMetrics {
+    methods_cache_hits: IntCounter,
+    methods_cache_misses: IntCounter,
+    provider_latency: HistogramVec,
+    provider_errors: IntCounterVec,
+    provider_attempts: IntCounterVec,
+    provider_retries: IntCounterVec,
+    provider_failures: IntCounterVec,
+    /// The fraction of each provider's requests in the alert window which failed
+    provider_failure_rate: GaugeVec,
+    failure_alert: FailureRateAlert,
+    /// Whether each of a provider's most recent requests failed, oldest first
+    recent_failures: std::sync::Mutex<HashMap<String, VecDeque<bool>>>,
+}
 
-        if let Some(body) = body {
-            request = request.json(&body);
+impl PayoutsMetrics {
+    fn new() -> Self {
+        PayoutsMetrics {
+            methods_cache_hits: IntCounter::new(
+                "labrinth_payout_methods_cache_hits",
+                "Payout method lookups served from the cache",
+            )
+            .expect("valid metric"),
+            methods_cache_misses: IntCounter::new(
+                "labrinth_payout_methods_cache_misses",
+                "Payout method lookups which refreshed the cache",
+            )
+            .expect("valid metric"),
+            provider_latency: HistogramVec::new(
+                HistogramOpts::new(
+                    "labrinth_payout_provider_request_duration_seconds",
+                    "Payment provider request latencies in seconds",
+                ),
+                &["provider"],
+            )
+            .expect("valid metric"),
+            provider_errors: IntCounterVec::new(
+                Opts::new(
+                    "labrinth_payout_provider_errors",
+                    "Payment provider request errors",
+                ),
+                &["provider", "category"],
+            )
+            .expect("valid metric"),
+            provider_attempts: IntCounterVec::new(
+                Opts::new(
+                    "labrinth_payout_provider_attempts",
+                    "Payment provider requests made",
+                ),
+                &["provider"],
+            )
+            .expect("valid metric"),
+            provider_retries: IntCounterVec::new(
+                Opts::new(
+                    "labrinth_payout_provider_retries",
+                    "Failed withdrawals sent to a payment provider again",
+                ),
+                &["provider"],
+            )
+            .expect("valid metric"),
+            provider_failures: IntCounterVec::new(
+                Opts::new(
+                    "labrinth_payout_provider_failures",
+                    "Payment provider requests which failed",
+                ),
+                &["provider"],
+            )
+            .expect("valid metric"),
+            provider_failure_rate: GaugeVec::new(
+                Opts::new(
+                    "labrinth_payout_provider_failure_rate",
+                    "Fraction of recent payment provider requests which failed",
+                ),
+                &["provider"],
+            )
+            .expect("valid metric"),
+            failure_alert: FailureRateAlert::from_env(),
+            recent_failures: std::sync::Mutex::new(HashMap::new()),
         }
+    }
 
-        let resp = request
-            .send()
-            .await
-            .map_err(|_| ApiError::Payments("could not communicate with Tremendous".to_string()))?;
+    fn register(&self, registry: &Registry) -> prometheus::Result<()> {
+        registry.register(Box::new(self.methods_cache_hits.clone()))?;
+        registry.register(Box::new(self.methods_cache_misses.clone()))?;
+        registry.register(Box::new(self.provider_latency.clone()))?;
+        registry.register(Box::new(self.provider_errors.clone()))?;
+        registry.register(Box::new(self.provider_attempts.clone()))?;
+        registry.register(Box::new(self.provider_retries.clone()))?;
+        registry.register(Box::new(self.provider_failures.clone()))?;
+        registry.register(Box::new(self.provider_failure_rate.clone()))?;
 
-        let status = resp.status();
+        Ok(())
+    }
 
-        let value = resp.json::<Value>().await.map_err(|_| {
-            ApiError::Payments("could not retrieve Tremendous response body".to_string())
-        })?;
+    fn observe_latency(&self, provider: &str, start: Instant) {
+        self.provider_latency
+            .with_label_values(&[provider])
+            .observe(start.elapsed().as_secs_f64());
+    }
 
-        if !status.is_success() {
-            if let Some(obj) = value.as_object() {
-                if let Some(array) = obj.get("errors") {
-                    #[derive(Deserialize)]
-                    struct TremendousError {
-                        message: String,
-                    }
+    fn observe_attempt(&self, provider: &str) {
+        self.provider_attempts.with_label_values(&[provider]).inc();
+    }
 
-                    let err =
-                        serde_json::from_value::<TremendousError>(array.clone()).map_err(|_| {
-                            ApiError::Payments(
-                                "could not retrieve Tremendous error json body".to_string(),
-                            )
-                        })?;
+    fn observe_retry(&self, provider: &str) {
+        self.provider_retries.with_label_values(&[provider]).inc();
+    }
 
-                    return Err(ApiError::Payments(err.message));
-                }
+    fn observe_success(&self, provider: &str) {
+        self.observe_outcome(provider, false);
+    }
 
-                return Err(ApiError::Payments(
-                    "could not retrieve Tremendous error body".to_string(),
-                ));
+    /// Records the final result of a provider request, a success or an error in its category
+    fn observe_result<X>(
+        &self,
+        provider: &str,
+        result: Result<X, ProviderRequestError>,
+    ) -> Result<X, ApiError> {
+        match result {
+            Ok(value) => {
+                self.observe_success(provider);
+                Ok(value)
+            }
+            Err(ProviderRequestError { category, error }) => {
+                self.observe_error(provider, category);
+                Err(error)
             }
         }
-
-        Ok(serde_json::from_value(value)?)
     }
 
-    pub async fn get_payout_methods(&self) -> Result<Vec<PayoutMethod>, ApiError> {
-        async fn refresh_payout_methods(queue: &PayoutsQueue) -> Result<PayoutMethods, ApiError> {
-            let mut options = queue.payout_options.write().await;
+    /// Records a provider error. `category` is one of `auth`, `config`, `transport`, `body`,
+    /// `api` or `decode`. Returns whether the provider's failures were escalated.
+    fn observe_error(&self, provider: &str, category: &str) -> bool {
+        self.provider_errors
+            .with_label_values(&[provider, category])
+            .inc();
+        self.provider_failures.with_label_values(&[provider]).inc();
+        self.observe_outcome(provider, true)
+    }
 
-            let mut methods = Vec::new();
+    /// Updates the provider's failure rate with the outcome of a request, logging an error if
+    /// the rate has reached the [alert](FailureRateAlert). Returns whether it did.
+    fn observe_outcome(&self, provider: &str, failed: bool) -> bool {
+        let mut recent_failures = self
+            .recent_failures
+            .lock()
+            .unwrap_or_else(|x| x.into_inner());
+        let recent = recent_failures.entry(provider.to_string()).or_default();
+        recent.push_back(failed);
+        while recent.len() > self.failure_alert.window {
+            recent.pop_front();
+        }
 
-            #[derive(Deserialize)]
-            pub struct Sku {
-                pub min: Decimal,
-                pub max: Decimal,
-            }
+        let rate = recent.iter().filter(|x| **x).count() as f64 / recent.len() as f64;
+        self.provider_failure_rate
+            .with_label_values(&[provider])
+            .set(rate);
 
-            #[derive(Deserialize, Eq, PartialEq)]
-            #[serde(rename_all = "snake_case")]
-            pub enum ProductImageType {
-                Card,
-                Logo,
-            }
+        let alert =
+            failed && recent.len() >= self.failure_alert.window && rate >= self.failure_alert.rate;
+        if alert {
+            log::error!(
+                "{:.0}% of the last {} {provider} requests failed",
+                rate * 100.0,
+                recent.len()
+            );
+        }
 
-            #[derive(Deserialize)]
-            pub struct ProductImage {
-                pub src: String,
-                #[serde(rename = "type")]
-                pub type_: ProductImageType,
-            }
+        alert
+    }
+}
 
-            #[derive(Deserialize)]
-            pub struct ProductCountry {
-                pub abbr: String,
-            }
+#[derive(Clone)]
+struct PayPalCredentials {
+    access_token: String,
+    token_type: String,
+    expires: DateTime<Utc>,
+}
 
-            #[derive(Deserialize)]
-            pub struct Product {
-                pub id: String,
-                pub category: String,
-                pub name: String,
-                pub description: String,
-                pub disclosure: String,
-                pub skus: Vec<Sku>,
-                pub currency_codes: Vec<String>,
-                pub countries: Vec<ProductCountry>,
-                pub images: Vec<ProductImage>,
-            }
+impl PayPalCredentials {
+    /// Whether the token has expired or will expire within `skew`, so that a request
+    /// never starts with a token that lapses mid-flight.
+    fn needs_refresh(&self, skew: Duration) -> bool {
+        self.expires - skew < Utc::now()
+    }
+}
 
-            #[derive(Deserialize)]
-            pub struct TremendousResponse {
-                pub products: Vec<Product>,
-            }
-
-            let response = queue
-                .make_tremendous_request::<(), TremendousResponse>(Method::GET, "products", None)
-                .await?;
-
-            for product in response.products {
-                const BLACKLISTED_IDS: &[&str] = &[
-                    // physical visa
-                    "A2J05SWPI2QG",
-                    // crypto
-                    "1UOOSHUUYTAM",
-                    "5EVJN47HPDFT",
-                    "NI9M4EVAVGFJ",
-                    "VLY29QHTMNGT",
-                    "7XU98H109Y3A",
-                    "0CGEDFP2UIKV",
-                    "PDYLQU0K073Y",
-                    "HCS5Z7O2NV5G",
-                    "IY1VMST1MOXS",
-                    "VRPZLJ7HCA8X",
-                    // bitcard (crypto)
-                    "GWQQS5RM8IZS",
-                    "896MYD4SGOGZ",
-                    "PWLEN1VZGMZA",
-                    "A2VRM96J5K5W",
-                    "HV9ICIM3JT7P",
-                    "K2KLSPVWC2Q4",
-                    "HRBRQLLTDF95",
-                    "UUBYLZVK7QAB",
-                    "BH8W3XEDEOJN",
-                    "7WGE043X1RYQ",
-                    "2B13MHUZZVTF",
-                    "JN6R44P86EYX",
-                    "DA8H43GU84SO",
-                    "QK2XAQHSDEH4",
-                    "J7K1IQFS76DK",
-                    "NL4JQ2G7UPRZ",
-                    "OEFTMSBA5ELH",
-                    "A3CQK6UHNV27",
-                ];
-                const SUPPORTED_METHODS: &[&str] =
-                    &["merchant_cards", "visa", "bank", "ach", "visa_card"];
-
-                if !SUPPORTED_METHODS.contains(&&*product.category)
-                    || BLACKLISTED_IDS.contains(&&*product.id)
-                {
-                    continue;
-                };
-
-                let method = PayoutMethod {
-                    id: product.id,
-                    type_: PayoutMethodType::Tremendous,
-                    name: product.name.clone(),
-                    supported_countries: product.countries.into_iter().map(|x| x.abbr).collect(),
-                    image_url: product
-                        .images
-                        .into_iter()
-                        .find(|x| x.type_ == ProductImageType::Card)
-                        .map(|x| x.src),
-                    interval: if product.skus.len() > 1 {
-                        let mut values = product
-                            .skus
-                            .into_iter()
-                            .map(|x| PayoutDecimal(x.min))
-                            .collect::<Vec<_>>();
-                        values.sort_by(|a, b| a.0.cmp(&b.0));
-
-                        PayoutInterval::Fixed { values }
-                    } else if let Some(first) = product.skus.first() {
-                        PayoutInterval::Standard {
-                            min: first.min,
-                            max: first.max,
-                        }
-                    } else {
-                        PayoutInterval::Standard {
-                            min: Decimal::ZERO,
-                            max: Decimal::from(5_000),
-                        }
-                    },
-                    fee: if product.category == "ach" {
-                        PayoutMethodFee {
-                            percentage: Decimal::from(4) / Decimal::from(100),
-                            min: Decimal::from(1) / Decimal::from(4),
-                            max: None,
-                        }
-                    } else {
-                        PayoutMethodFee {
-                            percentage: Default::default(),
-                            min: Default::default(),
-                            max: None,
-                        }
-                    },
-                };
+/// The key of the default PayPal credential set, configured through `PAYPAL_CLIENT_ID`,
+/// `PAYPAL_CLIENT_SECRET` and `PAYPAL_API_URL`
+pub const PRIMARY_PAYPAL_CREDENTIALS: &str = "primary";
 
-                // we do not support interval gift cards with non US based currencies since we cannot do currency conversions properly
-                if let PayoutInterval::Fixed { .. } = method.interval {
-                    if !product.currency_codes.contains(&"USD".to_string()) {
-                        continue;
-                    }
-                }
+/// Reads a PayPal variable for the given credential set. Additional sets are configured with
+/// the key inserted after the prefix, ex: `PAYPAL_SANDBOX_CLIENT_ID` for the `sandbox` key.
+fn paypal_var(credentials_key: &str, name: &str) -> Result<String, dotenvy::Error> {
+    if credentials_key == PRIMARY_PAYPAL_CREDENTIALS {
+        dotenvy::var(format!("PAYPAL_{name}"))
+    } else {
+        dotenvy::var(format!("PAYPAL_{}_{name}", credentials_key.to_uppercase()))
+    }
+}
 
-                methods.push(method);
-            }
+#[derive(Deserialize)]
+struct PayPalError {
+    pub name: String,
+    pub message: String,
+    #[serde(default)]
+    pub details: Vec<PayPalErrorDetail>,
+}
 
-            const UPRANK_IDS: &[&str] = &["ET0ZVETV5ILN", "Q24BD9EZ332JT", "UIL1ZYJU5MKN"];
-            const DOWNRANK_IDS: &[&str] = &["EIPF8Q00EMM1", "OU2MWXYWPNWQ"];
+/// A field level issue in a PayPal error, ex: a malformed receiver
+#[derive(Deserialize)]
+struct PayPalErrorDetail {
+    pub field: Option<String>,
+    pub issue: Option<String>,
+    pub description: Option<String>,
+}
 
-            methods.sort_by(|a, b| {
-                let a_top = UPRANK_IDS.contains(&&*a.id);
-                let a_bottom = DOWNRANK_IDS.contains(&&*a.id);
-                let b_top = UPRANK_IDS.contains(&&*b.id);
-                let b_bottom = DOWNRANK_IDS.contains(&&*b.id);
+impl std::fmt::Display for PayPalErrorDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.field.as_deref().unwrap_or("request"),
+            self.issue.as_deref().unwrap_or("invalid")
+        )?;
 
-                match (a_top, a_bottom, b_top, b_bottom) {
-                    (true, _, true, _) => a.name.cmp(&b.name), // Both in top_priority: sort alphabetically
-                    (_, true, _, true) => a.name.cmp(&b.name), // Both in bottom_priority: sort alphabetically
-                    (true, _, _, _) => std::cmp::Ordering::Less, // a in top_priority: a comes first
-                    (_, _, true, _) => std::cmp::Ordering::Greater, // b in top_priority: b comes first
-                    (_, true, _, _) => std::cmp::Ordering::Greater, // a in bottom_priority: b comes first
-                    (_, _, _, true) => std::cmp::Ordering::Less, // b in bottom_priority: a comes first
-                    (_, _, _, _) => a.name.cmp(&b.name), // Neither in priority: sort alphabetically
-                }
-            });
+        if let Some(description) = &self.description {
+            write!(f, " ({description})")?;
+        }
 
-            {
-                let paypal_us = PayoutMethod {
-                    id: "paypal_us".to_string(),
-                    type_: PayoutMethodType::PayPal,
-                    name: "PayPal".to_string(),
-                    supported_countries: vec!["US".to_string()],
-                    image_url: None,
-                    interval: PayoutInterval::Standard {
-                        min: Decimal::from(1) / Decimal::from(4),
-                        max: Decimal::from(100_000),
-                    },
-                    fee: PayoutMethodFee {
-                        percentage: Decimal::from(2) / Decimal::from(100),
-                        min: Decimal::from(1) / Decimal::from(4),
-                        max: Some(Decimal::from(1)),
-                    },
-                };
-
-                let mut venmo = paypal_us.clone();
-                venmo.id = "venmo".to_string();
-                venmo.name = "Venmo".to_string();
-                venmo.type_ = PayoutMethodType::Venmo;
-
-                methods.insert(0, paypal_us);
-                methods.insert(1, venmo)
-            }
-
-            methods.insert(
-                2,
-                PayoutMethod {
-                    id: "paypal_in".to_string(),
-                    type_: PayoutMethodType::PayPal,
-                    name: "PayPal".to_string(),
-                    supported_countries: rust_iso3166::ALL
-                        .iter()
-                        .filter(|x| x.alpha2 != "US")
-                        .map(|x| x.alpha2.to_string())
-                        .collect(),
-                    image_url: None,
-                    interval: PayoutInterval::Standard {
-                        min: Decimal::from(1) / Decimal::from(4),
-                        max: Decimal::from(100_000),
-                    },
-                    fee: PayoutMethodFee {
-                        percentage: Decimal::from(2) / Decimal::from(100),
-                        min: Decimal::ZERO,
-                        max: Some(Decimal::from(20)),
-                    },
-                },
-            );
+        Ok(())
+    }
+}
 
-            let new_options = PayoutMethods {
-                options: methods,
-                expires: Utc::now() + Duration::hours(6),
-            };
+impl PayPalError {
+    /// Whether PayPal rejected the payout because its `sender_item_id` was already used. As
+    /// the item id is the payout id, the payout may have already been sent. Each batch holds a
+    /// single item, so only that item's field is matched.
+    fn is_sender_item_id_collision(&self) -> bool {
+        self.details
+            .iter()
+            .any(|x| x.field.as_deref() == Some("items[0].sender_item_id"))
+    }
+}
 
-            *options = Some(new_options.clone());
+#[derive(Deserialize)]
+struct PayPalIdentityError {
+    pub error: String,
+    pub error_description: String,
+}
 
-            Ok(new_options)
+/// Converts the body of an unsuccessful PayPal response to an error
+fn paypal_error(value: Value) -> ApiError {
+    if let Ok(error) = serde_json::from_value::<PayPalError>(value.clone()) {
+        if error.is_sender_item_id_collision() {
+            return ApiError::DuplicatePayout(
+                "This payout has already been submitted to PayPal. Check your payout history before trying again."
+                    .to_string(),
+            );
         }
 
-        let read = self.payout_options.read().await;
-        let options = if let Some(options) = read.as_ref() {
-            if options.expires < Utc::now() {
-                drop(read);
-                refresh_payout_methods(self).await?
-            } else {
-                options.clone()
-            }
-        } else {
-            drop(read);
-            refresh_payout_methods(self).await?
-        };
+        let mut message = format!("error name: {}, message: {}", error.name, error.message);
+        if !error.details.is_empty() {
+            message.push_str(&format!(
+                ", details: {}",
+                error.details.iter().map(|x| x.to_string()).join("; ")
+            ));
+        }
 
-        Ok(options.options)
+        return ApiError::Payments(message);
     }
 
-    pub fn lock_user_payouts(&self, user_id: UserId) -> Arc<Mutex<()>> {
-        self.payouts_locks
-            .entry(user_id)
-            .or_insert_with(|| Arc::new(Mutex::new(())))
-            .clone()
+    if let Ok(error) = serde_json::from_value::<PayPalIdentityError>(value) {
+        return ApiError::Payments(format!(
+            "error name: {}, message: {}",
+            error.error, error.error_description
+        ));
     }
+
+    ApiError::Payments("could not retrieve PayPal error body".to_string())
 }
 
-pub async fn process_payout(
-    pool: &PgPool,
-    redis: &RedisPool,
-    client: &clickhouse::Client,
-) -> Result<(), ApiError> {
-    let start: DateTime<Utc> = DateTime::from_naive_utc_and_offset(
-        (Utc::now() - Duration::days(1))
-            .date_naive()
-            .and_hms_nano_opt(0, 0, 0, 0)
-            .unwrap_or_default(),
-        Utc,
-    );
+/// Builds an authenticated PayPal request tagged with `correlation_id`
+fn paypal_request(
+    client: &reqwest::Client,
+    method: Method,
+    url: String,
+    credentials: &PayPalCredentials,
+    correlation_id: &str,
+) -> reqwest::RequestBuilder {
+    client
+        .request(method, url)
+        .header(
+            "Authorization",
+            format!("{} {}", credentials.token_type, credentials.access_token),
+        )
+        .header(PAYPAL_REQUEST_ID_HEADER, correlation_id)
+}
 
-    let results = sqlx::query!(
-        "SELECT EXISTS(SELECT 1 FROM payouts_values WHERE created = $1)",
-        start,
-    )
-    .fetch_one(pool)
-    .await?;
+/// The grace period before expiry in which PayPal tokens are preemptively refreshed.
+/// Defaults to 60 seconds if unset.
+fn paypal_token_refresh_skew() -> Duration {
+    Duration::seconds(parse_var("PAYPAL_TOKEN_REFRESH_SKEW").unwrap_or(60))
+}
+
+/// A random amount of up to `PAYPAL_TOKEN_REFRESH_JITTER` seconds (30 by default) taken off each
+/// new token's lifetime, so instances which fetched tokens together do not all refresh together
+fn paypal_token_refresh_jitter() -> Duration {
+    use rand::Rng;
+
+    let max = parse_var::<i64>("PAYPAL_TOKEN_REFRESH_JITTER")
+        .unwrap_or(30)
+        .max(0);
+
+    Duration::seconds(rand::thread_rng().gen_range(0..=max))
+}
+
+/// A payout setting which is missing or malformed, found when validating the configuration at
+/// startup rather than partway through a withdrawal
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PayoutConfigError {
+    #[error("Variable `{0}` missing in dotenv")]
+    Missing(&'static str),
+    #[error("Variable `{var}` is invalid: {reason}")]
+    Invalid { var: &'static str, reason: String },
+}
+
+/// Provider settings every withdrawal depends on
+const REQUIRED_PAYOUT_VARS: &[&str] = &[
+    "PAYPAL_API_URL",
+    "PAYPAL_WEBHOOK_ID",
+    "PAYPAL_CLIENT_ID",
+    "PAYPAL_CLIENT_SECRET",
+    "TREMENDOUS_API_URL",
+    "TREMENDOUS_API_KEY",
+    "TREMENDOUS_PRIVATE_KEY",
+    "TREMENDOUS_CAMPAIGN_ID",
+];
 
-    if results.exists.unwrap_or(false) {
-        return Ok(());
+/// Optional switches, which must be `true` or `false` when set
+const PAYOUT_FLAG_VARS: &[&str] = &[
+    "PAYOUTS_WITHDRAWALS_ENABLED",
+    "PAYOUTS_TREMENDOUS_ENABLED",
+    "PAYOUTS_AUTO_RETRY",
+    "PAYOUTS_SHARED_METHODS_CACHE",
+    "PAYOUTS_CONVERSION_WEIGHTING",
+];
+
+/// Every problem with the payout configuration in the environment
+pub fn payout_config_errors() -> Vec<PayoutConfigError> {
+    payout_config_errors_with(env_var)
+}
+
+/// Every problem with the payout configuration, reading variables with `var`
+fn payout_config_errors_with(var: impl Fn(&str) -> Option<String>) -> Vec<PayoutConfigError> {
+    let mut errors = Vec::new();
+
+    for &name in REQUIRED_PAYOUT_VARS {
+        match var(name) {
+            Some(value) if !value.trim().is_empty() => {
+                if name.ends_with("_URL") {
+                    match url::Url::parse(&value) {
+                        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                        _ => errors.push(PayoutConfigError::Invalid {
+                            var: name,
+                            reason: format!("`{value}` is not an http(s) URL"),
+                        }),
+                    }
+                }
+            }
+            _ => errors.push(PayoutConfigError::Missing(name)),
+        }
     }
 
-    let end = start + Duration::days(1);
-    #[derive(Deserialize, clickhouse::Row)]
-    struct ProjectMultiplier {
-        pub page_views: u64,
-        pub project_id: u64,
-    }
-
-    let (views_values, views_sum, downloads_values, downloads_sum) = futures::future::try_join4(
-        client
-            .query(
-                r#"
-                SELECT COUNT(1) page_views, project_id
-                FROM views
-                WHERE (recorded BETWEEN ? AND ?) AND (project_id != 0)
-                GROUP BY project_id
-                ORDER BY page_views DESC
-                "#,
-            )
-            .bind(start.timestamp())
-            .bind(end.timestamp())
-            .fetch_all::<ProjectMultiplier>(),
-        client
-            .query("SELECT COUNT(1) FROM views WHERE (recorded BETWEEN ? AND ?) AND (project_id != 0)")
-            .bind(start.timestamp())
-            .bind(end.timestamp())
-            .fetch_one::<u64>(),
-        client
-            .query(
-                r#"
-                SELECT COUNT(1) page_views, project_id
-                FROM downloads
-                WHERE (recorded BETWEEN ? AND ?) AND (user_id != 0)
-                GROUP BY project_id
-                ORDER BY page_views DESC
-                "#,
-            )
-            .bind(start.timestamp())
-            .bind(end.timestamp())
-            .fetch_all::<ProjectMultiplier>(),
-        client
-            .query("SELECT COUNT(1) FROM downloads WHERE (recorded BETWEEN ? AND ?) AND (user_id != 0)")
-            .bind(start.timestamp())
-            .bind(end.timestamp())
-            .fetch_one::<u64>(),
-    )
-        .await?;
+    for &name in PAYOUT_FLAG_VARS {
+        if let Some(value) = var(name) {
+            if value.parse::<bool>().is_err() {
+                errors.push(PayoutConfigError::Invalid {
+                    var: name,
+                    reason: format!("`{value}` is not `true` or `false`"),
+                });
+            }
+        }
+    }
 
-    let mut transaction = pool.begin().await?;
+    for name in [
+        "PAYOUTS_FAILURE_ALERT_RATE",
+        "PAYOUTS_REVIEW_THRESHOLD",
+        "PAYOUTS_WEEKEND_BONUS",
+        "PAYOUTS_CONVERSION_WEIGHT_MIN",
+        "PAYOUTS_CONVERSION_WEIGHT_MAX",
+        "PAYOUTS_MIN_WITHDRAWAL",
+    ] {
+        if let Some(value) = var(name) {
+            if value.parse::<Decimal>().is_err() {
+                errors.push(PayoutConfigError::Invalid {
+                    var: name,
+                    reason: format!("`{value}` is not a decimal amount"),
+                });
+            }
+        }
+    }
 
-    struct PayoutMultipliers {
-        sum: u64,
-        values: HashMap<u64, u64>,
+    if let Some(value) = var("PAYOUTS_ANALYTICS_SOURCE") {
+        if PayoutAnalyticsSource::parse(&value).is_none() {
+            errors.push(PayoutConfigError::Invalid {
+                var: "PAYOUTS_ANALYTICS_SOURCE",
+                reason: format!("`{value}` is not `clickhouse` or `postgres`"),
+            });
+        }
     }
 
-    let mut views_values = views_values
-        .into_iter()
-        .map(|x| (x.project_id, x.page_views))
-        .collect::<HashMap<u64, u64>>();
-    let downloads_values = downloads_values
-        .into_iter()
-        .map(|x| (x.project_id, x.page_views))
-        .collect::<HashMap<u64, u64>>();
+    if let Some(value) = var("PAYOUTS_BLOCKED_COUNTRIES") {
+        for code in BlockedCountries::parse(&value).1 {
+            errors.push(PayoutConfigError::Invalid {
+                var: "PAYOUTS_BLOCKED_COUNTRIES",
+                reason: format!("`{code}` is not an ISO 3166-1 alpha-2 country code"),
+            });
+        }
+    }
 
-    for (key, value) in downloads_values.iter() {
-        let counter = views_values.entry(*key).or_insert(0);
-        *counter += *value;
+    if let Some(value) = var("PAYOUTS_TREMENDOUS_INTERVAL_OVERRIDES") {
+        if let Err(err) = TremendousIntervalOverrides::parse(&value) {
+            errors.push(PayoutConfigError::Invalid {
+                var: "PAYOUTS_TREMENDOUS_INTERVAL_OVERRIDES",
+                reason: format!("`{value}` is not a JSON object of product intervals: {err}"),
+            });
+        }
     }
 
-    let multipliers: PayoutMultipliers = PayoutMultipliers {
-        sum: downloads_sum + views_sum,
-        values: views_values,
-    };
+    if let Some(value) = var("PAYOUTS_MAINTENANCE_WINDOWS") {
+        if let Err(err) = PayoutMaintenanceWindows::parse(&value) {
+            errors.push(PayoutConfigError::Invalid {
+                var: "PAYOUTS_MAINTENANCE_WINDOWS",
+                reason: format!("`{value}` is not a JSON array of maintenance windows: {err}"),
+            });
+        }
+    }
 
-    struct Project {
-        // user_id, payouts_split
-        team_members: Vec<(i64, Decimal)>,
+    if let Some(value) = var("PAYOUTS_RESERVE_USER") {
+        if parse_base62(value.trim()).is_err() {
+            errors.push(PayoutConfigError::Invalid {
+                var: "PAYOUTS_RESERVE_USER",
+                reason: format!("`{value}` is not a user id"),
+            });
+        }
     }
 
-    let mut projects_map: HashMap<i64, Project> = HashMap::new();
+    if let Some(value) = var("PAYOUTS_WEEKEND_DAYS") {
+        if let Err(day) = parse_weekend_days(&value) {
+            errors.push(PayoutConfigError::Invalid {
+                var: "PAYOUTS_WEEKEND_DAYS",
+                reason: format!("`{day}` is not a day of the week"),
+            });
+        }
+    }
 
-    use futures::TryStreamExt;
+    if let Some(value) = var("PAYOUTS_PROJECT_CAP_PERCENTILE") {
+        if !matches!(value.parse::<u8>(), Ok(x) if x <= 100) {
+            errors.push(PayoutConfigError::Invalid {
+                var: "PAYOUTS_PROJECT_CAP_PERCENTILE",
+                reason: format!("`{value}` is not a percentile from 0 to 100"),
+            });
+        }
+    }
 
-    sqlx::query!(
-        "
-        SELECT m.id id, tm.user_id user_id, tm.payouts_split payouts_split
-        FROM mods m
-        INNER JOIN team_members tm on m.team_id = tm.team_id AND tm.accepted = TRUE
-        WHERE m.id = ANY($1) AND m.monetization_status = $2
-        ",
-        &multipliers
-            .values
-            .keys()
-            .map(|x| *x as i64)
-            .collect::<Vec<i64>>(),
-        MonetizationStatus::Monetized.as_str(),
-    )
-    .fetch_many(&mut *transaction)
-    .try_for_each(|e| {
-        if let Some(row) = e.right() {
-            if let Some(project) = projects_map.get_mut(&row.id) {
-                project.team_members.push((row.user_id, row.payouts_split));
-            } else {
-                projects_map.insert(
-                    row.id,
-                    Project {
-                        team_members: vec![(row.user_id, row.payouts_split)],
-                    },
-                );
+    for name in [
+        "PAYOUTS_VIEW_WEIGHT",
+        "PAYOUTS_DOWNLOAD_WEIGHT",
+        "PAYOUTS_RUN_INTERVAL",
+        "PAYOUTS_CATCH_UP_MAX_DAYS",
+        "PAYOUTS_WITHDRAWAL_RATE_LIMIT",
+        "PAYOUTS_WITHDRAWAL_RATE_WINDOW",
+        "PAYOUTS_PROJECT_CAP",
+        "PAYOUTS_PROJECT_MINIMUM",
+        "PAYOUTS_FAILURE_ALERT_WINDOW",
+        "PAYOUTS_SHUTDOWN_TIMEOUT",
+        "PAYOUTS_METHOD_SNAPSHOT_INTERVAL",
+    ] {
+        if let Some(value) = var(name) {
+            if value.parse::<u64>().is_err() {
+                errors.push(PayoutConfigError::Invalid {
+                    var: name,
+                    reason: format!("`{value}` is not a whole number"),
+                });
             }
         }
+    }
 
-        futures::future::ready(Ok(()))
-    })
-    .await?;
+    errors
+}
 
-    let amount = Decimal::from(parse_var::<u64>("PAYOUTS_BUDGET").unwrap_or(0));
+/// Whether new withdrawals are accepted. Operators can set `PAYOUTS_WITHDRAWALS_ENABLED=false`
+/// during provider outages or audits; balances still accrue through `process_payout`.
+pub fn withdrawals_enabled() -> bool {
+    withdrawals_enabled_with(env_var)
+}
 
-    let days = Decimal::from(28);
-    let weekdays = Decimal::from(20);
-    let weekend_bonus = Decimal::from(5) / Decimal::from(4);
+fn withdrawals_enabled_with(var: impl Fn(&str) -> Option<String>) -> bool {
+    parse_var_with(var, "PAYOUTS_WITHDRAWALS_ENABLED").unwrap_or(true)
+}
 
-    let weekday_amount = amount / (weekdays + (weekend_bonus) * (days - weekdays));
-    let weekend_amount = weekday_amount * weekend_bonus;
+/// Whether Tremendous gift card and bank transfer methods are offered. Operators can set
+/// `PAYOUTS_TREMENDOUS_ENABLED=false` during incidents to pull them while keeping PayPal and
+/// Venmo available.
+pub fn tremendous_enabled() -> bool {
+    tremendous_enabled_with(env_var)
+}
 
-    let payout = match start.weekday() {
-        Weekday::Sat | Weekday::Sun => weekend_amount,
-        _ => weekday_amount,
-    };
+fn tremendous_enabled_with(var: impl Fn(&str) -> Option<String>) -> bool {
+    parse_var_with(var, "PAYOUTS_TREMENDOUS_ENABLED").unwrap_or(true)
+}
+
+/// Whether withdrawals which failed at their provider are sent once more after their funds are
+/// returned, configured by `PAYOUTS_AUTO_RETRY`. See [`reconcile_failed_payouts`].
+pub fn auto_retry_failed_payouts() -> bool {
+    auto_retry_failed_payouts_with(env_var)
+}
+
+fn auto_retry_failed_payouts_with(var: impl Fn(&str) -> Option<String>) -> bool {
+    parse_var_with(var, "PAYOUTS_AUTO_RETRY").unwrap_or(false)
+}
+
+/// Withdrawals sending more than this are held for manual review by an admin instead of being
+/// sent to the provider straight away, configured by `PAYOUTS_REVIEW_THRESHOLD`. Nothing is held
+/// if unset.
+pub fn review_threshold() -> Option<Decimal> {
+    review_threshold_with(env_var)
+}
+
+fn review_threshold_with(var: impl Fn(&str) -> Option<String>) -> Option<Decimal> {
+    parse_var_with(var, "PAYOUTS_REVIEW_THRESHOLD")
+}
+
+/// Redis namespace the payout methods are shared between instances under
+pub const PAYOUT_METHODS_NAMESPACE: &str = "payout_methods";
+
+#[derive(Clone, Serialize, Deserialize)]
+struct PayoutMethods {
+    options: Vec<PayoutMethod>,
+    /// Whether Tremendous methods were fetched, so they are refetched once re-enabled
+    tremendous: bool,
+    expires: DateTime<Utc>,
+}
+
+impl PayoutMethods {
+    /// Whether these methods can still be offered: they have not expired, and include
+    /// Tremendous methods if `tremendous` methods are enabled
+    fn is_fresh(&self, tremendous: bool) -> bool {
+        self.expires >= Utc::now() && (self.tremendous || !tremendous)
+    }
+}
+
+impl Default for PayoutsQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+// Batches payouts and handles token refresh
+impl PayoutsQueue {
+    pub fn new() -> Self {
+        Self::with_providers(vec![Box::new(PayPalProvider), Box::new(TremendousProvider)])
+    }
+
+    pub fn with_providers(providers: Vec<Box<dyn PayoutProvider>>) -> Self {
+        PayoutsQueue {
+            credentials: RwLock::new(HashMap::new()),
+            payout_options: RwLock::new(None),
+            payouts_locks: DashMap::new(),
+            draining: AtomicBool::new(false),
+            drained_locks: Mutex::new(Vec::new()),
+            metrics: PayoutsMetrics::new(),
+            providers,
+            redis: None,
+            paypal_api_url: None,
+            tremendous_api_url: None,
+        }
+    }
+
+    /// Shares fetched payout methods with other instances through Redis until they expire, so
+    /// Tremendous is asked once per expiry rather than once per instance. Each instance still
+    /// checks its own copy first.
+    pub fn with_redis(mut self, redis: RedisPool) -> Self {
+        self.redis = Some(redis);
+        self
+    }
+
+    /// Sends PayPal and Tremendous requests to the given base URLs instead of the configured
+    /// ones, ex: to a local mock of both in tests. Paths are appended to the URLs, so they should
+    /// end with a slash.
+    pub fn with_api_urls(
+        mut self,
+        paypal: impl Into<String>,
+        tremendous: impl Into<String>,
+    ) -> Self {
+        self.paypal_api_url = Some(paypal.into());
+        self.tremendous_api_url = Some(tremendous.into());
+        self
+    }
+
+    /// The base URL of the PayPal API for the credential set `credentials_key`
+    fn paypal_api_url(&self, credentials_key: &str) -> Result<String, dotenvy::Error> {
+        match &self.paypal_api_url {
+            Some(url) => Ok(url.clone()),
+            None => paypal_var(credentials_key, "API_URL"),
+        }
+    }
 
-    let mut clear_cache_users = Vec::new();
-    let (mut insert_user_ids, mut insert_project_ids, mut insert_payouts, mut insert_starts) =
-        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
-    for (id, project) in projects_map {
-        if let Some(value) = &multipliers.values.get(&(id as u64)) {
-            let project_multiplier: Decimal =
-                Decimal::from(**value) / Decimal::from(multipliers.sum);
-
-            let sum_splits: Decimal = project.team_members.iter().map(|x| x.1).sum();
-
-            if sum_splits > Decimal::ZERO {
-                for (user_id, split) in project.team_members {
-                    let payout: Decimal = payout * project_multiplier * (split / sum_splits);
-
-                    if payout > Decimal::ZERO {
-                        insert_user_ids.push(user_id);
-                        insert_project_ids.push(id);
-                        insert_payouts.push(payout);
-                        insert_starts.push(start);
-
-                        sqlx::query!(
-                            "
-                            UPDATE users
-                            SET balance = balance + $1
-                            WHERE id = $2
-                            ",
-                            payout,
-                            user_id
-                        )
-                        .execute(&mut *transaction)
-                        .await?;
-
-                        clear_cache_users.push(user_id);
+    /// The base URL of the Tremendous API
+    fn tremendous_api_url(&self) -> Result<String, dotenvy::Error> {
+        match &self.tremendous_api_url {
+            Some(url) => Ok(url.clone()),
+            None => dotenvy::var("TREMENDOUS_API_URL"),
+        }
+    }
+
+    fn provider(&self, method: PayoutMethodType) -> Result<&dyn PayoutProvider, ApiError> {
+        self.providers
+            .iter()
+            .find(|x| x.supported_methods().contains(&method))
+            .map(|x| &**x)
+            .ok_or_else(|| ApiError::InvalidInput(format!("Unsupported payout method: {method}")))
+    }
+
+    /// The methods sent by the provider named `name`, or `None` if there is no such provider
+    pub fn provider_methods(&self, name: &str) -> Option<&'static [PayoutMethodType]> {
+        self.providers
+            .iter()
+            .find(|x| x.name() == name)
+            .map(|x| x.supported_methods())
+    }
+
+    /// Whether each provider is in sandbox or live mode, keyed by provider name
+    pub fn provider_modes(&self) -> HashMap<&'static str, PayoutProviderMode> {
+        self.providers
+            .iter()
+            .map(|x| (x.name(), x.mode()))
+            .collect()
+    }
+
+    /// Logs the mode of every provider. Called at startup, as pointing production at a sandbox
+    /// (or the reverse) is an easy misconfiguration to miss.
+    pub fn log_provider_modes(&self) {
+        for (name, mode) in self.provider_modes() {
+            match mode {
+                PayoutProviderMode::Sandbox => {
+                    log::warn!(
+                        "Payout provider {name} is in SANDBOX mode, no real money will be sent"
+                    )
+                }
+                PayoutProviderMode::Live => log::info!("Payout provider {name} is in LIVE mode"),
+            }
+        }
+    }
+
+    /// The payout settings this queue and the payout job run with, see [`PayoutConfigSummary`]
+    pub fn config_summary(&self) -> PayoutConfigSummary {
+        self.config_summary_with(env_var)
+    }
+
+    /// Like [`config_summary`](Self::config_summary), reading variables with `var`
+    fn config_summary_with(&self, var: impl Fn(&str) -> Option<String>) -> PayoutConfigSummary {
+        let period = PayoutPeriod::ending_at_with(start_of_today(), &var);
+
+        PayoutConfigSummary {
+            budget: period.budget,
+            weekend_bonus: period.weekend_bonus,
+            weights: PayoutWeights::from_env_with(&var),
+            project_cap: PayoutProjectCap::from_env_with(&var),
+            analytics_source: PayoutAnalyticsSource::from_env_with(&var),
+            eligible_statuses: eligible_monetization_statuses_with(&var)
+                .iter()
+                .map(|x| x.as_str())
+                .collect(),
+            providers: self
+                .providers
+                .iter()
+                .filter(|x| {
+                    x.supported_methods().iter().any(|x| {
+                        *x != PayoutMethodType::Tremendous || tremendous_enabled_with(&var)
+                    })
+                })
+                .map(|x| x.name())
+                .collect(),
+            withdrawals_enabled: withdrawals_enabled_with(&var),
+            auto_retry: auto_retry_failed_payouts_with(&var),
+            review_threshold: review_threshold_with(&var),
+            max_daily_user_payout: max_daily_user_payout_with(&var),
+            withdrawal_rate_limit: WithdrawalRateLimit::from_env_with(&var),
+            run_interval: payout_run_interval_with(&var),
+        }
+    }
+
+    /// Logs every payout setting on one line. Called at startup, so operators can verify a
+    /// deployment's budget, weights, caps and providers at a glance.
+    pub fn log_config_summary(&self) {
+        log::info!("Payout configuration: {}", self.config_summary());
+    }
+
+    /// Sends a payout through the provider for its method, returning the provider's id for it
+    pub async fn create_payout(&self, request: &PayoutRequest) -> Result<Option<String>, ApiError> {
+        self.provider(request.method)?
+            .create_payout(self, request)
+            .await
+    }
+
+    /// Sends a new withdrawal through its provider, unless it is above `review_threshold`, in
+    /// which case it is held for review. Returns the withdrawal's status and provider id.
+    pub async fn submit_payout(
+        &self,
+        request: &PayoutRequest,
+        review_threshold: Option<Decimal>,
+    ) -> Result<(PayoutStatus, Option<String>), ApiError> {
+        if matches!(review_threshold, Some(x) if request.amount > x) {
+            log::info!(
+                "Holding payout {} of {} for review",
+                request.id,
+                request.amount
+            );
+            return Ok((PayoutStatus::PendingReview, None));
+        }
+
+        Ok((PayoutStatus::InTransit, self.create_payout(request).await?))
+    }
+
+    /// Submits each part of a split withdrawal, see [`submit_payout`](Self::submit_payout). The
+    /// withdrawal is reviewed as a whole: if its parts add up to more than `review_threshold`,
+    /// every part is held for review, so it can't be dodged by splitting. Otherwise it is all or
+    /// nothing: if a part fails, the parts already sent are cancelled.
+    pub async fn submit_split_payout(
+        &self,
+        requests: &[PayoutRequest],
+        review_threshold: Option<Decimal>,
+    ) -> Result<Vec<(PayoutStatus, Option<String>)>, ApiError> {
+        let total = requests.iter().map(|x| x.amount).sum::<Decimal>();
+        if matches!(review_threshold, Some(x) if total > x) {
+            log::info!(
+                "Holding a split withdrawal of {total} in {} parts for review",
+                requests.len()
+            );
+            return Ok(vec![(PayoutStatus::PendingReview, None); requests.len()]);
+        }
+
+        let mut submitted = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            match self.submit_payout(request, None).await {
+                Ok(result) => submitted.push(result),
+                Err(err) => {
+                    for (sent, (_, platform_id)) in requests.iter().zip(&submitted) {
+                        if let Some(platform_id) = platform_id {
+                            if let Err(cancel_err) =
+                                self.cancel_payout(sent.method, platform_id).await
+                            {
+                                log::error!(
+                                    "Could not cancel payout {} of failed split withdrawal {}: {}",
+                                    sent.id,
+                                    request.correlation_id,
+                                    cancel_err
+                                );
+                            }
+                        }
                     }
+
+                    return Err(err);
                 }
             }
         }
+
+        Ok(submitted)
     }
 
-    sqlx::query!(
-        "
-        INSERT INTO payouts_values (user_id, mod_id, amount, created)
-        SELECT * FROM UNNEST ($1::bigint[], $2::bigint[], $3::numeric[], $4::timestamptz[])
-        ",
-        &insert_user_ids[..],
-        &insert_project_ids[..],
-        &insert_payouts[..],
-        &insert_starts[..]
-    )
-    .execute(&mut *transaction)
-    .await?;
+    pub async fn get_payout_status(
+        &self,
+        method: PayoutMethodType,
+        platform_id: &str,
+    ) -> Result<PayoutStatus, ApiError> {
+        self.provider(method)?.get_status(self, platform_id).await
+    }
 
-    transaction.commit().await?;
+    pub async fn cancel_payout(
+        &self,
+        method: PayoutMethodType,
+        platform_id: &str,
+    ) -> Result<(), ApiError> {
+        self.provider(method)?
+            .cancel_payout(self, platform_id)
+            .await
+    }
 
-    if !clear_cache_users.is_empty() {
-        crate::database::models::User::clear_caches(
-            &clear_cache_users
-                .into_iter()
-                .map(|x| (crate::database::models::UserId(x), None))
-                .collect::<Vec<_>>(),
-            redis,
-        )
-        .await?;
+    /// Registers the payout metrics with the given registry, usually the one
+    /// served by the Prometheus middleware.
+    pub fn register_metrics(&self, registry: &Registry) -> prometheus::Result<()> {
+        self.metrics.register(registry)
     }
 
-    Ok(())
-}
+    /// Fetches a new token for the credential set `credentials_key` from PayPal
+    async fn fetch_token(&self, credentials_key: &str) -> Result<PayPalCredentials, ApiError> {
+        let client = reqwest::Client::new();
 
-// Used for testing, should be the same as the above function
-pub async fn insert_payouts(
-    insert_user_ids: Vec<i64>,
-    insert_project_ids: Vec<i64>,
-    insert_payouts: Vec<Decimal>,
-    insert_starts: Vec<DateTime<Utc>>,
-    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
-) -> sqlx::Result<PgQueryResult> {
-    sqlx::query!(
-        "
-        INSERT INTO payouts_values (user_id, mod_id, amount, created)
-        SELECT * FROM UNNEST ($1::bigint[], $2::bigint[], $3::numeric[], $4::timestamptz[])
-        ",
-        &insert_user_ids[..],
-        &insert_project_ids[..],
-        &insert_payouts[..],
-        &insert_starts[..]
-    )
-    .execute(&mut **transaction)
-    .await
+        let combined_key = format!(
+            "{}:{}",
+            paypal_var(credentials_key, "CLIENT_ID")?,
+            paypal_var(credentials_key, "CLIENT_SECRET")?
+        );
+        let formatted_key = format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(combined_key)
+        );
+
+        let mut form = HashMap::new();
+        form.insert("grant_type", "client_credentials");
+
+        #[derive(Deserialize)]
+        struct PaypalCredential {
+            access_token: String,
+            token_type: String,
+            expires_in: i64,
+        }
+
+        let credential: PaypalCredential = client
+            .post(&format!(
+                "{}oauth2/token",
+                self.paypal_api_url(credentials_key)?
+            ))
+            .header("Accept", "application/json")
+            .header("Accept-Language", "en_US")
+            .header("Authorization", formatted_key)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|_| ApiError::Payments("Error while authenticating with PayPal".to_string()))?
+            .json()
+            .await
+            .map_err(|_| {
+                ApiError::Payments(
+                    "Error while authenticating with PayPal (deser error)".to_string(),
+                )
+            })?;
+
+        Ok(PayPalCredentials {
+            access_token: credential.access_token,
+            token_type: credential.token_type,
+            expires: Utc::now() + Duration::seconds(credential.expires_in)
+                - paypal_token_refresh_jitter(),
+        })
+    }
+
+    /// Returns usable credentials for `credentials_key`, refreshing them if they are missing or
+    /// about to expire
+    async fn paypal_credentials(
+        &self,
+        credentials_key: &str,
+    ) -> Result<PayPalCredentials, ApiError> {
+        self.paypal_credentials_with(credentials_key, || self.fetch_token(credentials_key))
+            .await
+    }
+
+    /// Same as `paypal_credentials`, fetching new credentials with `fetch`
+    async fn paypal_credentials_with<F, Fut>(
+        &self,
+        credentials_key: &str,
+        fetch: F,
+    ) -> Result<PayPalCredentials, ApiError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<PayPalCredentials, ApiError>>,
+    {
+        if let Some(credentials) = self.cached_credentials(credentials_key).await {
+            return Ok(credentials);
+        }
+
+        // Only one refresh runs at a time. Requests which queued behind it find the credentials
+        // it stored once they get the lock, and use those rather than refreshing again.
+        let mut creds = self.credentials.write().await;
+        if let Some(credentials) = creds
+            .get(credentials_key)
+            .filter(|credentials| !credentials.needs_refresh(paypal_token_refresh_skew()))
+        {
+            return Ok(credentials.clone());
+        }
+
+        let new_creds = fetch().await?;
+        creds.insert(credentials_key.to_string(), new_creds.clone());
+
+        Ok(new_creds)
+    }
+
+    /// Returns the stored credentials for `credentials_key` if they do not need a refresh yet
+    async fn cached_credentials(&self, credentials_key: &str) -> Option<PayPalCredentials> {
+        self.credentials
+            .read()
+            .await
+            .get(credentials_key)
+            .filter(|credentials| !credentials.needs_refresh(paypal_token_refresh_skew()))
+            .cloned()
+    }
+
+    pub async fn make_paypal_request<T: Serialize, X: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<T>,
+        raw_text: Option<String>,
+        no_api_prefix: Option<bool>,
+        correlation_id: Option<&str>,
+    ) -> Result<X, ApiError> {
+        self.make_paypal_request_with(
+            PRIMARY_PAYPAL_CREDENTIALS,
+            method,
+            path,
+            body,
+            raw_text,
+            no_api_prefix,
+            correlation_id,
+        )
+        .await
+    }
+
+    /// Same as `make_paypal_request`, but authenticates with the credential set `credentials_key`
+    pub async fn make_paypal_request_with<T: Serialize, X: DeserializeOwned>(
+        &self,
+        credentials_key: &str,
+        method: Method,
+        path: &str,
+        body: Option<T>,
+        raw_text: Option<String>,
+        no_api_prefix: Option<bool>,
+        correlation_id: Option<&str>,
+    ) -> Result<X, ApiError> {
+        let correlation_id = correlation_id.map_or_else(new_correlation_id, str::to_string);
+        self.metrics.observe_attempt("paypal");
+
+        let result = self
+            .send_paypal_request(
+                credentials_key,
+                method,
+                path,
+                body,
+                raw_text,
+                no_api_prefix,
+                &correlation_id,
+            )
+            .await;
+        self.metrics.observe_result("paypal", result)
+    }
+
+    /// Sends a request for [`make_paypal_request_with`](Self::make_paypal_request_with), whose
+    /// outcome is recorded once it has its final result
+    async fn send_paypal_request<T: Serialize, X: DeserializeOwned>(
+        &self,
+        credentials_key: &str,
+        method: Method,
+        path: &str,
+        body: Option<T>,
+        raw_text: Option<String>,
+        no_api_prefix: Option<bool>,
+        correlation_id: &str,
+    ) -> Result<X, ProviderRequestError> {
+        let credentials = self
+            .paypal_credentials(credentials_key)
+            .await
+            .map_err(|_| {
+                ProviderRequestError::new(
+                    "auth",
+                    ApiError::Payments("Error while authenticating with PayPal".to_string()),
+                )
+            })?;
+
+        log::info!("PayPal request {correlation_id}: {method} {path}");
+
+        let url = if no_api_prefix.unwrap_or(false) {
+            path.to_string()
+        } else {
+            let api_url = self
+                .paypal_api_url(credentials_key)
+                .map_err(|err| ProviderRequestError::new("config", err))?;
+            format!("{api_url}{path}")
+        };
+
+        let client = reqwest::Client::new();
+        let mut request = paypal_request(&client, method, url, &credentials, correlation_id);
+
+        if let Some(body) = body {
+            request = request.json(&body);
+        } else if let Some(body) = raw_text {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+
+        let start = Instant::now();
+        let resp = request.send().await.map_err(|err| {
+            log::warn!("PayPal request {correlation_id} failed: {err}");
+            ProviderRequestError::new(
+                "transport",
+                ApiError::Payments("could not communicate with PayPal".to_string()),
+            )
+        })?;
+
+        let status = resp.status();
+
+        let value = resp.json::<Value>().await.map_err(|_| {
+            ProviderRequestError::new(
+                "body",
+                ApiError::Payments("could not retrieve PayPal response body".to_string()),
+            )
+        })?;
+        self.metrics.observe_latency("paypal", start);
+
+        if !status.is_success() {
+            log::warn!("PayPal request {correlation_id} returned {status}: {value}");
+            return Err(ProviderRequestError::new("api", paypal_error(value)));
+        }
+
+        serde_json::from_value(value).map_err(|err| ProviderRequestError::new("decode", err))
+    }
+
+    pub async fn make_tremendous_request<T: Serialize, X: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<T>,
+        correlation_id: Option<&str>,
+    ) -> Result<X, ApiError> {
+        // Tremendous has no request id header, so the correlation id only appears in our logs
+        let correlation_id = correlation_id.map_or_else(new_correlation_id, str::to_string);
+        log::info!("Tremendous request {correlation_id}: {method} {path}");
+        self.metrics.observe_attempt("tremendous");
+
+        let result = self
+            .send_tremendous_request(method, path, body, &correlation_id)
+            .await;
+        self.metrics.observe_result("tremendous", result)
+    }
+
+    /// Sends a request for [`make_tremendous_request`](Self::make_tremendous_request), whose
+    /// outcome is recorded once it has its final result
+    async fn send_tremendous_request<T: Serialize, X: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<T>,
+        correlation_id: &str,
+    ) -> Result<X, ProviderRequestError> {
+        let api_url = self
+            .tremendous_api_url()
+            .map_err(|err| ProviderRequestError::new("config", err))?;
+        let api_key = dotenvy::var("TREMENDOUS_API_KEY")
+            .map_err(|err| ProviderRequestError::new("config", err))?;
+
+        let client = reqwest::Client::new();
+        let mut request = client
+            .request(method, format!("{api_url}{path}"))
+            .header("Authorization", format!("Bearer {api_key}"));
+
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let start = Instant::now();
+        let resp = request.send().await.map_err(|err| {
+            log::warn!("Tremendous request {correlation_id} failed: {err}");
+            ProviderRequestError::new(
+                "transport",
+                ApiError::Payments("could not communicate with Tremendous".to_string()),
+            )
+        })?;
+
+        let status = resp.status();
+
+        let value = resp.json::<Value>().await.map_err(|_| {
+            ProviderRequestError::new(
+                "body",
+                ApiError::Payments("could not retrieve Tremendous response body".to_string()),
+            )
+        })?;
+        self.metrics.observe_latency("tremendous", start);
+
+        if !status.is_success() {
+            log::warn!("Tremendous request {correlation_id} returned {status}: {value}");
+
+            #[derive(Deserialize)]
+            struct TremendousError {
+                message: String,
+            }
+
+            let message = value
+                .get("errors")
+                .and_then(|x| serde_json::from_value::<TremendousError>(x.clone()).ok())
+                .map_or_else(
+                    || "could not retrieve Tremendous error body".to_string(),
+                    |x| x.message,
+                );
+
+            return Err(ProviderRequestError::new(
+                "api",
+                ApiError::Payments(message),
+            ));
+        }
+
+        serde_json::from_value(value).map_err(|err| ProviderRequestError::new("decode", err))
+    }
+
+    pub async fn get_payout_methods(&self) -> Result<Vec<PayoutMethod>, ApiError> {
+        self.get_payout_methods_with(tremendous_enabled()).await
+    }
+
+    /// What a withdrawal already stored with `method` and `method_id` is ordered for if it is
+    /// sent through Tremendous, from the product as it is listed now, see
+    /// [`PayoutRequest::tremendous_value`]
+    pub async fn stored_tremendous_value(
+        &self,
+        method: PayoutMethodType,
+        method_id: &str,
+        amount: Decimal,
+    ) -> Result<Option<TremendousOrderValue>, ApiError> {
+        if method != PayoutMethodType::Tremendous {
+            return Ok(None);
+        }
+
+        let product = self
+            .get_payout_methods()
+            .await?
+            .into_iter()
+            .find(|x| x.type_ == method && x.id == method_id)
+            .ok_or_else(|| {
+                ApiError::InvalidInput("Invalid payment method specified!".to_string())
+            })?;
+
+        TremendousOrderValue::for_method(&product, amount, &FxRates::from_env())
+    }
+
+    /// Like [`get_payout_methods`](Self::get_payout_methods), offering Tremendous methods only
+    /// if `tremendous` is set
+    async fn get_payout_methods_with(
+        &self,
+        tremendous: bool,
+    ) -> Result<Vec<PayoutMethod>, ApiError> {
+        async fn refresh_payout_methods(
+            queue: &PayoutsQueue,
+            tremendous: bool,
+        ) -> Result<PayoutMethods, ApiError> {
+            let mut options = queue.payout_options.write().await;
+
+            #[derive(Deserialize)]
+            pub struct TremendousResponse {
+                pub products: Vec<TremendousProduct>,
+            }
+
+            let methods = if tremendous {
+                let response = queue
+                    .make_tremendous_request::<(), TremendousResponse>(
+                        Method::GET,
+                        "products",
+                        None,
+                        None,
+                    )
+                    .await?;
+
+                response
+                    .products
+                    .into_iter()
+                    .filter_map(tremendous_payout_method)
+                    .collect::<Vec<_>>()
+            } else {
+                Vec::new()
+            };
+
+            let methods = rank_payout_methods(methods);
+
+            let new_options = PayoutMethods {
+                options: methods,
+                tremendous,
+                expires: Utc::now() + Duration::hours(6),
+            };
+
+            *options = Some(new_options.clone());
+            queue.share_payout_methods(&new_options).await;
+
+            Ok(new_options)
+        }
+
+        let options = if let Some(options) = self.stored_payout_methods(tremendous).await {
+            options
+        } else {
+            refresh_payout_methods(self, tremendous).await?.options
+        };
+
+        Ok(Self::list_payout_methods(options, tremendous))
+    }
+
+    /// The payout methods as they are currently cached by this instance or shared by another,
+    /// without fetching them. `None` if none are fresh.
+    pub async fn get_cached_payout_methods(&self) -> Option<Vec<PayoutMethod>> {
+        let tremendous = tremendous_enabled();
+        let options = self.stored_payout_methods(tremendous).await?;
+
+        Some(Self::list_payout_methods(options, tremendous))
+    }
+
+    /// The fresh payout methods cached by this instance, or else shared by another
+    async fn stored_payout_methods(&self, tremendous: bool) -> Option<Vec<PayoutMethod>> {
+        if let Some(options) = self.cached_payout_methods(tremendous).await {
+            Some(options)
+        } else {
+            self.shared_payout_methods(tremendous).await
+        }
+    }
+
+    /// `options` as they are listed, with the configured overrides, maintenance windows and
+    /// currency details applied. Tremendous methods are dropped unless `tremendous` is set.
+    fn list_payout_methods(mut options: Vec<PayoutMethod>, tremendous: bool) -> Vec<PayoutMethod> {
+        // Methods cached before Tremendous was disabled are dropped straight away
+        if !tremendous {
+            options.retain(|x| x.type_ != PayoutMethodType::Tremendous);
+        }
+
+        // Overridden here rather than when fetched, so changes apply to methods already cached
+        // or shared by other instances
+        let overrides = TremendousIntervalOverrides::from_env();
+        let mut options = options
+            .into_iter()
+            .filter_map(|x| overrides.apply(x))
+            .collect::<Vec<_>>();
+
+        let available = withdrawals_enabled();
+        let fx_rates = FxRates::from_env();
+        let min_withdrawal = min_withdrawal();
+        let maintenance = PayoutMaintenanceWindows::from_env();
+        let now = Utc::now();
+        for method in &mut options {
+            method.maintenance = maintenance.active(method, now);
+            method.available = available && method.maintenance.is_none();
+            method.annotate_currency();
+            method.usd_equivalent = fx_rates.usd_equivalent(method);
+            method.minimum = fx_rates.minimum(method, min_withdrawal);
+        }
+
+        options
+    }
+
+    /// Looks up several payout methods at once, in the order their ids are given. Ids which
+    /// match no method are reported rather than failing the lookup.
+    pub async fn get_payout_methods_by_ids(
+        &self,
+        ids: &[String],
+    ) -> Result<PayoutMethodLookup, ApiError> {
+        Ok(PayoutMethodLookup::new(
+            self.get_payout_methods().await?,
+            ids,
+        ))
+    }
+
+    /// Returns the cached payout methods if they have not expired, recording a cache hit or miss.
+    async fn cached_payout_methods(&self, tremendous: bool) -> Option<Vec<PayoutMethod>> {
+        let read = self.payout_options.read().await;
+        let options = read
+            .as_ref()
+            .filter(|options| options.is_fresh(tremendous))
+            .map(|options| options.options.clone());
+
+        if options.is_some() {
+            self.metrics.methods_cache_hits.inc();
+        } else {
+            self.metrics.methods_cache_misses.inc();
+        }
+
+        options
+    }
+
+    /// Returns the payout methods another instance shared in Redis if they are still fresh,
+    /// keeping them as this instance's copy
+    async fn shared_payout_methods(&self, tremendous: bool) -> Option<Vec<PayoutMethod>> {
+        let redis = self.redis.as_ref()?;
+
+        let shared = async {
+            redis
+                .connect()
+                .await?
+                .get_deserialized_from_json::<PayoutMethods>(PAYOUT_METHODS_NAMESPACE, "all")
+                .await
+        }
+        .await;
+
+        let shared = match shared {
+            Ok(shared) => shared.filter(|x| x.is_fresh(tremendous))?,
+            Err(err) => {
+                log::warn!("Could not read shared payout methods: {err}");
+                return None;
+            }
+        };
+
+        *self.payout_options.write().await = Some(shared.clone());
+
+        Some(shared.options)
+    }
+
+    /// Stores freshly fetched payout methods in Redis until they expire, for other instances.
+    /// Failing to is only logged, as every instance can still fetch them itself.
+    async fn share_payout_methods(&self, methods: &PayoutMethods) {
+        let Some(redis) = &self.redis else {
+            return;
+        };
+
+        let result = async {
+            redis
+                .connect()
+                .await?
+                .set_serialized_to_json(
+                    PAYOUT_METHODS_NAMESPACE,
+                    "all",
+                    methods,
+                    Some((methods.expires - Utc::now()).num_seconds().max(1)),
+                )
+                .await
+        }
+        .await;
+
+        if let Err(err) = result {
+            log::warn!("Could not share payout methods: {err}");
+        }
+    }
+
+    /// Reports which payout methods the user can withdraw with, and why the others are excluded.
+    /// Users `on_hold` can't withdraw with any. The user's `preferred_method` is listed first if
+    /// they can withdraw with it.
+    pub async fn get_payout_eligibility(
+        &self,
+        user: &crate::database::models::User,
+        on_hold: bool,
+        preferred_method: Option<&str>,
+    ) -> Result<Vec<PayoutMethodEligibility>, ApiError> {
+        let recipient = PayoutRecipient {
+            country_blocked: BlockedCountries::from_env()
+                .is_blocked(user.paypal_country.as_deref()),
+            on_hold,
+            min_withdrawal: min_withdrawal(),
+            ..PayoutRecipient::from(user)
+        };
+
+        let mut eligibility = self
+            .get_payout_methods()
+            .await?
+            .into_iter()
+            .map(|method| {
+                let reasons = recipient.ineligibility(&method);
+
+                PayoutMethodEligibility {
+                    preferred: preferred_method == Some(&*method.id),
+                    method_id: method.id,
+                    type_: method.type_,
+                    eligible: reasons.is_empty(),
+                    reasons,
+                    maintenance: method.maintenance,
+                    note: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(position) = eligibility.iter().position(|x| x.preferred) {
+            if eligibility[position].eligible {
+                let preferred = eligibility.remove(position);
+                eligibility.insert(0, preferred);
+            } else {
+                eligibility[position].note = Some(
+                    "You can not withdraw with your preferred method right now, so it is listed in its usual place"
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(eligibility)
+    }
+
+    /// Reports how far the user is through setting up withdrawals, by their
+    /// [eligibility](Self::get_payout_eligibility) for each payout method
+    pub async fn get_payout_onboarding(
+        &self,
+        user: &crate::database::models::User,
+        on_hold: bool,
+    ) -> Result<PayoutOnboarding, ApiError> {
+        let eligibility = self.get_payout_eligibility(user, on_hold, None).await?;
+
+        Ok(PayoutOnboarding {
+            destination_set: user.paypal_id.is_some()
+                || user.venmo_handle.is_some()
+                || user.email.is_some(),
+            country_set: user.paypal_country.is_some(),
+            verification_complete: eligibility
+                .iter()
+                .any(|x| !x.reasons.contains(&PayoutIneligibility::AccountUnverified)),
+            eligible: eligibility.iter().any(|x| x.eligible),
+        })
+    }
+
+    pub fn lock_user_payouts(&self, user_id: UserId) -> Arc<Mutex<()>> {
+        self.payouts_locks
+            .entry(user_id)
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone()
+    }
+
+    /// Whether the queue is draining for shutdown, in which case no new withdrawals should be
+    /// started
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Waits for the withdrawals in flight to finish before the service exits, so none are
+    /// dropped halfway through a provider call. Every withdrawal holds its user's payout lock
+    /// while it runs, so each lock is taken in turn, and kept so that no more withdrawals start.
+    /// Withdrawals only check [`is_draining`](Self::is_draining) once they hold their lock, so
+    /// one either sees the queue draining or is waited for. Returns `false` if some were still
+    /// running after `timeout`; their transactions are rolled back when the process exits.
+    pub async fn drain(&self, timeout: std::time::Duration) -> bool {
+        self.draining.store(true, Ordering::SeqCst);
+
+        let locks = self
+            .payouts_locks
+            .iter()
+            .map(|x| x.value().clone())
+            .collect::<Vec<_>>();
+
+        let drained = tokio::time::timeout(timeout, async {
+            for lock in locks {
+                let guard = lock.lock_owned().await;
+                self.drained_locks.lock().await.push(guard);
+            }
+        })
+        .await;
+
+        drained.is_ok()
+    }
+}
+
+/// Orders Tremendous methods with the preferred gift cards first and the least preferred last,
+/// each group by name and then id, then puts PayPal and Venmo ahead of them all. Each method's
+/// `order` is set to its position.
+fn rank_payout_methods(mut methods: Vec<PayoutMethod>) -> Vec<PayoutMethod> {
+    const UPRANK_IDS: &[&str] = &["ET0ZVETV5ILN", "Q24BD9EZ332JT", "UIL1ZYJU5MKN"];
+    const DOWNRANK_IDS: &[&str] = &["EIPF8Q00EMM1", "OU2MWXYWPNWQ"];
+
+    methods.sort_by(|a, b| {
+        let a_top = UPRANK_IDS.contains(&&*a.id);
+        let a_bottom = DOWNRANK_IDS.contains(&&*a.id);
+        let b_top = UPRANK_IDS.contains(&&*b.id);
+        let b_bottom = DOWNRANK_IDS.contains(&&*b.id);
+
+        match (a_top, a_bottom, b_top, b_bottom) {
+            (true, _, true, _) => a.name.cmp(&b.name), // Both in top_priority: sort alphabetically
+            (_, true, _, true) => a.name.cmp(&b.name), // Both in bottom_priority: sort alphabetically
+            (true, _, _, _) => std::cmp::Ordering::Less, // a in top_priority: a comes first
+            (_, _, true, _) => std::cmp::Ordering::Greater, // b in top_priority: b comes first
+            (_, true, _, _) => std::cmp::Ordering::Greater, // a in bottom_priority: b comes first
+            (_, _, _, true) => std::cmp::Ordering::Less, // b in bottom_priority: a comes first
+            (_, _, _, _) => a.name.cmp(&b.name),       // Neither in priority: sort alphabetically
+        }
+        // Products can share a name, and Tremendous lists them in no set order
+        .then_with(|| a.id.cmp(&b.id))
+    });
+
+    {
+        let paypal_us = PayoutMethod {
+            id: "paypal_us".to_string(),
+            type_: PayoutMethodType::PayPal,
+            name: "PayPal".to_string(),
+            supported_countries: vec!["US".to_string()],
+            supported_currencies: vec!["USD".to_string()],
+            image_url: None,
+            interval: PayoutInterval::Standard {
+                min: Decimal::from(1) / Decimal::from(4),
+                max: Decimal::from(100_000),
+            },
+            fee: PayoutMethodFee {
+                percentage: Decimal::from(2) / Decimal::from(100),
+                min: Decimal::from(1) / Decimal::from(4),
+                max: Some(Decimal::from(1)),
+            },
+            usd_equivalent: None,
+            order: 0,
+            available: true,
+            currency: None,
+            decimal_places: 2,
+            description: None,
+            disclosure: None,
+            maintenance: None,
+            minimum: None,
+        };
+
+        let mut venmo = paypal_us.clone();
+        venmo.id = "venmo".to_string();
+        venmo.name = "Venmo".to_string();
+        venmo.type_ = PayoutMethodType::Venmo;
+
+        methods.insert(0, paypal_us);
+        methods.insert(1, venmo)
+    }
+
+    methods.insert(
+        2,
+        PayoutMethod {
+            id: "paypal_in".to_string(),
+            type_: PayoutMethodType::PayPal,
+            name: "PayPal".to_string(),
+            supported_countries: rust_iso3166::ALL
+                .iter()
+                .filter(|x| x.alpha2 != "US")
+                .map(|x| x.alpha2.to_string())
+                .collect(),
+            supported_currencies: vec!["USD".to_string()],
+            image_url: None,
+            interval: PayoutInterval::Standard {
+                min: Decimal::from(1) / Decimal::from(4),
+                max: Decimal::from(100_000),
+            },
+            fee: PayoutMethodFee {
+                percentage: Decimal::from(2) / Decimal::from(100),
+                min: Decimal::ZERO,
+                max: Some(Decimal::from(20)),
+            },
+            usd_equivalent: None,
+            order: 0,
+            available: true,
+            currency: None,
+            decimal_places: 2,
+            description: None,
+            disclosure: None,
+            maintenance: None,
+            minimum: None,
+        },
+    );
+
+    for (order, method) in methods.iter_mut().enumerate() {
+        method.order = order as u32;
+    }
+
+    methods
+}
+
+/// How payout amounts are rounded to cents when they are finalized, configured by
+/// `PAYOUTS_ROUNDING_MODE` (`half_up` or `half_even`). Defaults to `half_up`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PayoutRoundingMode {
+    HalfUp,
+    HalfEven,
+}
+
+impl PayoutRoundingMode {
+    pub fn from_env() -> Self {
+        match dotenvy::var("PAYOUTS_ROUNDING_MODE").as_deref() {
+            Ok("half_even") => PayoutRoundingMode::HalfEven,
+            _ => PayoutRoundingMode::HalfUp,
+        }
+    }
+
+    pub fn round(&self, amount: Decimal) -> Decimal {
+        amount.round_dp_with_strategy(2, self.strategy())
+    }
+
+    /// How midpoints are rounded, for rounding withdrawal amounts to cents
+    pub fn strategy(&self) -> RoundingStrategy {
+        match self {
+            PayoutRoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            PayoutRoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+        }
+    }
+}
+
+/// What withdrawing an amount with a payout method would cost
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PayoutFeeComparison {
+    pub method_id: String,
+    #[serde(rename = "type")]
+    pub type_: PayoutMethodType,
+    pub name: String,
+    #[serde(with = "crate::models::payouts::money")]
+    pub fee: Decimal,
+    /// The amount sent after the fee
+    #[serde(with = "crate::models::payouts::money")]
+    pub net: Decimal,
+    /// ISO 4217 code of the currency `fee` and `net` are in
+    pub currency: String,
+    /// The number of decimal places amounts in `currency` are rounded and formatted to
+    pub decimal_places: u32,
+}
+
+/// Compares the fees for withdrawing `amount` with each of `methods`, highest net payout first.
+/// Methods which are unavailable, not offered in `country`, or cannot send `amount` are left out.
+pub fn compare_payout_fees(
+    methods: &[PayoutMethod],
+    amount: Gross,
+    country: Option<&str>,
+    rounding: PayoutRoundingMode,
+    fee_caps: &PayPalFeeCaps,
+) -> Vec<PayoutFeeComparison> {
+    let mut comparisons = methods
+        .iter()
+        .filter(|x| x.available && x.interval.contains(amount.amount()))
+        .filter(|x| {
+            country.map_or(true, |country| {
+                x.supported_countries.iter().any(|x| x == country)
+            })
+        })
+        .filter_map(|x| {
+            let fee = fee_caps
+                .fee(x, amount, country)
+                .round_to_cents(rounding.strategy());
+            let net = (amount - fee).round_to_cents(rounding.strategy());
+
+            (net.amount() > Decimal::ZERO).then(|| PayoutFeeComparison {
+                method_id: x.id.clone(),
+                type_: x.type_,
+                name: x.name.clone(),
+                fee: fee.amount(),
+                net: net.amount(),
+                currency: BALANCE_CURRENCY.to_string(),
+                decimal_places: currency_decimal_places(BALANCE_CURRENCY),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    comparisons.sort_by(|a, b| b.net.cmp(&a.net));
+    comparisons
+}
+
+/// How a payout method's fee is worked out, for showing every method's fees side by side
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutFeeSchedule {
+    pub method_id: String,
+    #[serde(rename = "type")]
+    pub type_: PayoutMethodType,
+    pub name: String,
+    /// The percentage of the amount withdrawn charged, between its minimum and maximum
+    pub fee: PayoutMethodFee,
+    /// ISO 4217 code of the currency the fee's minimum and maximum are in
+    pub currency: String,
+}
+
+/// The fee schedule of each of `methods`, in US dollars as balances are withdrawn in. Country
+/// caps on international PayPal fees, see [`PayPalFeeCaps`], are not included.
+pub fn payout_fee_schedules(methods: &[PayoutMethod]) -> Vec<PayoutFeeSchedule> {
+    methods
+        .iter()
+        .map(|x| PayoutFeeSchedule {
+            method_id: x.id.clone(),
+            type_: x.type_,
+            name: x.name.clone(),
+            fee: x.balance_fee().clone(),
+            currency: BALANCE_CURRENCY.to_string(),
+        })
+        .collect()
+}
+
+/// The most a user can withdraw with a payout method at once, for withdrawing everything
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutMaxWithdrawal {
+    pub method_id: String,
+    #[serde(rename = "type")]
+    pub type_: PayoutMethodType,
+    pub name: String,
+    /// The amount to request, taken from the balance
+    #[serde(with = "crate::models::payouts::money")]
+    pub amount: Decimal,
+    #[serde(with = "crate::models::payouts::money")]
+    pub fee: Decimal,
+    #[serde(with = "crate::models::payouts::money")]
+    pub withheld: Decimal,
+    /// The amount sent after the fee and withheld tax
+    #[serde(with = "crate::models::payouts::money")]
+    pub net: Decimal,
+    /// ISO 4217 code of the currency the amounts are in
+    pub currency: String,
+}
+
+/// The most a user with `balance`, in `country`, can withdraw with each of `methods`: their
+/// balance, limited to the method's maximum, or the largest fixed value it covers. Methods which
+/// are unavailable, not offered in `country`, or which the balance is too small for are left out.
+pub fn max_withdrawals(
+    methods: &[PayoutMethod],
+    balance: Decimal,
+    country: Option<&str>,
+    rounding: PayoutRoundingMode,
+    withholding: &PayoutWithholding,
+    fee_caps: &PayPalFeeCaps,
+) -> Vec<PayoutMaxWithdrawal> {
+    methods
+        .iter()
+        .filter(|x| x.available)
+        .filter(|x| {
+            country.map_or(true, |country| {
+                x.supported_countries.iter().any(|x| x == country)
+            })
+        })
+        .filter_map(|x| {
+            let amount = Gross::new(x.balance_interval().max_withdrawable(balance)?);
+            let amounts =
+                WithdrawalAmounts::calculate(x, amount, country, rounding, withholding, fee_caps)
+                    .ok()?;
+
+            Some(PayoutMaxWithdrawal {
+                method_id: x.id.clone(),
+                type_: x.type_,
+                name: x.name.clone(),
+                amount: amount.amount(),
+                fee: amounts.fee.amount(),
+                withheld: amounts.withheld.amount(),
+                net: amounts.transfer.amount(),
+                currency: BALANCE_CURRENCY.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// How a withdrawal is divided between the fee, tax withheld and the amount sent
+#[derive(Clone, Debug, PartialEq)]
+pub struct WithdrawalAmounts {
+    pub fee: Fee,
+    pub withheld: Withheld,
+    /// The amount sent, after the fee and withheld tax
+    pub transfer: Net,
+}
+
+impl WithdrawalAmounts {
+    /// Splits `amount` withdrawn with `method` by a recipient in `country`. Fails if nothing
+    /// would be left to send, or tax can't be withheld as the country is unknown.
+    pub fn calculate(
+        method: &PayoutMethod,
+        amount: Gross,
+        country: Option<&str>,
+        rounding: PayoutRoundingMode,
+        withholding: &PayoutWithholding,
+        fee_caps: &PayPalFeeCaps,
+    ) -> Result<Self, ApiError> {
+        withholding.check(country)?;
+
+        let strategy = rounding.strategy();
+        let fee = fee_caps
+            .fee(method, amount, country)
+            .round_to_cents(strategy);
+        let net = amount - fee;
+        let withheld = withholding.calculate(country, net).round_to_cents(strategy);
+
+        let transfer = (net - withheld).round_to_cents(strategy);
+        if transfer.amount() <= Decimal::ZERO {
+            return Err(ApiError::InvalidInput(
+                "You need to withdraw more to cover the fee!".to_string(),
+            ));
+        }
+
+        Ok(WithdrawalAmounts {
+            fee,
+            withheld,
+            transfer,
+        })
+    }
+}
+
+/// Checks that split withdrawal destinations are distinct, and that their percentages are
+/// positive, in hundredths at most, and add up to 100
+pub fn validate_payout_destinations(destinations: &[PayoutDestination]) -> Result<(), ApiError> {
+    if destinations.is_empty() {
+        return Err(ApiError::InvalidInput(
+            "At least one payout destination is required!".to_string(),
+        ));
+    }
+
+    if !destinations.iter().map(|x| &x.method_id).all_unique() {
+        return Err(ApiError::InvalidInput(
+            "Each payout method can only be used once!".to_string(),
+        ));
+    }
+
+    if destinations
+        .iter()
+        .any(|x| x.percentage <= Decimal::ZERO || x.percentage.round_dp(2) != x.percentage)
+    {
+        return Err(ApiError::InvalidInput(
+            "Payout percentages must be positive with at most two decimal places!".to_string(),
+        ));
+    }
+
+    if destinations.iter().map(|x| x.percentage).sum::<Decimal>() != Decimal::ONE_HUNDRED {
+        return Err(ApiError::InvalidInput(
+            "Payout percentages must add up to 100!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Divides `amount` between `destinations` by their percentages. Each part is rounded down to
+/// the cent and the leftover cents go to the first destination, so the parts add up to `amount`.
+pub fn split_withdrawal(amount: Decimal, destinations: &[PayoutDestination]) -> Vec<Decimal> {
+    let mut parts = destinations
+        .iter()
+        .map(|x| {
+            (amount * x.percentage / Decimal::ONE_HUNDRED)
+                .round_dp_with_strategy(2, RoundingStrategy::ToZero)
+        })
+        .collect::<Vec<_>>();
+
+    let remainder = amount - parts.iter().sum::<Decimal>();
+    if let Some(first) = parts.first_mut() {
+        *first += remainder;
+    }
+
+    parts
+}
+
+/// Tax withholding rates by recipient country, configured by `PAYOUTS_TAX_WITHHOLDING` as
+/// comma separated `COUNTRY:RATE` pairs, ex: `US:0.3,GB:0.2`. Nothing is withheld for countries
+/// which are not listed. Recipients without a known country can't withdraw while any rates are
+/// configured, as what to withhold from them can't be known, see [`check`](Self::check).
+#[derive(Clone, Debug, Default)]
+pub struct PayoutWithholding {
+    rates: HashMap<String, Decimal>,
+}
+
+impl PayoutWithholding {
+    pub fn new(rates: HashMap<String, Decimal>) -> Self {
+        PayoutWithholding { rates }
+    }
+
+    pub fn from_env() -> Self {
+        PayoutWithholding {
+            rates: parse_code_decimals("PAYOUTS_TAX_WITHHOLDING", |rate| {
+                (Decimal::ZERO..=Decimal::ONE).contains(&rate)
+            }),
+        }
+    }
+
+    /// The fraction of a payout withheld for recipients in `country`
+    pub fn rate(&self, country: Option<&str>) -> Decimal {
+        country
+            .and_then(|x| self.rates.get(&x.to_uppercase()))
+            .copied()
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// The amount withheld from `amount`, which has the provider fee deducted, before rounding
+    pub fn calculate(&self, country: Option<&str>, amount: Net) -> Withheld {
+        amount.withhold(self.rate(country))
+    }
+
+    /// Fails with [`ApiError::Compliance`] if tax is withheld from anyone but the country of
+    /// the recipient, the same one [`BlockedCountries`] checks, is unknown
+    pub fn check(&self, country: Option<&str>) -> Result<(), ApiError> {
+        if country.is_none() && !self.rates.is_empty() {
+            return Err(ApiError::Compliance(
+                "Payouts are only available once your country is known. Link your PayPal account to verify it."
+                    .to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The least that can be withdrawn at once in US dollars with any method, configured by
+/// `PAYOUTS_MIN_WITHDRAWAL`. Methods in other currencies list it converted to their currency as
+/// their [`minimum`](PayoutMethod::minimum). Unset, each method's own minimum applies.
+pub fn min_withdrawal() -> Option<Decimal> {
+    parse_var("PAYOUTS_MIN_WITHDRAWAL")
+}
+
+/// Regulatory caps on the fee for international PayPal (`paypal_in`) payouts by recipient
+/// country, configured by `PAYOUTS_PAYPAL_FEE_CAPS` as comma separated `COUNTRY:CAP` pairs, ex:
+/// `BR:5,IN:10`. Countries which are not listed only have the method's own fee cap.
+#[derive(Clone, Debug, Default)]
+pub struct PayPalFeeCaps {
+    caps: HashMap<String, Decimal>,
+}
+
+impl PayPalFeeCaps {
+    pub fn new(caps: HashMap<String, Decimal>) -> Self {
+        PayPalFeeCaps { caps }
+    }
+
+    pub fn from_env() -> Self {
+        PayPalFeeCaps {
+            caps: parse_code_decimals("PAYOUTS_PAYPAL_FEE_CAPS", |cap| cap >= Decimal::ZERO),
+        }
+    }
+
+    /// The fee charged for withdrawing `amount` with `method` to `country`, before rounding.
+    /// International PayPal fees are clamped to the country's cap.
+    pub fn fee(&self, method: &PayoutMethod, amount: Gross, country: Option<&str>) -> Fee {
+        let fee = method.fee.calculate(amount);
+        if method.id != "paypal_in" {
+            return fee;
+        }
+
+        let country = country.map(|x| x.to_uppercase());
+        match country.as_ref().and_then(|x| self.caps.get(x)) {
+            Some(cap) if fee.amount() > *cap => {
+                log::info!(
+                    "Clamped PayPal fee of {} for {} to the cap of {cap} for {}",
+                    fee.amount(),
+                    amount.amount(),
+                    country.as_deref().unwrap_or_default()
+                );
+                fee.capped(*cap)
+            }
+            _ => fee,
+        }
+    }
+}
+
+/// Weights payouts toward recently published projects to help them get discovered, configured
+/// by `PAYOUTS_DISCOVERY_BOOST` (the multiplier applied to their views and downloads) and
+/// `PAYOUTS_DISCOVERY_BOOST_DAYS` (how long a project counts as new, 30 days by default). The
+/// boost comes out of the same daily budget, so it is paid for by older projects.
+#[derive(Clone, Debug)]
+pub struct DiscoveryBoost {
+    pub factor: Decimal,
+    pub max_age: Duration,
+}
+
+impl DiscoveryBoost {
+    /// The configured boost, if there is one
+    pub fn from_env() -> Option<Self> {
+        let factor: Decimal = parse_var("PAYOUTS_DISCOVERY_BOOST")?;
+        if factor < Decimal::ONE {
+            log::warn!("Ignoring PAYOUTS_DISCOVERY_BOOST of {factor}, boosts must be at least 1");
+            return None;
+        }
+
+        Some(DiscoveryBoost {
+            factor,
+            max_age: Duration::days(parse_var("PAYOUTS_DISCOVERY_BOOST_DAYS").unwrap_or(30)),
+        })
+    }
+
+    /// The multiplier for a project published at `published`, as of the payout day starting at
+    /// `day`
+    pub fn multiplier(&self, published: DateTime<Utc>, day: DateTime<Utc>) -> Decimal {
+        if day - published < self.max_age {
+            self.factor
+        } else {
+            Decimal::ONE
+        }
+    }
+}
+
+/// Weights each project's views and downloads by how often its views turn into downloads,
+/// relative to every project's rate that day, favoring projects users actually install. Enabled
+/// by `PAYOUTS_CONVERSION_WEIGHTING=true` and bounded by `PAYOUTS_CONVERSION_WEIGHT_MIN` and
+/// `PAYOUTS_CONVERSION_WEIGHT_MAX`, 0.5 and 2 by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConversionWeighting {
+    pub min: Decimal,
+    pub max: Decimal,
+}
+
+impl Default for ConversionWeighting {
+    fn default() -> Self {
+        ConversionWeighting {
+            min: Decimal::from(1) / Decimal::from(2),
+            max: Decimal::from(2),
+        }
+    }
+}
+
+impl ConversionWeighting {
+    /// The configured weighting, if it is enabled
+    pub fn from_env() -> Option<Self> {
+        if !parse_var("PAYOUTS_CONVERSION_WEIGHTING").unwrap_or(false) {
+            return None;
+        }
+
+        let default = Self::default();
+        let weighting = ConversionWeighting {
+            min: parse_var("PAYOUTS_CONVERSION_WEIGHT_MIN").unwrap_or(default.min),
+            max: parse_var("PAYOUTS_CONVERSION_WEIGHT_MAX").unwrap_or(default.max),
+        };
+        if weighting.min > Decimal::ONE || weighting.max < Decimal::ONE {
+            log::warn!(
+                "Ignoring conversion weighting bounds of {} to {}, they must include 1",
+                weighting.min,
+                weighting.max
+            );
+            return Some(default);
+        }
+
+        Some(weighting)
+    }
+
+    /// Downloads per view, if there were any views
+    fn rate(views: u64, downloads: u64) -> Option<Decimal> {
+        (views > 0).then(|| Decimal::from(downloads) / Decimal::from(views))
+    }
+
+    /// The multiplier for a project with `views` and `downloads` on a day where every project
+    /// converted at `overall`. Projects without views, or days without downloads, are left as
+    /// they are.
+    pub fn factor(&self, views: u64, downloads: u64, overall: Option<Decimal>) -> Decimal {
+        match (Self::rate(views, downloads), overall) {
+            (Some(rate), Some(overall)) if overall > Decimal::ZERO => {
+                (rate / overall).clamp(self.min, self.max)
+            }
+            _ => Decimal::ONE,
+        }
+    }
+}
+
+const WITHDRAWAL_RATE_LIMIT_NAMESPACE: &str = "withdrawal_rate_limit";
+
+/// How many withdrawals a user may request within a window, complementing the per-user lock
+/// (which only serializes withdrawals) and idempotency keys (which only dedupe retries).
+/// Configured by `PAYOUTS_WITHDRAWAL_RATE_LIMIT` and `PAYOUTS_WITHDRAWAL_RATE_WINDOW` (seconds),
+/// defaulting to 10 withdrawals an hour.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WithdrawalRateLimit {
+    pub max: i64,
+    pub window_seconds: i64,
+}
+
+impl WithdrawalRateLimit {
+    pub fn from_env() -> Self {
+        Self::from_env_with(env_var)
+    }
+
+    fn from_env_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        WithdrawalRateLimit {
+            max: parse_var_with(&var, "PAYOUTS_WITHDRAWAL_RATE_LIMIT").unwrap_or(10),
+            window_seconds: parse_var_with(&var, "PAYOUTS_WITHDRAWAL_RATE_WINDOW")
+                .unwrap_or(60 * 60),
+        }
+    }
+
+    /// Counts a withdrawal by `user_id`, failing with [`ApiError::RateLimited`] once they have
+    /// made more than `max` in the current window. The count resets when the window ends.
+    pub async fn check(&self, redis: &RedisPool, user_id: UserId) -> Result<(), ApiError> {
+        let mut redis = redis.connect().await?;
+        let count = redis
+            .increment(
+                WITHDRAWAL_RATE_LIMIT_NAMESPACE,
+                &user_id.to_string(),
+                self.window_seconds,
+            )
+            .await?;
+
+        if count > self.max {
+            return Err(ApiError::RateLimited(
+                "You have made too many withdrawals recently. Please try again later.".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Countries payouts may not be sent to for sanctions or compliance reasons, whichever methods
+/// the providers offer there. Configured by `PAYOUTS_BLOCKED_COUNTRIES` as comma separated
+/// ISO 3166-1 alpha-2 codes, ex: `CU,IR,KP`. While any are blocked, recipients whose country is
+/// unknown are too, as only PayPal reports a country and they could be in a blocked one.
+#[derive(Clone, Debug, Default)]
+pub struct BlockedCountries {
+    countries: HashSet<String>,
+}
+
+impl BlockedCountries {
+    /// Reads the blocked countries, skipping any codes that are not countries. Those are
+    /// reported when the configuration is checked at startup.
+    pub fn from_env() -> Self {
+        Self::parse(&dotenvy::var("PAYOUTS_BLOCKED_COUNTRIES").unwrap_or_default()).0
+    }
+
+    /// Parses a comma separated list of country codes, returning the codes that are not
+    /// ISO 3166-1 alpha-2 countries separately
+    pub fn parse(value: &str) -> (Self, Vec<String>) {
+        let mut blocked = BlockedCountries::default();
+        let mut invalid = Vec::new();
+
+        for code in value.split(',').map(|x| x.trim()).filter(|x| !x.is_empty()) {
+            let code = code.to_uppercase();
+
+            if rust_iso3166::ALL.iter().any(|x| x.alpha2 == code) {
+                blocked.countries.insert(code);
+            } else {
+                invalid.push(code);
+            }
+        }
+
+        (blocked, invalid)
+    }
+
+    /// Whether payouts to `country` are blocked
+    pub fn is_blocked(&self, country: Option<&str>) -> bool {
+        match country {
+            Some(country) => self.countries.contains(&country.to_uppercase()),
+            None => !self.countries.is_empty(),
+        }
+    }
+
+    /// Fails with [`ApiError::Compliance`] if payouts to `country` are blocked
+    pub fn check(&self, country: Option<&str>) -> Result<(), ApiError> {
+        if !self.is_blocked(country) {
+            return Ok(());
+        }
+
+        Err(ApiError::Compliance(match country {
+            Some(country) => {
+                format!("Payouts to {country} are not available for compliance reasons")
+            }
+            None => "Payouts are only available once your country is known. Link your PayPal account to verify it.".to_string(),
+        }))
+    }
+}
+
+/// A period a payout method is unavailable for, ex: a provider's scheduled maintenance
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutMaintenanceWindow {
+    /// The id of the method, ex: `paypal_us`, or a method type to cover all of its methods, ex:
+    /// `tremendous`
+    pub method: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Why the method is unavailable, shown to users
+    pub reason: String,
+}
+
+/// Scheduled periods payout methods are unavailable for, configured by
+/// `PAYOUTS_MAINTENANCE_WINDOWS` as a JSON array of [`PayoutMaintenanceWindow`]s, ex:
+/// `[{"method": "paypal_us", "start": "2024-01-06T02:00:00Z", "end": "2024-01-06T04:00:00Z",
+/// "reason": "PayPal maintenance"}]`. Methods are marked unavailable while in one, so they are
+/// neither eligible nor can be withdrawn with.
+#[derive(Clone, Debug, Default)]
+pub struct PayoutMaintenanceWindows {
+    windows: Vec<PayoutMaintenanceWindow>,
+}
+
+impl PayoutMaintenanceWindows {
+    pub fn new(windows: Vec<PayoutMaintenanceWindow>) -> Self {
+        PayoutMaintenanceWindows { windows }
+    }
+
+    pub fn from_env() -> Self {
+        match dotenvy::var("PAYOUTS_MAINTENANCE_WINDOWS") {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|err| {
+                log::warn!("Ignoring invalid PAYOUTS_MAINTENANCE_WINDOWS: {err}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value).map(Self::new)
+    }
+
+    /// The maintenance `method` is under at `now`, if any. If windows overlap, the one ending
+    /// last is reported, as the method is only back once it ends.
+    pub fn active(&self, method: &PayoutMethod, now: DateTime<Utc>) -> Option<PayoutMaintenance> {
+        self.windows
+            .iter()
+            .filter(|x| x.method == method.id || x.method == method.type_.as_str())
+            .filter(|x| x.start <= now && now < x.end)
+            .max_by_key(|x| x.end)
+            .map(|x| PayoutMaintenance {
+                reason: x.reason.clone(),
+                until: x.end,
+            })
+    }
+}
+
+/// Fails with [`ApiError::ServiceUnavailable`] if `method` is down for maintenance
+pub fn check_payout_maintenance(method: &PayoutMethod) -> Result<(), ApiError> {
+    match &method.maintenance {
+        Some(maintenance) => Err(ApiError::ServiceUnavailable(format!(
+            "{} is unavailable until {}: {}",
+            method.name,
+            maintenance.until.to_rfc3339(),
+            maintenance.reason
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Fails if `user` can't start a withdrawal right now: payouts to their country are blocked,
+/// their payouts are on hold, or withdrawals are disabled or draining for shutdown. Every way of
+/// withdrawing checks this before sending anything.
+pub async fn check_withdrawals_allowed(
+    queue: &PayoutsQueue,
+    user: &crate::database::models::User,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+) -> Result<(), ApiError> {
+    BlockedCountries::from_env().check(user.paypal_country.as_deref())?;
+
+    if let Some(hold) =
+        crate::database::models::payout_hold_item::PayoutHold::get_active(user.id, exec).await?
+    {
+        return Err(ApiError::Compliance(format!(
+            "Your payouts are on hold until {}. Please contact support for more information.",
+            hold.expires.format("%Y-%m-%d")
+        )));
+    }
+
+    if !withdrawals_enabled() || queue.is_draining() {
+        return Err(ApiError::ServiceUnavailable(
+            "Withdrawals are temporarily disabled. Please try again later.".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fails if `amount` of a balance can't be withdrawn with `method`: it is down for maintenance,
+/// the amount is not one the method can send, or it is under the [`min_withdrawal`]
+pub fn check_withdrawal_method(method: &PayoutMethod, amount: Decimal) -> Result<(), ApiError> {
+    check_payout_maintenance(method)?;
+
+    if !method.balance_interval().contains(amount) {
+        return Err(ApiError::InvalidInput(format!(
+            "{amount} can not be withdrawn with this method!"
+        )));
+    }
+
+    match min_withdrawal() {
+        Some(min) if amount < min => Err(ApiError::InvalidInput(format!(
+            "amount: must be at least {min} to withdraw"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The parts of a user relevant to whether they can withdraw with a payout method
+pub struct PayoutRecipient {
+    pub country: Option<String>,
+    pub balance: Decimal,
+    pub email_verified: bool,
+    pub paypal_linked: bool,
+    pub venmo_linked: bool,
+    /// Whether payouts to `country` are blocked, see [`BlockedCountries`]
+    pub country_blocked: bool,
+    /// Whether the recipient's payouts are on hold, see [`crate::models::payouts::UserPayoutHold`]
+    pub on_hold: bool,
+    /// The least that can be withdrawn at once in US dollars, see [`min_withdrawal`]
+    pub min_withdrawal: Option<Decimal>,
+}
+
+impl From<&crate::database::models::User> for PayoutRecipient {
+    fn from(user: &crate::database::models::User) -> Self {
+        PayoutRecipient {
+            country: user.paypal_country.clone(),
+            balance: user.balance,
+            email_verified: user.email.is_some() && user.email_verified,
+            paypal_linked: user.paypal_id.is_some() && user.paypal_country.is_some(),
+            venmo_linked: user.venmo_handle.is_some(),
+            country_blocked: false,
+            on_hold: false,
+            min_withdrawal: None,
+        }
+    }
+}
+
+impl PayoutRecipient {
+    /// Every reason the recipient cannot withdraw with `method`, empty if they can
+    pub fn ineligibility(&self, method: &PayoutMethod) -> Vec<PayoutIneligibility> {
+        let mut reasons = Vec::new();
+
+        if self.country_blocked {
+            reasons.push(PayoutIneligibility::CountryBlocked);
+        }
+
+        if self.on_hold {
+            reasons.push(PayoutIneligibility::OnHold);
+        }
+
+        if let Some(country) = &self.country {
+            if !method.supported_countries.contains(country) {
+                reasons.push(PayoutIneligibility::CountryUnsupported);
+            }
+        }
+
+        // The balance is in US dollars, so it is compared to the method's minimum in them
+        let minimum = method.balance_interval().min();
+        if self.balance < self.min_withdrawal.map_or(minimum, |x| minimum.max(x)) {
+            reasons.push(PayoutIneligibility::UnderThreshold);
+        }
+
+        let verified = match method.type_ {
+            PayoutMethodType::Venmo => self.venmo_linked,
+            PayoutMethodType::PayPal => self.paypal_linked,
+            PayoutMethodType::Tremendous => self.email_verified,
+            PayoutMethodType::Unknown => false,
+        };
+        if !verified {
+            reasons.push(PayoutIneligibility::AccountUnverified);
+        }
+
+        if method.maintenance.is_some() {
+            reasons.push(PayoutIneligibility::Maintenance);
+        } else if !method.available {
+            reasons.push(PayoutIneligibility::ProviderDown);
+        }
+
+        reasons
+    }
+}
+
+/// The payout methods found for a list of ids
+#[derive(Debug, Default)]
+pub struct PayoutMethodLookup {
+    /// The methods found, in the order of their ids
+    pub methods: Vec<PayoutMethod>,
+    /// The ids no method has, in the order given
+    pub unknown_ids: Vec<String>,
+}
+
+impl PayoutMethodLookup {
+    pub fn new(methods: Vec<PayoutMethod>, ids: &[String]) -> Self {
+        let by_id = methods
+            .into_iter()
+            .map(|x| (x.id.clone(), x))
+            .collect::<HashMap<_, _>>();
+
+        let mut lookup = PayoutMethodLookup::default();
+        for id in ids {
+            match by_id.get(id) {
+                Some(method) => lookup.methods.push(method.clone()),
+                None => lookup.unknown_ids.push(id.clone()),
+            }
+        }
+
+        lookup
+    }
+}
+
+/// A budgeting period for creator payouts. The budget is split evenly across the period's
+/// days, with `weekend_days` weighted by `weekend_bonus`.
+#[derive(Clone, Debug)]
+pub struct PayoutPeriod {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub budget: Decimal,
+    pub weekend_bonus: Decimal,
+    pub weekend_days: Vec<Weekday>,
+}
+
+impl PayoutPeriod {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>, budget: Decimal) -> Self {
+        PayoutPeriod {
+            start,
+            end,
+            budget,
+            weekend_bonus: Decimal::from(5) / Decimal::from(4),
+            weekend_days: vec![Weekday::Sat, Weekday::Sun],
+        }
+    }
+
+    /// The 28 day period ending with the most recently completed day, budgeted by
+    /// `PAYOUTS_BUDGET`
+    pub fn current() -> Self {
+        Self::ending_at(start_of_today())
+    }
+
+    /// The 28 day period ending at `end`, budgeted by `PAYOUTS_BUDGET`. Weekend days are
+    /// weighted by `PAYOUTS_WEEKEND_BONUS` when it is set, and are the days listed in
+    /// `PAYOUTS_WEEKEND_DAYS`, ex: `fri,sat`, when it is set.
+    pub fn ending_at(end: DateTime<Utc>) -> Self {
+        Self::ending_at_with(end, env_var)
+    }
+
+    fn ending_at_with(end: DateTime<Utc>, var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut period = PayoutPeriod::new(
+            end - Duration::days(28),
+            end,
+            Decimal::from(parse_var_with::<u64>(&var, "PAYOUTS_BUDGET").unwrap_or(0)),
+        );
+
+        if let Some(weekend_bonus) = parse_var_with(&var, "PAYOUTS_WEEKEND_BONUS") {
+            period.weekend_bonus = weekend_bonus;
+        }
+
+        if let Some(value) = var("PAYOUTS_WEEKEND_DAYS") {
+            match parse_weekend_days(&value) {
+                Ok(days) => period.weekend_days = days,
+                Err(day) => {
+                    log::warn!("Ignoring PAYOUTS_WEEKEND_DAYS, `{day}` is not a day of the week")
+                }
+            }
+        }
+
+        period
+    }
+
+    /// The start of every day in the period
+    pub fn days(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        (0..(self.end - self.start).num_days()).map(move |x| self.start + Duration::days(x))
+    }
+
+    /// The start of the last day in the period
+    pub fn last_day(&self) -> DateTime<Utc> {
+        self.end - Duration::days(1)
+    }
+
+    fn is_weekend(&self, day: DateTime<Utc>) -> bool {
+        self.weekend_days.contains(&day.weekday())
+    }
+
+    /// The share of the budget allocated to the day starting at `day`
+    pub fn daily_allocation(&self, day: DateTime<Utc>) -> Decimal {
+        if day < self.start || day >= self.end {
+            return Decimal::ZERO;
+        }
+
+        let (weekend_days, weekdays) = self.days().fold((0, 0), |(weekend, weekday), x| {
+            if self.is_weekend(x) {
+                (weekend + 1, weekday)
+            } else {
+                (weekend, weekday + 1)
+            }
+        });
+
+        let weekday_amount = self.budget
+            / (Decimal::from(weekdays) + self.weekend_bonus * Decimal::from(weekend_days));
+
+        if self.is_weekend(day) {
+            weekday_amount * self.weekend_bonus
+        } else {
+            weekday_amount
+        }
+    }
+}
+
+/// Reads comma separated day names, ex: `fri,sat` or `Friday, Saturday`, failing with the first
+/// which is not a day of the week
+fn parse_weekend_days(value: &str) -> Result<Vec<Weekday>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse::<Weekday>().map_err(|_| x.to_string()))
+        .collect()
+}
+
+/// How many shares of a day's budget each view and each download earns, configured by
+/// `PAYOUTS_VIEW_WEIGHT` and `PAYOUTS_DOWNLOAD_WEIGHT`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PayoutWeights {
+    pub views: u64,
+    pub downloads: u64,
+}
+
+impl Default for PayoutWeights {
+    fn default() -> Self {
+        PayoutWeights {
+            views: 1,
+            downloads: 1,
+        }
+    }
+}
+
+impl PayoutWeights {
+    pub fn from_env() -> Self {
+        Self::from_env_with(env_var)
+    }
+
+    fn from_env_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        let default = Self::default();
+
+        PayoutWeights {
+            views: parse_var_with(&var, "PAYOUTS_VIEW_WEIGHT").unwrap_or(default.views),
+            downloads: parse_var_with(&var, "PAYOUTS_DOWNLOAD_WEIGHT").unwrap_or(default.downloads),
+        }
+    }
+}
+
+/// The fewest weighted views and downloads a project needs in a day to be paid out for it,
+/// configured by `PAYOUTS_PROJECT_MINIMUM`. Projects below it would only earn sub-cent payouts,
+/// so they are excluded and their share goes to the rest. Defaults to 0, excluding none.
+pub fn payout_project_minimum() -> u64 {
+    parse_var("PAYOUTS_PROJECT_MINIMUM").unwrap_or(0)
+}
+
+/// The most weighted views and downloads a single project is credited with in a day, so one
+/// project spiking to millions of views cannot absorb most of the budget. Configured by
+/// `PAYOUTS_PROJECT_CAP` as an absolute count and by `PAYOUTS_PROJECT_CAP_PERCENTILE` as a
+/// percentile of every project's count that day, ex: `99`. When both are set, the lower binds.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PayoutProjectCap {
+    pub absolute: Option<u64>,
+    pub percentile: Option<u8>,
+}
+
+impl PayoutProjectCap {
+    pub fn from_env() -> Self {
+        Self::from_env_with(env_var)
+    }
+
+    fn from_env_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        PayoutProjectCap {
+            absolute: parse_var_with(&var, "PAYOUTS_PROJECT_CAP"),
+            percentile: parse_var_with::<u8>(&var, "PAYOUTS_PROJECT_CAP_PERCENTILE")
+                .filter(|x| *x <= 100),
+        }
+    }
+
+    /// The cap for a day where projects earned `values`, if any
+    pub fn limit<'a>(&self, values: impl Iterator<Item = &'a u64>) -> Option<u64> {
+        let percentile = self.percentile.and_then(|percentile| {
+            let mut values = values.copied().collect::<Vec<_>>();
+            values.sort_unstable();
+
+            // Nearest rank, so the cap is always a count some project actually earned
+            let rank = (values.len() * percentile as usize + 99) / 100;
+            values.get(rank.max(1) - 1).copied()
+        });
+
+        match (self.absolute, percentile) {
+            (Some(absolute), Some(percentile)) => Some(absolute.min(percentile)),
+            (absolute, percentile) => absolute.or(percentile),
+        }
+    }
+}
+
+/// Every setting affecting how much is paid out and how, see
+/// [`PayoutsQueue::log_config_summary`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayoutConfigSummary {
+    pub budget: Decimal,
+    pub weekend_bonus: Decimal,
+    pub weights: PayoutWeights,
+    pub project_cap: PayoutProjectCap,
+    pub analytics_source: PayoutAnalyticsSource,
+    pub eligible_statuses: Vec<&'static str>,
+    /// The providers withdrawals can currently be sent through
+    pub providers: Vec<&'static str>,
+    pub withdrawals_enabled: bool,
+    pub auto_retry: bool,
+    pub review_threshold: Option<Decimal>,
+    pub max_daily_user_payout: Option<Decimal>,
+    pub withdrawal_rate_limit: WithdrawalRateLimit,
+    pub run_interval: std::time::Duration,
+}
+
+impl std::fmt::Display for PayoutConfigSummary {
+    /// Writes the settings as space separated `key=value` fields, with `none` for unset limits
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn or_none(value: Option<impl ToString>) -> String {
+            value.map_or_else(|| "none".to_string(), |x| x.to_string())
+        }
+
+        write!(
+            f,
+            "budget={} weekend_bonus={} view_weight={} download_weight={} project_cap={} \
+            project_cap_percentile={} analytics_source={} eligible_statuses={} providers={} \
+            withdrawals_enabled={} auto_retry={} review_threshold={} max_daily_user_payout={} \
+            withdrawal_rate_limit={}/{}s run_interval={}s",
+            self.budget,
+            self.weekend_bonus,
+            self.weights.views,
+            self.weights.downloads,
+            or_none(self.project_cap.absolute),
+            or_none(self.project_cap.percentile),
+            self.analytics_source.as_str(),
+            self.eligible_statuses.join(","),
+            self.providers.join(","),
+            self.withdrawals_enabled,
+            self.auto_retry,
+            or_none(self.review_threshold),
+            or_none(self.max_daily_user_payout),
+            self.withdrawal_rate_limit.max,
+            self.withdrawal_rate_limit.window_seconds,
+            self.run_interval.as_secs(),
+        )
+    }
+}
+
+/// The constants [`process_payout`] is currently paying out with
+#[derive(Serialize, Deserialize)]
+pub struct PayoutConstants {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    #[serde(with = "crate::models::payouts::money")]
+    pub budget: Decimal,
+    pub weekend_bonus: Decimal,
+    #[serde(with = "crate::models::payouts::money")]
+    pub weekday_allocation: Decimal,
+    #[serde(with = "crate::models::payouts::money")]
+    pub weekend_allocation: Decimal,
+    pub weights: PayoutWeights,
+    pub project_cap: PayoutProjectCap,
+}
+
+impl PayoutConstants {
+    /// Reads the constants from the same configuration the payout job uses
+    pub fn current() -> Self {
+        let period = PayoutPeriod::current();
+        let weekday_allocation = period.daily_allocation(
+            period
+                .days()
+                .find(|x| !period.is_weekend(*x))
+                .unwrap_or(period.start),
+        );
+
+        PayoutConstants {
+            period_start: period.start,
+            period_end: period.end,
+            budget: period.budget,
+            weekend_bonus: period.weekend_bonus,
+            weekday_allocation,
+            weekend_allocation: weekday_allocation * period.weekend_bonus,
+            weights: PayoutWeights::from_env(),
+            project_cap: PayoutProjectCap::from_env(),
+        }
+    }
+}
+
+fn start_of_today() -> DateTime<Utc> {
+    DateTime::from_naive_utc_and_offset(
+        Utc::now()
+            .date_naive()
+            .and_hms_nano_opt(0, 0, 0, 0)
+            .unwrap_or_default(),
+        Utc,
+    )
+}
+
+/// Invalidates the cached users whose balances payouts change, so running payouts does not
+/// depend on a particular cache
+#[async_trait]
+pub trait PayoutCache: Send + Sync {
+    async fn clear_users(
+        &self,
+        user_ids: &[crate::database::models::UserId],
+    ) -> Result<(), ApiError>;
+}
+
+#[async_trait]
+impl PayoutCache for RedisPool {
+    async fn clear_users(
+        &self,
+        user_ids: &[crate::database::models::UserId],
+    ) -> Result<(), ApiError> {
+        let users = user_ids.iter().map(|x| (*x, None)).collect::<Vec<_>>();
+        crate::database::models::User::clear_caches(&users, self).await?;
+
+        Ok(())
+    }
+}
+
+/// A cache holding nothing, for running payouts without one. The users it is asked to clear are
+/// recorded, so tests can check them.
+#[derive(Debug, Default)]
+pub struct NoopPayoutCache {
+    cleared: std::sync::Mutex<Vec<crate::database::models::UserId>>,
+}
+
+impl NoopPayoutCache {
+    /// Every user asked to be cleared so far, in order
+    pub fn cleared(&self) -> Vec<crate::database::models::UserId> {
+        self.cleared.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl PayoutCache for NoopPayoutCache {
+    async fn clear_users(
+        &self,
+        user_ids: &[crate::database::models::UserId],
+    ) -> Result<(), ApiError> {
+        self.cleared.lock().unwrap().extend_from_slice(user_ids);
+
+        Ok(())
+    }
+}
+
+/// Pays out the last day of `period`, unless it has already been paid out or was recorded as
+/// having no activity. With `force`, such a day is reprocessed, replacing its previous payouts.
+/// The `audit` entry for an admin action is recorded in the same transaction as the payouts.
+pub async fn process_payout(
+    pool: &PgPool,
+    cache: &dyn PayoutCache,
+    client: &clickhouse::Client,
+    period: &PayoutPeriod,
+    force: bool,
+    audit: Option<&PayoutAuditEntry>,
+) -> Result<(), ApiError> {
+    let start = period.last_day();
+
+    if !force {
+        let results = sqlx::query!(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM payouts_values WHERE created = $1)
+                OR EXISTS(SELECT 1 FROM payout_runs WHERE day = $1 AND outcome = $2) "exists"
+            "#,
+            start,
+            PayoutDayOutcome::ZeroActivity.as_str(),
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if results.exists.unwrap_or(false) {
+            // Nothing is paid out, so only the attempt is audited
+            if let Some(audit) = audit {
+                crate::database::models::payout_audit_item::PayoutAuditLog::insert(audit, pool)
+                    .await?;
+            }
+
+            return Ok(());
+        }
+    }
+
+    let started = Instant::now();
+    let multipliers = live_payout_multipliers(pool, client, start).await?;
+
+    distribute_payout_day(
+        pool,
+        cache,
+        period,
+        start,
+        multipliers,
+        &eligible_monetization_statuses(),
+        payout_reserve_user(),
+        force,
+        Some(started),
+        audit,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// The multipliers the day starting at `start` is paid out by, counted from the configured
+/// analytics source with the configured weights
+async fn live_payout_multipliers(
+    pool: &PgPool,
+    client: &clickhouse::Client,
+    start: DateTime<Utc>,
+) -> Result<PayoutMultipliers, ApiError> {
+    let analytics = PayoutAnalyticsSource::from_env().analytics(client, pool);
+
+    fetch_payout_multipliers(
+        &*analytics,
+        start,
+        start + Duration::days(1),
+        PayoutWeights::from_env(),
+    )
+    .await
+}
+
+/// A summary of paying out the day starting at `start`, which started at `started`, for the run
+/// history. The totals are of what was credited to projects' team members, so anything swept
+/// into the reserve is not counted.
+fn payout_run_summary(
+    period: &PayoutPeriod,
+    start: DateTime<Utc>,
+    outcome: PayoutDayOutcome,
+    forced: bool,
+    started: Instant,
+    credits: &[PayoutCredit],
+) -> PayoutRun {
+    let run = PayoutRun {
+        created: Utc::now(),
+        day: start,
+        budget: period.daily_allocation(start),
+        distributed: credits.iter().map(|x| x.amount).sum(),
+        projects: credits.iter().map(|x| x.project_id).unique().count() as i32,
+        users: credits.iter().map(|x| x.user_id).unique().count() as i32,
+        duration_ms: started.elapsed().as_millis() as i64,
+        outcome: outcome.as_str().to_string(),
+        forced,
+    };
+
+    log::info!(
+        "Paid out {} of {} for {start} to {} users for {} projects in {}ms",
+        run.distributed,
+        run.budget,
+        run.users,
+        run.projects,
+        run.duration_ms
+    );
+
+    run
+}
+
+/// How often payouts are run, configured in seconds by `PAYOUTS_RUN_INTERVAL`. Defaults to every
+/// 6 hours. Each run catches up on every day missed since the last, see [`catch_up_payouts`].
+pub fn payout_run_interval() -> std::time::Duration {
+    payout_run_interval_with(env_var)
+}
+
+fn payout_run_interval_with(var: impl Fn(&str) -> Option<String>) -> std::time::Duration {
+    std::time::Duration::from_secs(
+        parse_var_with(var, "PAYOUTS_RUN_INTERVAL").unwrap_or(60 * 60 * 6),
+    )
+}
+
+/// How often the payout methods are snapshotted, configured in seconds by
+/// `PAYOUTS_METHOD_SNAPSHOT_INTERVAL`. Defaults to daily. See [`snapshot_payout_methods`].
+pub fn payout_method_snapshot_interval() -> std::time::Duration {
+    std::time::Duration::from_secs(
+        parse_var("PAYOUTS_METHOD_SNAPSHOT_INTERVAL").unwrap_or(60 * 60 * 24),
+    )
+}
+
+/// How long shutdown waits for withdrawals in flight, configured in seconds by
+/// `PAYOUTS_SHUTDOWN_TIMEOUT`. Defaults to 30 seconds. See [`PayoutsQueue::drain`].
+pub fn payout_shutdown_timeout() -> std::time::Duration {
+    std::time::Duration::from_secs(parse_var("PAYOUTS_SHUTDOWN_TIMEOUT").unwrap_or(30))
+}
+
+/// The most days a single run catches up on, configured by `PAYOUTS_CATCH_UP_MAX_DAYS`. Any
+/// further days are left for the next run. Defaults to 31.
+fn catch_up_max_days() -> i64 {
+    parse_var("PAYOUTS_CATCH_UP_MAX_DAYS").unwrap_or(31)
+}
+
+/// The days which have not been paid out since the most recently paid out day before `today`,
+/// oldest first and up to [`catch_up_max_days`] of them. If no day has ever been paid out, this
+/// is only the day before `today`.
+pub async fn unprocessed_payout_days(
+    pool: &PgPool,
+    today: DateTime<Utc>,
+) -> Result<Vec<DateTime<Utc>>, ApiError> {
+    // Days without any activity have no payouts, only a recorded run
+    let last = sqlx::query!(
+        "
+        SELECT GREATEST(
+            (SELECT MAX(created) FROM payouts_values WHERE created < $1),
+            (SELECT MAX(day) FROM payout_runs WHERE day < $1 AND outcome = $2)
+        ) last
+        ",
+        today,
+        PayoutDayOutcome::ZeroActivity.as_str(),
+    )
+    .fetch_one(pool)
+    .await?
+    .last;
+
+    let yesterday = today - Duration::days(1);
+    let first = match last {
+        Some(last) => last + Duration::days(1),
+        None => yesterday,
+    };
+
+    Ok((0..catch_up_max_days())
+        .map(|x| first + Duration::days(x))
+        .take_while(|x| *x <= yesterday)
+        .collect())
+}
+
+/// Pays out every day missed since the most recently paid out one, oldest first, so payouts
+/// recover by themselves after an outage of several days. Returns the days processed. The
+/// `audit` entry for an admin action is recorded for each day with its payouts, or on its own if
+/// there was nothing to catch up on.
+pub async fn catch_up_payouts(
+    pool: &PgPool,
+    cache: &dyn PayoutCache,
+    client: &clickhouse::Client,
+    audit: Option<&PayoutAuditEntry>,
+) -> Result<Vec<DateTime<Utc>>, ApiError> {
+    let days = catch_up_payouts_with(pool, start_of_today(), |day| async move {
+        let period = PayoutPeriod::ending_at(day + Duration::days(1));
+        // Each day is audited along with its payouts
+        let audit = audit.map(|audit| PayoutAuditEntry {
+            target: Some(day.date_naive().to_string()),
+            ..audit.clone()
+        });
+        process_payout(pool, cache, client, &period, false, audit.as_ref()).await
+    })
+    .await?;
+
+    if days.is_empty() {
+        if let Some(audit) = audit {
+            crate::database::models::payout_audit_item::PayoutAuditLog::insert(audit, pool).await?;
+        }
+    }
+
+    Ok(days)
+}
+
+/// Same as [`catch_up_payouts`], paying out each day with `process_day`. Stops at the first day
+/// which fails, so days are never paid out of order. Only one instance catches up at a time, the
+/// others return no days, see [`try_lock_payout_job`].
+pub async fn catch_up_payouts_with<F, Fut>(
+    pool: &PgPool,
+    today: DateTime<Utc>,
+    mut process_day: F,
+) -> Result<Vec<DateTime<Utc>>, ApiError>
+where
+    F: FnMut(DateTime<Utc>) -> Fut,
+    Fut: std::future::Future<Output = Result<(), ApiError>>,
+{
+    // Held until every day is paid out, so no other instance pays out the same days
+    let Some(job_lock) = try_lock_payout_job(pool, PAYOUT_JOB_LOCK).await? else {
+        log::info!("Payouts are already being caught up on by another instance");
+        return Ok(Vec::new());
+    };
+
+    let days = unprocessed_payout_days(pool, today).await?;
+
+    for day in &days {
+        log::info!("Catching up on payouts for {day}");
+        process_day(*day).await?;
+    }
+
+    job_lock.rollback().await?;
+
+    Ok(days)
+}
+
+/// The weighted view and download counts each project earned over a payout day
+#[derive(Debug, Eq, PartialEq)]
+pub struct PayoutMultipliers {
+    pub sum: u64,
+    pub values: HashMap<u64, u64>,
+}
+
+impl PayoutMultipliers {
+    /// The fraction of the day's budget the project earned
+    pub fn project_share(&self, project_id: u64) -> Decimal {
+        match self.values.get(&project_id) {
+            Some(value) if self.sum > 0 => Decimal::from(*value) / Decimal::from(self.sum),
+            _ => Decimal::ZERO,
+        }
+    }
+
+    /// Removes a project's views and downloads, including from the total
+    pub fn exclude(&mut self, project_id: u64) {
+        if let Some(value) = self.values.remove(&project_id) {
+            self.sum = self.sum.saturating_sub(value);
+        }
+    }
+
+    /// Excludes each project with fewer than `minimum` weighted views and downloads, removing
+    /// them from the total so their share goes to the projects which qualify
+    pub fn apply_minimum(&mut self, minimum: u64) {
+        let sum = &mut self.sum;
+        self.values.retain(|project_id, value| {
+            if *value >= minimum {
+                return true;
+            }
+
+            log::debug!(
+                "Excluding project {project_id} with {value} of the minimum {minimum} weighted views and downloads"
+            );
+            *sum = sum.saturating_sub(*value);
+            false
+        });
+    }
+
+    /// Scales each project's views and downloads by `weighting`, given each project's raw
+    /// `views` and `downloads`. The total grows or shrinks with them, so boosted projects take
+    /// their extra share from everyone else. Projects are compared against the conversion of
+    /// only those still being paid out, ex: after [`apply_minimum`](Self::apply_minimum).
+    pub fn apply_conversion_weighting(
+        &mut self,
+        weighting: &ConversionWeighting,
+        views: &HashMap<u64, u64>,
+        downloads: &HashMap<u64, u64>,
+    ) {
+        let count = |counts: &HashMap<u64, u64>| {
+            self.values
+                .keys()
+                .map(|x| counts.get(x).copied().unwrap_or(0))
+                .sum::<u64>()
+        };
+        let overall = ConversionWeighting::rate(count(views), count(downloads));
+
+        for (project_id, value) in self.values.iter_mut() {
+            let factor = weighting.factor(
+                views.get(project_id).copied().unwrap_or(0),
+                downloads.get(project_id).copied().unwrap_or(0),
+                overall,
+            );
+            if factor == Decimal::ONE {
+                continue;
+            }
+
+            let weighted =
+                u64::try_from((Decimal::from(*value) * factor).round()).unwrap_or(*value);
+            self.sum = (self.sum + weighted).saturating_sub(*value);
+            *value = weighted;
+        }
+    }
+
+    /// Lowers each project's views and downloads to at most `cap`, removing the excess from the
+    /// total so it is shared by every other project instead
+    pub fn apply_cap(&mut self, cap: &PayoutProjectCap) {
+        let Some(limit) = cap.limit(self.values.values()) else {
+            return;
+        };
+
+        for (project_id, value) in self.values.iter_mut() {
+            if *value > limit {
+                log::info!(
+                    "Capping project {project_id} at {limit} of its {value} weighted views and downloads"
+                );
+                self.sum = self.sum.saturating_sub(*value - limit);
+                *value = limit;
+            }
+        }
+    }
+}
+
+/// Estimates a project's payout for the in-progress day. Today's partial views and downloads
+/// are assumed to be representative of the whole day, so this is only an estimate.
+pub async fn estimate_project_payout(
+    client: &clickhouse::Client,
+    pool: &PgPool,
+    project_id: u64,
+) -> Result<Decimal, ApiError> {
+    let start = start_of_today();
+    let period = PayoutPeriod::ending_at(start + Duration::days(1));
+    let analytics = PayoutAnalyticsSource::from_env().analytics(client, pool);
+    let multipliers =
+        fetch_payout_multipliers(&*analytics, start, Utc::now(), PayoutWeights::from_env()).await?;
+
+    Ok(period.daily_allocation(start) * multipliers.project_share(project_id))
+}
+
+/// Redis namespace users' payout estimates for the in-progress day are cached under
+pub const PAYOUT_ESTIMATES_NAMESPACE: &str = "payout_estimates";
+
+/// How many seconds a user's payout estimate is reused for, configured by `PAYOUTS_ESTIMATE_TTL`.
+/// Defaults to 15 minutes.
+fn payout_estimate_ttl() -> i64 {
+    parse_var("PAYOUTS_ESTIMATE_TTL").unwrap_or(15 * 60)
+}
+
+/// Estimates a user's payout for the in-progress day, across every project they would be
+/// credited for by their payout split, the same way as [`estimate_project_payout`]. Estimates
+/// are cached per user and day for `PAYOUTS_ESTIMATE_TTL` seconds, so the day's analytics aren't
+/// aggregated on every request, and a day's estimate is never served once it is over.
+pub async fn estimate_user_payout(
+    client: &clickhouse::Client,
+    pool: &PgPool,
+    redis: &RedisPool,
+    user_id: UserId,
+) -> Result<Decimal, ApiError> {
+    let start = start_of_today();
+    let key = format!("{}:{}", user_id.0, start.format("%Y-%m-%d"));
+
+    let mut redis = redis.connect().await?;
+    if let Some(estimate) = redis
+        .get_deserialized_from_json::<Decimal>(PAYOUT_ESTIMATES_NAMESPACE, &key)
+        .await?
+    {
+        return Ok(estimate);
+    }
+
+    let estimate = calculate_user_payout_estimate(client, pool, user_id, start).await?;
+
+    let until_tomorrow = (start + Duration::days(1) - Utc::now())
+        .num_seconds()
+        .max(1);
+    redis
+        .set_serialized_to_json(
+            PAYOUT_ESTIMATES_NAMESPACE,
+            &key,
+            estimate,
+            Some(payout_estimate_ttl().min(until_tomorrow)),
+        )
+        .await?;
+
+    Ok(estimate)
+}
+
+/// See [`estimate_user_payout`], for the day starting at `start`
+async fn calculate_user_payout_estimate(
+    client: &clickhouse::Client,
+    pool: &PgPool,
+    user_id: UserId,
+    start: DateTime<Utc>,
+) -> Result<Decimal, ApiError> {
+    let projects = sqlx::query!(
+        r#"
+        SELECT m.id id, tm.payouts_split payouts_split,
+            (
+                SELECT SUM(o.payouts_split)
+                FROM team_members o
+                WHERE o.team_id = m.team_id AND o.accepted = TRUE
+            ) "team_split!"
+        FROM mods m
+        INNER JOIN team_members tm ON m.team_id = tm.team_id AND tm.accepted = TRUE
+        WHERE tm.user_id = $1 AND m.monetization_status = ANY($2)
+            AND m.payout_hold = FALSE AND m.payouts_enabled = TRUE
+        "#,
+        user_id.0 as i64,
+        &eligible_monetization_statuses()
+            .iter()
+            .map(|x| x.as_str().to_string())
+            .collect::<Vec<_>>(),
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Skip querying analytics for users with nothing to be paid for
+    if projects.is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+
+    let period = PayoutPeriod::ending_at(start + Duration::days(1));
+    let analytics = PayoutAnalyticsSource::from_env().analytics(client, pool);
+    let multipliers =
+        fetch_payout_multipliers(&*analytics, start, Utc::now(), PayoutWeights::from_env()).await?;
+    let allocation = period.daily_allocation(start);
+
+    let estimate: Decimal = projects
+        .into_iter()
+        .filter(|x| x.team_split > Decimal::ZERO)
+        .map(|x| {
+            allocation * multipliers.project_share(x.id as u64) * (x.payouts_split / x.team_split)
+        })
+        .sum();
+
+    Ok(match max_daily_user_payout() {
+        Some(cap) => estimate.min(cap),
+        None => estimate,
+    })
+}
+
+/// The weighted views and downloads in `analytics` over `start` to `end`, with each project's
+/// weighted by [`ConversionWeighting`] and capped by [`PayoutProjectCap`]
+pub async fn fetch_payout_multipliers(
+    analytics: &dyn PayoutAnalytics,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    weights: PayoutWeights,
+) -> Result<PayoutMultipliers, ApiError> {
+    use futures::TryStreamExt;
+
+    let timeout = analytics_query_timeout();
+    let mut views = HashMap::new();
+    let mut downloads = HashMap::new();
+    let project_counts = |event| {
+        analytics
+            .project_counts(event, start, end)
+            .map_ok(|x| (x.project_id, x.page_views))
+    };
+
+    // The per-project counts are streamed into a map per event as they arrive, rather than each
+    // query's full result being fetched first, so a busy day never holds more than the maps in
+    // memory
+    let (views_sum, downloads_sum, ()) = futures::future::try_join3(
+        with_query_timeout(timeout, analytics.count(AnalyticsEvent::Views, start, end)),
+        with_query_timeout(
+            timeout,
+            analytics.count(AnalyticsEvent::Downloads, start, end),
+        ),
+        with_query_timeout(timeout, async {
+            accumulate_project_counts(&mut views, 1, project_counts(AnalyticsEvent::Views)).await?;
+
+            accumulate_project_counts(&mut downloads, 1, project_counts(AnalyticsEvent::Downloads))
+                .await
+        }),
+    )
+    .await?;
+
+    let mut values = HashMap::with_capacity(views.len());
+    for (project_id, count) in &views {
+        *values.entry(*project_id).or_insert(0) += count * weights.views;
+    }
+    for (project_id, count) in &downloads {
+        *values.entry(*project_id).or_insert(0) += count * weights.downloads;
+    }
+
+    let mut multipliers = PayoutMultipliers {
+        sum: downloads_sum * weights.downloads + views_sum * weights.views,
+        values,
+    };
+    multipliers.apply_minimum(payout_project_minimum());
+    if let Some(weighting) = ConversionWeighting::from_env() {
+        multipliers.apply_conversion_weighting(&weighting, &views, &downloads);
+    }
+    multipliers.apply_cap(&PayoutProjectCap::from_env());
+
+    Ok(multipliers)
+}
+
+/// Adds each project's count in `rows`, multiplied by `weight`, onto `values` one row at a time,
+/// so rows are never buffered and memory grows with the number of projects rather than rows
+async fn accumulate_project_counts<E>(
+    values: &mut HashMap<u64, u64>,
+    weight: u64,
+    rows: impl futures::Stream<Item = Result<(u64, u64), E>>,
+) -> Result<(), E> {
+    use futures::TryStreamExt;
+
+    rows.try_for_each(|(project_id, count)| {
+        *values.entry(project_id).or_insert(0) += count * weight;
+        futures::future::ready(Ok(()))
+    })
+    .await
+}
+
+/// How long each analytics query made while paying out a day may run, whichever the
+/// [`PayoutAnalyticsSource`], configured by `PAYOUTS_CLICKHOUSE_TIMEOUT_SECS`. Defaults to 5
+/// minutes.
+fn analytics_query_timeout() -> std::time::Duration {
+    std::time::Duration::from_secs(parse_var("PAYOUTS_CLICKHOUSE_TIMEOUT_SECS").unwrap_or(300))
+}
+
+/// Fails with `ApiError::Timeout` if `query` takes longer than `timeout`. The day being paid out
+/// is left untouched, so it is picked up again by the next run.
+async fn with_query_timeout<T, E: Into<ApiError>>(
+    timeout: std::time::Duration,
+    query: impl std::future::Future<Output = Result<T, E>>,
+) -> Result<T, ApiError> {
+    tokio::time::timeout(timeout, query)
+        .await
+        .map_err(|_| {
+            ApiError::Timeout(format!(
+                "Analytics query exceeded {} seconds",
+                timeout.as_secs()
+            ))
+        })?
+        .map_err(Into::into)
+}
+
+/// What [`distribute_payouts`] did with a day
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PayoutDayOutcome {
+    Paid,
+    /// No views or downloads were recorded, so nothing was paid out
+    ZeroActivity,
+}
+
+impl PayoutDayOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutDayOutcome::Paid => "paid",
+            PayoutDayOutcome::ZeroActivity => "zero_activity",
+        }
+    }
+}
+
+/// The monetization statuses which projects are paid out in, configured by
+/// `PAYOUTS_ELIGIBLE_MONETIZATION_STATUSES` as a comma separated list, ex:
+/// `monetized,demonetized`. Defaults to only `monetized`.
+pub fn eligible_monetization_statuses() -> Vec<MonetizationStatus> {
+    eligible_monetization_statuses_with(env_var)
+}
+
+fn eligible_monetization_statuses_with(
+    var: impl Fn(&str) -> Option<String>,
+) -> Vec<MonetizationStatus> {
+    let statuses = var("PAYOUTS_ELIGIBLE_MONETIZATION_STATUSES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .filter_map(|x| {
+            // `from_string` falls back to monetized, which would hide typos
+            let status = MonetizationStatus::from_string(x);
+            if status.as_str() == x {
+                Some(status)
+            } else {
+                log::warn!("Ignoring unknown PAYOUTS_ELIGIBLE_MONETIZATION_STATUSES entry: {x}");
+                None
+            }
+        })
+        .unique_by(|x| x.as_str())
+        .collect::<Vec<_>>();
+
+    if statuses.is_empty() {
+        vec![MonetizationStatus::Monetized]
+    } else {
+        statuses
+    }
+}
+
+/// The user whose balance the part of each day's budget left undistributed is swept into,
+/// configured by `PAYOUTS_RESERVE_USER` as a user id, ex: `MpxzqsyW`. Without it, what is left
+/// over is simply not paid out.
+pub fn payout_reserve_user() -> Option<crate::database::models::UserId> {
+    let value = dotenvy::var("PAYOUTS_RESERVE_USER").ok()?;
+
+    match parse_base62(value.trim()) {
+        Ok(id) => Some(crate::database::models::UserId(id as i64)),
+        Err(_) => {
+            log::warn!("Ignoring PAYOUTS_RESERVE_USER, `{value}` is not a user id");
+            None
+        }
+    }
+}
+
+/// Splits the day's share of the period budget across projects with one of `eligible_statuses`
+/// by their share of `multipliers` and credits each team member's balance according to their
+/// payout split. Whatever is left over, ex: the shares of projects which were skipped or capped,
+/// is credited to `reserve` if given, so the day's payouts add up to its share of the budget.
+/// Every amount is rounded to cents with the configured [`PayoutRoundingMode`] as it is written,
+/// and whatever is lost to rounding is not paid out. With `replace`, any payouts already made for
+/// the day are reversed in the same transaction first.
+#[allow(clippy::too_many_arguments)]
+pub async fn distribute_payouts(
+    pool: &PgPool,
+    cache: &dyn PayoutCache,
+    period: &PayoutPeriod,
+    start: DateTime<Utc>,
+    multipliers: PayoutMultipliers,
+    eligible_statuses: &[MonetizationStatus],
+    reserve: Option<crate::database::models::UserId>,
+    replace: bool,
+) -> Result<PayoutDayOutcome, ApiError> {
+    distribute_payout_day(
+        pool,
+        cache,
+        period,
+        start,
+        multipliers,
+        eligible_statuses,
+        reserve,
+        replace,
+        None,
+        None,
+    )
+    .await
+}
+
+/// See [`distribute_payouts`]. If the run started at `started` is given, its summary is recorded
+/// in the run history along with the day's payouts, in the same transaction, as is `audit`.
+#[allow(clippy::too_many_arguments)]
+async fn distribute_payout_day(
+    pool: &PgPool,
+    cache: &dyn PayoutCache,
+    period: &PayoutPeriod,
+    start: DateTime<Utc>,
+    multipliers: PayoutMultipliers,
+    eligible_statuses: &[MonetizationStatus],
+    reserve: Option<crate::database::models::UserId>,
+    replace: bool,
+    started: Option<Instant>,
+    audit: Option<&PayoutAuditEntry>,
+) -> Result<PayoutDayOutcome, ApiError> {
+    // Nothing to split the budget by. The day is left without payouts and any payouts already
+    // made for it are kept. Once its run is recorded it is not caught up on again, but can still
+    // be reprocessed with `force` if its activity arrives late.
+    if multipliers.sum == 0 {
+        log::warn!("No views or downloads were recorded on {start}, skipping its payouts");
+
+        let mut transaction = pool.begin().await?;
+        if let Some(started) = started {
+            let run = payout_run_summary(
+                period,
+                start,
+                PayoutDayOutcome::ZeroActivity,
+                replace,
+                started,
+                &[],
+            );
+            crate::database::models::payout_run_item::PayoutRunHistory::insert(
+                &run,
+                &mut *transaction,
+            )
+            .await?;
+        }
+        if let Some(audit) = audit {
+            crate::database::models::payout_audit_item::PayoutAuditLog::insert(
+                audit,
+                &mut *transaction,
+            )
+            .await?;
+        }
+        transaction.commit().await?;
+
+        return Ok(PayoutDayOutcome::ZeroActivity);
+    }
+
+    let allocation = period.daily_allocation(start);
+    let rounding = PayoutRoundingMode::from_env();
+    let credits = round_payout_credits(
+        payout_day_credits(pool, period, start, multipliers, eligible_statuses).await?,
+        rounding,
+    );
+
+    let reserve = reserve
+        .map(|user_id| PayoutReserve {
+            user_id: user_id.0,
+            amount: rounding.round(allocation - credits.iter().map(|x| x.amount).sum::<Decimal>()),
+        })
+        .filter(|x| x.amount > Decimal::ZERO);
+    if let Some(reserve) = &reserve {
+        log::info!(
+            "Sweeping {} left undistributed on {start} into the reserve",
+            reserve.amount
+        );
+    }
+
+    let run = started.map(|started| {
+        payout_run_summary(
+            period,
+            start,
+            PayoutDayOutcome::Paid,
+            replace,
+            started,
+            &credits,
+        )
+    });
+    // Everything is written in one transaction, so the day is either fully paid out or not at all
+    let clear_cache_users = write_payout_day(
+        pool,
+        start,
+        &credits,
+        reserve.as_ref(),
+        replace,
+        run.as_ref(),
+        audit,
+    )
+    .await?;
+
+    if !clear_cache_users.is_empty() {
+        cache
+            .clear_users(&clear_cache_batch(clear_cache_users))
+            .await?;
+    }
+
+    Ok(PayoutDayOutcome::Paid)
+}
+
+/// Rounds each credit to cents with `rounding` as it is written to balances, dropping those which
+/// round to nothing
+fn round_payout_credits(
+    credits: Vec<PayoutCredit>,
+    rounding: PayoutRoundingMode,
+) -> Vec<PayoutCredit> {
+    credits
+        .into_iter()
+        .map(|credit| PayoutCredit {
+            amount: rounding.round(credit.amount),
+            ..credit
+        })
+        .filter(|credit| credit.amount > Decimal::ZERO)
+        .collect()
+}
+
+/// What each team member would be credited for the day starting at `start`, as
+/// [`distribute_payouts`] would credit it before rounding, without writing anything
+async fn payout_day_credits(
+    pool: &PgPool,
+    period: &PayoutPeriod,
+    start: DateTime<Utc>,
+    mut multipliers: PayoutMultipliers,
+    eligible_statuses: &[MonetizationStatus],
+) -> Result<Vec<PayoutCredit>, ApiError> {
+    if multipliers.sum == 0 {
+        return Ok(Vec::new());
+    }
+
+    let end = start + Duration::days(1);
+
+    let mut projects_map: HashMap<i64, PayoutProject> = HashMap::new();
+    let mut excluded_projects = HashSet::new();
+    let discovery_boost = DiscoveryBoost::from_env();
+
+    use futures::TryStreamExt;
+
+    sqlx::query!(
+        "
+        SELECT m.id id, m.monetization_status_updated monetization_status_updated,
+            m.published published, m.payout_hold payout_hold, m.payouts_enabled payouts_enabled,
+            tm.user_id user_id, tm.payouts_split payouts_split
+        FROM mods m
+        INNER JOIN team_members tm on m.team_id = tm.team_id AND tm.accepted = TRUE
+        WHERE m.id = ANY($1) AND m.monetization_status = ANY($2)
+        ",
+        &multipliers
+            .values
+            .keys()
+            .map(|x| *x as i64)
+            .collect::<Vec<i64>>(),
+        &eligible_statuses
+            .iter()
+            .map(|x| x.as_str().to_string())
+            .collect::<Vec<_>>(),
+    )
+    .fetch_many(pool)
+    .try_for_each(|e| {
+        if let Some(row) = e.right() {
+            if row.payout_hold || !row.payouts_enabled {
+                excluded_projects.insert(row.id);
+            } else if let Some(project) = projects_map.get_mut(&row.id) {
+                project.team_members.push((row.user_id, row.payouts_split));
+            } else {
+                projects_map.insert(
+                    row.id,
+                    PayoutProject {
+                        team_members: vec![(row.user_id, row.payouts_split)],
+                        monetized_fraction: monetized_fraction(
+                            row.monetization_status_updated,
+                            start,
+                            end,
+                        ),
+                        boost: discovery_boost
+                            .as_ref()
+                            .map_or(Decimal::ONE, |x| x.multiplier(row.published, start)),
+                    },
+                );
+            }
+        }
+
+        futures::future::ready(Ok(()))
+    })
+    .await?;
+
+    // Held and opted out projects' views and downloads are dropped entirely, so their share goes
+    // to everyone else
+    for id in excluded_projects {
+        log::info!("Project {id} has its payouts held or disabled, excluding it from {start}");
+        multipliers.exclude(id as u64);
+    }
+
+    // So are those of projects which aren't monetized or have no one to pay, ex: deleted ones.
+    // Otherwise they would still count toward the total while never being credited, shrinking
+    // every eligible project's share.
+    let ineligible = multipliers
+        .values
+        .keys()
+        .filter(|id| !projects_map.contains_key(&(**id as i64)))
+        .copied()
+        .collect::<Vec<_>>();
+    for id in ineligible {
+        log::debug!("Project {id} is not eligible for payouts, excluding it from {start}");
+        multipliers.exclude(id);
+    }
+
+    let allocation = period.daily_allocation(start);
+    let mut credits = project_payout_credits(allocation, &multipliers, projects_map);
+
+    if let Some(cap) = max_daily_user_payout() {
+        for (user_id, excess) in cap_user_payouts(&mut credits, cap) {
+            log::warn!(
+                "Payout of user {user_id} for {start} exceeded the daily cap of {cap}, leaving {excess} undistributed"
+            );
+        }
+    }
+
+    Ok(credits)
+}
+
+/// Simulates paying out every day from `start` to `end` as if `PAYOUTS_BUDGET` were `budget`,
+/// returning what each user would have been credited in total. Each day is computed the same way
+/// as a real payout, from its 28 day [`PayoutPeriod`] and the configured analytics, weights and
+/// rounding, but no balances or payouts are written.
+pub async fn simulate_payouts(
+    pool: &PgPool,
+    client: &clickhouse::Client,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    budget: Decimal,
+) -> Result<HashMap<UserId, Decimal>, ApiError> {
+    let eligible_statuses = eligible_monetization_statuses();
+    let rounding = PayoutRoundingMode::from_env();
+    let mut totals = HashMap::new();
+
+    let mut day = start;
+    while day < end {
+        let period = PayoutPeriod {
+            budget,
+            ..PayoutPeriod::ending_at(day + Duration::days(1))
+        };
+        let multipliers = live_payout_multipliers(pool, client, day).await?;
+        let credits = round_payout_credits(
+            payout_day_credits(pool, &period, day, multipliers, &eligible_statuses).await?,
+            rounding,
+        );
+
+        for credit in credits {
+            *totals
+                .entry(UserId(credit.user_id as u64))
+                .or_insert(Decimal::ZERO) += credit.amount;
+        }
+
+        day += Duration::days(1);
+    }
+
+    Ok(totals)
+}
+
+/// Clears the cache of every user credited for the day starting at `start`, returning how many
+/// were cleared. This is safe to run any number of times, so it can be used to recover when a
+/// payout run committed its balance updates but was interrupted before clearing caches.
+pub async fn reconcile_payout_caches(
+    exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+    cache: &dyn PayoutCache,
+    start: DateTime<Utc>,
+) -> Result<usize, ApiError> {
+    let users = sqlx::query!(
+        "
+        SELECT DISTINCT user_id
+        FROM payouts_values
+        WHERE created = $1
+        ",
+        start,
+    )
+    .fetch_all(exec)
+    .await?
+    .into_iter()
+    .map(|x| x.user_id)
+    .collect::<Vec<_>>();
+
+    let users = clear_cache_batch(users);
+    if !users.is_empty() {
+        cache.clear_users(&users).await?;
+    }
+
+    Ok(users.len())
+}
+
+/// Records the payout methods as they are currently listed, so what a method looked like when a
+/// past payout was made can be looked up. Only the methods already cached or shared by another
+/// instance are recorded, so this never reaches out to the providers. Returns `None` without
+/// recording anything if no methods are cached, another instance is snapshotting them, or they
+/// were snapshotted less than half a [`payout_method_snapshot_interval`] ago, so every instance
+/// can run this on the same schedule without duplicating snapshots.
+pub async fn snapshot_payout_methods(
+    pool: &PgPool,
+    queue: &PayoutsQueue,
+) -> Result<Option<PayoutMethodSnapshot>, ApiError> {
+    let Some(mut job_lock) = try_lock_payout_job(pool, SNAPSHOT_JOB_LOCK).await? else {
+        log::info!("Payout methods are being snapshotted by another instance, skipping");
+        return Ok(None);
+    };
+
+    let interval = Duration::from_std(payout_method_snapshot_interval() / 2)
+        .unwrap_or_else(|_| Duration::days(1));
+    let last =
+        crate::database::models::payout_method_snapshot_item::PayoutMethodSnapshots::get_last_time(
+            &mut *job_lock,
+        )
+        .await?;
+    if let Some(last) = last.filter(|x| *x > Utc::now() - interval) {
+        log::info!("Payout methods were already snapshotted at {last}, skipping");
+        return Ok(None);
+    }
+
+    let Some(methods) = queue.get_cached_payout_methods().await else {
+        log::info!("No payout methods are cached, skipping their snapshot");
+        return Ok(None);
+    };
+
+    let snapshot = PayoutMethodSnapshot {
+        created: Utc::now(),
+        methods,
+    };
+    crate::database::models::payout_method_snapshot_item::PayoutMethodSnapshots::insert(
+        &snapshot,
+        &mut *job_lock,
+    )
+    .await?;
+    job_lock.commit().await?;
+
+    Ok(Some(snapshot))
+}
+
+/// Postgres advisory lock held while [`reconcile_failed_payouts`] runs
+pub const RECONCILE_JOB_LOCK: i64 = 0x7061_796f_7574_0001;
+
+/// Postgres advisory lock held while [`snapshot_payout_methods`] runs
+pub const SNAPSHOT_JOB_LOCK: i64 = 0x7061_796f_7574_0002;
+
+/// Postgres advisory lock held while [`catch_up_payouts`] runs
+pub const PAYOUT_JOB_LOCK: i64 = 0x7061_796f_7574_0003;
+
+/// Begins a transaction holding the Postgres advisory lock `key`, so a scheduled payout job runs
+/// on one instance at a time. Returns `None` if another instance holds it. The lock is released
+/// when the transaction ends, so the job keeps it open until it is done.
+pub async fn try_lock_payout_job(
+    pool: &PgPool,
+    key: i64,
+) -> Result<Option<sqlx::Transaction<'static, sqlx::Postgres>>, ApiError> {
+    let mut transaction = pool.begin().await?;
+
+    let locked = sqlx::query!(
+        "
+        SELECT pg_try_advisory_xact_lock($1) \"locked!\"
+        ",
+        key,
+    )
+    .fetch_one(&mut *transaction)
+    .await?
+    .locked;
+
+    Ok(locked.then_some(transaction))
+}
+
+/// Failed withdrawals older than this many days are never retried automatically
+const AUTO_RETRY_WINDOW_DAYS: i64 = 7;
+
+/// Returns a withdrawal still in transit to its user's balance, marking it `status` (failed or
+/// cancelled). Returns whether it was returned: withdrawals which have already left transit are
+/// left alone, so funds are never returned twice.
+pub async fn return_payout_funds(
+    pool: &PgPool,
+    redis: &RedisPool,
+    queue: &PayoutsQueue,
+    id: crate::database::models::PayoutId,
+    status: PayoutStatus,
+) -> Result<bool, ApiError> {
+    // The user's lock is taken before the row is, like every other withdrawal path
+    let Some(user_id) = crate::database::models::payout_item::Payout::get(id, pool)
+        .await?
+        .map(|x| x.user_id)
+    else {
+        return Ok(false);
+    };
+
+    let mtx = queue.lock_user_payouts(user_id.into());
+    let _guard = mtx.lock().await;
+
+    let mut transaction = pool.begin().await?;
+
+    let Some(payout) = sqlx::query!(
+        "
+        SELECT user_id, amount, fee, withheld
+        FROM payouts
+        WHERE id = $1 AND status = $2
+        FOR UPDATE
+        ",
+        id.0,
+        PayoutStatus::InTransit.as_str(),
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let updated = sqlx::query!(
+        "
+        UPDATE payouts
+        SET status = $1
+        WHERE id = $2 AND status = $3
+        ",
+        status.as_str(),
+        id.0,
+        PayoutStatus::InTransit.as_str(),
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    if updated.rows_affected() != 1 {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "
+        UPDATE users
+        SET balance = balance + $1
+        WHERE id = $2
+        ",
+        payout.amount
+            + payout.fee.unwrap_or(Decimal::ZERO)
+            + payout.withheld.unwrap_or(Decimal::ZERO),
+        payout.user_id,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    crate::database::models::User::clear_caches(
+        &[(crate::database::models::UserId(payout.user_id), None)],
+        redis,
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// How a failed withdrawal is sent again by [`retry_failed_payout`]
+#[derive(Clone, Debug, Default)]
+pub struct PayoutRetry {
+    /// Sends it with this method instead of the original one
+    pub method: Option<PayoutMethod>,
+    /// Kept on the new withdrawal, so retrying again with the same key returns it
+    pub idempotency_key: Option<String>,
+}
+
+/// Sends a failed withdrawal again to the user's current address for its method, taking the
+/// funds from their balance once more. Returns the new withdrawal's id, or `None` if it was
+/// already retried or the user no longer has the funds. A withdrawal already retried with
+/// `retry.idempotency_key` returns that retry's id instead, and a key used for any other
+/// withdrawal is rejected.
+///
+/// The retry is checked like a new withdrawal, see [`check_withdrawals_allowed`] and
+/// [`check_withdrawal_method`], and fails if its method is no longer offered. Its fee and
+/// withholding are calculated afresh from everything the failed withdrawal took from the
+/// balance, and it is held for review if it is above [`review_threshold`].
+pub async fn retry_failed_payout(
+    pool: &PgPool,
+    redis: &RedisPool,
+    queue: &PayoutsQueue,
+    id: crate::database::models::PayoutId,
+    retry: &PayoutRetry,
+) -> Result<Option<crate::database::models::PayoutId>, ApiError> {
+    // The user's lock is taken before the failed withdrawal's row is, like every other
+    // withdrawal path
+    let user_id = crate::database::models::payout_item::Payout::get(id, pool)
+        .await?
+        .ok_or(ApiError::NotFound)?
+        .user_id;
+
+    let mtx = queue.lock_user_payouts(user_id.into());
+    let _guard = mtx.lock().await;
+
+    let mut transaction = pool.begin().await?;
+
+    // Locks the failed withdrawal, so other instances can't retry it at the same time
+    let retryable = sqlx::query!(
+        "
+        SELECT id FROM payouts p
+        WHERE id = $1 AND status = $2
+            AND NOT EXISTS (SELECT 1 FROM payouts r WHERE r.retry_of = p.id)
+        FOR UPDATE
+        ",
+        id.0,
+        PayoutStatus::Failed.as_str(),
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    if let Some(key) = &retry.idempotency_key {
+        if let Some(existing) = existing_retry(user_id, id, key, &mut transaction).await? {
+            return Ok(Some(existing));
+        }
+    }
+
+    if retryable.is_none() {
+        return Ok(None);
+    }
+
+    let payout = crate::database::models::payout_item::Payout::get(id, &mut *transaction)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let user = crate::database::models::User::get_id(payout.user_id, &mut *transaction, redis)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    check_withdrawals_allowed(queue, &user, &mut *transaction).await?;
+
+    let total = payout.amount
+        + payout.fee.unwrap_or(Decimal::ZERO)
+        + payout.withheld.unwrap_or(Decimal::ZERO);
+    if !crate::database::models::User::withdraw_balance(user.id, total, &mut transaction).await? {
+        return Ok(None);
+    }
+
+    // The key may have been used to retry another withdrawal on another instance while this one
+    // waited for the user's row, and is checked again before anything is sent
+    if let Some(key) = &retry.idempotency_key {
+        if let Some(existing) = existing_retry(user_id, id, key, &mut transaction).await? {
+            return Ok(Some(existing));
+        }
+    }
+
+    let payout_method = match &retry.method {
+        Some(payout_method) => payout_method.clone(),
+        None => {
+            let method = payout.method.unwrap_or(PayoutMethodType::Unknown);
+            let method_id = payout.method_id.clone().unwrap_or_default();
+            queue
+                .get_payout_methods()
+                .await?
+                .into_iter()
+                .find(|x| x.type_ == method && x.id == method_id)
+                .ok_or_else(|| {
+                    ApiError::InvalidInput(
+                        "The withdrawal's payment method is no longer available!".to_string(),
+                    )
+                })?
+        }
+    };
+    check_withdrawal_method(&payout_method, total)?;
+
+    let amounts = WithdrawalAmounts::calculate(
+        &payout_method,
+        Gross::new(total),
+        user.paypal_country.as_deref(),
+        PayoutRoundingMode::from_env(),
+        &PayoutWithholding::from_env(),
+        &PayPalFeeCaps::from_env(),
+    )?;
+
+    let method = payout_method.type_;
+    let method_id = payout_method.id.clone();
+    let amount = amounts.transfer.amount();
+    let fee = Some(amounts.fee.amount());
+    let withheld = Some(amounts.withheld.amount());
+    let tremendous_value =
+        TremendousOrderValue::for_method(&payout_method, amount, &FxRates::from_env())?;
+
+    let (address, display_address) =
+        crate::routes::v3::payouts::payout_address(&user, method, &method_id)?;
+
+    let retry_id = crate::database::models::generate_payout_id(&mut transaction).await?;
+    queue.metrics.observe_retry(queue.provider(method)?.name());
+    let (status, platform_id) = queue
+        .submit_payout(
+            &PayoutRequest {
+                id: retry_id.into(),
+                method,
+                method_id: method_id.clone(),
+                amount,
+                address,
+                recipient_name: user.username,
+                correlation_id: new_correlation_id(),
+                note: payout.note.clone(),
+                tremendous_value,
+            },
+            review_threshold(),
+        )
+        .await?;
+
+    crate::database::models::payout_item::Payout {
+        id: retry_id,
+        user_id: user.id,
+        created: Utc::now(),
+        status,
+        amount,
+        fee,
+        withheld,
+        method: Some(method),
+        method_id: Some(method_id),
+        method_address: Some(display_address),
+        platform_id,
+        note: payout.note,
+        idempotency_key: retry.idempotency_key.clone(),
+    }
+    .insert(&mut transaction)
+    .await?;
+
+    sqlx::query!(
+        "
+        UPDATE payouts
+        SET retry_of = $1
+        WHERE id = $2
+        ",
+        id.0,
+        retry_id.0,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    crate::database::models::User::clear_caches(&[(user.id, None)], redis).await?;
+
+    Ok(Some(retry_id))
+}
+
+/// The retry of withdrawal `id` the user already made with `idempotency_key`, if any. Fails with
+/// [`ApiError::DuplicatePayout`] if the key was used for any other withdrawal.
+async fn existing_retry(
+    user_id: crate::database::models::UserId,
+    id: crate::database::models::PayoutId,
+    idempotency_key: &str,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Option<crate::database::models::PayoutId>, ApiError> {
+    let Some(existing) = crate::database::models::payout_item::Payout::get_id_by_idempotency_key(
+        user_id,
+        idempotency_key,
+        &mut **transaction,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let retry_of = sqlx::query!(
+        "
+        SELECT retry_of
+        FROM payouts
+        WHERE id = $1
+        ",
+        existing.0,
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .retry_of;
+
+    if retry_of != Some(id.0) {
+        return Err(ApiError::DuplicatePayout(format!(
+            "idempotency_key: {idempotency_key} was already used for another withdrawal"
+        )));
+    }
+
+    Ok(Some(existing))
+}
+
+/// Catches withdrawals whose failure was missed, e.g. because the provider's webhook never
+/// arrived: every withdrawal in transit is checked with its provider, and those which failed or
+/// were cancelled are returned to the user's balance. With [`auto_retry_failed_payouts`] on,
+/// recently failed withdrawals are then sent once more. Returns how many were returned. Only one
+/// instance reconciles at a time, the others return 0, see [`try_lock_payout_job`].
+pub async fn reconcile_failed_payouts(
+    pool: &PgPool,
+    redis: &RedisPool,
+    queue: &PayoutsQueue,
+) -> Result<usize, ApiError> {
+    let Some(job_lock) = try_lock_payout_job(pool, RECONCILE_JOB_LOCK).await? else {
+        log::info!("Failed payouts are already being reconciled by another instance");
+        return Ok(0);
+    };
+
+    let in_transit = sqlx::query!(
+        "
+        SELECT id, method, platform_id
+        FROM payouts
+        WHERE status = $1 AND platform_id IS NOT NULL
+        ",
+        PayoutStatus::InTransit.as_str(),
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut returned = 0;
+    for payout in in_transit {
+        let id = crate::database::models::PayoutId(payout.id);
+        let method = PayoutMethodType::from_string(payout.method.as_deref().unwrap_or_default());
+        let platform_id = payout.platform_id.unwrap_or_default();
+
+        let status = match queue.get_payout_status(method, &platform_id).await {
+            Ok(status) => status,
+            Err(err) => {
+                log::warn!("Could not check the status of payout {}: {}", id.0, err);
+                continue;
+            }
+        };
+
+        match status {
+            PayoutStatus::Failed | PayoutStatus::Cancelled => {
+                match return_payout_funds(pool, redis, queue, id, status).await {
+                    Ok(true) => {
+                        log::info!("Returned {} payout {} to its user", status, id.0);
+                        returned += 1;
+                    }
+                    Ok(false) => {}
+                    Err(err) => log::warn!("Could not return payout {}: {}", id.0, err),
+                }
+            }
+            PayoutStatus::Success => {
+                if let Err(err) = sqlx::query!(
+                    "
+                    UPDATE payouts
+                    SET status = $1
+                    WHERE id = $2 AND status = $3
+                    ",
+                    PayoutStatus::Success.as_str(),
+                    id.0,
+                    PayoutStatus::InTransit.as_str(),
+                )
+                .execute(pool)
+                .await
+                {
+                    log::warn!("Could not mark payout {} as succeeded: {}", id.0, err);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if auto_retry_failed_payouts() {
+        let failed = sqlx::query!(
+            "
+            SELECT id
+            FROM payouts p
+            WHERE status = $1 AND created > $2 AND retry_of IS NULL
+                AND NOT EXISTS (SELECT 1 FROM payouts r WHERE r.retry_of = p.id)
+            ",
+            PayoutStatus::Failed.as_str(),
+            Utc::now() - Duration::days(AUTO_RETRY_WINDOW_DAYS),
+        )
+        .fetch_all(pool)
+        .await?;
+
+        for payout in failed {
+            if queue.is_draining() {
+                break;
+            }
+
+            let id = crate::database::models::PayoutId(payout.id);
+            match retry_failed_payout(pool, redis, queue, id, &PayoutRetry::default()).await {
+                Ok(Some(retry_id)) => {
+                    log::info!("Retried failed payout {} as {}", id.0, retry_id.0)
+                }
+                Ok(None) => {}
+                Err(err) => log::warn!("Could not retry failed payout {}: {}", id.0, err),
+            }
+        }
+    }
+
+    job_lock.rollback().await?;
+
+    Ok(returned)
+}
+
+/// Column headings of the daily payout export, see [`export_payout_day`]
+pub const PAYOUT_EXPORT_HEADER: &str = "user_id,project_id,amount,created\n";
+
+/// Streams the payouts credited for the day starting at `start` as CSV, for reconciling with
+/// provider statements. Rows are sent as they are read, so large days are never held in memory.
+pub fn export_payout_day(
+    pool: PgPool,
+    start: DateTime<Utc>,
+) -> ReceiverStream<Result<Bytes, ApiError>> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(64);
+
+    actix_rt::spawn(async move {
+        use futures::TryStreamExt;
+
+        if sender
+            .send(Ok(Bytes::from_static(PAYOUT_EXPORT_HEADER.as_bytes())))
+            .await
+            .is_err()
+        {
+            return;
+        }
+
+        let mut rows = sqlx::query!(
+            "
+            SELECT user_id, mod_id, amount, created
+            FROM payouts_values
+            WHERE created = $1
+            ORDER BY user_id, mod_id
+            ",
+            start,
+        )
+        .fetch(&pool);
+
+        loop {
+            let row = match rows.try_next().await {
+                Ok(Some(row)) => Ok(Bytes::from(payout_export_row(
+                    row.user_id,
+                    row.mod_id,
+                    row.amount,
+                    row.created,
+                ))),
+                Ok(None) => break,
+                Err(err) => Err(ApiError::from(err)),
+            };
+
+            // Stop once the client is gone, or after sending an error to cut the download short
+            let failed = row.is_err();
+            if sender.send(row).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    ReceiverStream::new(receiver)
+}
+
+/// A row of the daily payout export, with ids in base62 as they appear in the API
+fn payout_export_row(
+    user_id: i64,
+    project_id: Option<i64>,
+    amount: Decimal,
+    created: DateTime<Utc>,
+) -> String {
+    format!(
+        "{},{},{},{}\n",
+        to_base62(user_id as u64),
+        project_id.map(|x| to_base62(x as u64)).unwrap_or_default(),
+        amount.normalize(),
+        created.to_rfc3339()
+    )
+}
+
+/// Credits `credits` for the day starting at `start` in a single transaction, returning the users
+/// whose balances changed. With `replace`, the payouts already made for the day are reversed
+/// first. The `payouts_values` rows which mark the day as paid out are written last, and nothing
+/// is visible until the commit, so if this is cancelled at any await (ex: on shutdown), the
+/// dropped transaction is rolled back and the day is left to be processed again in full. The
+/// `run` summary and `audit` entry are written in the same transaction.
+async fn write_payout_day(
+    pool: &PgPool,
+    start: DateTime<Utc>,
+    credits: &[PayoutCredit],
+    reserve: Option<&PayoutReserve>,
+    replace: bool,
+    run: Option<&PayoutRun>,
+    audit: Option<&PayoutAuditEntry>,
+) -> Result<Vec<i64>, ApiError> {
+    let mut transaction = pool.begin().await?;
+
+    let mut changed_users = if replace {
+        clear_payout_day(start, &mut transaction).await?
+    } else {
+        Vec::new()
+    };
+
+    let mut totals: HashMap<i64, Decimal> = HashMap::new();
+    for credit in credits {
+        *totals.entry(credit.user_id).or_insert(Decimal::ZERO) += credit.amount;
+    }
+    if let Some(reserve) = reserve {
+        *totals.entry(reserve.user_id).or_insert(Decimal::ZERO) += reserve.amount;
+    }
+    let (user_ids, amounts): (Vec<i64>, Vec<Decimal>) = totals.into_iter().unzip();
+
+    sqlx::query!(
+        "
+        UPDATE users u
+        SET balance = u.balance + c.amount
+        FROM UNNEST($1::bigint[], $2::numeric[]) AS c(user_id, amount)
+        WHERE u.id = c.user_id
+        ",
+        &user_ids[..],
+        &amounts[..],
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO payouts_values (user_id, mod_id, amount, created)
+        SELECT * FROM UNNEST ($1::bigint[], $2::bigint[], $3::numeric[], $4::timestamptz[])
+        ",
+        &credits.iter().map(|x| x.user_id).collect::<Vec<_>>(),
+        &credits.iter().map(|x| x.project_id).collect::<Vec<_>>(),
+        &credits.iter().map(|x| x.amount).collect::<Vec<_>>(),
+        &vec![start; credits.len()],
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    // The reserve is credited for no project in particular
+    if let Some(reserve) = reserve {
+        sqlx::query!(
+            "
+            INSERT INTO payouts_values (user_id, mod_id, amount, created)
+            VALUES ($1, NULL, $2, $3)
+            ",
+            reserve.user_id,
+            reserve.amount,
+            start,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    if let Some(run) = run {
+        crate::database::models::payout_run_item::PayoutRunHistory::insert(run, &mut *transaction)
+            .await?;
+    }
+    if let Some(audit) = audit {
+        crate::database::models::payout_audit_item::PayoutAuditLog::insert(
+            audit,
+            &mut *transaction,
+        )
+        .await?;
+    }
+
+    transaction.commit().await?;
+
+    changed_users.extend(user_ids);
+    Ok(changed_users)
+}
+
+/// Deletes the payouts made for the day starting at `start` and takes them back out of the
+/// credited users' balances, returning those users. Balances are taken down to zero at most, ex:
+/// when a payout was already withdrawn, and whatever could not be taken back is logged.
+async fn clear_payout_day(
+    start: DateTime<Utc>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Vec<i64>, ApiError> {
+    let removed = sqlx::query!(
+        "
+        DELETE FROM payouts_values
+        WHERE created = $1
+        RETURNING user_id, amount
+        ",
+        start,
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    let mut totals: HashMap<i64, Decimal> = HashMap::new();
+    for row in removed {
+        *totals.entry(row.user_id).or_insert(Decimal::ZERO) += row.amount;
+    }
+
+    for (user_id, amount) in totals.iter() {
+        // Users may have already withdrawn what they were paid, so balances never go negative
+        let unrecovered = sqlx::query!(
+            r#"
+            UPDATE users u
+            SET balance = GREATEST(u.balance - $1, 0)
+            FROM users old
+            WHERE u.id = $2 AND old.id = u.id
+            RETURNING GREATEST($1 - old.balance, 0) "unrecovered!"
+            "#,
+            amount,
+            user_id
+        )
+        .fetch_optional(&mut **transaction)
+        .await?
+        .map(|x| x.unrecovered)
+        .unwrap_or_default();
+
+        if unrecovered > Decimal::ZERO {
+            log::warn!(
+                "Could not take back {unrecovered} of the payouts for {start} from user {user_id}, \
+                 it was already withdrawn"
+            );
+        }
+    }
+
+    if !totals.is_empty() {
+        log::warn!(
+            "Reversed payouts for {start} to {} users before reprocessing",
+            totals.len()
+        );
+    }
+
+    Ok(totals.into_keys().collect())
+}
+
+/// A monetized project's team, as considered for a payout day
+#[derive(Debug)]
+struct PayoutProject {
+    // user_id, payouts_split
+    team_members: Vec<(i64, Decimal)>,
+    monetized_fraction: Decimal,
+    /// Weight applied to the project's views and downloads, see [`DiscoveryBoost`]
+    boost: Decimal,
+}
+
+/// Splits `payout` across `projects` by their boosted share of `multipliers` and the fraction
+/// of the day they were monetized, then across each team by payout split. Boosted views and
+/// downloads count toward the total as well, so boosts never grow the amount paid out. Members
+/// with no share are left out.
+fn project_payout_credits(
+    payout: Decimal,
+    multipliers: &PayoutMultipliers,
+    projects: HashMap<i64, PayoutProject>,
+) -> Vec<PayoutCredit> {
+    let project_value =
+        |id: i64| Decimal::from(multipliers.values.get(&(id as u64)).copied().unwrap_or(0));
+
+    let boosted_sum = Decimal::from(multipliers.sum)
+        + projects
+            .iter()
+            .map(|(id, project)| project_value(*id) * (project.boost - Decimal::ONE))
+            .sum::<Decimal>();
+
+    let mut credits = Vec::new();
+    if boosted_sum <= Decimal::ZERO {
+        return credits;
+    }
+
+    for (id, project) in projects {
+        let project_multiplier =
+            project_value(id) * project.boost / boosted_sum * project.monetized_fraction;
+
+        let sum_splits: Decimal = project.team_members.iter().map(|x| x.1).sum();
+
+        if project_multiplier > Decimal::ZERO && sum_splits > Decimal::ZERO {
+            for (user_id, split) in project.team_members {
+                let payout: Decimal = payout * project_multiplier * (split / sum_splits);
+
+                if payout > Decimal::ZERO {
+                    credits.push(PayoutCredit {
+                        user_id,
+                        project_id: id,
+                        amount: payout,
+                    });
+                }
+            }
+        }
+    }
+
+    credits
+}
+
+/// What is left of a day's budget after every project is paid out, see [`payout_reserve_user`]
+#[derive(Clone, Debug, PartialEq)]
+struct PayoutReserve {
+    user_id: i64,
+    amount: Decimal,
+}
+
+/// A team member's share of a project's payout for the day
+#[derive(Clone, Debug, PartialEq)]
+struct PayoutCredit {
+    user_id: i64,
+    project_id: i64,
+    amount: Decimal,
+}
+
+/// The most a single user can be credited for one day across all of their projects, configured
+/// by `PAYOUTS_MAX_DAILY_USER_PAYOUT`. This limits the damage from inflated analytics. Unlimited
+/// if unset.
+fn max_daily_user_payout() -> Option<Decimal> {
+    max_daily_user_payout_with(env_var)
+}
+
+fn max_daily_user_payout_with(var: impl Fn(&str) -> Option<String>) -> Option<Decimal> {
+    parse_var_with(var, "PAYOUTS_MAX_DAILY_USER_PAYOUT")
+}
+
+/// Scales down the credits of any user whose total exceeds `cap`, keeping the proportions
+/// between their projects. The excess is not redistributed to other users, but is left over with
+/// the rest of the day's undistributed budget, which goes to the reserve if one is configured (see
+/// [`distribute_payouts`]). It is returned by user.
+fn cap_user_payouts(credits: &mut [PayoutCredit], cap: Decimal) -> HashMap<i64, Decimal> {
+    let mut totals: HashMap<i64, Decimal> = HashMap::new();
+    for credit in credits.iter() {
+        *totals.entry(credit.user_id).or_insert(Decimal::ZERO) += credit.amount;
+    }
+
+    let excess = totals
+        .into_iter()
+        .filter(|(_, total)| *total > cap)
+        .map(|(user_id, total)| (user_id, total - cap))
+        .collect::<HashMap<_, _>>();
+
+    for credit in credits.iter_mut() {
+        if let Some(excess) = excess.get(&credit.user_id) {
+            let total = cap + excess;
+            credit.amount = credit.amount * cap / total;
+        }
+    }
+
+    excess
+}
+
+/// A credit the payout self-test expected but was not computed, or computed differently
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutSelfTestDiff {
+    pub user_id: i64,
+    pub project_id: i64,
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub expected: Option<Decimal>,
+    #[serde(with = "rust_decimal::serde::float_option")]
+    pub actual: Option<Decimal>,
+}
+
+/// The outcome of [`payout_self_test`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutSelfTest {
+    pub passed: bool,
+    pub diffs: Vec<PayoutSelfTestDiff>,
+}
+
+/// Runs the payout distribution over a fixed synthetic day and compares it to the known
+/// correct credits, to verify a deployment's payout math without touching real data. The day
+/// covers the activity minimum, discovery boosts, partially monetized projects, payout splits
+/// and the daily user cap, and is independent of the configured environment.
+pub fn payout_self_test() -> PayoutSelfTest {
+    let project = |team_members: Vec<(i64, Decimal)>, monetized_fraction, boost| PayoutProject {
+        team_members,
+        monetized_fraction,
+        boost,
+    };
+
+    let mut multipliers = PayoutMultipliers {
+        sum: 950,
+        values: HashMap::from([(1, 500), (2, 300), (3, 100), (4, 50)]),
+    };
+    multipliers.apply_minimum(100);
+
+    let projects = HashMap::from([
+        (
+            1,
+            project(
+                vec![(1, Decimal::from(3)), (2, Decimal::ONE)],
+                Decimal::ONE,
+                Decimal::ONE,
+            ),
+        ),
+        (
+            2,
+            project(vec![(3, Decimal::ONE)], Decimal::new(5, 1), Decimal::ONE),
+        ),
+        (
+            3,
+            project(vec![(4, Decimal::ONE)], Decimal::ONE, Decimal::from(2)),
+        ),
+        (
+            4,
+            project(vec![(5, Decimal::ONE)], Decimal::ONE, Decimal::ONE),
+        ),
+    ]);
+
+    let mut credits = project_payout_credits(Decimal::from(1000), &multipliers, projects);
+    cap_user_payouts(&mut credits, Decimal::from(300));
+
+    let expected = [
+        (1, 1, Decimal::from(300)),
+        (2, 1, Decimal::from(125)),
+        (3, 2, Decimal::from(150)),
+        (4, 3, Decimal::from(200)),
+    ]
+    .into_iter()
+    .map(|(user_id, project_id, amount)| PayoutCredit {
+        user_id,
+        project_id,
+        amount,
+    })
+    .collect::<Vec<_>>();
+
+    let diffs = diff_payout_credits(&expected, &credits);
+    if !diffs.is_empty() {
+        log::error!("Payout self-test failed: {diffs:?}");
+    }
+
+    PayoutSelfTest {
+        passed: diffs.is_empty(),
+        diffs,
+    }
+}
+
+/// Compares the credits computed for each user and project against those expected, in order
+/// of project and user
+fn diff_payout_credits(
+    expected: &[PayoutCredit],
+    actual: &[PayoutCredit],
+) -> Vec<PayoutSelfTestDiff> {
+    let amounts = |credits: &[PayoutCredit]| {
+        credits
+            .iter()
+            .map(|x| ((x.project_id, x.user_id), x.amount))
+            .collect::<HashMap<_, _>>()
+    };
+    let expected = amounts(expected);
+    let actual = amounts(actual);
+
+    expected
+        .keys()
+        .chain(actual.keys())
+        .unique()
+        .sorted()
+        .filter_map(|key| {
+            let (expected, actual) = (expected.get(key).copied(), actual.get(key).copied());
+
+            (expected != actual).then_some(PayoutSelfTestDiff {
+                user_id: key.1,
+                project_id: key.0,
+                expected,
+                actual,
+            })
+        })
+        .collect()
+}
+
+/// Users are credited once per project they are paid for, so the same user can appear many
+/// times. This collapses them into a single cache invalidation each.
+fn clear_cache_batch(users: Vec<i64>) -> Vec<crate::database::models::UserId> {
+    users
+        .into_iter()
+        .unique()
+        .map(crate::database::models::UserId)
+        .collect()
+}
+
+/// The fraction of `[start, end)` a project spent monetized, given when its monetization
+/// status last changed. Projects without a recorded change are credited for the whole period,
+/// and so are those whose status only changed after it ended, ex: when a past day is caught up on
+/// or reprocessed, since that change says nothing about the period itself.
+fn monetized_fraction(
+    monetized_since: Option<DateTime<Utc>>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Decimal {
+    match monetized_since {
+        Some(since) if since > start && since < end => {
+            Decimal::from((end - since).num_seconds()) / Decimal::from((end - start).num_seconds())
+        }
+        _ => Decimal::ONE,
+    }
+}
+
+/// A day's total payouts against the views and downloads they were distributed over
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutRatePoint {
+    pub time: i64,
+    #[serde(with = "crate::models::payouts::money")]
+    pub payouts: Decimal,
+    pub views_and_downloads: u64,
+    /// The payout per view or download, if there were any
+    #[serde(with = "crate::models::payouts::money::option")]
+    pub rate: Option<Decimal>,
+}
+
+/// Builds a payout rate series with one point per day in `days`, keyed by unix timestamp
+pub fn payout_rate_trend(
+    days: impl Iterator<Item = DateTime<Utc>>,
+    payouts: &HashMap<i64, Decimal>,
+    views_and_downloads: &HashMap<i64, u64>,
+) -> Vec<PayoutRatePoint> {
+    days.map(|day| {
+        let time = day.timestamp();
+        let payouts = payouts.get(&time).copied().unwrap_or(Decimal::ZERO);
+        let views_and_downloads = views_and_downloads.get(&time).copied().unwrap_or(0);
+
+        PayoutRatePoint {
+            time,
+            payouts,
+            views_and_downloads,
+            rate: if views_and_downloads > 0 {
+                Some(payouts / Decimal::from(views_and_downloads))
+            } else {
+                None
+            },
+        }
+    })
+    .collect()
+}
+
+const PAYOUT_RATE_TRENDS_NAMESPACE: &str = "payout_rate_trends";
+
+/// Fetches the payout rate for each of the last `days` completed days. Results are cached, and
+/// keyed by day so that the series rolls over once today is complete.
+pub async fn fetch_payout_rate_trend(
+    pool: &PgPool,
+    redis: &RedisPool,
+    client: Arc<clickhouse::Client>,
+    days: u32,
+) -> Result<Vec<PayoutRatePoint>, ApiError> {
+    let end = start_of_today();
+    let start = end - Duration::days(days as i64);
+    let cache_key = format!("{}_{}", end.timestamp(), days);
+
+    let mut redis = redis.connect().await?;
+    if let Some(trend) = redis
+        .get_deserialized_from_json(PAYOUT_RATE_TRENDS_NAMESPACE, &cache_key)
+        .await?
+    {
+        return Ok(trend);
+    }
+
+    let payouts = sqlx::query!(
+        "
+        SELECT DATE_TRUNC('day', created) AS day, SUM(amount) amount_sum
+        FROM payouts_values
+        WHERE created >= $1 AND created < $2
+        GROUP BY day
+        ",
+        start,
+        end,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|x| Some((x.day?.timestamp(), x.amount_sum?)))
+    .collect::<HashMap<_, _>>();
+
+    let (views, downloads) =
+        crate::clickhouse::fetch_daily_payout_totals(start, end, client).await?;
+    let mut views_and_downloads = HashMap::new();
+    for total in views.into_iter().chain(downloads) {
+        *views_and_downloads.entry(total.time as i64).or_insert(0) += total.total;
+    }
+
+    let trend = payout_rate_trend(
+        (0..days as i64).map(|x| start + Duration::days(x)),
+        &payouts,
+        &views_and_downloads,
+    );
+
+    redis
+        .set_serialized_to_json(PAYOUT_RATE_TRENDS_NAMESPACE, cache_key, &trend, None)
+        .await?;
+
+    Ok(trend)
+}
+
+/// The bucket size payout totals are reported in
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutGranularity {
+    Day,
+    Week,
+    Month,
+}
+
+impl PayoutGranularity {
+    /// The `DATE_TRUNC` field buckets start at
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutGranularity::Day => "day",
+            PayoutGranularity::Week => "week",
+            PayoutGranularity::Month => "month",
+        }
+    }
+}
+
+/// The total paid out to creators over a day, week or month
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PayoutTotal {
+    /// Unix timestamp of the start of the bucket. Weeks start on Monday.
+    pub time: i64,
+    #[serde(with = "crate::models::payouts::money")]
+    pub total: Decimal,
+}
+
+/// Sums the payouts made in `[start, end)` by `granularity`, for finance reporting. Buckets
+/// without payouts are left out. The range is matched against the `payouts_values_created` index.
+pub async fn fetch_payout_totals(
+    pool: &PgPool,
+    granularity: PayoutGranularity,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<PayoutTotal>, ApiError> {
+    let totals = sqlx::query!(
+        "
+        SELECT DATE_TRUNC($1, created) AS bucket, SUM(amount) total
+        FROM payouts_values
+        WHERE created >= $2 AND created < $3
+        GROUP BY bucket
+        ORDER BY bucket
+        ",
+        granularity.as_str(),
+        start,
+        end,
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .filter_map(|x| {
+        Some(PayoutTotal {
+            time: x.bucket?.timestamp(),
+            total: x.total?,
+        })
+    })
+    .collect();
+
+    Ok(totals)
+}
+
+// Used for testing, should be the same as the above function
+pub async fn insert_payouts(
+    insert_user_ids: Vec<i64>,
+    insert_project_ids: Vec<i64>,
+    insert_payouts: Vec<Decimal>,
+    insert_starts: Vec<DateTime<Utc>>,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> sqlx::Result<PgQueryResult> {
+    sqlx::query!(
+        "
+        INSERT INTO payouts_values (user_id, mod_id, amount, created)
+        SELECT * FROM UNNEST ($1::bigint[], $2::bigint[], $3::numeric[], $4::timestamptz[])
+        ",
+        &insert_user_ids[..],
+        &insert_project_ids[..],
+        &insert_payouts[..],
+        &insert_starts[..]
+    )
+    .execute(&mut **transaction)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::payouts::PayoutDecimal;
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+
+    fn credentials_expiring_in(duration: Duration) -> PayPalCredentials {
+        PayPalCredentials {
+            access_token: "token".to_string(),
+            token_type: "Bearer".to_string(),
+            expires: Utc::now() + duration,
+        }
+    }
+
+    #[test]
+    fn paypal_token_expiring_within_skew_needs_refresh() {
+        let credentials = credentials_expiring_in(Duration::seconds(30));
+        assert!(credentials.needs_refresh(Duration::seconds(60)));
+    }
+
+    #[test]
+    fn paypal_token_expiring_after_skew_is_reused() {
+        let credentials = credentials_expiring_in(Duration::minutes(10));
+        assert!(!credentials.needs_refresh(Duration::seconds(60)));
+    }
+
+    #[actix_rt::test]
+    async fn project_counts_are_accumulated_without_buffering_rows() {
+        const PROJECTS: u64 = 1_000;
+        const ROWS: u64 = 1_000_000;
+
+        // The rows are generated as they are consumed, as they are streamed from ClickHouse, so
+        // only the accumulated map is ever held in memory
+        let rows = futures::stream::iter((0..ROWS).map(|x| Ok::<_, ()>((x % PROJECTS, 2))));
+
+        let mut values = HashMap::with_capacity(PROJECTS as usize);
+        let capacity = values.capacity();
+        accumulate_project_counts(&mut values, 1, rows)
+            .await
+            .unwrap();
+
+        assert_eq!(values.len(), PROJECTS as usize);
+        assert_eq!(values.capacity(), capacity);
+        assert!(values.values().all(|x| *x == 2 * ROWS / PROJECTS));
+    }
+
+    #[actix_rt::test]
+    async fn project_count_stream_errors_are_returned() {
+        let rows = futures::stream::iter(vec![Ok((1, 5)), Err("disconnected"), Ok((2, 5))]);
+
+        let mut values = HashMap::new();
+        assert_eq!(
+            accumulate_project_counts(&mut values, 1, rows).await,
+            Err("disconnected")
+        );
+        assert_eq!(values, HashMap::from([(1, 5)]));
+    }
+
+    #[test]
+    fn missing_payout_config_is_reported_by_name() {
+        let vars = HashMap::from([
+            ("PAYPAL_API_URL", "https://api-m.sandbox.paypal.com/v1/"),
+            ("PAYPAL_WEBHOOK_ID", "webhook"),
+            ("PAYPAL_CLIENT_ID", "client"),
+            ("TREMENDOUS_API_URL", "testflight.tremendous.com"),
+            ("TREMENDOUS_API_KEY", "key"),
+            ("TREMENDOUS_PRIVATE_KEY", " "),
+            ("TREMENDOUS_CAMPAIGN_ID", "campaign"),
+            ("PAYOUTS_AUTO_RETRY", "yes"),
+            ("PAYOUTS_VIEW_WEIGHT", "1.5"),
+            ("PAYOUTS_BLOCKED_COUNTRIES", "KP,ZZ"),
+            ("PAYOUTS_ANALYTICS_SOURCE", "mysql"),
+        ]);
+
+        let errors = payout_config_errors_with(|var| vars.get(var).map(|x| x.to_string()));
+
+        assert_eq!(
+            errors,
+            vec![
+                PayoutConfigError::Missing("PAYPAL_CLIENT_SECRET"),
+                PayoutConfigError::Invalid {
+                    var: "TREMENDOUS_API_URL",
+                    reason: "`testflight.tremendous.com` is not an http(s) URL".to_string(),
+                },
+                PayoutConfigError::Missing("TREMENDOUS_PRIVATE_KEY"),
+                PayoutConfigError::Invalid {
+                    var: "PAYOUTS_AUTO_RETRY",
+                    reason: "`yes` is not `true` or `false`".to_string(),
+                },
+                PayoutConfigError::Invalid {
+                    var: "PAYOUTS_ANALYTICS_SOURCE",
+                    reason: "`mysql` is not `clickhouse` or `postgres`".to_string(),
+                },
+                PayoutConfigError::Invalid {
+                    var: "PAYOUTS_BLOCKED_COUNTRIES",
+                    reason: "`ZZ` is not an ISO 3166-1 alpha-2 country code".to_string(),
+                },
+                PayoutConfigError::Invalid {
+                    var: "PAYOUTS_VIEW_WEIGHT",
+                    reason: "`1.5` is not a whole number".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "Variable `PAYPAL_CLIENT_SECRET` missing in dotenv"
+        );
+    }
+
+    #[test]
+    fn config_summary_reflects_configured_vars() {
+        let vars = HashMap::from([
+            ("PAYOUTS_PROJECT_CAP_PERCENTILE", "99"),
+            ("PAYOUTS_ANALYTICS_SOURCE", "postgres"),
+            ("PAYOUTS_WITHDRAWAL_RATE_LIMIT", "3"),
+            ("PAYOUTS_MAX_DAILY_USER_PAYOUT", "250.5"),
+        ]);
+        let summary =
+            PayoutsQueue::new().config_summary_with(|var| vars.get(var).map(|x| x.to_string()));
+
+        assert_eq!(
+            summary.project_cap,
+            PayoutProjectCap {
+                absolute: None,
+                percentile: Some(99),
+            }
+        );
+        assert_eq!(summary.analytics_source, PayoutAnalyticsSource::Postgres);
+        assert_eq!(summary.withdrawal_rate_limit.max, 3);
+        assert_eq!(summary.max_daily_user_payout, Some(Decimal::new(2505, 1)));
+
+        let line = summary.to_string();
+        for field in [
+            "project_cap=none",
+            "project_cap_percentile=99",
+            "analytics_source=postgres",
+            "withdrawal_rate_limit=3/",
+            "max_daily_user_payout=250.5",
+        ] {
+            assert!(line.contains(field), "`{field}` missing from `{line}`");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn payout_methods_cache_records_hits_and_misses() {
+        let queue = PayoutsQueue::new();
+
+        assert!(queue.cached_payout_methods(true).await.is_none());
+        assert_eq!(queue.metrics.methods_cache_misses.get(), 1);
+        assert_eq!(queue.metrics.methods_cache_hits.get(), 0);
+
+        *queue.payout_options.write().await = Some(PayoutMethods {
+            options: Vec::new(),
+            tremendous: true,
+            expires: Utc::now() + Duration::hours(6),
+        });
+
+        assert!(queue.cached_payout_methods(true).await.is_some());
+        assert_eq!(queue.metrics.methods_cache_misses.get(), 1);
+        assert_eq!(queue.metrics.methods_cache_hits.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn disabled_tremendous_methods_are_not_offered() {
+        let methods = PayoutsQueue::new()
+            .get_payout_methods_with(false)
+            .await
+            .unwrap();
+
+        assert!(methods
+            .iter()
+            .all(|x| x.type_ != PayoutMethodType::Tremendous));
+        assert_eq!(
+            methods.iter().map(|x| &*x.id).collect::<Vec<_>>(),
+            vec!["paypal_us", "venmo", "paypal_in"]
+        );
+    }
+
+    #[test]
+    fn payout_method_order_follows_ranking() {
+        let product = |id: &str, name: &str| PayoutMethod {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..test_method(PayoutMethodType::Tremendous)
+        };
+
+        let methods = rank_payout_methods(vec![
+            product("EIPF8Q00EMM1", "A Downranked Card"),
+            product("PLAINCARD001", "Plain Card"),
+            product("UIL1ZYJU5MKN", "Z Upranked Card"),
+        ]);
+
+        assert_eq!(
+            methods.iter().map(|x| &*x.id).collect::<Vec<_>>(),
+            vec![
+                "paypal_us",
+                "venmo",
+                "paypal_in",
+                "UIL1ZYJU5MKN",
+                "PLAINCARD001",
+                "EIPF8Q00EMM1",
+            ]
+        );
+        assert!(methods.windows(2).all(|x| x[0].order < x[1].order));
+    }
+
+    #[actix_rt::test]
+    async fn shutdown_waits_for_an_in_flight_payout() {
+        let queue = PayoutsQueue::new();
+        let sent = Arc::new(AtomicBool::new(false));
+
+        let mtx = queue.lock_user_payouts(UserId(1));
+        let guard = mtx.lock_owned().await;
+        let in_flight = {
+            let sent = sent.clone();
+            tokio::spawn(async move {
+                // Stands in for the provider call, made while holding the user's lock
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                sent.store(true, Ordering::SeqCst);
+                drop(guard);
+            })
+        };
+
+        assert!(queue.drain(std::time::Duration::from_secs(5)).await);
+        assert!(sent.load(Ordering::SeqCst));
+        assert!(queue.is_draining());
+        in_flight.await.unwrap();
+
+        // The lock stays taken, so no further withdrawal can start
+        assert!(queue.lock_user_payouts(UserId(1)).try_lock().is_err());
+    }
+
+    #[actix_rt::test]
+    async fn shutdown_gives_up_on_payouts_running_past_the_timeout() {
+        let queue = PayoutsQueue::new();
+
+        let mtx = queue.lock_user_payouts(UserId(1));
+        let _guard = mtx.lock().await;
+
+        assert!(!queue.drain(std::time::Duration::from_millis(50)).await);
+    }
+
+    #[test]
+    fn same_named_methods_are_ordered_by_id() {
+        let product = |id: &str| PayoutMethod {
+            id: id.to_string(),
+            name: "Visa Card".to_string(),
+            ..test_method(PayoutMethodType::Tremendous)
+        };
+        let ids = |methods: Vec<PayoutMethod>| {
+            rank_payout_methods(methods)
+                .into_iter()
+                .map(|x| x.id)
+                .filter(|x| x.starts_with("VISA"))
+                .collect::<Vec<_>>()
+        };
+
+        let expected = vec!["VISA00000001", "VISA00000002", "VISA00000003"];
+        for _ in 0..3 {
+            assert_eq!(
+                ids(vec![
+                    product("VISA00000002"),
+                    product("VISA00000003"),
+                    product("VISA00000001"),
+                ]),
+                expected
+            );
+            assert_eq!(
+                ids(vec![
+                    product("VISA00000003"),
+                    product("VISA00000001"),
+                    product("VISA00000002"),
+                ]),
+                expected
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn paypal_credential_sets_are_independent() {
+        let queue = PayoutsQueue::new();
+
+        queue.credentials.write().await.extend([
+            (
+                PRIMARY_PAYPAL_CREDENTIALS.to_string(),
+                credentials_expiring_in(Duration::hours(1)),
+            ),
+            (
+                "sandbox".to_string(),
+                credentials_expiring_in(Duration::seconds(30)),
+            ),
+        ]);
+
+        assert!(queue
+            .cached_credentials(PRIMARY_PAYPAL_CREDENTIALS)
+            .await
+            .is_some());
+        assert!(queue.cached_credentials("sandbox").await.is_none());
+        assert!(queue.cached_credentials("unknown").await.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn concurrent_expired_token_requests_refresh_once() {
+        let queue = PayoutsQueue::new();
+        queue.credentials.write().await.insert(
+            PRIMARY_PAYPAL_CREDENTIALS.to_string(),
+            credentials_expiring_in(Duration::seconds(-10)),
+        );
+
+        let fetches = std::sync::atomic::AtomicUsize::new(0);
+        let results = futures::future::join_all((0..50).map(|_| {
+            queue.paypal_credentials_with(PRIMARY_PAYPAL_CREDENTIALS, || async {
+                let fetch = fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+                Ok(PayPalCredentials {
+                    access_token: format!("token-{fetch}"),
+                    ..credentials_expiring_in(Duration::hours(1))
+                })
+            })
+        }))
+        .await;
+
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(results
+            .into_iter()
+            .all(|x| x.unwrap().access_token == "token-0"));
+    }
+
+    #[test]
+    fn sustained_provider_failures_are_escalated() {
+        let mut metrics = PayoutsMetrics::new();
+        metrics.failure_alert = FailureRateAlert {
+            rate: 0.5,
+            window: 4,
+        };
+        let rate = |provider| {
+            metrics
+                .provider_failure_rate
+                .with_label_values(&[provider])
+                .get()
+        };
+
+        // Too few requests to tell a sustained failure from a blip
+        metrics.observe_success("paypal");
+        assert!(!metrics.observe_error("paypal", "transport"));
+        assert!(!metrics.observe_error("paypal", "transport"));
+        assert_eq!(rate("paypal"), 2.0 / 3.0);
+
+        // Three and then all of the last four requests failed
+        assert!(metrics.observe_error("paypal", "api"));
+        assert!(metrics.observe_error("paypal", "api"));
+        assert_eq!(
+            metrics
+                .provider_failures
+                .with_label_values(&["paypal"])
+                .get(),
+            4
+        );
+        assert_eq!(rate("paypal"), 1.0);
+
+        // Other providers are tracked separately
+        metrics.observe_success("tremendous");
+        assert_eq!(rate("tremendous"), 0.0);
+
+        // Recovered, so a single failure is no longer escalated
+        for _ in 0..3 {
+            metrics.observe_success("paypal");
+        }
+        assert_eq!(rate("paypal"), 0.25);
+        assert!(!metrics.observe_error("paypal", "api"));
+    }
+
+    #[test]
+    fn provider_results_are_recorded_once_with_their_category() {
+        let metrics = PayoutsMetrics::new();
+        let count = |category| {
+            metrics
+                .provider_errors
+                .with_label_values(&["tremendous", category])
+                .get()
+        };
+
+        let decoded = metrics.observe_result(
+            "tremendous",
+            serde_json::from_str::<u64>("\"not a number\"")
+                .map_err(|err| ProviderRequestError::new("decode", err)),
+        );
+        assert!(matches!(decoded, Err(ApiError::Json(_))));
+        assert_eq!(count("decode"), 1);
+
+        assert_eq!(metrics.observe_result("tremendous", Ok(1)).unwrap(), 1);
+        assert_eq!(
+            metrics
+                .provider_failures
+                .with_label_values(&["tremendous"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics
+                .provider_failure_rate
+                .with_label_values(&["tremendous"])
+                .get(),
+            0.5
+        );
+    }
+
+    #[test]
+    fn paypal_sender_item_id_collision_is_a_duplicate_payout() {
+        let error = paypal_error(serde_json::json!({
+            "name": "VALIDATION_ERROR",
+            "message": "Invalid request - see details",
+            "details": [{
+                "field": "items[0].sender_item_id",
+                "location": "body",
+                "issue": "Batch contains duplicate sender_item_id"
+            }]
+        }));
+
+        assert!(matches!(error, ApiError::DuplicatePayout(_)));
+    }
+
+    #[test]
+    fn other_paypal_errors_are_payment_errors() {
+        let error = paypal_error(serde_json::json!({
+            "name": "VALIDATION_ERROR",
+            "message": "Invalid request - see details",
+            "details": [{
+                "field": "items[0].amount.value",
+                "issue": "Amount must be greater than zero"
+            }]
+        }));
+        assert!(matches!(error, ApiError::Payments(_)));
+
+        let error = paypal_error(serde_json::json!({
+            "name": "VALIDATION_ERROR",
+            "message": "Invalid request - see details",
+            "details": [{
+                "field": "items[0].recipient_sender_item_id",
+                "issue": "Unknown field"
+            }]
+        }));
+        assert!(matches!(error, ApiError::Payments(_)));
+
+        let error = paypal_error(serde_json::json!({
+            "error": "invalid_client",
+            "error_description": "Client Authentication failed"
+        }));
+        assert!(matches!(error, ApiError::Payments(_)));
+    }
+
+    #[derive(Default)]
+    struct MockProvider {
+        calls: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl PayoutProvider for MockProvider {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn mode(&self) -> PayoutProviderMode {
+            PayoutProviderMode::Sandbox
+        }
+
+        fn supported_methods(&self) -> &'static [PayoutMethodType] {
+            &[PayoutMethodType::Tremendous]
+        }
+
+        async fn create_payout(
+            &self,
+            _queue: &PayoutsQueue,
+            request: &PayoutRequest,
+        ) -> Result<Option<String>, ApiError> {
+            self.calls.lock().unwrap().push(format!(
+                "create {} {} {}",
+                request.address, request.amount, request.correlation_id
+            ));
+            Ok(Some(format!("mock-{}", request.method_id)))
+        }
+
+        async fn get_status(
+            &self,
+            _queue: &PayoutsQueue,
+            platform_id: &str,
+        ) -> Result<PayoutStatus, ApiError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("status {platform_id}"));
+            Ok(PayoutStatus::Success)
+        }
+
+        async fn cancel_payout(
+            &self,
+            _queue: &PayoutsQueue,
+            platform_id: &str,
+        ) -> Result<(), ApiError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("cancel {platform_id}"));
+            Ok(())
+        }
+    }
+
+    fn test_request(method: PayoutMethodType) -> PayoutRequest {
+        PayoutRequest {
+            id: PayoutId(1),
+            method,
+            method_id: "product".to_string(),
+            amount: Decimal::from(10),
+            address: "user@modrinth.com".to_string(),
+            recipient_name: "user".to_string(),
+            correlation_id: "correlation".to_string(),
+            note: None,
+            tremendous_value: None,
+        }
+    }
+
+    #[test]
+    fn paypal_requests_carry_correlation_id() {
+        let correlation_id = new_correlation_id();
+        let request = paypal_request(
+            &reqwest::Client::new(),
+            Method::POST,
+            "https://api-m.sandbox.paypal.com/v1/payments/payouts".to_string(),
+            &credentials_expiring_in(Duration::hours(1)),
+            &correlation_id,
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            request.headers()[PAYPAL_REQUEST_ID_HEADER],
+            correlation_id.as_str()
+        );
+        assert_ne!(new_correlation_id(), correlation_id);
+    }
+
+    #[actix_rt::test]
+    async fn slow_clickhouse_query_times_out() {
+        let slow = with_query_timeout(std::time::Duration::from_millis(10), async {
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            Ok::<u64, clickhouse::error::Error>(1)
+        })
+        .await;
+        assert!(matches!(slow, Err(ApiError::Timeout(_))));
+
+        let fast = with_query_timeout(std::time::Duration::from_secs(60), async {
+            Ok::<u64, clickhouse::error::Error>(1)
+        })
+        .await;
+        assert_eq!(fast.unwrap(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn payouts_are_dispatched_to_provider() {
+        let provider = MockProvider::default();
+        let calls = provider.calls.clone();
+        let queue = PayoutsQueue::with_providers(vec![Box::new(provider)]);
+
+        let platform_id = queue
+            .create_payout(&test_request(PayoutMethodType::Tremendous))
+            .await
+            .unwrap();
+        assert_eq!(platform_id.as_deref(), Some("mock-product"));
+
+        let status = queue
+            .get_payout_status(PayoutMethodType::Tremendous, "mock-product")
+            .await
+            .unwrap();
+        assert_eq!(status, PayoutStatus::Success);
+
+        queue
+            .cancel_payout(PayoutMethodType::Tremendous, "mock-product")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "create user@modrinth.com 10 correlation",
+                "status mock-product",
+                "cancel mock-product",
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn payouts_below_review_threshold_are_sent() {
+        let provider = MockProvider::default();
+        let calls = provider.calls.clone();
+        let queue = PayoutsQueue::with_providers(vec![Box::new(provider)]);
+
+        let (status, platform_id) = queue
+            .submit_payout(
+                &test_request(PayoutMethodType::Tremendous),
+                Some(Decimal::from(10)),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(status, PayoutStatus::InTransit);
+        assert_eq!(platform_id.as_deref(), Some("mock-product"));
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn payouts_above_review_threshold_are_held() {
+        let provider = MockProvider::default();
+        let calls = provider.calls.clone();
+        let queue = PayoutsQueue::with_providers(vec![Box::new(provider)]);
+
+        let (status, platform_id) = queue
+            .submit_payout(
+                &test_request(PayoutMethodType::Tremendous),
+                Some(Decimal::from(5)),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(status, PayoutStatus::PendingReview);
+        assert_eq!(platform_id, None);
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn payouts_without_provider_are_rejected() {
+        let provider = MockProvider::default();
+        let calls = provider.calls.clone();
+        let queue = PayoutsQueue::with_providers(vec![Box::new(provider)]);
+
+        let result = queue
+            .create_payout(&test_request(PayoutMethodType::PayPal))
+            .await;
+
+        assert!(matches!(result, Err(ApiError::InvalidInput(_))));
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    fn test_destination(
+        method: PayoutMethodType,
+        method_id: &str,
+        percentage: i64,
+    ) -> PayoutDestination {
+        PayoutDestination {
+            method,
+            method_id: method_id.to_string(),
+            percentage: Decimal::from(percentage),
+        }
+    }
+
+    fn split_requests(
+        amount: Decimal,
+        destinations: &[PayoutDestination],
+        methods: &[PayoutMethod],
+    ) -> Vec<PayoutRequest> {
+        destinations
+            .iter()
+            .zip(split_withdrawal(amount, destinations))
+            .enumerate()
+            .map(|(index, (destination, amount))| {
+                let method = methods
+                    .iter()
+                    .find(|x| x.id == destination.method_id)
+                    .unwrap();
+                let amounts = WithdrawalAmounts::calculate(
+                    method,
+                    Gross::new(amount),
+                    Some("US"),
+                    PayoutRoundingMode::HalfUp,
+                    &PayoutWithholding::default(),
+                    &PayPalFeeCaps::default(),
+                )
+                .unwrap();
+
+                PayoutRequest {
+                    id: PayoutId(index as u64),
+                    method: destination.method,
+                    method_id: destination.method_id.clone(),
+                    amount: amounts.transfer.amount(),
+                    correlation_id: format!("correlation-{index}"),
+                    ..test_request(destination.method)
+                }
+            })
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn split_withdrawal_creates_a_payout_per_destination() {
+        let provider = MockProvider::default();
+        let calls = provider.calls.clone();
+        let queue = PayoutsQueue::with_providers(vec![Box::new(provider)]);
+
+        let methods = [
+            PayoutMethod {
+                id: "main".to_string(),
+                ..test_method(PayoutMethodType::Tremendous)
+            },
+            PayoutMethod {
+                id: "savings".to_string(),
+                fee: PayoutMethodFee {
+                    percentage: Decimal::ZERO,
+                    min: Decimal::ONE,
+                    max: None,
+                },
+                ..test_method(PayoutMethodType::Tremendous)
+            },
+        ];
+        let destinations = [
+            test_destination(PayoutMethodType::Tremendous, "main", 80),
+            test_destination(PayoutMethodType::Tremendous, "savings", 20),
+        ];
+        validate_payout_destinations(&destinations).unwrap();
+
+        let results = queue
+            .submit_split_payout(
+                &split_requests(Decimal::from(100), &destinations, &methods),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                (PayoutStatus::InTransit, Some("mock-main".to_string())),
+                (PayoutStatus::InTransit, Some("mock-savings".to_string())),
+            ]
+        );
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "create user@modrinth.com 80 correlation-0",
+                "create user@modrinth.com 19 correlation-1",
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn split_withdrawal_is_reviewed_by_its_total() {
+        let provider = MockProvider::default();
+        let calls = provider.calls.clone();
+        let queue = PayoutsQueue::with_providers(vec![Box::new(provider)]);
+
+        let methods = [
+            PayoutMethod {
+                id: "main".to_string(),
+                ..test_method(PayoutMethodType::Tremendous)
+            },
+            PayoutMethod {
+                id: "savings".to_string(),
+                ..test_method(PayoutMethodType::Tremendous)
+            },
+        ];
+        let destinations = [
+            test_destination(PayoutMethodType::Tremendous, "main", 50),
+            test_destination(PayoutMethodType::Tremendous, "savings", 50),
+        ];
+
+        // Neither part is over the threshold, but together they are
+        let results = queue
+            .submit_split_payout(
+                &split_requests(Decimal::from(100), &destinations, &methods),
+                Some(Decimal::from(75)),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(results, vec![(PayoutStatus::PendingReview, None); 2]);
+        assert!(calls.lock().unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn failed_split_withdrawal_cancels_sent_parts() {
+        let provider = MockProvider::default();
+        let calls = provider.calls.clone();
+        let queue = PayoutsQueue::with_providers(vec![Box::new(provider)]);
+
+        let methods = [
+            PayoutMethod {
+                id: "main".to_string(),
+                ..test_method(PayoutMethodType::Tremendous)
+            },
+            PayoutMethod {
+                id: "paypal".to_string(),
+                ..test_method(PayoutMethodType::PayPal)
+            },
+        ];
+        let destinations = [
+            test_destination(PayoutMethodType::Tremendous, "main", 80),
+            test_destination(PayoutMethodType::PayPal, "paypal", 20),
+        ];
+
+        let result = queue
+            .submit_split_payout(
+                &split_requests(Decimal::from(100), &destinations, &methods),
+                None,
+            )
+            .await;
+
+        assert!(matches!(result, Err(ApiError::InvalidInput(_))));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "create user@modrinth.com 80 correlation-0",
+                "cancel mock-main",
+            ]
+        );
+    }
+
+    #[test]
+    fn split_withdrawal_parts_add_up_to_amount() {
+        let destinations = [
+            test_destination(PayoutMethodType::PayPal, "a", 34),
+            test_destination(PayoutMethodType::PayPal, "b", 33),
+            test_destination(PayoutMethodType::PayPal, "c", 33),
+        ];
+
+        assert_eq!(
+            split_withdrawal(Decimal::new(1001, 2), &destinations),
+            vec![
+                Decimal::new(341, 2),
+                Decimal::new(330, 2),
+                Decimal::new(330, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn payout_destinations_must_add_up_to_100() {
+        assert!(validate_payout_destinations(&[
+            test_destination(PayoutMethodType::PayPal, "a", 80),
+            test_destination(PayoutMethodType::PayPal, "b", 30),
+        ])
+        .is_err());
+        assert!(validate_payout_destinations(&[
+            test_destination(PayoutMethodType::PayPal, "a", 80),
+            test_destination(PayoutMethodType::PayPal, "a", 20),
+        ])
+        .is_err());
+        assert!(validate_payout_destinations(&[
+            test_destination(PayoutMethodType::PayPal, "a", 120),
+            test_destination(PayoutMethodType::PayPal, "b", -20),
+        ])
+        .is_err());
+        assert!(validate_payout_destinations(&[]).is_err());
+        assert!(validate_payout_destinations(&[PayoutDestination {
+            percentage: Decimal::new(100_001, 3),
+            ..test_destination(PayoutMethodType::PayPal, "a", 0)
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn invalid_destination_formats_are_rejected() {
+        assert!(validate_destination_format(PayoutMethodType::Tremendous, "not an email").is_err());
+        assert!(validate_destination_format(PayoutMethodType::PayPal, "user@@modrinth").is_err());
+        assert!(validate_destination_format(PayoutMethodType::Venmo, "no spaces allowed").is_err());
+
+        assert!(
+            validate_destination_format(PayoutMethodType::Tremendous, "user@modrinth.com").is_ok()
+        );
+        assert!(
+            validate_destination_format(PayoutMethodType::PayPal, " user@modrinth.com ").is_ok()
+        );
+        assert!(validate_destination_format(PayoutMethodType::Venmo, "@modrinth-user").is_ok());
+    }
+
+    #[test]
+    fn destinations_are_format_checked() {
+        let invalid =
+            DestinationValidation::check(PayoutMethodType::Tremendous, "user.modrinth.com");
+        assert!(!invalid.deliverable);
+        assert!(invalid.reason.is_some());
+
+        assert_eq!(
+            DestinationValidation::check(PayoutMethodType::Tremendous, "user@modrinth.com"),
+            DestinationValidation {
+                deliverable: true,
+                reason: None,
+            }
+        );
+    }
+
+    #[test]
+    fn sandbox_urls_are_classified_as_sandbox() {
+        for url in [
+            "https://api-m.sandbox.paypal.com/v1/",
+            "https://testflight.tremendous.com/api/v2/",
+        ] {
+            assert_eq!(
+                PayoutProviderMode::from_api_url(url),
+                PayoutProviderMode::Sandbox
+            );
+        }
+    }
+
+    #[test]
+    fn live_urls_are_classified_as_live() {
+        for url in [
+            "https://api-m.paypal.com/v1/",
+            "https://www.tremendous.com/api/v2/",
+        ] {
+            assert_eq!(
+                PayoutProviderMode::from_api_url(url),
+                PayoutProviderMode::Live
+            );
+        }
+    }
+
+    #[test]
+    fn provider_modes_are_keyed_by_name() {
+        let queue = PayoutsQueue::with_providers(vec![Box::new(MockProvider::default())]);
+
+        assert_eq!(
+            queue.provider_modes(),
+            HashMap::from([("mock", PayoutProviderMode::Sandbox)])
+        );
+    }
+
+    #[test]
+    fn default_providers_cover_every_method() {
+        let queue = PayoutsQueue::new();
+
+        for method in [
+            PayoutMethodType::PayPal,
+            PayoutMethodType::Venmo,
+            PayoutMethodType::Tremendous,
+        ] {
+            assert!(queue.provider(method).is_ok());
+        }
+        assert!(queue.provider(PayoutMethodType::Unknown).is_err());
+    }
+
+    #[test]
+    fn project_monetized_at_noon_receives_half_credit() {
+        let start = Utc.with_ymd_and_hms(2023, 12, 1, 0, 0, 0).unwrap();
+        let end = start + Duration::days(1);
+
+        assert_eq!(
+            monetized_fraction(Some(start + Duration::hours(12)), start, end),
+            Decimal::new(5, 1)
+        );
+    }
+
+    #[test]
+    fn project_monetized_all_day_receives_full_credit() {
+        let start = Utc.with_ymd_and_hms(2023, 12, 1, 0, 0, 0).unwrap();
+        let end = start + Duration::days(1);
+
+        assert_eq!(monetized_fraction(None, start, end), Decimal::ONE);
+        assert_eq!(
+            monetized_fraction(Some(start - Duration::days(3)), start, end),
+            Decimal::ONE
+        );
+    }
+
+    #[test]
+    fn status_changes_after_the_day_are_ignored() {
+        let start = Utc.with_ymd_and_hms(2023, 12, 1, 0, 0, 0).unwrap();
+        let end = start + Duration::days(1);
+
+        assert_eq!(monetized_fraction(Some(end), start, end), Decimal::ONE);
+        assert_eq!(
+            monetized_fraction(Some(end + Duration::hours(1)), start, end),
+            Decimal::ONE
+        );
+    }
+
+    fn test_period() -> PayoutPeriod {
+        // 2023-12-04 is a Monday
+        let start = Utc.with_ymd_and_hms(2023, 12, 4, 0, 0, 0).unwrap();
+        PayoutPeriod::new(start, start + Duration::days(28), Decimal::from(2800))
+    }
+
+    #[test]
+    fn payout_period_allocations_sum_to_budget() {
+        let period = test_period();
+
+        let total: Decimal = period.days().map(|x| period.daily_allocation(x)).sum();
+        assert_eq!(total.round_dp(10), period.budget);
+    }
+
+    #[test]
+    fn payout_period_weights_weekends() {
+        let period = test_period();
+        let monday = period.start;
+        let saturday = period.start + Duration::days(5);
+
+        // 20 weekdays + 8 weekend days weighted by 1.25 = 30 shares
+        assert_eq!(
+            period.daily_allocation(monday),
+            Decimal::from(2800) / Decimal::from(30)
+        );
+        assert_eq!(
+            period.daily_allocation(saturday),
+            period.daily_allocation(monday) * period.weekend_bonus
+        );
+    }
+
+    #[test]
+    fn configured_weekend_days_receive_the_bonus() {
+        let mut period = test_period();
+        period.weekend_days = parse_weekend_days("fri,sat").unwrap();
+        let monday = period.start;
+        let friday = period.start + Duration::days(4);
+        let saturday = period.start + Duration::days(5);
+        let sunday = period.start + Duration::days(6);
+
+        let bonus = period.daily_allocation(monday) * period.weekend_bonus;
+        assert_eq!(period.daily_allocation(friday), bonus);
+        assert_eq!(period.daily_allocation(saturday), bonus);
+        assert_eq!(
+            period.daily_allocation(sunday),
+            period.daily_allocation(monday)
+        );
+    }
+
+    #[test]
+    fn weekend_days_must_be_days_of_the_week() {
+        assert_eq!(
+            parse_weekend_days("Friday, sat"),
+            Ok(vec![Weekday::Fri, Weekday::Sat])
+        );
+        assert_eq!(
+            parse_weekend_days("fri,someday"),
+            Err("someday".to_string())
+        );
+    }
+
+    #[test]
+    fn payout_period_allocates_nothing_outside_period() {
+        let period = test_period();
+
+        assert_eq!(
+            period.daily_allocation(period.start - Duration::days(1)),
+            Decimal::ZERO
+        );
+        assert_eq!(period.daily_allocation(period.end), Decimal::ZERO);
+        assert_eq!(period.days().count(), 28);
+        assert_eq!(period.last_day(), period.start + Duration::days(27));
+    }
+
+    #[test]
+    fn project_share_scales_with_partial_counts() {
+        let multipliers = |project_count| PayoutMultipliers {
+            sum: 1000,
+            values: HashMap::from([(1, project_count), (2, 1000 - project_count)]),
+        };
+
+        let period = test_period();
+        let estimate =
+            |count| period.daily_allocation(period.start) * multipliers(count).project_share(1);
+
+        assert_eq!(estimate(200), estimate(100) * Decimal::from(2));
+        assert_eq!(multipliers(100).project_share(3), Decimal::ZERO);
+        assert_eq!(
+            PayoutMultipliers {
+                sum: 0,
+                values: HashMap::new(),
+            }
+            .project_share(1),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn outlier_project_is_capped_and_the_rest_share_its_excess() {
+        let multipliers = || PayoutMultipliers {
+            sum: 1_000_400,
+            values: HashMap::from([(1, 1_000_000), (2, 100), (3, 300)]),
+        };
+
+        let mut capped = multipliers();
+        capped.apply_cap(&PayoutProjectCap {
+            absolute: Some(1_000),
+            percentile: None,
+        });
+        assert_eq!(
+            capped.values,
+            HashMap::from([(1, 1_000), (2, 100), (3, 300)])
+        );
+        assert_eq!(capped.sum, 1_400);
+        assert_eq!(
+            capped.project_share(1),
+            Decimal::from(1_000) / Decimal::from(1_400)
+        );
+
+        // The rest keep their counts, so the excess is shared between them in proportion
+        assert!(capped.project_share(2) > multipliers().project_share(2) * Decimal::from(500));
+        assert_eq!(capped.values.values().sum::<u64>(), capped.sum);
+
+        let mut uncapped = multipliers();
+        uncapped.apply_cap(&PayoutProjectCap::default());
+        assert_eq!(uncapped, multipliers());
+    }
+
+    #[test]
+    fn high_conversion_projects_are_boosted_over_low_conversion_ones() {
+        // Both projects have 100 views, but only one is downloaded much
+        let views = HashMap::from([(1, 100), (2, 100)]);
+        let downloads = HashMap::from([(1, 50), (2, 10)]);
+        let multipliers = || PayoutMultipliers {
+            sum: 260,
+            values: HashMap::from([(1, 150), (2, 110)]),
+        };
+
+        let mut weighted = multipliers();
+        weighted.apply_conversion_weighting(&ConversionWeighting::default(), &views, &downloads);
+
+        // Converting at 0.5 and 0.1 against 0.3 overall, the second held to the 0.5x lower bound
+        assert_eq!(weighted.values, HashMap::from([(1, 250), (2, 55)]));
+        assert_eq!(weighted.sum, 305);
+        assert!(weighted.project_share(1) > multipliers().project_share(1));
+        assert!(weighted.project_share(2) < multipliers().project_share(2));
+
+        let mut neutral = multipliers();
+        neutral.apply_conversion_weighting(
+            &ConversionWeighting {
+                min: Decimal::ONE,
+                max: Decimal::ONE,
+            },
+            &views,
+            &downloads,
+        );
+        assert_eq!(neutral, multipliers());
+
+        // Projects no longer being paid out don't move the overall rate
+        let views = HashMap::from([(1, 100), (2, 100), (3, 1_000)]);
+        let mut filtered = multipliers();
+        filtered.apply_conversion_weighting(&ConversionWeighting::default(), &views, &downloads);
+        assert_eq!(filtered, weighted);
+    }
+
+    #[test]
+    fn projects_below_the_minimum_are_excluded_and_their_share_redistributed() {
+        let multipliers = || PayoutMultipliers {
+            sum: 1_005,
+            values: HashMap::from([(1, 600), (2, 400), (3, 5)]),
+        };
+
+        let mut filtered = multipliers();
+        filtered.apply_minimum(10);
+        assert_eq!(filtered.values, HashMap::from([(1, 600), (2, 400)]));
+        assert_eq!(filtered.sum, 1_000);
+        assert_eq!(filtered.project_share(3), Decimal::ZERO);
+        assert_eq!(
+            filtered.project_share(1) + filtered.project_share(2),
+            Decimal::ONE
+        );
+        assert!(filtered.project_share(1) > multipliers().project_share(1));
+
+        let mut unfiltered = multipliers();
+        unfiltered.apply_minimum(0);
+        assert_eq!(unfiltered, multipliers());
+    }
+
+    #[test]
+    fn percentile_cap_binds_at_a_count_a_project_earned() {
+        let values = [10, 20, 30, 40, 10_000];
+        let cap = |absolute, percentile| {
+            PayoutProjectCap {
+                absolute,
+                percentile,
+            }
+            .limit(values.iter())
+        };
+
+        assert_eq!(cap(None, Some(80)), Some(40));
+        assert_eq!(cap(None, Some(100)), Some(10_000));
+        assert_eq!(cap(Some(25), Some(80)), Some(25));
+        assert_eq!(cap(Some(50), Some(80)), Some(40));
+        assert_eq!(cap(None, None), None);
+        assert_eq!(
+            PayoutProjectCap {
+                absolute: None,
+                percentile: Some(80),
+            }
+            .limit([].iter()),
+            None
+        );
+    }
+
+    fn test_method(type_: PayoutMethodType) -> PayoutMethod {
+        PayoutMethod {
+            id: "test".to_string(),
+            type_,
+            name: "Test".to_string(),
+            supported_countries: vec!["US".to_string()],
+            supported_currencies: vec!["USD".to_string()],
+            image_url: None,
+            interval: PayoutInterval::Standard {
+                min: Decimal::from(5),
+                max: Decimal::from(100),
+            },
+            fee: PayoutMethodFee {
+                percentage: Decimal::ZERO,
+                min: Decimal::ZERO,
+                max: None,
+            },
+            usd_equivalent: None,
+            order: 0,
+            available: true,
+            currency: None,
+            decimal_places: 2,
+            description: None,
+            disclosure: None,
+            maintenance: None,
+            minimum: None,
+        }
+    }
+
+    #[test]
+    fn payout_methods_are_looked_up_in_the_order_given() {
+        let method = |id: &str| PayoutMethod {
+            id: id.to_string(),
+            ..test_method(PayoutMethodType::Tremendous)
+        };
+        let ids = ["venmo", "missing", "paypal_us", "venmo", "gone"]
+            .map(String::from)
+            .to_vec();
+
+        let lookup = PayoutMethodLookup::new(
+            vec![method("paypal_us"), method("paypal_in"), method("venmo")],
+            &ids,
+        );
+
+        assert_eq!(
+            lookup.methods.iter().map(|x| &*x.id).collect::<Vec<_>>(),
+            vec!["venmo", "paypal_us", "venmo"]
+        );
+        assert_eq!(lookup.unknown_ids, vec!["missing", "gone"]);
+        assert!(PayoutMethodLookup::new(Vec::new(), &[]).methods.is_empty());
+    }
+
+    fn test_recipient() -> PayoutRecipient {
+        PayoutRecipient {
+            country: Some("US".to_string()),
+            balance: Decimal::from(50),
+            email_verified: true,
+            paypal_linked: true,
+            venmo_linked: true,
+            country_blocked: false,
+            on_hold: false,
+            min_withdrawal: None,
+        }
+    }
+
+    #[test]
+    fn balances_under_the_minimum_withdrawal_are_under_threshold() {
+        let method = test_method(PayoutMethodType::PayPal);
+        let recipient = |balance: i64| PayoutRecipient {
+            balance: Decimal::from(balance),
+            min_withdrawal: Some(Decimal::from(20)),
+            ..test_recipient()
+        };
+
+        assert_eq!(
+            recipient(19).ineligibility(&method),
+            vec![PayoutIneligibility::UnderThreshold]
+        );
+        assert!(recipient(20).ineligibility(&method).is_empty());
+    }
+
+    #[test]
+    fn blocked_countries_are_parsed_and_checked() {
+        let (blocked, invalid) = BlockedCountries::parse(" kp, CU,,XX ,IR");
+
+        assert_eq!(invalid, vec!["XX"]);
+        assert!(blocked.is_blocked(Some("KP")));
+        assert!(blocked.is_blocked(Some("cu")));
+        assert!(!blocked.is_blocked(Some("US")));
+        assert!(matches!(
+            blocked.check(Some("IR")),
+            Err(ApiError::Compliance(_))
+        ));
+        assert!(blocked.check(Some("DE")).is_ok());
+
+        // Recipients who could be anywhere are only let through while nowhere is blocked
+        assert!(blocked.is_blocked(None));
+        assert!(matches!(blocked.check(None), Err(ApiError::Compliance(_))));
+        assert!(!BlockedCountries::default().is_blocked(None));
+        assert!(BlockedCountries::default().check(None).is_ok());
+    }
+
+    #[test]
+    fn maintenance_windows_cover_methods_by_id_or_type() {
+        let at = |hour: u32| Utc.with_ymd_and_hms(2024, 1, 6, hour, 0, 0).unwrap();
+        let windows = PayoutMaintenanceWindows::parse(
+            r#"[
+                {"method": "paypal_us", "start": "2024-01-06T02:00:00Z", "end": "2024-01-06T04:00:00Z", "reason": "PayPal maintenance"},
+                {"method": "paypal", "start": "2024-01-06T03:00:00Z", "end": "2024-01-06T05:00:00Z", "reason": "PayPal upgrade"}
+            ]"#,
+        )
+        .unwrap();
+        let paypal_us = PayoutMethod {
+            id: "paypal_us".to_string(),
+            ..test_method(PayoutMethodType::PayPal)
+        };
+        let venmo = test_method(PayoutMethodType::Venmo);
+
+        assert_eq!(windows.active(&paypal_us, at(1)), None);
+        assert_eq!(
+            windows.active(&paypal_us, at(2)),
+            Some(PayoutMaintenance {
+                reason: "PayPal maintenance".to_string(),
+                until: at(4),
+            })
+        );
+        // Overlapping windows report the one the method is back after
+        assert_eq!(windows.active(&paypal_us, at(3)).unwrap().until, at(5));
+        assert_eq!(windows.active(&paypal_us, at(5)), None);
+        assert_eq!(windows.active(&venmo, at(3)), None);
+
+        assert!(PayoutMaintenanceWindows::parse("paypal_us").is_err());
+    }
+
+    #[test]
+    fn method_under_maintenance_reports_the_maintenance() {
+        let mut method = test_method(PayoutMethodType::PayPal);
+        method.available = false;
+        method.maintenance = Some(PayoutMaintenance {
+            reason: "PayPal maintenance".to_string(),
+            until: Utc::now() + Duration::hours(1),
+        });
+
+        assert_eq!(
+            test_recipient().ineligibility(&method),
+            vec![PayoutIneligibility::Maintenance]
+        );
+        assert!(matches!(
+            check_payout_maintenance(&method),
+            Err(ApiError::ServiceUnavailable(message)) if message.ends_with(": PayPal maintenance")
+        ));
+    }
+
+    #[test]
+    fn blocked_recipient_is_ineligible_for_every_method() {
+        let recipient = PayoutRecipient {
+            country_blocked: true,
+            ..test_recipient()
+        };
+
+        for type_ in [PayoutMethodType::PayPal, PayoutMethodType::Tremendous] {
+            assert_eq!(
+                recipient.ineligibility(&test_method(type_)),
+                vec![PayoutIneligibility::CountryBlocked]
+            );
+        }
+    }
+
+    #[test]
+    fn held_recipient_is_ineligible_for_every_method() {
+        let recipient = PayoutRecipient {
+            on_hold: true,
+            ..test_recipient()
+        };
+
+        for type_ in [PayoutMethodType::PayPal, PayoutMethodType::Tremendous] {
+            assert_eq!(
+                recipient.ineligibility(&test_method(type_)),
+                vec![PayoutIneligibility::OnHold]
+            );
+        }
+    }
+
+    #[test]
+    fn unverified_recipient_is_ineligible_for_gift_cards() {
+        let recipient = PayoutRecipient {
+            email_verified: false,
+            ..test_recipient()
+        };
+
+        assert_eq!(
+            recipient.ineligibility(&test_method(PayoutMethodType::Tremendous)),
+            vec![PayoutIneligibility::AccountUnverified]
+        );
+        assert!(recipient
+            .ineligibility(&test_method(PayoutMethodType::PayPal))
+            .is_empty());
+    }
+
+    #[test]
+    fn ineligibility_lists_every_reason() {
+        let recipient = PayoutRecipient {
+            country: Some("DE".to_string()),
+            balance: Decimal::ONE,
+            ..test_recipient()
+        };
+        let mut method = test_method(PayoutMethodType::PayPal);
+        method.available = false;
+
+        assert_eq!(
+            recipient.ineligibility(&method),
+            vec![
+                PayoutIneligibility::CountryUnsupported,
+                PayoutIneligibility::UnderThreshold,
+                PayoutIneligibility::ProviderDown,
+            ]
+        );
+    }
+
+    #[test]
+    fn fee_comparison_ranks_methods_by_net() {
+        let method = |id: &str, percentage: Decimal, min: Decimal| PayoutMethod {
+            id: id.to_string(),
+            fee: PayoutMethodFee {
+                percentage,
+                min,
+                max: None,
+            },
+            ..test_method(PayoutMethodType::Tremendous)
+        };
+        let mut unsupported = method("unsupported", Decimal::ZERO, Decimal::ZERO);
+        unsupported.supported_countries = vec!["DE".to_string()];
+        let mut too_small = method("too_small", Decimal::ZERO, Decimal::ZERO);
+        too_small.interval = PayoutInterval::Standard {
+            min: Decimal::from(5),
+            max: Decimal::from(25),
+        };
+
+        let comparison = compare_payout_fees(
+            &[
+                method("ach", Decimal::new(4, 2), Decimal::new(25, 2)),
+                unsupported,
+                method("flat", Decimal::ZERO, Decimal::ONE),
+                method("free", Decimal::ZERO, Decimal::ZERO),
+                too_small,
+            ],
+            Gross::new(Decimal::from(50)),
+            Some("US"),
+            PayoutRoundingMode::HalfUp,
+            &PayPalFeeCaps::default(),
+        );
+
+        assert_eq!(
+            comparison
+                .iter()
+                .map(|x| (x.method_id.as_str(), x.fee, x.net))
+                .collect::<Vec<_>>(),
+            vec![
+                ("free", Decimal::ZERO, Decimal::from(50)),
+                ("flat", Decimal::ONE, Decimal::from(49)),
+                ("ach", Decimal::from(2), Decimal::from(48)),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_withdrawal_is_bounded_by_balance_and_method_max() {
+        let capped = PayoutMethod {
+            id: "capped".to_string(),
+            fee: PayoutMethodFee {
+                percentage: Decimal::new(2, 2),
+                min: Decimal::new(25, 2),
+                max: Some(Decimal::ONE),
+            },
+            ..test_method(PayoutMethodType::PayPal)
+        };
+        let mut fixed = PayoutMethod {
+            id: "fixed".to_string(),
+            ..test_method(PayoutMethodType::Tremendous)
+        };
+        fixed.interval = PayoutInterval::Fixed {
+            values: vec![
+                PayoutDecimal(Decimal::from(10)),
+                PayoutDecimal(Decimal::from(25)),
+                PayoutDecimal(Decimal::from(100)),
+            ],
+        };
+        let methods = [capped, fixed];
+        let max = |balance: Decimal| {
+            max_withdrawals(
+                &methods,
+                balance,
+                Some("US"),
+                PayoutRoundingMode::HalfUp,
+                &PayoutWithholding::default(),
+                &PayPalFeeCaps::default(),
+            )
+            .into_iter()
+            .map(|x| (x.method_id, x.amount, x.fee, x.net))
+            .collect::<Vec<_>>()
+        };
+
+        // The balance is rounded down to the cent, and the fee is taken out of it
+        assert_eq!(
+            max(Decimal::new(50_009, 3)),
+            vec![
+                (
+                    "capped".to_string(),
+                    Decimal::new(5000, 2),
+                    Decimal::ONE,
+                    Decimal::from(49)
+                ),
+                (
+                    "fixed".to_string(),
+                    Decimal::from(25),
+                    Decimal::ZERO,
+                    Decimal::from(25)
+                ),
+            ]
+        );
+        // The method's maximum bounds a larger balance, and the fee stops at its cap
+        assert_eq!(
+            max(Decimal::from(1000)),
+            vec![
+                (
+                    "capped".to_string(),
+                    Decimal::from(100),
+                    Decimal::ONE,
+                    Decimal::from(99)
+                ),
+                (
+                    "fixed".to_string(),
+                    Decimal::from(100),
+                    Decimal::ZERO,
+                    Decimal::from(100)
+                ),
+            ]
+        );
+        // Nothing can be withdrawn from a balance under every minimum
+        assert!(max(Decimal::from(4)).is_empty());
+    }
+
+    #[test]
+    fn half_up_rounds_half_cents_away_from_zero() {
+        let rounding = PayoutRoundingMode::HalfUp;
+
+        assert_eq!(rounding.round(Decimal::new(125, 3)), Decimal::new(13, 2));
+        assert_eq!(rounding.round(Decimal::new(135, 3)), Decimal::new(14, 2));
+        assert_eq!(rounding.round(Decimal::new(1249, 4)), Decimal::new(12, 2));
+    }
+
+    #[test]
+    fn half_even_rounds_half_cents_to_even() {
+        let rounding = PayoutRoundingMode::HalfEven;
+
+        assert_eq!(rounding.round(Decimal::new(125, 3)), Decimal::new(12, 2));
+        assert_eq!(rounding.round(Decimal::new(135, 3)), Decimal::new(14, 2));
+        assert_eq!(rounding.round(Decimal::new(1251, 4)), Decimal::new(13, 2));
+    }
+
+    #[test]
+    fn credits_are_rounded_to_cents_when_written() {
+        let credit = |user_id, amount| PayoutCredit {
+            user_id,
+            project_id: 1,
+            amount,
+        };
+        let credits = vec![
+            credit(1, Decimal::new(125, 3)),
+            credit(2, Decimal::new(4, 3)),
+            credit(3, Decimal::new(10, 0)),
+        ];
+
+        assert_eq!(
+            round_payout_credits(credits.clone(), PayoutRoundingMode::HalfUp),
+            vec![
+                credit(1, Decimal::new(13, 2)),
+                credit(3, Decimal::new(10, 0))
+            ]
+        );
+        assert_eq!(
+            round_payout_credits(credits, PayoutRoundingMode::HalfEven),
+            vec![
+                credit(1, Decimal::new(12, 2)),
+                credit(3, Decimal::new(10, 0))
+            ]
+        );
+    }
+
+    fn test_withholding() -> PayoutWithholding {
+        PayoutWithholding::new(HashMap::from([
+            ("US".to_string(), Decimal::new(3, 1)),
+            ("GB".to_string(), Decimal::ZERO),
+        ]))
+    }
+
+    #[test]
+    fn withholding_applies_after_fee() {
+        let fee = PayoutMethodFee {
+            percentage: Decimal::new(2, 2),
+            min: Decimal::new(25, 2),
+            max: None,
+        };
+        let amount = Gross::new(Decimal::from(100));
+        let after_fee = amount - fee.calculate(amount);
+
+        let withheld = test_withholding().calculate(Some("US"), after_fee);
+
+        assert_eq!(withheld.amount(), Decimal::new(294, 1));
+        assert_eq!((after_fee - withheld).amount(), Decimal::new(686, 1));
+    }
+
+    fn paypal_in_method() -> PayoutMethod {
+        PayoutMethod {
+            id: "paypal_in".to_string(),
+            fee: PayoutMethodFee {
+                percentage: Decimal::new(2, 2),
+                min: Decimal::ZERO,
+                max: Some(Decimal::from(20)),
+            },
+            ..test_method(PayoutMethodType::PayPal)
+        }
+    }
+
+    #[test]
+    fn paypal_fee_is_clamped_to_country_cap() {
+        let fee_caps = PayPalFeeCaps::new(HashMap::from([("BR".to_string(), Decimal::from(5))]));
+        let method = paypal_in_method();
+
+        assert_eq!(
+            fee_caps
+                .fee(&method, Gross::new(Decimal::from(2000)), Some("br"))
+                .amount(),
+            Decimal::from(5)
+        );
+        assert_eq!(
+            fee_caps
+                .fee(&method, Gross::new(Decimal::from(100)), Some("BR"))
+                .amount(),
+            Decimal::from(2)
+        );
+        assert_eq!(
+            fee_caps
+                .fee(&method, Gross::new(Decimal::from(2000)), Some("DE"))
+                .amount(),
+            Decimal::from(20)
+        );
+
+        let amounts = WithdrawalAmounts::calculate(
+            &method,
+            Gross::new(Decimal::from(2000)),
+            Some("BR"),
+            PayoutRoundingMode::HalfUp,
+            &PayoutWithholding::default(),
+            &fee_caps,
+        )
+        .unwrap();
+        assert_eq!(amounts.fee.amount(), Decimal::from(5));
+        assert_eq!(amounts.transfer.amount(), Decimal::from(1995));
+    }
+
+    #[test]
+    fn fee_caps_only_apply_to_international_paypal() {
+        let fee_caps = PayPalFeeCaps::new(HashMap::from([("BR".to_string(), Decimal::ZERO)]));
+        let method = PayoutMethod {
+            id: "paypal_us".to_string(),
+            ..paypal_in_method()
+        };
+
+        assert_eq!(
+            fee_caps
+                .fee(&method, Gross::new(Decimal::from(100)), Some("BR"))
+                .amount(),
+            Decimal::from(2)
+        );
+    }
+
+    #[test]
+    fn withholding_is_zero_for_untaxed_countries() {
+        let withholding = test_withholding();
+        let net = Gross::new(Decimal::from(100)) - Fee::default();
+
+        for country in [Some("GB"), Some("DE"), None] {
+            assert_eq!(withholding.calculate(country, net).amount(), Decimal::ZERO);
+        }
+    }
+
+    #[test]
+    fn unknown_countries_can_not_withdraw_while_tax_is_withheld() {
+        let withdraw = |country: Option<&str>, withholding: &PayoutWithholding| {
+            WithdrawalAmounts::calculate(
+                &paypal_in_method(),
+                Gross::new(Decimal::from(100)),
+                country,
+                PayoutRoundingMode::HalfUp,
+                withholding,
+                &PayPalFeeCaps::default(),
+            )
+        };
+
+        assert!(matches!(
+            withdraw(None, &test_withholding()),
+            Err(ApiError::Compliance(_))
+        ));
+        assert!(withdraw(Some("DE"), &test_withholding()).is_ok());
+        assert!(withdraw(None, &PayoutWithholding::default()).is_ok());
+    }
+
+    #[test]
+    fn dominating_project_credit_is_capped() {
+        let multipliers = PayoutMultipliers {
+            sum: 1000,
+            values: HashMap::from([(1, 990), (2, 10)]),
+        };
+        let payout = Decimal::from(1000);
+        let mut credits = vec![
+            PayoutCredit {
+                user_id: 1,
+                project_id: 1,
+                amount: payout * multipliers.project_share(1),
+            },
+            PayoutCredit {
+                user_id: 2,
+                project_id: 2,
+                amount: payout * multipliers.project_share(2),
+            },
+        ];
+
+        let excess = cap_user_payouts(&mut credits, Decimal::from(100));
+
+        assert_eq!(credits[0].amount, Decimal::from(100));
+        assert_eq!(credits[1].amount, Decimal::from(10));
+        assert_eq!(excess, HashMap::from([(1, Decimal::from(890))]));
+    }
+
+    #[test]
+    fn new_project_gets_more_than_its_view_share() {
+        let day = Utc.with_ymd_and_hms(2023, 12, 1, 0, 0, 0).unwrap();
+        let boost = DiscoveryBoost {
+            factor: Decimal::from(2),
+            max_age: Duration::days(30),
+        };
+        let multipliers = PayoutMultipliers {
+            sum: 1000,
+            values: HashMap::from([(1, 100), (2, 900)]),
+        };
+        let project = |user_id, published| PayoutProject {
+            team_members: vec![(user_id, Decimal::from(100))],
+            monetized_fraction: Decimal::ONE,
+            boost: boost.multiplier(published, day),
+        };
+        let payout = Decimal::from(1100);
+
+        let credits = project_payout_credits(
+            payout,
+            &multipliers,
+            HashMap::from([
+                (1, project(1, day - Duration::days(3))),
+                (2, project(2, day - Duration::days(365))),
+            ]),
+        );
+
+        let new_credit = credits.iter().find(|x| x.project_id == 1).unwrap();
+        assert!(new_credit.amount > payout * multipliers.project_share(1));
+        assert_eq!(new_credit.amount.round_dp(10), Decimal::from(200));
+        assert!(credits.iter().map(|x| x.amount).sum::<Decimal>() <= payout);
+    }
+
+    #[test]
+    fn capped_user_keeps_project_proportions() {
+        let mut credits = vec![
+            PayoutCredit {
+                user_id: 1,
+                project_id: 1,
+                amount: Decimal::from(300),
+            },
+            PayoutCredit {
+                user_id: 1,
+                project_id: 2,
+                amount: Decimal::from(100),
+            },
+        ];
+
+        cap_user_payouts(&mut credits, Decimal::from(100));
+
+        assert_eq!(credits[0].amount, Decimal::from(75));
+        assert_eq!(credits[1].amount, Decimal::from(25));
+    }
+
+    #[test]
+    fn self_test_reports_missing_and_differing_credits() {
+        let credit = |user_id, project_id, amount: i64| PayoutCredit {
+            user_id,
+            project_id,
+            amount: Decimal::from(amount),
+        };
+
+        let diffs = diff_payout_credits(
+            &[credit(1, 1, 10), credit(2, 1, 20), credit(3, 2, 30)],
+            &[credit(1, 1, 10), credit(2, 1, 25), credit(4, 3, 40)],
+        );
+
+        assert_eq!(
+            diffs
+                .iter()
+                .map(|x| (x.project_id, x.user_id, x.expected, x.actual))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, 2, Some(Decimal::from(20)), Some(Decimal::from(25))),
+                (2, 3, Some(Decimal::from(30)), None),
+                (3, 4, None, Some(Decimal::from(40))),
+            ]
+        );
+    }
+
+    #[test]
+    fn user_credited_for_many_projects_is_cleared_once() {
+        let batch = clear_cache_batch(vec![1, 2, 1, 3, 1]);
+
+        assert_eq!(
+            batch
+                .iter()
+                .filter(|id| **id == crate::database::models::UserId(1))
+                .count(),
+            1
+        );
+        assert_eq!(batch.len(), 3);
+    }
+
+    #[test]
+    fn payout_rate_trend_has_a_point_per_day() {
+        let period = test_period();
+        let days = period.days().take(3).collect::<Vec<_>>();
+
+        let payouts = HashMap::from([
+            (days[0].timestamp(), Decimal::from(100)),
+            (days[1].timestamp(), Decimal::from(50)),
+        ]);
+        let views_and_downloads =
+            HashMap::from([(days[0].timestamp(), 400), (days[2].timestamp(), 10)]);
+
+        let trend = payout_rate_trend(days.iter().copied(), &payouts, &views_and_downloads);
+
+        assert_eq!(trend.len(), 3);
+        assert_eq!(trend[0].rate, Some(Decimal::new(25, 2)));
+        assert_eq!(trend[1].payouts, Decimal::from(50));
+        assert_eq!(trend[1].rate, None);
+        assert_eq!(trend[2].rate, Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn expired_paypal_token_needs_refresh() {
+        let credentials = credentials_expiring_in(Duration::seconds(-1));
+        assert!(credentials.needs_refresh(Duration::zero()));
+    }
+
+    fn decimals_close(a: Decimal, b: Decimal) -> bool {
+        // Decimal keeps 28 significant digits, so chained divisions can be off in the last few
+        (a - b).abs() <= Decimal::new(1, 12)
+    }
+
+    prop_compose! {
+        fn arb_distribution()(
+            payout in (0i64..100_000_000).prop_map(|x| Decimal::new(x, 2)),
+            // views, percent of the day monetized, team payout splits
+            projects in proptest::collection::vec(
+                (0u64..100_000, 0i64..=100, proptest::collection::vec(0i64..=100, 1..5)),
+                0..20,
+            ),
+            unmonetized_views in 0u64..100_000,
+        ) -> (Decimal, PayoutMultipliers, HashMap<i64, PayoutProject>) {
+            let multipliers = PayoutMultipliers {
+                sum: projects.iter().map(|x| x.0).sum::<u64>() + unmonetized_views,
+                values: projects
+                    .iter()
+                    .enumerate()
+                    .map(|(id, x)| (id as u64, x.0))
+                    .collect(),
+            };
+            let projects = projects
+                .into_iter()
+                .enumerate()
+                .map(|(id, (_, monetized, splits))| {
+                    (
+                        id as i64,
+                        PayoutProject {
+                            team_members: splits
+                                .into_iter()
+                                .enumerate()
+                                .map(|(member, split)| {
+                                    ((id * 100 + member) as i64, Decimal::from(split))
+                                })
+                                .collect(),
+                            monetized_fraction: Decimal::new(monetized, 2),
+                            boost: Decimal::ONE,
+                        },
+                    )
+                })
+                .collect();
+
+            (payout, multipliers, projects)
+        }
+    }
+
+    prop_compose! {
+        fn arb_boosted_distribution()(
+            (payout, multipliers, mut projects) in arb_distribution(),
+            // discovery boost percent of each project
+            boosts in proptest::collection::vec(100i64..=300, 20),
+        ) -> (Decimal, PayoutMultipliers, HashMap<i64, PayoutProject>) {
+            for (id, project) in projects.iter_mut() {
+                project.boost = Decimal::new(boosts[*id as usize], 2);
+            }
+
+            (payout, multipliers, projects)
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn distributed_payouts_stay_within_budget(
+            (payout, multipliers, projects) in arb_distribution()
+        ) {
+            let credits = project_payout_credits(payout, &multipliers, projects);
+            let total: Decimal = credits.iter().map(|x| x.amount).sum();
+
+            prop_assert!(total <= payout || decimals_close(total, payout));
+        }
+
+        #[test]
+        fn distributed_payouts_are_positive(
+            (payout, multipliers, projects) in arb_distribution()
+        ) {
+            let credits = project_payout_credits(payout, &multipliers, projects);
+
+            prop_assert!(credits.iter().all(|x| x.amount > Decimal::ZERO));
+        }
+
+        #[test]
+        fn distributed_payouts_respect_splits(
+            (payout, multipliers, projects) in arb_distribution()
+        ) {
+            let splits = projects
+                .iter()
+                .flat_map(|(_, project)| project.team_members.iter().copied())
+                .collect::<HashMap<_, _>>();
+            let project_payouts = projects
+                .iter()
+                .map(|(id, project)| {
+                    (
+                        *id,
+                        (
+                            payout
+                                * multipliers.project_share(*id as u64)
+                                * project.monetized_fraction,
+                            project.team_members.iter().map(|x| x.1).sum::<Decimal>(),
+                        ),
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+
+            let credits = project_payout_credits(payout, &multipliers, projects);
+
+            for credit in credits {
+                let (project_payout, sum_splits) = project_payouts[&credit.project_id];
+                let expected = project_payout * splits[&credit.user_id] / sum_splits;
+
+                prop_assert!(decimals_close(credit.amount, expected));
+            }
+        }
+
+        #[test]
+        fn distributed_payouts_skip_projects_without_views(
+            (payout, mut multipliers, projects) in arb_distribution()
+        ) {
+            multipliers.sum = 0;
+
+            prop_assert!(project_payout_credits(payout, &multipliers, projects).is_empty());
+        }
+
+        #[test]
+        fn boosted_payouts_stay_within_budget(
+            (payout, multipliers, projects) in arb_boosted_distribution()
+        ) {
+            let credits = project_payout_credits(payout, &multipliers, projects);
+            let total: Decimal = credits.iter().map(|x| x.amount).sum();
+
+            prop_assert!(total <= payout || decimals_close(total, payout));
+        }
+
+        #[test]
+        fn boosted_payouts_respect_splits(
+            (payout, multipliers, projects) in arb_boosted_distribution()
+        ) {
+            let splits = projects
+                .iter()
+                .flat_map(|(_, project)| project.team_members.iter().copied())
+                .collect::<HashMap<_, _>>();
+            let sum_splits = projects
+                .iter()
+                .map(|(id, project)| (*id, project.team_members.iter().map(|x| x.1).sum()))
+                .collect::<HashMap<_, Decimal>>();
+
+            let credits = project_payout_credits(payout, &multipliers, projects);
+
+            let mut project_payouts: HashMap<i64, Decimal> = HashMap::new();
+            for credit in credits.iter() {
+                *project_payouts.entry(credit.project_id).or_insert(Decimal::ZERO) += credit.amount;
+            }
+
+            for credit in credits {
+                let expected = project_payouts[&credit.project_id] * splits[&credit.user_id]
+                    / sum_splits[&credit.project_id];
+
+                prop_assert!(decimals_close(credit.amount, expected));
+            }
+        }
+    }
 }
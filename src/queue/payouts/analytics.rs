@@ -0,0 +1,308 @@
+use crate::routes::ApiError;
+use crate::util::env::env_var;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+/// A project's number of views or downloads over a payout day
+#[derive(Deserialize, clickhouse::Row, Debug, Eq, PartialEq)]
+pub struct ProjectMultiplier {
+    pub page_views: u64,
+    pub project_id: u64,
+}
+
+/// The events payouts are split by
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AnalyticsEvent {
+    /// Views of a project's page
+    Views,
+    /// Downloads by signed in users
+    Downloads,
+}
+
+/// Where the views and downloads each day's budget is split by are read from, configured by
+/// `PAYOUTS_ANALYTICS_SOURCE` (`clickhouse` or `postgres`). Defaults to `clickhouse`. Smaller
+/// deployments without ClickHouse can record and read them in Postgres instead.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PayoutAnalyticsSource {
+    ClickHouse,
+    Postgres,
+}
+
+impl PayoutAnalyticsSource {
+    pub fn from_env() -> Self {
+        Self::from_env_with(env_var)
+    }
+
+    pub(super) fn from_env_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        match var("PAYOUTS_ANALYTICS_SOURCE").as_deref() {
+            Some("postgres") => PayoutAnalyticsSource::Postgres,
+            _ => PayoutAnalyticsSource::ClickHouse,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayoutAnalyticsSource::ClickHouse => "clickhouse",
+            PayoutAnalyticsSource::Postgres => "postgres",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "clickhouse" => Some(PayoutAnalyticsSource::ClickHouse),
+            "postgres" => Some(PayoutAnalyticsSource::Postgres),
+            _ => None,
+        }
+    }
+
+    /// Reads this source's analytics through `client` or `pool`
+    pub fn analytics<'a>(
+        &self,
+        client: &'a clickhouse::Client,
+        pool: &'a PgPool,
+    ) -> Box<dyn PayoutAnalytics + 'a> {
+        match self {
+            PayoutAnalyticsSource::ClickHouse => Box::new(ClickHouseAnalytics(client)),
+            PayoutAnalyticsSource::Postgres => Box::new(PostgresAnalytics(pool)),
+        }
+    }
+}
+
+/// Counts the views and downloads payouts are split by. Views of no project and anonymous
+/// downloads are never counted.
+#[async_trait]
+pub trait PayoutAnalytics: Send + Sync {
+    /// The number of `event`s recorded from `start` to `end`, inclusive
+    async fn count(
+        &self,
+        event: AnalyticsEvent,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, ApiError>;
+
+    /// Each project's number of `event`s recorded from `start` to `end`, inclusive. The rows are
+    /// streamed, so a busy day is never held in memory at once.
+    fn project_counts(
+        &self,
+        event: AnalyticsEvent,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxStream<'_, Result<ProjectMultiplier, ApiError>>;
+}
+
+/// Reads the `views` and `downloads` tables in ClickHouse
+pub struct ClickHouseAnalytics<'a>(pub &'a clickhouse::Client);
+
+#[async_trait]
+impl PayoutAnalytics for ClickHouseAnalytics<'_> {
+    async fn count(
+        &self,
+        event: AnalyticsEvent,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, ApiError> {
+        let query = match event {
+            AnalyticsEvent::Views => {
+                "SELECT COUNT(1) FROM views WHERE (recorded BETWEEN ? AND ?) AND (project_id != 0)"
+            }
+            AnalyticsEvent::Downloads => {
+                "SELECT COUNT(1) FROM downloads WHERE (recorded BETWEEN ? AND ?) AND (user_id != 0)"
+            }
+        };
+
+        Ok(self
+            .0
+            .query(query)
+            .bind(start.timestamp())
+            .bind(end.timestamp())
+            .fetch_one::<u64>()
+            .await?)
+    }
+
+    fn project_counts(
+        &self,
+        event: AnalyticsEvent,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxStream<'_, Result<ProjectMultiplier, ApiError>> {
+        let query = match event {
+            AnalyticsEvent::Views => {
+                r#"
+                SELECT COUNT(1) page_views, project_id
+                FROM views
+                WHERE (recorded BETWEEN ? AND ?) AND (project_id != 0)
+                GROUP BY project_id
+                ORDER BY page_views DESC
+                "#
+            }
+            AnalyticsEvent::Downloads => {
+                r#"
+                SELECT COUNT(1) page_views, project_id
+                FROM downloads
+                WHERE (recorded BETWEEN ? AND ?) AND (user_id != 0)
+                GROUP BY project_id
+                ORDER BY page_views DESC
+                "#
+            }
+        };
+
+        let cursor = self
+            .0
+            .query(query)
+            .bind(start.timestamp())
+            .bind(end.timestamp())
+            .fetch::<ProjectMultiplier>();
+
+        match cursor {
+            Ok(cursor) => futures::stream::try_unfold(cursor, |mut cursor| async move {
+                Ok::<_, ApiError>(cursor.next().await?.map(|x| (x, cursor)))
+            })
+            .boxed(),
+            Err(err) => futures::stream::once(async move { Err(err.into()) }).boxed(),
+        }
+    }
+}
+
+/// Reads the `analytics_views` and `analytics_downloads` tables in Postgres
+pub struct PostgresAnalytics<'a>(pub &'a PgPool);
+
+#[async_trait]
+impl PayoutAnalytics for PostgresAnalytics<'_> {
+    async fn count(
+        &self,
+        event: AnalyticsEvent,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, ApiError> {
+        let count = match event {
+            AnalyticsEvent::Views => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COUNT(1) "count!"
+                    FROM analytics_views
+                    WHERE (recorded BETWEEN $1 AND $2) AND (project_id != 0)
+                    "#,
+                    start,
+                    end,
+                )
+                .fetch_one(self.0)
+                .await?
+            }
+            AnalyticsEvent::Downloads => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COUNT(1) "count!"
+                    FROM analytics_downloads
+                    WHERE (recorded BETWEEN $1 AND $2) AND (user_id != 0)
+                    "#,
+                    start,
+                    end,
+                )
+                .fetch_one(self.0)
+                .await?
+            }
+        };
+
+        Ok(count as u64)
+    }
+
+    fn project_counts(
+        &self,
+        event: AnalyticsEvent,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> BoxStream<'_, Result<ProjectMultiplier, ApiError>> {
+        match event {
+            AnalyticsEvent::Views => sqlx::query!(
+                r#"
+                SELECT COUNT(1) "page_views!", project_id
+                FROM analytics_views
+                WHERE (recorded BETWEEN $1 AND $2) AND (project_id != 0)
+                GROUP BY project_id
+                ORDER BY 1 DESC
+                "#,
+                start,
+                end,
+            )
+            .fetch(self.0)
+            .map_ok(|x| ProjectMultiplier {
+                page_views: x.page_views as u64,
+                project_id: x.project_id as u64,
+            })
+            .map_err(ApiError::from)
+            .boxed(),
+            AnalyticsEvent::Downloads => sqlx::query!(
+                r#"
+                SELECT COUNT(1) "page_views!", project_id
+                FROM analytics_downloads
+                WHERE (recorded BETWEEN $1 AND $2) AND (user_id != 0)
+                GROUP BY project_id
+                ORDER BY 1 DESC
+                "#,
+                start,
+                end,
+            )
+            .fetch(self.0)
+            .map_ok(|x| ProjectMultiplier {
+                page_views: x.page_views as u64,
+                project_id: x.project_id as u64,
+            })
+            .map_err(ApiError::from)
+            .boxed(),
+        }
+    }
+}
+
+/// Records `(recorded, project_id, user_id)` events for [`PostgresAnalytics`], with `recorded`
+/// in tenths of a millisecond since the epoch as the analytics queue records them
+pub async fn record_postgres_events(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    event: AnalyticsEvent,
+    events: impl IntoIterator<Item = (i64, u64, u64)>,
+) -> Result<(), ApiError> {
+    let mut recorded = Vec::new();
+    let mut project_ids = Vec::new();
+    let mut user_ids = Vec::new();
+    for (time, project_id, user_id) in events {
+        recorded.push(time);
+        project_ids.push(project_id as i64);
+        user_ids.push(user_id as i64);
+    }
+
+    match event {
+        AnalyticsEvent::Views => {
+            sqlx::query!(
+                "
+                INSERT INTO analytics_views (recorded, project_id, user_id)
+                SELECT to_timestamp(recorded / 10000.0), project_id, user_id
+                FROM UNNEST($1::bigint[], $2::bigint[], $3::bigint[]) AS t(recorded, project_id, user_id)
+                ",
+                &recorded,
+                &project_ids,
+                &user_ids,
+            )
+            .execute(&mut **transaction)
+            .await?;
+        }
+        AnalyticsEvent::Downloads => {
+            sqlx::query!(
+                "
+                INSERT INTO analytics_downloads (recorded, project_id, user_id)
+                SELECT to_timestamp(recorded / 10000.0), project_id, user_id
+                FROM UNNEST($1::bigint[], $2::bigint[], $3::bigint[]) AS t(recorded, project_id, user_id)
+                ",
+                &recorded,
+                &project_ids,
+                &user_ids,
+            )
+            .execute(&mut **transaction)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,358 @@
+use super::{PayoutProvider, PayoutProviderMode, PayoutRequest, PayoutsQueue};
+use crate::models::payouts::{PayoutMethodType, PayoutStatus};
+use crate::routes::ApiError;
+use async_trait::async_trait;
+use chrono::Utc;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::json;
+
+/// Sends PayPal and Venmo payouts through the PayPal Payouts API
+pub struct PayPalProvider;
+
+impl PayPalProvider {
+    fn status_from_str(status: &str) -> PayoutStatus {
+        match status {
+            "SUCCESS" => PayoutStatus::Success,
+            "PENDING" | "UNCLAIMED" | "ONHOLD" => PayoutStatus::InTransit,
+            "FAILED" | "BLOCKED" | "DENIED" | "RETURNED" | "REFUNDED" => PayoutStatus::Failed,
+            "CANCELED" => PayoutStatus::Cancelled,
+            _ => PayoutStatus::Unknown,
+        }
+    }
+}
+
+lazy_static! {
+    static ref E164_PHONE: Regex = Regex::new(r"^\+[1-9][0-9]{6,14}$").unwrap();
+    static ref PAYPAL_ID: Regex = Regex::new(r"^[A-Z0-9]{13}$").unwrap();
+}
+
+/// Who a PayPal payout is sent to. PayPal needs to be told which kind of recipient it is given,
+/// and each kind is formatted differently.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PayPalRecipient {
+    /// An email address registered with PayPal
+    Email(String),
+    /// A phone number registered with PayPal, in E.164 format, ex: `+14155550123`
+    Phone(String),
+    /// The payer id of a linked PayPal account, ex: `7KQ2ZBLRP3HTU`
+    PayPalId(String),
+}
+
+impl PayPalRecipient {
+    /// Reads a recipient, failing with why it is malformed. Phone numbers must start with their
+    /// `+` country code, and may be written with spaces, dashes and parentheses.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+
+        if value.contains('@') {
+            return if validator::validate_email(value) {
+                Ok(PayPalRecipient::Email(value.to_string()))
+            } else {
+                Err("Not a valid email address".to_string())
+            };
+        }
+
+        if value.starts_with('+') {
+            let phone = value
+                .chars()
+                .filter(|x| !matches!(x, ' ' | '-' | '(' | ')'))
+                .collect::<String>();
+
+            return if E164_PHONE.is_match(&phone) {
+                Ok(PayPalRecipient::Phone(phone))
+            } else {
+                Err("Not a valid phone number, ex: +14155550123".to_string())
+            };
+        }
+
+        if PAYPAL_ID.is_match(value) {
+            Ok(PayPalRecipient::PayPalId(value.to_string()))
+        } else {
+            Err("Not a valid email address, phone number or PayPal account".to_string())
+        }
+    }
+
+    /// The `recipient_type` PayPal expects for this recipient
+    pub fn recipient_type(&self) -> &'static str {
+        match self {
+            PayPalRecipient::Email(_) => "email",
+            PayPalRecipient::Phone(_) => "phone",
+            PayPalRecipient::PayPalId(_) => "paypal_id",
+        }
+    }
+
+    /// The recipient as sent to PayPal as the `receiver`
+    pub fn receiver(&self) -> &str {
+        match self {
+            PayPalRecipient::Email(x)
+            | PayPalRecipient::Phone(x)
+            | PayPalRecipient::PayPalId(x) => x,
+        }
+    }
+}
+
+/// The body of a PayPal Payouts request sending `request`. The user's note, if they left one,
+/// replaces the default note shown to them by PayPal. Fails without anything being sent if a
+/// PayPal recipient is malformed.
+fn paypal_payout_body(request: &PayoutRequest) -> Result<serde_json::Value, ApiError> {
+    let (wallet, wallet_type, receiver) = if request.method == PayoutMethodType::Venmo {
+        ("Venmo", "user_handle", request.address.clone())
+    } else {
+        let recipient = PayPalRecipient::parse(&request.address)
+            .map_err(|err| ApiError::InvalidInput(format!("Invalid PayPal recipient: {err}")))?;
+
+        (
+            "PayPal",
+            recipient.recipient_type(),
+            recipient.receiver().to_string(),
+        )
+    };
+
+    Ok(json! ({
+        "sender_batch_header": {
+            "sender_batch_id": format!("{}-payouts", Utc::now().to_rfc3339()),
+            "email_subject": "You have received a payment from Modrinth!",
+            "email_message": "Thank you for creating projects on Modrinth. Please claim this payment within 30 days.",
+        },
+        "items": [{
+            "amount": {
+                "currency": "USD",
+                "value": request.amount.to_string()
+            },
+            "receiver": receiver,
+            "note": request
+                .note
+                .as_deref()
+                .unwrap_or("Payment from Modrinth creator monetization program"),
+            "recipient_type": wallet_type,
+            "recipient_wallet": wallet,
+            "sender_item_id": request.id,
+        }]
+    }))
+}
+
+#[async_trait]
+impl PayoutProvider for PayPalProvider {
+    fn name(&self) -> &'static str {
+        "paypal"
+    }
+
+    fn mode(&self) -> PayoutProviderMode {
+        PayoutProviderMode::from_env("PAYPAL_SANDBOX", "PAYPAL_API_URL")
+    }
+
+    fn supported_methods(&self) -> &'static [PayoutMethodType] {
+        &[PayoutMethodType::PayPal, PayoutMethodType::Venmo]
+    }
+
+    async fn create_payout(
+        &self,
+        queue: &PayoutsQueue,
+        request: &PayoutRequest,
+    ) -> Result<Option<String>, ApiError> {
+        #[derive(Deserialize)]
+        struct PayPalLink {
+            href: String,
+        }
+
+        #[derive(Deserialize)]
+        struct PayoutsResponse {
+            pub links: Vec<PayPalLink>,
+        }
+
+        let res: PayoutsResponse = queue
+            .make_paypal_request(
+                Method::POST,
+                "payments/payouts",
+                Some(paypal_payout_body(request)?),
+                None,
+                None,
+                Some(&request.correlation_id),
+            )
+            .await?;
+
+        if let Some(link) = res.links.first() {
+            #[derive(Deserialize)]
+            struct PayoutItem {
+                pub payout_item_id: String,
+            }
+
+            #[derive(Deserialize)]
+            struct PayoutData {
+                pub items: Vec<PayoutItem>,
+            }
+
+            if let Ok(res) = queue
+                .make_paypal_request::<(), PayoutData>(
+                    Method::GET,
+                    &link.href,
+                    None,
+                    None,
+                    Some(true),
+                    Some(&request.correlation_id),
+                )
+                .await
+            {
+                if let Some(data) = res.items.first() {
+                    return Ok(Some(data.payout_item_id.clone()));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn get_status(
+        &self,
+        queue: &PayoutsQueue,
+        platform_id: &str,
+    ) -> Result<PayoutStatus, ApiError> {
+        #[derive(Deserialize)]
+        struct PayoutItem {
+            pub transaction_status: String,
+        }
+
+        let item: PayoutItem = queue
+            .make_paypal_request::<(), _>(
+                Method::GET,
+                &format!("payments/payouts-item/{}", platform_id),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?;
+
+        Ok(Self::status_from_str(&item.transaction_status))
+    }
+
+    async fn cancel_payout(&self, queue: &PayoutsQueue, platform_id: &str) -> Result<(), ApiError> {
+        queue
+            .make_paypal_request::<(), ()>(
+                Method::POST,
+                &format!("payments/payouts-item/{}/cancel", platform_id),
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ids::PayoutId;
+    use crate::queue::payouts::{sanitize_payout_note, PAYOUT_NOTE_MAX_LENGTH};
+    use rust_decimal::Decimal;
+
+    fn request_with_note(note: Option<&str>) -> PayoutRequest {
+        PayoutRequest {
+            id: PayoutId(1),
+            method: PayoutMethodType::PayPal,
+            method_id: "paypal_us".to_string(),
+            amount: Decimal::from(10),
+            address: "user@modrinth.com".to_string(),
+            recipient_name: "user".to_string(),
+            correlation_id: "correlation".to_string(),
+            note: note.and_then(sanitize_payout_note),
+            tremendous_value: None,
+        }
+    }
+
+    #[test]
+    fn user_note_is_sent_to_paypal() {
+        let body = paypal_payout_body(&request_with_note(Some("  March\nsponsorship\t "))).unwrap();
+        assert_eq!(body["items"][0]["note"], "March sponsorship");
+
+        let body = paypal_payout_body(&request_with_note(None)).unwrap();
+        assert_eq!(
+            body["items"][0]["note"],
+            "Payment from Modrinth creator monetization program"
+        );
+    }
+
+    #[test]
+    fn long_user_note_is_truncated() {
+        let note = "é".repeat(PAYOUT_NOTE_MAX_LENGTH + 50);
+        let body = paypal_payout_body(&request_with_note(Some(&note))).unwrap();
+
+        assert_eq!(
+            body["items"][0]["note"].as_str().unwrap(),
+            "é".repeat(PAYOUT_NOTE_MAX_LENGTH)
+        );
+        assert!(sanitize_payout_note(" \u{7} \n").is_none());
+    }
+
+    #[test]
+    fn email_recipient_is_sent_as_an_email() {
+        let recipient = PayPalRecipient::parse(" user@modrinth.com ").unwrap();
+        assert_eq!(
+            recipient,
+            PayPalRecipient::Email("user@modrinth.com".to_string())
+        );
+
+        let body = paypal_payout_body(&request_with_note(None)).unwrap();
+        assert_eq!(body["items"][0]["recipient_type"], "email");
+        assert_eq!(body["items"][0]["receiver"], "user@modrinth.com");
+    }
+
+    #[test]
+    fn phone_recipient_is_normalized_to_e164() {
+        assert_eq!(
+            PayPalRecipient::parse("+1 (415) 555-0123"),
+            Ok(PayPalRecipient::Phone("+14155550123".to_string()))
+        );
+
+        let mut request = request_with_note(None);
+        request.address = "+442071838750".to_string();
+        let body = paypal_payout_body(&request).unwrap();
+        assert_eq!(body["items"][0]["recipient_type"], "phone");
+        assert_eq!(body["items"][0]["receiver"], "+442071838750");
+    }
+
+    #[test]
+    fn linked_account_is_sent_as_a_paypal_id() {
+        assert_eq!(
+            PayPalRecipient::parse("7KQ2ZBLRP3HTU"),
+            Ok(PayPalRecipient::PayPalId("7KQ2ZBLRP3HTU".to_string()))
+        );
+    }
+
+    #[test]
+    fn malformed_recipients_are_rejected_before_sending() {
+        for address in [
+            "user@@modrinth",
+            "+0123456789",
+            "+1 415 555 01234 5678",
+            "+1415abc0123",
+            "4155550123",
+            "not a recipient",
+            "",
+        ] {
+            assert!(
+                PayPalRecipient::parse(address).is_err(),
+                "{address} should be rejected"
+            );
+        }
+
+        let mut request = request_with_note(None);
+        request.address = "user@@modrinth".to_string();
+        assert!(matches!(
+            paypal_payout_body(&request),
+            Err(ApiError::InvalidInput(_))
+        ));
+
+        // Venmo handles are not PayPal recipients
+        request.method = PayoutMethodType::Venmo;
+        request.address = "@modrinth-user".to_string();
+        assert_eq!(
+            paypal_payout_body(&request).unwrap()["items"][0]["receiver"],
+            "@modrinth-user"
+        );
+    }
+}
@@ -0,0 +1,695 @@
+use super::{PayoutProvider, PayoutProviderMode, PayoutRequest, PayoutsQueue};
+use crate::models::payouts::{
+    currency_decimal_places, PayoutDecimal, PayoutInterval, PayoutMethod, PayoutMethodFee,
+    PayoutMethodType, PayoutStatus, BALANCE_CURRENCY,
+};
+use crate::routes::ApiError;
+use crate::util::currency::FxRates;
+use async_trait::async_trait;
+use itertools::Itertools;
+use reqwest::Method;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Sends gift card and bank transfer payouts as Tremendous rewards
+pub struct TremendousProvider;
+
+impl TremendousProvider {
+    fn status_from_str(status: &str) -> PayoutStatus {
+        match status {
+            "SUCCEEDED" => PayoutStatus::Success,
+            "SCHEDULED" | "PENDING" => PayoutStatus::InTransit,
+            "FAILED" => PayoutStatus::Failed,
+            _ => PayoutStatus::Unknown,
+        }
+    }
+}
+
+#[async_trait]
+impl PayoutProvider for TremendousProvider {
+    fn name(&self) -> &'static str {
+        "tremendous"
+    }
+
+    fn mode(&self) -> PayoutProviderMode {
+        PayoutProviderMode::from_env("TREMENDOUS_SANDBOX", "TREMENDOUS_API_URL")
+    }
+
+    fn supported_methods(&self) -> &'static [PayoutMethodType] {
+        &[PayoutMethodType::Tremendous]
+    }
+
+    async fn create_payout(
+        &self,
+        queue: &PayoutsQueue,
+        request: &PayoutRequest,
+    ) -> Result<Option<String>, ApiError> {
+        #[derive(Deserialize)]
+        struct Reward {
+            pub id: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Order {
+            pub rewards: Vec<Reward>,
+        }
+
+        #[derive(Deserialize)]
+        struct TremendousResponse {
+            pub order: Order,
+        }
+
+        let value = request.tremendous_value.as_ref().ok_or_else(|| {
+            ApiError::InvalidInput("Invalid payment method specified!".to_string())
+        })?;
+
+        let res: TremendousResponse = queue
+            .make_tremendous_request(
+                Method::POST,
+                "orders",
+                Some(json! ({
+                    "payment": {
+                        "funding_source_id": "BALANCE",
+                    },
+                    "rewards": [{
+                        "value": value,
+                        "delivery": {
+                            "method": "EMAIL"
+                        },
+                        "recipient": {
+                            "name": request.recipient_name,
+                            "email": request.address
+                        },
+                        "products": [
+                            &request.method_id,
+                        ],
+                        "campaign_id": dotenvy::var("TREMENDOUS_CAMPAIGN_ID")?,
+                    }]
+                })),
+                Some(&request.correlation_id),
+            )
+            .await?;
+
+        Ok(res.order.rewards.first().map(|x| x.id.clone()))
+    }
+
+    async fn get_status(
+        &self,
+        queue: &PayoutsQueue,
+        platform_id: &str,
+    ) -> Result<PayoutStatus, ApiError> {
+        #[derive(Deserialize)]
+        struct Delivery {
+            pub status: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Reward {
+            pub delivery: Delivery,
+        }
+
+        #[derive(Deserialize)]
+        struct RewardResponse {
+            pub reward: Reward,
+        }
+
+        let res: RewardResponse = queue
+            .make_tremendous_request::<(), _>(
+                Method::GET,
+                &format!("rewards/{}", platform_id),
+                None,
+                None,
+            )
+            .await?;
+
+        Ok(Self::status_from_str(&res.reward.delivery.status))
+    }
+
+    async fn cancel_payout(&self, queue: &PayoutsQueue, platform_id: &str) -> Result<(), ApiError> {
+        queue
+            .make_tremendous_request::<(), ()>(
+                Method::POST,
+                &format!("rewards/{}/cancel", platform_id),
+                None,
+                None,
+            )
+            .await
+    }
+}
+
+/// The value of a Tremendous reward, in the currency and precision its product is sold in
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TremendousOrderValue {
+    pub denomination: Decimal,
+    pub currency_code: String,
+}
+
+impl TremendousOrderValue {
+    /// What withdrawing `amount` with `method` is ordered for, converted at the rate in
+    /// `fx_rates`, or `None` if `method` is not a Tremendous product. Fails if the product's
+    /// currency has no known rate, or it can't be ordered for the amount, see [`Self::new`].
+    pub fn for_method(
+        method: &PayoutMethod,
+        amount: Decimal,
+        fx_rates: &FxRates,
+    ) -> Result<Option<Self>, ApiError> {
+        if method.type_ != PayoutMethodType::Tremendous {
+            return Ok(None);
+        }
+
+        let currency = method.currency.as_deref().unwrap_or(BALANCE_CURRENCY);
+        let rate = fx_rates.rate(currency).ok_or_else(|| {
+            ApiError::InvalidInput(format!(
+                "{} can not be sent in {currency} right now!",
+                method.name
+            ))
+        })?;
+
+        Self::new(method, amount, rate).map(Some)
+    }
+
+    /// Converts `amount`, in US dollars, to the currency of the product `method` was made from at
+    /// `rate` dollars per unit, rounding down to that currency's minor units. Fails if the product
+    /// can't be ordered for it: it is outside the product's range, or not one of its fixed
+    /// denominations.
+    pub fn new(method: &PayoutMethod, amount: Decimal, rate: Decimal) -> Result<Self, ApiError> {
+        let currency = method.currency.as_deref().unwrap_or(BALANCE_CURRENCY);
+        let denomination = (amount / rate)
+            .round_dp_with_strategy(currency_decimal_places(currency), RoundingStrategy::ToZero);
+
+        // Gift cards of fixed values are only sold in exactly those values
+        if !method.interval.contains(denomination) {
+            let available = match &method.interval {
+                PayoutInterval::Standard { min, max } => format!("from {min} to {max}"),
+                PayoutInterval::Fixed { values } => values.iter().map(|x| x.0).join(", "),
+            };
+
+            return Err(ApiError::InvalidInput(format!(
+                "{} can not be ordered for {denomination} {currency}, only {available} {currency}!",
+                method.name
+            )));
+        }
+
+        Ok(TremendousOrderValue {
+            denomination,
+            currency_code: currency.to_string(),
+        })
+    }
+}
+
+/// Limits on how much a product can be ordered for, overriding those of its SKUs, ex: where
+/// Tremendous rejects orders its SKUs say are fine
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TremendousIntervalOverride {
+    #[serde(default)]
+    pub min: Option<Decimal>,
+    #[serde(default)]
+    pub max: Option<Decimal>,
+}
+
+impl TremendousIntervalOverride {
+    /// `interval` within these limits. Fixed values outside of them are dropped.
+    pub fn apply(&self, interval: &PayoutInterval) -> PayoutInterval {
+        match interval {
+            PayoutInterval::Standard { min, max } => PayoutInterval::Standard {
+                min: self.min.unwrap_or(*min),
+                max: self.max.unwrap_or(*max),
+            },
+            PayoutInterval::Fixed { values } => PayoutInterval::Fixed {
+                values: values
+                    .iter()
+                    .filter(|x| self.min.map_or(true, |min| x.0 >= min))
+                    .filter(|x| self.max.map_or(true, |max| x.0 <= max))
+                    .cloned()
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Per-product interval overrides, configured by `PAYOUTS_TREMENDOUS_INTERVAL_OVERRIDES` as a
+/// JSON object of product ids to limits, ex: `{"ET0ZVETV5ILN": {"min": 5, "max": 500}}`. Either
+/// limit can be left out to keep the SKUs' own.
+#[derive(Clone, Debug, Default)]
+pub struct TremendousIntervalOverrides {
+    overrides: HashMap<String, TremendousIntervalOverride>,
+}
+
+impl TremendousIntervalOverrides {
+    pub fn new(overrides: HashMap<String, TremendousIntervalOverride>) -> Self {
+        TremendousIntervalOverrides { overrides }
+    }
+
+    pub fn from_env() -> Self {
+        match dotenvy::var("PAYOUTS_TREMENDOUS_INTERVAL_OVERRIDES") {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|err| {
+                log::warn!("Ignoring invalid PAYOUTS_TREMENDOUS_INTERVAL_OVERRIDES: {err}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value).map(Self::new)
+    }
+
+    /// `method` with its interval overridden, if it has an override. Products left with no
+    /// fixed values to order are dropped, as they can't be ordered at all.
+    pub fn apply(&self, mut method: PayoutMethod) -> Option<PayoutMethod> {
+        if let Some(limits) = self.overrides.get(&method.id) {
+            let interval = limits.apply(&method.interval);
+            if matches!(&interval, PayoutInterval::Fixed { values } if values.is_empty()) {
+                log::debug!(
+                    "Dropping {} ({}), its interval override leaves none of its values",
+                    method.id,
+                    method.name
+                );
+                return None;
+            }
+
+            log::debug!(
+                "Overriding the interval of {} ({}) from {:?} to {:?}",
+                method.id,
+                method.name,
+                method.interval,
+                interval
+            );
+            method.interval = interval;
+        }
+
+        Some(method)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TremendousSku {
+    pub min: Decimal,
+    pub max: Decimal,
+}
+
+#[derive(Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TremendousProductImageType {
+    Card,
+    Logo,
+}
+
+#[derive(Deserialize)]
+pub struct TremendousProductImage {
+    pub src: String,
+    #[serde(rename = "type")]
+    pub type_: TremendousProductImageType,
+}
+
+#[derive(Deserialize)]
+pub struct TremendousProductCountry {
+    pub abbr: String,
+}
+
+#[derive(Deserialize)]
+pub struct TremendousProduct {
+    pub id: String,
+    pub category: String,
+    pub name: String,
+    pub description: String,
+    pub disclosure: String,
+    pub skus: Vec<TremendousSku>,
+    pub currency_codes: Vec<String>,
+    pub countries: Vec<TremendousProductCountry>,
+    pub images: Vec<TremendousProductImage>,
+}
+
+/// Converts a Tremendous product to a payout method, if it is one we offer
+pub(super) fn tremendous_payout_method(product: TremendousProduct) -> Option<PayoutMethod> {
+    const BLACKLISTED_IDS: &[&str] = &[
+        // physical visa
+        "A2J05SWPI2QG",
+        // crypto
+        "1UOOSHUUYTAM",
+        "5EVJN47HPDFT",
+        "NI9M4EVAVGFJ",
+        "VLY29QHTMNGT",
+        "7XU98H109Y3A",
+        "0CGEDFP2UIKV",
+        "PDYLQU0K073Y",
+        "HCS5Z7O2NV5G",
+        "IY1VMST1MOXS",
+        "VRPZLJ7HCA8X",
+        // bitcard (crypto)
+        "GWQQS5RM8IZS",
+        "896MYD4SGOGZ",
+        "PWLEN1VZGMZA",
+        "A2VRM96J5K5W",
+        "HV9ICIM3JT7P",
+        "K2KLSPVWC2Q4",
+        "HRBRQLLTDF95",
+        "UUBYLZVK7QAB",
+        "BH8W3XEDEOJN",
+        "7WGE043X1RYQ",
+        "2B13MHUZZVTF",
+        "JN6R44P86EYX",
+        "DA8H43GU84SO",
+        "QK2XAQHSDEH4",
+        "J7K1IQFS76DK",
+        "NL4JQ2G7UPRZ",
+        "OEFTMSBA5ELH",
+        "A3CQK6UHNV27",
+    ];
+    const SUPPORTED_METHODS: &[&str] = &["merchant_cards", "visa", "bank", "ach", "visa_card"];
+
+    if !SUPPORTED_METHODS.contains(&&*product.category) || BLACKLISTED_IDS.contains(&&*product.id) {
+        return None;
+    };
+
+    let mut method = PayoutMethod {
+        id: product.id,
+        type_: PayoutMethodType::Tremendous,
+        name: product.name.clone(),
+        supported_countries: product.countries.into_iter().map(|x| x.abbr).collect(),
+        supported_currencies: product.currency_codes,
+        image_url: product
+            .images
+            .into_iter()
+            .find(|x| x.type_ == TremendousProductImageType::Card)
+            .map(|x| x.src),
+        interval: if product.skus.len() > 1 {
+            let mut values = product
+                .skus
+                .into_iter()
+                .map(|x| PayoutDecimal(x.min))
+                .collect::<Vec<_>>();
+            values.sort_by(|a, b| a.0.cmp(&b.0));
+
+            PayoutInterval::Fixed { values }
+        } else if let Some(first) = product.skus.first() {
+            PayoutInterval::Standard {
+                min: first.min,
+                max: first.max,
+            }
+        } else {
+            PayoutInterval::Standard {
+                min: Decimal::ZERO,
+                max: Decimal::from(5_000),
+            }
+        },
+        fee: if product.category == "ach" {
+            PayoutMethodFee {
+                percentage: Decimal::from(4) / Decimal::from(100),
+                min: Decimal::from(1) / Decimal::from(4),
+                max: None,
+            }
+        } else {
+            PayoutMethodFee {
+                percentage: Default::default(),
+                min: Default::default(),
+                max: None,
+            }
+        },
+        usd_equivalent: None,
+        order: 0,
+        available: true,
+        currency: None,
+        decimal_places: 2,
+        description: Some(product.description).filter(|x| !x.is_empty()),
+        disclosure: Some(product.disclosure).filter(|x| !x.is_empty()),
+        maintenance: None,
+        minimum: None,
+    };
+
+    // we do not support interval gift cards with non US based currencies since we cannot do currency conversions properly
+    if let PayoutInterval::Fixed { .. } = method.interval {
+        if !method.supported_currencies.contains(&"USD".to_string()) {
+            return None;
+        }
+    }
+
+    method.annotate_currency();
+    Some(method)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_product(category: &str, currency_codes: &[&str], skus: usize) -> TremendousProduct {
+        serde_json::from_value(serde_json::json!({
+            "id": "TESTPRODUCT1",
+            "category": category,
+            "name": "Test Card",
+            "description": "A test card.",
+            "disclosure": "Test Card is not a sponsor of the rewards.",
+            "skus": (0..skus).map(|x| serde_json::json!({
+                "min": 5 * (x + 1),
+                "max": 5 * (x + 1),
+            })).collect::<Vec<_>>(),
+            "currency_codes": currency_codes,
+            "countries": [{ "abbr": "US" }, { "abbr": "DE" }],
+            "images": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn multi_currency_product_lists_its_currencies() {
+        let method = tremendous_payout_method(test_product("visa", &["USD", "EUR", "GBP"], 1))
+            .expect("visa products are offered");
+
+        assert_eq!(method.supported_currencies, vec!["USD", "EUR", "GBP"]);
+    }
+
+    #[test]
+    fn eur_product_has_usd_equivalent_interval() {
+        let method = tremendous_payout_method(test_product("visa", &["EUR"], 1))
+            .expect("visa products are offered");
+        let fx_rates = FxRates::new(
+            [("EUR".to_string(), Decimal::new(11, 1))]
+                .into_iter()
+                .collect(),
+        );
+
+        assert_eq!(
+            fx_rates.usd_equivalent(&method).map(|x| x.interval),
+            Some(PayoutInterval::Standard {
+                min: Decimal::new(55, 1),
+                max: Decimal::new(55, 1),
+            })
+        );
+        assert!(FxRates::default().usd_equivalent(&method).is_none());
+    }
+
+    #[test]
+    fn product_disclosure_is_surfaced() {
+        let method = tremendous_payout_method(test_product("visa", &["USD"], 1))
+            .expect("visa products are offered");
+
+        assert_eq!(method.description.as_deref(), Some("A test card."));
+        assert_eq!(
+            method.disclosure.as_deref(),
+            Some("Test Card is not a sponsor of the rewards.")
+        );
+    }
+
+    #[test]
+    fn decimal_places_follow_the_method_currency() {
+        let mut jpy = tremendous_payout_method(test_product("visa", &["JPY"], 1))
+            .expect("visa products are offered");
+        jpy.annotate_currency();
+        assert_eq!(jpy.currency.as_deref(), Some("JPY"));
+        assert_eq!(jpy.decimal_places, 0);
+
+        let mut usd = tremendous_payout_method(test_product("visa", &["EUR", "USD"], 1))
+            .expect("visa products are offered");
+        usd.annotate_currency();
+        assert_eq!(usd.currency.as_deref(), Some("USD"));
+        assert_eq!(usd.decimal_places, 2);
+    }
+
+    #[test]
+    fn fixed_interval_product_without_usd_is_excluded() {
+        assert!(tremendous_payout_method(test_product("merchant_cards", &["EUR"], 3)).is_none());
+        assert!(
+            tremendous_payout_method(test_product("merchant_cards", &["EUR", "USD"], 3)).is_some()
+        );
+    }
+
+    fn ranged_product(currency_code: &str, min: i64, max: i64) -> PayoutMethod {
+        tremendous_payout_method(
+            serde_json::from_value(serde_json::json!({
+                "id": "TESTPRODUCT2",
+                "category": "visa",
+                "name": "Test Visa",
+                "description": "",
+                "disclosure": "",
+                "skus": [{ "min": min, "max": max }],
+                "currency_codes": [currency_code],
+                "countries": [{ "abbr": "US" }],
+                "images": [],
+            }))
+            .unwrap(),
+        )
+        .expect("visa products are offered")
+    }
+
+    #[test]
+    fn eur_product_minimum_is_the_usd_threshold_converted_to_eur() {
+        let fx_rates = FxRates::new(
+            [("EUR".to_string(), Decimal::new(125, 2))]
+                .into_iter()
+                .collect(),
+        );
+        let threshold = Some(Decimal::from(10));
+
+        // 10 USD at 1.25 USD per EUR
+        assert_eq!(
+            fx_rates.minimum(&ranged_product("EUR", 1, 100), threshold),
+            Some(Decimal::from(8))
+        );
+        // A method's own higher minimum still applies
+        assert_eq!(
+            fx_rates.minimum(&ranged_product("EUR", 20, 100), threshold),
+            Some(Decimal::from(20))
+        );
+        assert_eq!(
+            fx_rates.minimum(&ranged_product("USD", 1, 100), threshold),
+            Some(Decimal::from(10))
+        );
+        assert_eq!(
+            fx_rates.minimum(&ranged_product("EUR", 1, 100), None),
+            Some(Decimal::from(1))
+        );
+
+        // Rounded up, so the minimum is never worth less than the threshold
+        let fx_rates = FxRates::new(
+            [("EUR".to_string(), Decimal::new(11, 1))]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            fx_rates.minimum(&ranged_product("EUR", 1, 100), threshold),
+            Some(Decimal::new(910, 2))
+        );
+        assert_eq!(
+            FxRates::default().minimum(&ranged_product("EUR", 1, 100), threshold),
+            None
+        );
+    }
+
+    #[test]
+    fn fixed_denomination_product_is_ordered_for_one_of_its_values() {
+        let method = tremendous_payout_method(test_product("merchant_cards", &["USD"], 3))
+            .expect("merchant cards are offered");
+        assert_eq!(
+            TremendousOrderValue::new(&method, Decimal::new(10_004, 3), Decimal::ONE).unwrap(),
+            TremendousOrderValue {
+                denomination: Decimal::from(10),
+                currency_code: "USD".to_string(),
+            }
+        );
+        assert!(matches!(
+            TremendousOrderValue::new(&method, Decimal::from(12), Decimal::ONE),
+            Err(ApiError::InvalidInput(message))
+                if message == "Test Card can not be ordered for 12 USD, only 5, 10, 15 USD!"
+        ));
+    }
+
+    #[test]
+    fn ranged_product_is_ordered_in_its_currency_rounded_down() {
+        let fx_rates = FxRates::new(
+            [
+                ("EUR".to_string(), Decimal::new(11, 1)),
+                ("JPY".to_string(), Decimal::new(7, 3)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let eur = ranged_product("EUR", 5, 500);
+        assert_eq!(
+            TremendousOrderValue::for_method(&eur, Decimal::from(10), &fx_rates)
+                .unwrap()
+                .unwrap(),
+            TremendousOrderValue {
+                denomination: Decimal::new(909, 2),
+                currency_code: "EUR".to_string(),
+            }
+        );
+        assert!(matches!(
+            TremendousOrderValue::for_method(&eur, Decimal::from(1_000), &fx_rates),
+            Err(ApiError::InvalidInput(message))
+                if message == "Test Visa can not be ordered for 909.09 EUR, only from 5 to 500 EUR!"
+        ));
+
+        let jpy = ranged_product("JPY", 500, 50_000);
+        assert_eq!(
+            TremendousOrderValue::for_method(&jpy, Decimal::from(10), &fx_rates)
+                .unwrap()
+                .unwrap()
+                .denomination,
+            Decimal::from(1_428)
+        );
+
+        // Without a rate the amount can't be converted at all
+        assert!(
+            TremendousOrderValue::for_method(&eur, Decimal::from(10), &FxRates::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn interval_override_takes_precedence_over_the_skus() {
+        let overrides = TremendousIntervalOverrides::parse(
+            r#"{"TESTPRODUCT2": {"min": 10}, "TESTPRODUCT1": {"max": 10}}"#,
+        )
+        .unwrap();
+
+        let ranged = ranged_product("USD", 5, 500);
+        assert!(ranged.interval.contains(Decimal::from(5)));
+        let ranged = overrides.apply(ranged).unwrap();
+        assert_eq!(
+            ranged.interval,
+            PayoutInterval::Standard {
+                min: Decimal::from(10),
+                max: Decimal::from(500),
+            }
+        );
+        assert!(!ranged.interval.contains(Decimal::from(5)));
+        assert!(ranged.interval.contains(Decimal::from(10)));
+
+        let fixed = overrides
+            .apply(
+                tremendous_payout_method(test_product("merchant_cards", &["USD"], 3))
+                    .expect("merchant cards are offered"),
+            )
+            .unwrap();
+        assert_eq!(
+            fixed.interval,
+            PayoutInterval::Fixed {
+                values: vec![
+                    PayoutDecimal(Decimal::from(5)),
+                    PayoutDecimal(Decimal::from(10))
+                ],
+            }
+        );
+        assert!(!fixed.interval.contains(Decimal::from(15)));
+
+        // Products without an override keep their SKUs' interval
+        let other = PayoutMethod {
+            id: "OTHERPRODUCT".to_string(),
+            ..ranged_product("USD", 5, 500)
+        };
+        assert_eq!(overrides.apply(other.clone()), Some(other));
+
+        // Fixed value products left with no values can't be ordered, so aren't offered
+        let overrides =
+            TremendousIntervalOverrides::parse(r#"{"TESTPRODUCT1": {"min": 100}}"#).unwrap();
+        assert_eq!(
+            overrides.apply(
+                tremendous_payout_method(test_product("merchant_cards", &["USD"], 3))
+                    .expect("merchant cards are offered"),
+            ),
+            None
+        );
+    }
+}
@@ -1,17 +1,29 @@
 use crate::auth::validate::get_user_record_from_bearer_token;
 use crate::database::redis::RedisPool;
 use crate::models::analytics::Download;
-use crate::models::ids::ProjectId;
+use crate::models::ids::{PayoutId, ProjectId, UserId};
 use crate::models::pats::Scopes;
+use crate::models::payouts::{
+    Payout, PayoutAuditAction, PayoutAuditEntry, PayoutMethodType, PayoutStatus,
+};
 use crate::queue::analytics::AnalyticsQueue;
 use crate::queue::maxmind::MaxMindIndexer;
+use crate::queue::payouts::{
+    catch_up_payouts, export_payout_day, fetch_payout_totals, new_correlation_id, process_payout,
+    reconcile_payout_caches, PayoutGranularity, PayoutPeriod, PayoutRequest, PayoutsQueue,
+    CORRELATION_ID_HEADER,
+};
 use crate::queue::session::AuthQueue;
+use crate::routes::v3::payouts::{lock_pending_review_payout, refund_pending_review_payout};
 use crate::routes::ApiError;
 use crate::search::SearchConfig;
 use crate::util::date::get_current_tenths_of_ms;
-use crate::util::guards::admin_key_guard;
-use actix_web::{patch, post, web, HttpRequest, HttpResponse};
-use serde::Deserialize;
+use crate::util::guards::{admin_key_guard, ADMIN_ACTOR_HEADER};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::info;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
 use std::net::Ipv4Addr;
@@ -21,7 +33,26 @@ pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("admin")
             .service(count_download)
-            .service(force_reindex),
+            .service(force_reindex)
+            .service(recompute_balance)
+            .service(payout_eligibility)
+            .service(payout_providers)
+            .service(reprocess_payout)
+            .service(catch_up_payout)
+            .service(reconcile_payout)
+            .service(payout_export)
+            .service(payout_by_reference)
+            .service(payout_hold)
+            .service(payout_totals)
+            .service(approve_payout)
+            .service(deny_payout)
+            .service(payout_audit_log)
+            .service(payout_method_snapshot)
+            .service(payout_runs)
+            .service(payout_self_test)
+            .service(place_user_payout_hold)
+            .service(renew_user_payout_hold)
+            .service(lift_user_payout_hold),
     );
 }
 
@@ -145,3 +176,816 @@ pub async fn force_reindex(
     index_projects(pool.as_ref().clone(), redis.clone(), &config).await?;
     Ok(HttpResponse::NoContent().finish())
 }
+
+#[derive(Deserialize)]
+pub struct RecomputeBalanceQuery {
+    /// Whether to overwrite the stored balance with the ledger-derived one
+    #[serde(default)]
+    pub correct: bool,
+}
+
+#[derive(Serialize)]
+pub struct RecomputedBalance {
+    #[serde(with = "crate::models::payouts::money")]
+    pub previous: Decimal,
+    #[serde(with = "crate::models::payouts::money")]
+    pub ledger: Decimal,
+    pub corrected: bool,
+}
+
+#[post("/_recompute-balance/{id}", guard = "admin_key_guard")]
+pub async fn recompute_balance(
+    req: HttpRequest,
+    info: web::Path<(UserId,)>,
+    query: web::Query<RecomputeBalanceQuery>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = info.into_inner().0;
+    let db_user_id: crate::database::models::ids::UserId = user_id.into();
+
+    let mtx = payouts_queue.lock_user_payouts(user_id);
+    let _guard = mtx.lock().await;
+
+    let mut transaction = pool.begin().await?;
+
+    let previous = sqlx::query!(
+        "SELECT balance FROM users WHERE id = $1 FOR UPDATE",
+        db_user_id.0
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(ApiError::NotFound)?
+    .balance;
+
+    let ledger = crate::database::models::payout_item::Payout::get_ledger_balance(
+        db_user_id,
+        &mut *transaction,
+    )
+    .await?;
+
+    let corrected = query.correct && previous != ledger;
+    if corrected {
+        sqlx::query!(
+            "
+            UPDATE users
+            SET balance = $1
+            WHERE id = $2
+            ",
+            ledger,
+            db_user_id.0
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    audit_log(
+        &req,
+        PayoutAuditAction::RecomputeBalance,
+        Some(user_id.to_string()),
+        serde_json::json!({
+            "previous": previous,
+            "ledger": ledger,
+            "corrected": corrected,
+        }),
+        &mut *transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    if corrected {
+        crate::database::models::User::clear_caches(&[(db_user_id, None)], &redis).await?;
+    }
+
+    info!(
+        "Recomputed balance for user {}: stored {}, ledger {}{}",
+        user_id,
+        previous,
+        ledger,
+        if corrected { " (corrected)" } else { "" }
+    );
+
+    Ok(HttpResponse::Ok().json(RecomputedBalance {
+        previous,
+        ledger,
+        corrected,
+    }))
+}
+
+/// Lists which payout methods a user can withdraw with, and why the others are excluded
+#[get("/_payout-eligibility/{id}", guard = "admin_key_guard")]
+pub async fn payout_eligibility(
+    info: web::Path<(UserId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = crate::database::models::User::get_id(info.into_inner().0.into(), &**pool, &redis)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let on_hold =
+        crate::database::models::payout_hold_item::PayoutHold::get_active(user.id, &**pool)
+            .await?
+            .is_some();
+    let preferred_method =
+        crate::database::models::payout_preference_item::PayoutPreferences::get_preferred_method(
+            user.id, &**pool,
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(
+        payouts_queue
+            .get_payout_eligibility(&user, on_hold, preferred_method.as_deref())
+            .await?,
+    ))
+}
+
+/// Reports whether each payout provider is in sandbox or live mode
+#[get("/_payout-providers", guard = "admin_key_guard")]
+pub async fn payout_providers(
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(payouts_queue.provider_modes()))
+}
+
+#[derive(Deserialize)]
+pub struct ReprocessPayoutQuery {
+    /// The day to pay out, defaulting to the most recently completed day
+    pub day: Option<NaiveDate>,
+    /// Replaces the payouts already made for the day instead of skipping it
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Runs the payout for a single day, which is skipped if the day was already paid out unless
+/// `force` is set
+#[post("/_process-payout", guard = "admin_key_guard")]
+pub async fn reprocess_payout(
+    req: HttpRequest,
+    query: web::Query<ReprocessPayoutQuery>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    clickhouse: web::Data<clickhouse::Client>,
+) -> Result<HttpResponse, ApiError> {
+    let period = match query.day {
+        Some(day) => PayoutPeriod::ending_at(
+            day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc() + Duration::days(1),
+        ),
+        None => PayoutPeriod::current(),
+    };
+
+    info!(
+        "Processing payouts for {}{}",
+        period.last_day(),
+        if query.force { " (forced)" } else { "" }
+    );
+
+    let entry = audit_entry(
+        &req,
+        PayoutAuditAction::ProcessPayout,
+        Some(period.last_day().date_naive().to_string()),
+        serde_json::json!({ "force": query.force }),
+    );
+    process_payout(
+        &pool,
+        &**redis,
+        &clickhouse,
+        &period,
+        query.force,
+        Some(&entry),
+    )
+    .await?;
+    log_audit_entry(&entry);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Pays out every day missed since the most recently paid out one, oldest first, returning the
+/// days processed. This is what each scheduled payout run does.
+#[post("/_catch-up-payouts", guard = "admin_key_guard")]
+pub async fn catch_up_payout(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    clickhouse: web::Data<clickhouse::Client>,
+) -> Result<HttpResponse, ApiError> {
+    // Each day caught up on is audited with its payouts
+    let entry = audit_entry(
+        &req,
+        PayoutAuditAction::CatchUpPayouts,
+        None,
+        serde_json::json!({}),
+    );
+    let days = catch_up_payouts(&pool, &**redis, &clickhouse, Some(&entry)).await?;
+    log_audit_entry(&entry);
+
+    Ok(HttpResponse::Ok().json(days))
+}
+
+#[derive(Deserialize)]
+pub struct ReconcilePayoutQuery {
+    pub day: NaiveDate,
+}
+
+/// Clears the cache of every user credited on a day, to recover from a payout run which was
+/// interrupted after crediting balances
+#[post("/_reconcile-payout", guard = "admin_key_guard")]
+pub async fn reconcile_payout(
+    req: HttpRequest,
+    query: web::Query<ReconcilePayoutQuery>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+) -> Result<HttpResponse, ApiError> {
+    let start = query.day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+
+    // Clearing caches can't be rolled back, but the entry is only recorded once they are cleared
+    let mut transaction = pool.begin().await?;
+    let users = reconcile_payout_caches(&mut *transaction, &**redis, start).await?;
+
+    info!("Cleared caches of {users} users paid out on {start}");
+
+    audit_log(
+        &req,
+        PayoutAuditAction::ReconcilePayout,
+        Some(query.day.to_string()),
+        serde_json::json!({ "users": users }),
+        &mut *transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Deserialize)]
+pub struct PayoutExportQuery {
+    pub day: NaiveDate,
+}
+
+/// Downloads the payouts credited on a day as CSV
+#[get("/_payout-export", guard = "admin_key_guard")]
+pub async fn payout_export(
+    query: web::Query<PayoutExportQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let start = query.day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"payouts-{}.csv\"", query.day),
+        ))
+        .streaming(export_payout_day(pool.get_ref().clone(), start)))
+}
+
+#[derive(Deserialize)]
+pub struct PayoutHoldQuery {
+    pub hold: bool,
+}
+
+/// Holds back or releases a project's payouts, for use by moderation when a project fails
+/// content checks. Held projects are excluded from payouts and their share goes to other projects.
+#[post("/_payout-hold/{id}", guard = "admin_key_guard")]
+pub async fn payout_hold(
+    req: HttpRequest,
+    info: web::Path<(ProjectId,)>,
+    query: web::Query<PayoutHoldQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let project_id = info.into_inner().0;
+
+    let mut transaction = pool.begin().await?;
+    let exists = crate::database::models::Project::set_payout_hold(
+        project_id.into(),
+        query.hold,
+        &mut transaction,
+    )
+    .await?;
+    if !exists {
+        return Err(ApiError::NotFound);
+    }
+
+    audit_log(
+        &req,
+        PayoutAuditAction::PayoutHold,
+        Some(project_id.to_string()),
+        serde_json::json!({ "hold": query.hold }),
+        &mut *transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    info!(
+        "{} payouts for project {}",
+        if query.hold { "Held" } else { "Released" },
+        project_id
+    );
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Deserialize)]
+pub struct UserPayoutHoldRequest {
+    pub reason: String,
+    /// How long the hold lasts before it expires, unless it is renewed
+    pub duration_days: i64,
+}
+
+/// Places a compliance hold on all of a user's withdrawals, replacing any hold they already
+/// have. The hold expires after the given number of days unless it is renewed.
+#[post("/_user-payout-hold/{id}", guard = "admin_key_guard")]
+pub async fn place_user_payout_hold(
+    req: HttpRequest,
+    info: web::Path<(UserId,)>,
+    body: web::Json<UserPayoutHoldRequest>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = info.into_inner().0;
+    if body.reason.trim().is_empty() {
+        return Err(ApiError::InvalidInput(
+            "A reason for the hold is required".to_string(),
+        ));
+    }
+    let expires = hold_expiry(body.duration_days)?;
+
+    crate::database::models::User::get_id(user_id.into(), &**pool, &redis)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let mut transaction = pool.begin().await?;
+    let hold = crate::database::models::payout_hold_item::PayoutHold::set(
+        user_id.into(),
+        body.reason.trim(),
+        expires,
+        &mut *transaction,
+    )
+    .await?;
+    audit_log(
+        &req,
+        PayoutAuditAction::UserPayoutHold,
+        Some(user_id.to_string()),
+        serde_json::json!({ "hold": "placed", "reason": hold.reason, "expires": hold.expires }),
+        &mut *transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    info!("Held payouts for user {} until {}", user_id, hold.expires);
+
+    Ok(HttpResponse::Ok().json(hold))
+}
+
+#[derive(Deserialize)]
+pub struct RenewUserPayoutHoldRequest {
+    pub duration_days: i64,
+}
+
+/// Extends a user's active hold to expire the given number of days from now. Holds which have
+/// already expired must be placed again instead.
+#[post("/_user-payout-hold/{id}/renew", guard = "admin_key_guard")]
+pub async fn renew_user_payout_hold(
+    req: HttpRequest,
+    info: web::Path<(UserId,)>,
+    body: web::Json<RenewUserPayoutHoldRequest>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = info.into_inner().0;
+    let expires = hold_expiry(body.duration_days)?;
+
+    let mut transaction = pool.begin().await?;
+    let hold = crate::database::models::payout_hold_item::PayoutHold::renew(
+        user_id.into(),
+        expires,
+        &mut *transaction,
+    )
+    .await?
+    .ok_or(ApiError::NotFound)?;
+    audit_log(
+        &req,
+        PayoutAuditAction::UserPayoutHold,
+        Some(user_id.to_string()),
+        serde_json::json!({ "hold": "renewed", "expires": hold.expires }),
+        &mut *transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    info!(
+        "Renewed the payout hold of user {} until {}",
+        user_id, hold.expires
+    );
+
+    Ok(HttpResponse::Ok().json(hold))
+}
+
+/// Lifts a user's active hold before it expires
+#[delete("/_user-payout-hold/{id}", guard = "admin_key_guard")]
+pub async fn lift_user_payout_hold(
+    req: HttpRequest,
+    info: web::Path<(UserId,)>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = info.into_inner().0;
+
+    let mut transaction = pool.begin().await?;
+    let lifted = crate::database::models::payout_hold_item::PayoutHold::lift(
+        user_id.into(),
+        &mut *transaction,
+    )
+    .await?;
+    if !lifted {
+        return Err(ApiError::NotFound);
+    }
+    audit_log(
+        &req,
+        PayoutAuditAction::UserPayoutHold,
+        Some(user_id.to_string()),
+        serde_json::json!({ "hold": "lifted" }),
+        &mut *transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    info!("Lifted the payout hold of user {}", user_id);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// The longest a hold can be placed or renewed for at once
+const USER_PAYOUT_HOLD_MAX_DAYS: i64 = 365;
+
+fn hold_expiry(duration_days: i64) -> Result<DateTime<Utc>, ApiError> {
+    if !(1..=USER_PAYOUT_HOLD_MAX_DAYS).contains(&duration_days) {
+        return Err(ApiError::InvalidInput(format!(
+            "Holds must last between 1 and {USER_PAYOUT_HOLD_MAX_DAYS} days"
+        )));
+    }
+
+    Ok(Utc::now() + Duration::days(duration_days))
+}
+
+#[derive(Deserialize)]
+pub struct PayoutTotalsQuery {
+    pub granularity: PayoutGranularity,
+    /// Defaults to the first payout
+    pub start: Option<DateTime<Utc>>,
+    /// Defaults to now
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// Totals the payouts made to creators per day, week or month, for finance reporting
+#[get("/_payout-totals", guard = "admin_key_guard")]
+pub async fn payout_totals(
+    query: web::Query<PayoutTotalsQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let totals = fetch_payout_totals(
+        &pool,
+        query.granularity,
+        query.start.unwrap_or_default(),
+        query.end.unwrap_or_else(Utc::now),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(totals))
+}
+
+/// A withdrawal along with the user who made it, for support lookups
+#[derive(Serialize, Deserialize)]
+pub struct PayoutRecord {
+    pub payout: Payout,
+    /// The `PayoutMethod` withdrawn with, ex: a Tremendous product id
+    pub method_id: Option<String>,
+    pub username: String,
+    pub email: Option<String>,
+    #[serde(with = "crate::models::payouts::money")]
+    pub balance: Decimal,
+    /// Everything ever credited to the user in `payouts_values`
+    #[serde(with = "crate::models::payouts::money")]
+    pub total_credited: Decimal,
+}
+
+/// Looks up a withdrawal by the id its provider gave it (a PayPal payout item id or Tremendous
+/// reward id), so support can go straight from a provider's dashboard to the payout
+#[get("/_payout-by-reference/{provider}/{id}", guard = "admin_key_guard")]
+pub async fn payout_by_reference(
+    info: web::Path<(String, String)>,
+    pool: web::Data<PgPool>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (provider, platform_id) = info.into_inner();
+    let methods = payouts_queue
+        .provider_methods(&provider)
+        .ok_or(ApiError::NotFound)?;
+
+    let record = sqlx::query!(
+        r#"
+        SELECT p.id, u.username, u.email, u.balance,
+            COALESCE((SELECT SUM(v.amount) FROM payouts_values v WHERE v.user_id = u.id), 0) "total_credited!"
+        FROM payouts p
+        INNER JOIN users u ON u.id = p.user_id
+        WHERE p.platform_id = $1 AND p.method = ANY($2)
+        "#,
+        platform_id,
+        &methods
+            .iter()
+            .map(|x| x.as_str().to_string())
+            .collect::<Vec<_>>(),
+    )
+    .fetch_optional(&**pool)
+    .await?
+    .ok_or(ApiError::NotFound)?;
+
+    let payout = crate::database::models::payout_item::Payout::get(
+        crate::database::models::PayoutId(record.id),
+        &**pool,
+    )
+    .await?
+    .ok_or(ApiError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(PayoutRecord {
+        method_id: payout.method_id.clone(),
+        payout: Payout::from(payout),
+        username: record.username,
+        email: record.email,
+        balance: record.balance,
+        total_credited: record.total_credited,
+    }))
+}
+
+/// The user who made a withdrawal, so their payout lock can be taken before the withdrawal's row
+async fn payout_user_id(id: PayoutId, pool: &PgPool) -> Result<UserId, ApiError> {
+    Ok(
+        crate::database::models::payout_item::Payout::get(id.into(), pool)
+            .await?
+            .ok_or(ApiError::NotFound)?
+            .user_id
+            .into(),
+    )
+}
+
+/// Sends a withdrawal held for review to its provider
+#[post("/_payout-review/{id}/approve", guard = "admin_key_guard")]
+pub async fn approve_payout(
+    req: HttpRequest,
+    info: web::Path<(PayoutId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let id = info.into_inner().0;
+
+    let mtx = payouts_queue.lock_user_payouts(payout_user_id(id, &pool).await?);
+    let _guard = mtx.lock().await;
+
+    if payouts_queue.is_draining() {
+        return Err(ApiError::ServiceUnavailable(
+            "Payouts are shutting down. Please try again later.".to_string(),
+        ));
+    }
+
+    let mut transaction = pool.begin().await?;
+    let payout = lock_pending_review_payout(id, &mut transaction).await?;
+
+    let user = crate::database::models::User::get_id(payout.user_id, &mut *transaction, &redis)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let method = payout.method.unwrap_or(PayoutMethodType::Unknown);
+    let method_id = payout.method_id.unwrap_or_default();
+    let (address, _) = crate::routes::v3::payouts::payout_address(&user, method, &method_id)?;
+    let tremendous_value = payouts_queue
+        .stored_tremendous_value(method, &method_id, payout.amount)
+        .await?;
+
+    let correlation_id = new_correlation_id();
+    let platform_id = payouts_queue
+        .create_payout(&PayoutRequest {
+            id,
+            method,
+            method_id,
+            amount: payout.amount,
+            address,
+            recipient_name: user.username,
+            correlation_id: correlation_id.clone(),
+            note: payout.note,
+            tremendous_value,
+        })
+        .await?;
+
+    sqlx::query!(
+        "
+        UPDATE payouts
+        SET status = $1, platform_id = $2
+        WHERE id = $3
+        ",
+        PayoutStatus::InTransit.as_str(),
+        platform_id,
+        payout.id.0
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    audit_log(
+        &req,
+        PayoutAuditAction::ApprovePayout,
+        Some(id.to_string()),
+        serde_json::json!({ "platform_id": platform_id }),
+        &mut *transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    info!("Approved payout {id} after review");
+
+    Ok(HttpResponse::NoContent()
+        .insert_header((CORRELATION_ID_HEADER, correlation_id))
+        .finish())
+}
+
+/// Cancels a withdrawal held for review, returning the funds to the user's balance
+#[post("/_payout-review/{id}/deny", guard = "admin_key_guard")]
+pub async fn deny_payout(
+    req: HttpRequest,
+    info: web::Path<(PayoutId,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let id = info.into_inner().0;
+
+    let mtx = payouts_queue.lock_user_payouts(payout_user_id(id, &pool).await?);
+    let _guard = mtx.lock().await;
+
+    let mut transaction = pool.begin().await?;
+    let payout = lock_pending_review_payout(id, &mut transaction).await?;
+    let refunded = refund_pending_review_payout(&payout, &mut transaction).await?;
+
+    audit_log(
+        &req,
+        PayoutAuditAction::DenyPayout,
+        Some(id.to_string()),
+        serde_json::json!({
+            "user_id": UserId::from(payout.user_id),
+            "refunded": refunded,
+        }),
+        &mut *transaction,
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    crate::database::models::User::clear_caches(&[(payout.user_id, None)], &redis).await?;
+
+    info!("Denied payout {id} after review");
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Who admin requests are authenticated as. The admin key is shared, so no one more specific.
+const ADMIN_ACTOR: &str = "admin";
+
+/// Who an admin request says made it, as named by its `Modrinth-Admin-Actor` header. Anyone with
+/// the admin key can send any name, so it is only recorded as a claim.
+fn claimed_admin_actor(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(ADMIN_ACTOR_HEADER)
+        .and_then(|x| x.to_str().ok())
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.chars().take(255).collect())
+}
+
+/// An admin payout action taken by the actor of `req`, to be recorded in the audit log
+fn audit_entry(
+    req: &HttpRequest,
+    action: PayoutAuditAction,
+    target: Option<String>,
+    details: serde_json::Value,
+) -> PayoutAuditEntry {
+    PayoutAuditEntry {
+        created: Utc::now(),
+        actor: ADMIN_ACTOR.to_string(),
+        claimed_actor: claimed_admin_actor(req),
+        action,
+        target,
+        details,
+    }
+}
+
+fn log_audit_entry(entry: &PayoutAuditEntry) {
+    info!(
+        "{} (claiming to be {}) took payout action {} on {}",
+        entry.actor,
+        entry.claimed_actor.as_deref().unwrap_or("-"),
+        entry.action,
+        entry.target.as_deref().unwrap_or("-")
+    );
+}
+
+/// Records an admin payout action in the audit log, taken by the actor of `req`
+async fn audit_log(
+    req: &HttpRequest,
+    action: PayoutAuditAction,
+    target: Option<String>,
+    details: serde_json::Value,
+    exec: impl sqlx::Executor<'_, Database = sqlx::Postgres>,
+) -> Result<(), ApiError> {
+    let entry = audit_entry(req, action, target, details);
+    crate::database::models::payout_audit_item::PayoutAuditLog::insert(&entry, exec).await?;
+    log_audit_entry(&entry);
+
+    Ok(())
+}
+
+/// The most entries returned by the payout audit log at once
+const AUDIT_LOG_LIMIT: i64 = 1000;
+
+#[derive(Deserialize)]
+pub struct PayoutAuditLogQuery {
+    pub target: Option<String>,
+    pub actor: Option<String>,
+    pub claimed_actor: Option<String>,
+    /// Defaults to the first entry
+    pub start: Option<DateTime<Utc>>,
+    /// Defaults to now
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// Lists the admin payout actions taken, newest first, up to [`AUDIT_LOG_LIMIT`] of them
+#[get("/_payout-audit-log", guard = "admin_key_guard")]
+pub async fn payout_audit_log(
+    query: web::Query<PayoutAuditLogQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let entries = crate::database::models::payout_audit_item::PayoutAuditLog::get_entries(
+        query.target.as_deref(),
+        query.actor.as_deref(),
+        query.claimed_actor.as_deref(),
+        query.start.unwrap_or_default(),
+        query.end.unwrap_or_else(Utc::now),
+        AUDIT_LOG_LIMIT,
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+/// The most payout runs listed at once
+const PAYOUT_RUNS_MAX_LIMIT: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct PayoutRunsQuery {
+    /// Defaults to 30, at most [`PAYOUT_RUNS_MAX_LIMIT`]
+    pub limit: Option<i64>,
+}
+
+/// Lists the most recent payout runs, newest first
+#[get("/_payout-runs", guard = "admin_key_guard")]
+pub async fn payout_runs(
+    query: web::Query<PayoutRunsQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let runs = crate::database::models::payout_run_item::PayoutRunHistory::get_recent(
+        query.limit.unwrap_or(30).clamp(1, PAYOUT_RUNS_MAX_LIMIT),
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+/// Runs the payout math over a fixed synthetic day and reports any credits which differ from
+/// the known correct ones
+#[get("/_payout-self-test", guard = "admin_key_guard")]
+pub async fn payout_self_test() -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(crate::queue::payouts::payout_self_test()))
+}
+
+#[derive(Deserialize)]
+pub struct PayoutMethodSnapshotQuery {
+    pub at: DateTime<Utc>,
+}
+
+/// The payout methods as they were listed closest to a point in time, for investigating a past
+/// payout
+#[get("/_payout-method-snapshot", guard = "admin_key_guard")]
+pub async fn payout_method_snapshot(
+    query: web::Query<PayoutMethodSnapshotQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let snapshot =
+        crate::database::models::payout_method_snapshot_item::PayoutMethodSnapshots::get_near(
+            query.at, &**pool,
+        )
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(snapshot))
+}
@@ -107,6 +107,10 @@ pub enum ApiError {
     Indexing(#[from] crate::search::indexing::IndexingError),
     #[error("Payments Error: {0}")]
     Payments(String),
+    #[error("Duplicate Payout: {0}")]
+    DuplicatePayout(String),
+    #[error("Compliance Error: {0}")]
+    Compliance(String),
     #[error("Discord Error: {0}")]
     Discord(String),
     #[error("Captcha Error. Try resubmitting the form.")]
@@ -125,6 +129,12 @@ pub enum ApiError {
     Reroute(#[from] reqwest::Error),
     #[error("Resource not found")]
     NotFound,
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
 }
 
 impl actix_web::ResponseError for ApiError {
@@ -144,6 +154,8 @@ impl actix_web::ResponseError for ApiError {
             ApiError::InvalidInput(..) => StatusCode::BAD_REQUEST,
             ApiError::Validation(..) => StatusCode::BAD_REQUEST,
             ApiError::Payments(..) => StatusCode::FAILED_DEPENDENCY,
+            ApiError::DuplicatePayout(..) => StatusCode::CONFLICT,
+            ApiError::Compliance(..) => StatusCode::FORBIDDEN,
             ApiError::Discord(..) => StatusCode::FAILED_DEPENDENCY,
             ApiError::Turnstile => StatusCode::BAD_REQUEST,
             ApiError::Decoding(..) => StatusCode::BAD_REQUEST,
@@ -153,6 +165,9 @@ impl actix_web::ResponseError for ApiError {
             ApiError::Mail(..) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::Reroute(..) => StatusCode::INTERNAL_SERVER_ERROR,
             ApiError::NotFound => StatusCode::NOT_FOUND,
+            ApiError::ServiceUnavailable(..) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Timeout(..) => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::RateLimited(..) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
@@ -172,6 +187,8 @@ impl actix_web::ResponseError for ApiError {
                 ApiError::InvalidInput(..) => "invalid_input",
                 ApiError::Validation(..) => "invalid_input",
                 ApiError::Payments(..) => "payments_error",
+                ApiError::DuplicatePayout(..) => "duplicate_payout",
+                ApiError::Compliance(..) => "compliance_error",
                 ApiError::Discord(..) => "discord_error",
                 ApiError::Turnstile => "turnstile_error",
                 ApiError::Decoding(..) => "decoding_error",
@@ -182,6 +199,9 @@ impl actix_web::ResponseError for ApiError {
                 ApiError::Clickhouse(..) => "clickhouse_error",
                 ApiError::Reroute(..) => "reroute_error",
                 ApiError::NotFound => "not_found",
+                ApiError::ServiceUnavailable(..) => "service_unavailable",
+                ApiError::Timeout(..) => "timeout",
+                ApiError::RateLimited(..) => "ratelimit_error",
             },
             description: &self.to_string(),
         })
@@ -1,7 +1,9 @@
 use super::ApiError;
 use crate::database;
 use crate::database::redis::RedisPool;
+use crate::models::projects::MonetizationStatus;
 use crate::models::teams::ProjectPermissions;
+use crate::queue::payouts::{estimate_project_payout, fetch_payout_rate_trend};
 use crate::{
     auth::get_user_from_headers,
     database::models::user_item,
@@ -13,6 +15,7 @@ use crate::{
 };
 use actix_web::{web, HttpRequest, HttpResponse};
 use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::types::PgInterval;
 use sqlx::PgPool;
@@ -26,6 +29,8 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("views", web::get().to(views_get))
             .route("downloads", web::get().to(downloads_get))
             .route("revenue", web::get().to(revenue_get))
+            .route("revenue/estimate", web::get().to(revenue_estimate_get))
+            .route("revenue/trend", web::get().to(revenue_trend_get))
             .route(
                 "countries/downloads",
                 web::get().to(countries_downloads_get),
@@ -510,6 +515,131 @@ fn condense_countries(countries: HashMap<String, u64>) -> HashMap<String, u64> {
     hm
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetEstimateData {
+    pub project_id: String,
+}
+
+/// An estimate of a project's payout for the current, in-progress day. This is extrapolated
+/// from partial analytics and may differ from the amount actually paid out once the day is over.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RevenueEstimate {
+    #[serde(with = "crate::models::payouts::money")]
+    pub project_amount: Decimal,
+    /// The requesting user's share of `project_amount`, according to their payouts split
+    #[serde(with = "crate::models::payouts::money")]
+    pub user_amount: Decimal,
+}
+
+/// Estimate what a project will earn today
+/// Estimates are extrapolated from today's partial views and downloads, and may differ from the final payout.
+pub async fn revenue_estimate_get(
+    req: HttpRequest,
+    clickhouse: web::Data<clickhouse::Client>,
+    data: web::Query<GetEstimateData>,
+    session_queue: web::Data<AuthQueue>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await
+    .map(|x| x.1)?;
+    let user_id = user.id;
+
+    let project_id = filter_allowed_ids(Some(vec![data.project_id.clone()]), user, &pool, &redis)
+        .await?
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .ok_or(ApiError::NotFound)?;
+
+    let project = database::models::Project::get_id(project_id.into(), &**pool, &redis)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    let project_amount = if project.inner.monetization_status == MonetizationStatus::Monetized {
+        estimate_project_payout(&clickhouse, &pool, project_id.0).await?
+    } else {
+        Decimal::ZERO
+    };
+
+    let members =
+        database::models::TeamMember::get_from_team_full(project.inner.team_id, &**pool, &redis)
+            .await?
+            .into_iter()
+            .filter(|x| x.accepted)
+            .collect::<Vec<_>>();
+    let sum_splits: Decimal = members.iter().map(|x| x.payouts_split).sum();
+    let user_split = members
+        .iter()
+        .find(|x| x.user_id == user_id.into())
+        .map(|x| x.payouts_split)
+        .unwrap_or_default();
+
+    let user_amount = if sum_splits > Decimal::ZERO {
+        project_amount * (user_split / sum_splits)
+    } else {
+        Decimal::ZERO
+    };
+
+    Ok(HttpResponse::Ok().json(RevenueEstimate {
+        project_amount,
+        user_amount,
+    }))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GetTrendData {
+    pub days: Option<u32>, // defaults to 28, the length of a payout period
+}
+
+/// Get the platform-wide payout per view or download for each of the last `days` completed days
+/// Data is returned as a list of points, from least to most recent
+/// eg:
+/// [
+///     {
+///         "time": 1692835200,
+///         "payouts": 102.5,
+///         "views_and_downloads": 410000,
+///         "rate": 0.00025
+///     }
+/// ]
+/// Days without any views or downloads have a null rate.
+pub async fn revenue_trend_get(
+    req: HttpRequest,
+    clickhouse: web::Data<clickhouse::Client>,
+    data: web::Query<GetTrendData>,
+    session_queue: web::Data<AuthQueue>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+) -> Result<HttpResponse, ApiError> {
+    get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::ANALYTICS]),
+    )
+    .await?;
+
+    let days = data.days.unwrap_or(28);
+    if !(1..=365).contains(&days) {
+        return Err(ApiError::InvalidInput(
+            "days must be between 1 and 365".to_string(),
+        ));
+    }
+
+    let trend = fetch_payout_rate_trend(&pool, &redis, clickhouse.into_inner(), days).await?;
+
+    Ok(HttpResponse::Ok().json(trend))
+}
+
 async fn filter_allowed_ids(
     mut project_ids: Option<Vec<String>>,
     user: crate::models::users::User,
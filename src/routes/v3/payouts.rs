@@ -2,32 +2,64 @@ use crate::auth::validate::get_user_record_from_bearer_token;
 use crate::auth::{get_user_from_headers, AuthenticationError};
 use crate::database::models::generate_payout_id;
 use crate::database::redis::RedisPool;
-use crate::models::ids::PayoutId;
+use crate::models::ids::{PayoutId, ProjectId};
 use crate::models::pats::Scopes;
-use crate::models::payouts::{PayoutMethodType, PayoutStatus};
-use crate::queue::payouts::PayoutsQueue;
+use crate::models::payouts::{
+    validate_withdrawal_amount, Gross, PayoutBalance, PayoutDestination, PayoutMethodType,
+    PayoutStatus, WithdrawalRequest, WithdrawalResponse, WithdrawalRetryRequest,
+};
+use crate::queue::payouts::{
+    check_withdrawal_method, check_withdrawals_allowed, compare_payout_fees, estimate_user_payout,
+    max_withdrawals, new_correlation_id, payout_fee_schedules, retry_failed_payout,
+    return_payout_funds, review_threshold, sanitize_payout_note, split_withdrawal,
+    validate_payout_destinations, DestinationValidation, PayPalFeeCaps, PayoutConstants,
+    PayoutRequest, PayoutRetry, PayoutRoundingMode, PayoutWithholding, PayoutsQueue,
+    TremendousOrderValue, WithdrawalAmounts, WithdrawalRateLimit, CORRELATION_ID_HEADER,
+};
 use crate::queue::session::AuthQueue;
 use crate::routes::ApiError;
-use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
-use chrono::Utc;
-use hex::ToHex;
+use crate::util::currency::FxRates;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
 use hmac::{Hmac, Mac, NewMac};
 use hyper::Method;
 use rust_decimal::Decimal;
 use serde::Deserialize;
-use serde_json::json;
 use sha2::Sha256;
 use sqlx::PgPool;
 
+/// The largest JSON body the payout routes accept. Every body they take is a few fields at most.
+const PAYOUT_BODY_LIMIT: usize = 16 * 1024;
+
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("payout")
+            .app_data(
+                web::JsonConfig::default()
+                    .limit(PAYOUT_BODY_LIMIT)
+                    .error_handler(|err, _req| ApiError::Validation(err.to_string()).into()),
+            )
             .service(paypal_webhook)
             .service(tremendous_webhook)
             .service(user_payouts)
+            .service(user_payout_history)
+            .service(user_balance)
             .service(create_payout)
+            .service(user_payout_destinations)
+            .service(set_payout_destinations)
+            .service(create_split_payout)
             .service(cancel_payout)
-            .service(payment_methods),
+            .service(user_payout_attempts)
+            .service(retry_payout)
+            .service(user_max_withdrawals)
+            .service(user_payout_onboarding)
+            .service(user_payout_eligibility)
+            .service(set_preferred_payout_method)
+            .service(payment_methods)
+            .service(payment_fees)
+            .service(payment_fee_schedules)
+            .service(payout_constants)
+            .service(validate_destination),
     );
 }
 
@@ -89,6 +121,7 @@ pub async fn paypal_webhook(
                 dotenvy::var("PAYPAL_WEBHOOK_ID")?
             )),
             None,
+            None,
         )
         .await?;
 
@@ -111,84 +144,87 @@ pub async fn paypal_webhook(
 
     let webhook = serde_json::from_str::<PayPalWebhook>(&body)?;
 
-    match &*webhook.event_type {
+    let status = match &*webhook.event_type {
         "PAYMENT.PAYOUTS-ITEM.BLOCKED"
         | "PAYMENT.PAYOUTS-ITEM.DENIED"
         | "PAYMENT.PAYOUTS-ITEM.REFUNDED"
-        | "PAYMENT.PAYOUTS-ITEM.RETURNED"
-        | "PAYMENT.PAYOUTS-ITEM.CANCELED" => {
-            let mut transaction = pool.begin().await?;
-
-            let result = sqlx::query!(
-                "SELECT user_id, amount, fee FROM payouts WHERE platform_id = $1 AND status = $2",
-                webhook.resource.payout_item_id,
-                PayoutStatus::InTransit.as_str()
-            )
-            .fetch_optional(&mut *transaction)
-            .await?;
+        | "PAYMENT.PAYOUTS-ITEM.RETURNED" => PayoutStatus::Failed,
+        "PAYMENT.PAYOUTS-ITEM.CANCELED" => PayoutStatus::Cancelled,
+        "PAYMENT.PAYOUTS-ITEM.SUCCEEDED" => PayoutStatus::Success,
+        _ => return Ok(HttpResponse::NoContent().finish()),
+    };
 
-            if let Some(result) = result {
-                let mtx =
-                    payouts.lock_user_payouts(crate::models::ids::UserId(result.user_id as u64));
-                let _guard = mtx.lock().await;
+    settle_webhook_payout(
+        &pool,
+        &redis,
+        &payouts,
+        &webhook.resource.payout_item_id,
+        status,
+    )
+    .await?;
 
-                sqlx::query!(
-                    "
-                    UPDATE users
-                    SET balance = balance + $1
-                    WHERE id = $2
-                    ",
-                    result.amount + result.fee.unwrap_or(Decimal::ZERO),
-                    result.user_id
-                )
-                .execute(&mut *transaction)
-                .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
 
-                sqlx::query!(
-                    "
-                    UPDATE payouts
-                    SET status = $1
-                    WHERE platform_id = $2
-                    ",
-                    if &*webhook.event_type == "PAYMENT.PAYOUTS-ITEM.CANCELED" {
-                        PayoutStatus::Cancelled
-                    } else {
-                        PayoutStatus::Failed
-                    }
-                    .as_str(),
-                    webhook.resource.payout_item_id
-                )
-                .execute(&mut *transaction)
-                .await?;
+/// Applies a provider's webhook reporting that the withdrawal it knows as `platform_id` ended
+/// with `status`. Only withdrawals still in transit are updated, so a webhook arriving after
+/// [`reconcile_failed_payouts`] already returned the funds, or after another webhook, never
+/// returns them twice or marks a returned withdrawal as succeeded.
+///
+/// [`reconcile_failed_payouts`]: crate::queue::payouts::reconcile_failed_payouts
+async fn settle_webhook_payout(
+    pool: &PgPool,
+    redis: &RedisPool,
+    payouts: &PayoutsQueue,
+    platform_id: &str,
+    status: PayoutStatus,
+) -> Result<(), ApiError> {
+    if status == PayoutStatus::Success {
+        sqlx::query!(
+            "
+            UPDATE payouts
+            SET status = $1
+            WHERE platform_id = $2 AND status = $3
+            ",
+            PayoutStatus::Success.as_str(),
+            platform_id,
+            PayoutStatus::InTransit.as_str(),
+        )
+        .execute(pool)
+        .await?;
 
-                transaction.commit().await?;
+        return Ok(());
+    }
 
-                crate::database::models::user_item::User::clear_caches(
-                    &[(crate::database::models::UserId(result.user_id), None)],
-                    &redis,
-                )
-                .await?;
-            }
-        }
-        "PAYMENT.PAYOUTS-ITEM.SUCCEEDED" => {
-            let mut transaction = pool.begin().await?;
-            sqlx::query!(
-                "
-                UPDATE payouts
-                SET status = $1
-                WHERE platform_id = $2
-                ",
-                PayoutStatus::Success.as_str(),
-                webhook.resource.payout_item_id
-            )
-            .execute(&mut *transaction)
-            .await?;
-            transaction.commit().await?;
-        }
-        _ => {}
+    if let Some(id) =
+        crate::database::models::payout_item::Payout::get_id_by_platform_id(platform_id, pool)
+            .await?
+    {
+        return_payout_funds(pool, redis, payouts, id, status).await?;
     }
 
-    Ok(HttpResponse::NoContent().finish())
+    Ok(())
+}
+
+/// Checks a Tremendous webhook's `Tremendous-Webhook-Signature` header, which is
+/// `sha256=` followed by the hex HMAC-SHA256 of the body, keyed with our private key
+fn verify_tremendous_signature(
+    private_key: &str,
+    body: &str,
+    signature: Option<&str>,
+) -> Result<(), ApiError> {
+    let signature = signature
+        .and_then(|x| x.strip_prefix("sha256="))
+        .ok_or_else(|| ApiError::InvalidInput("missing webhook signature".to_string()))?;
+    let signature = hex::decode(signature)
+        .map_err(|_| ApiError::InvalidInput("Invalid webhook signature".to_string()))?;
+
+    let mut mac: Hmac<Sha256> = Hmac::new_from_slice(private_key.as_bytes())
+        .map_err(|_| ApiError::Payments("error initializing HMAC".to_string()))?;
+    mac.update(body.as_bytes());
+
+    mac.verify(&signature)
+        .map_err(|_| ApiError::InvalidInput("Invalid webhook signature".to_string()))
 }
 
 #[post("_tremendous")]
@@ -199,24 +235,13 @@ pub async fn tremendous_webhook(
     payouts: web::Data<PayoutsQueue>,
     body: String,
 ) -> Result<HttpResponse, ApiError> {
-    let signature = req
-        .headers()
-        .get("Tremendous-Webhook-Signature")
-        .and_then(|x| x.to_str().ok())
-        .and_then(|x| x.split('=').next_back())
-        .ok_or_else(|| ApiError::InvalidInput("missing webhook signature".to_string()))?;
-
-    let mut mac: Hmac<Sha256> =
-        Hmac::new_from_slice(dotenvy::var("TREMENDOUS_PRIVATE_KEY")?.as_bytes())
-            .map_err(|_| ApiError::Payments("error initializing HMAC".to_string()))?;
-    mac.update(body.as_bytes());
-    let request_signature = mac.finalize().into_bytes().encode_hex::<String>();
-
-    if &*request_signature != signature {
-        return Err(ApiError::InvalidInput(
-            "Invalid webhook signature".to_string(),
-        ));
-    }
+    verify_tremendous_signature(
+        &dotenvy::var("TREMENDOUS_PRIVATE_KEY")?,
+        &body,
+        req.headers()
+            .get("Tremendous-Webhook-Signature")
+            .and_then(|x| x.to_str().ok()),
+    )?;
 
     #[derive(Deserialize)]
     pub struct TremendousResource {
@@ -236,78 +261,21 @@ pub async fn tremendous_webhook(
 
     let webhook = serde_json::from_str::<TremendousWebhook>(&body)?;
 
-    match &*webhook.event {
-        "REWARDS.CANCELED" | "REWARDS.DELIVERY.FAILED" => {
-            let mut transaction = pool.begin().await?;
-
-            let result = sqlx::query!(
-                "SELECT user_id, amount, fee FROM payouts WHERE platform_id = $1 AND status = $2",
-                webhook.payload.resource.id,
-                PayoutStatus::InTransit.as_str()
-            )
-            .fetch_optional(&mut *transaction)
-            .await?;
-
-            if let Some(result) = result {
-                let mtx =
-                    payouts.lock_user_payouts(crate::models::ids::UserId(result.user_id as u64));
-                let _guard = mtx.lock().await;
-
-                sqlx::query!(
-                    "
-                    UPDATE users
-                    SET balance = balance + $1
-                    WHERE id = $2
-                    ",
-                    result.amount + result.fee.unwrap_or(Decimal::ZERO),
-                    result.user_id
-                )
-                .execute(&mut *transaction)
-                .await?;
-
-                sqlx::query!(
-                    "
-                    UPDATE payouts
-                    SET status = $1
-                    WHERE platform_id = $2
-                    ",
-                    if &*webhook.event == "REWARDS.CANCELED" {
-                        PayoutStatus::Cancelled
-                    } else {
-                        PayoutStatus::Failed
-                    }
-                    .as_str(),
-                    webhook.payload.resource.id
-                )
-                .execute(&mut *transaction)
-                .await?;
-
-                transaction.commit().await?;
+    let status = match &*webhook.event {
+        "REWARDS.DELIVERY.FAILED" => PayoutStatus::Failed,
+        "REWARDS.CANCELED" => PayoutStatus::Cancelled,
+        "REWARDS.DELIVERY.SUCCEEDED" => PayoutStatus::Success,
+        _ => return Ok(HttpResponse::NoContent().finish()),
+    };
 
-                crate::database::models::user_item::User::clear_caches(
-                    &[(crate::database::models::UserId(result.user_id), None)],
-                    &redis,
-                )
-                .await?;
-            }
-        }
-        "REWARDS.DELIVERY.SUCCEEDED" => {
-            let mut transaction = pool.begin().await?;
-            sqlx::query!(
-                "
-                UPDATE payouts
-                SET status = $1
-                WHERE platform_id = $2
-                ",
-                PayoutStatus::Success.as_str(),
-                webhook.payload.resource.id
-            )
-            .execute(&mut *transaction)
-            .await?;
-            transaction.commit().await?;
-        }
-        _ => {}
-    }
+    settle_webhook_payout(
+        &pool,
+        &redis,
+        &payouts,
+        &webhook.payload.resource.id,
+        status,
+    )
+    .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -343,12 +311,96 @@ pub async fn user_payouts(
     ))
 }
 
+/// The most payout history entries listed at once
+const PAYOUT_HISTORY_MAX_LIMIT: i64 = 100;
+
 #[derive(Deserialize)]
-pub struct Withdrawal {
-    #[serde(with = "rust_decimal::serde::float")]
-    amount: Decimal,
-    method: PayoutMethodType,
-    method_id: String,
+pub struct PayoutHistoryQuery {
+    /// Defaults to the first entry
+    pub start: Option<DateTime<Utc>>,
+    /// Defaults to now
+    pub end: Option<DateTime<Utc>>,
+    /// Only lists amounts earned by this project
+    pub project_id: Option<ProjectId>,
+    #[serde(default)]
+    pub offset: i64,
+    /// Defaults to 50, at most [`PAYOUT_HISTORY_MAX_LIMIT`]
+    pub limit: Option<i64>,
+}
+
+/// The amounts credited to the authenticated user, newest first, a page at a time
+#[get("history")]
+pub async fn user_payout_history(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    query: web::Query<PayoutHistoryQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?
+    .1;
+
+    if query.offset < 0 {
+        return Err(ApiError::InvalidInput(
+            "offset: must not be negative".to_string(),
+        ));
+    }
+
+    let page = crate::database::models::payout_history_item::PayoutHistory::get_page(
+        user.id.into(),
+        query.start.unwrap_or_default(),
+        query.end.unwrap_or_else(Utc::now),
+        query.project_id.map(Into::into),
+        query.offset,
+        query.limit.unwrap_or(50).clamp(1, PAYOUT_HISTORY_MAX_LIMIT),
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+/// The authenticated user's available and pending balances, lifetime earnings and an estimate of
+/// their next payout
+#[get("balance")]
+pub async fn user_balance(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    clickhouse: web::Data<clickhouse::Client>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?
+    .1;
+
+    let (pending, lifetime_earnings) =
+        crate::database::models::payout_item::Payout::get_pending_and_lifetime(
+            user.id.into(),
+            &**pool,
+        )
+        .await?;
+    let next_payout_estimate = estimate_user_payout(&clickhouse, &pool, &redis, user.id).await?;
+
+    Ok(HttpResponse::Ok().json(PayoutBalance {
+        available: user.payout_data.map(|x| x.balance).unwrap_or_default(),
+        pending,
+        lifetime_earnings,
+        next_payout_estimate,
+    }))
 }
 
 #[post("")]
@@ -356,10 +408,12 @@ pub async fn create_payout(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
-    body: web::Json<Withdrawal>,
+    body: web::Json<WithdrawalRequest>,
     session_queue: web::Data<AuthQueue>,
     payouts_queue: web::Data<PayoutsQueue>,
 ) -> Result<HttpResponse, ApiError> {
+    body.validate().map_err(ApiError::InvalidInput)?;
+
     let (scopes, user) =
         get_user_record_from_bearer_token(&req, None, &**pool, &redis, &session_queue)
             .await?
@@ -374,7 +428,19 @@ pub async fn create_payout(
     let mtx = payouts_queue.lock_user_payouts(user.id.into());
     let _guard = mtx.lock().await;
 
-    if user.balance < body.amount || body.amount < Decimal::ZERO {
+    // Checked once the user's lock is held, so shutdown waits for any withdrawal which gets past
+    // it, see `PayoutsQueue::drain`
+    check_withdrawals_allowed(&payouts_queue, &user, &**pool).await?;
+
+    if let Some(key) = &body.idempotency_key {
+        if let Some(existing) =
+            replayed_withdrawal(user.id, key, &body, &mut *pool.acquire().await?).await?
+        {
+            return Ok(withdrawal_response(&req, existing, None));
+        }
+    }
+
+    if user.balance < body.amount {
         return Err(ApiError::InvalidInput(
             "You do not have enough funds to make this payout!".to_string(),
         ));
@@ -386,251 +452,208 @@ pub async fn create_payout(
         .into_iter()
         .find(|x| x.id == body.method_id)
         .ok_or_else(|| ApiError::InvalidInput("Invalid payment method specified!".to_string()))?;
+    if payout_method.type_ != body.method {
+        return Err(ApiError::InvalidInput(format!(
+            "method_id: {} is not a {} method",
+            body.method_id, body.method
+        )));
+    }
+    // Only the requested amount is withdrawn, whatever the balance, so it is what has to be an
+    // amount the method can send. The rest of the balance stays available.
+    check_withdrawal_method(&payout_method, body.amount)?;
+
+    let WithdrawalAmounts {
+        fee,
+        withheld,
+        transfer,
+    } = WithdrawalAmounts::calculate(
+        &payout_method,
+        Gross::new(body.amount),
+        user.paypal_country.as_deref(),
+        PayoutRoundingMode::from_env(),
+        &PayoutWithholding::from_env(),
+        &PayPalFeeCaps::from_env(),
+    )?;
+    let tremendous_value =
+        TremendousOrderValue::for_method(&payout_method, transfer.amount(), &FxRates::from_env())?;
+
+    // Only counted once the withdrawal is valid, so rejected attempts don't use up the limit
+    WithdrawalRateLimit::from_env()
+        .check(&redis, user.id.into())
+        .await?;
 
-    let fee = std::cmp::min(
-        std::cmp::max(
-            payout_method.fee.min,
-            payout_method.fee.percentage * body.amount,
-        ),
-        payout_method.fee.max.unwrap_or(Decimal::MAX),
-    );
+    // The balance is taken before the payout is sent, and the row stays locked until this
+    // transaction ends, so concurrent withdrawals on other instances can't overdraw it
+    let mut transaction = pool.begin().await?;
+    withdraw_balance(user.id, body.amount, &mut transaction).await?;
 
-    let transfer = (body.amount - fee).round_dp(2);
-    if transfer <= Decimal::ZERO {
-        return Err(ApiError::InvalidInput(
-            "You need to withdraw more to cover the fee!".to_string(),
-        ));
+    // The same key may have been used on another instance while this one waited for the row
+    if let Some(key) = &body.idempotency_key {
+        if let Some(existing) = replayed_withdrawal(user.id, key, &body, &mut transaction).await? {
+            return Ok(withdrawal_response(&req, existing, None));
+        }
     }
 
-    let mut transaction = pool.begin().await?;
     let payout_id = generate_payout_id(&mut transaction).await?;
 
-    let payout_item = match body.method {
-        PayoutMethodType::Venmo | PayoutMethodType::PayPal => {
-            let (wallet, wallet_type, address, display_address) =
-                if body.method == PayoutMethodType::Venmo {
-                    if let Some(venmo) = user.venmo_handle {
-                        ("Venmo", "user_handle", venmo.clone(), venmo)
-                    } else {
-                        return Err(ApiError::InvalidInput(
-                            "Venmo address has not been set for account!".to_string(),
-                        ));
-                    }
-                } else if let Some(paypal_id) = user.paypal_id {
-                    if let Some(paypal_country) = user.paypal_country {
-                        if &*paypal_country == "US" && &*body.method_id != "paypal_us" {
-                            return Err(ApiError::InvalidInput(
-                                "Please use the US PayPal transfer option!".to_string(),
-                            ));
-                        } else if &*paypal_country != "US" && &*body.method_id == "paypal_us" {
-                            return Err(ApiError::InvalidInput(
-                                "Please use the International PayPal transfer option!".to_string(),
-                            ));
-                        }
-
-                        (
-                            "PayPal",
-                            "paypal_id",
-                            paypal_id.clone(),
-                            user.paypal_email.unwrap_or(paypal_id),
-                        )
-                    } else {
-                        return Err(ApiError::InvalidInput(
-                            "Please re-link your PayPal account!".to_string(),
-                        ));
-                    }
-                } else {
-                    return Err(ApiError::InvalidInput(
-                        "You have not linked a PayPal account!".to_string(),
-                    ));
-                };
+    let (address, display_address) = payout_address(&user, body.method, &body.method_id)?;
+
+    let correlation_id = new_correlation_id();
+    let note = body.note.as_deref().and_then(sanitize_payout_note);
+    let (status, platform_id) = payouts_queue
+        .submit_payout(
+            &PayoutRequest {
+                id: payout_id.into(),
+                method: body.method,
+                method_id: body.method_id.clone(),
+                amount: transfer.amount(),
+                address,
+                recipient_name: user.username,
+                correlation_id: correlation_id.clone(),
+                note: note.clone(),
+                tremendous_value,
+            },
+            review_threshold(),
+        )
+        .await?;
 
-            #[derive(Deserialize)]
-            struct PayPalLink {
-                href: String,
-            }
+    let payout_item = crate::database::models::payout_item::Payout {
+        id: payout_id,
+        user_id: user.id,
+        created: Utc::now(),
+        status,
+        amount: transfer.amount(),
+        fee: Some(fee.amount()),
+        withheld: Some(withheld.amount()),
+        method: Some(body.method),
+        method_id: Some(body.method_id.clone()),
+        method_address: Some(display_address),
+        platform_id,
+        note,
+        idempotency_key: body.idempotency_key.clone(),
+    };
 
-            #[derive(Deserialize)]
-            struct PayoutsResponse {
-                pub links: Vec<PayPalLink>,
-            }
+    payout_item.insert(&mut transaction).await?;
 
-            let mut payout_item = crate::database::models::payout_item::Payout {
-                id: payout_id,
-                user_id: user.id,
-                created: Utc::now(),
-                status: PayoutStatus::InTransit,
-                amount: transfer,
-                fee: Some(fee),
-                method: Some(body.method),
-                method_address: Some(display_address),
-                platform_id: None,
-            };
-
-            let res: PayoutsResponse = payouts_queue.make_paypal_request(
-                Method::POST,
-                "payments/payouts",
-                Some(
-                    json! ({
-                        "sender_batch_header": {
-                            "sender_batch_id": format!("{}-payouts", Utc::now().to_rfc3339()),
-                            "email_subject": "You have received a payment from Modrinth!",
-                            "email_message": "Thank you for creating projects on Modrinth. Please claim this payment within 30 days.",
-                        },
-                        "items": [{
-                            "amount": {
-                                "currency": "USD",
-                                "value": transfer.to_string()
-                            },
-                            "receiver": address,
-                            "note": "Payment from Modrinth creator monetization program",
-                            "recipient_type": wallet_type,
-                            "recipient_wallet": wallet,
-                            "sender_item_id": crate::models::ids::PayoutId::from(payout_id),
-                        }]
-                    })
-                ),
-                None,
-                None
-            ).await?;
-
-            if let Some(link) = res.links.first() {
-                #[derive(Deserialize)]
-                struct PayoutItem {
-                    pub payout_item_id: String,
-                }
+    transaction.commit().await?;
+    crate::database::models::User::clear_caches(&[(user.id, None)], &redis).await?;
 
-                #[derive(Deserialize)]
-                struct PayoutData {
-                    pub items: Vec<PayoutItem>,
-                }
+    Ok(withdrawal_response(
+        &req,
+        WithdrawalResponse::from(crate::models::payouts::Payout::from(payout_item)),
+        Some(correlation_id),
+    ))
+}
 
-                if let Ok(res) = payouts_queue
-                    .make_paypal_request::<(), PayoutData>(
-                        Method::GET,
-                        &link.href,
-                        None,
-                        None,
-                        Some(true),
-                    )
-                    .await
-                {
-                    if let Some(data) = res.items.first() {
-                        payout_item.platform_id = Some(data.payout_item_id.clone());
-                    }
-                }
-            }
+/// Responds to a withdrawal with 204 No Content, which existing clients of `POST /v3/payout`
+/// expect, unless the client sent `Prefer: return=representation`, in which case `withdrawal` is
+/// returned
+fn withdrawal_response(
+    req: &HttpRequest,
+    withdrawal: WithdrawalResponse,
+    correlation_id: Option<String>,
+) -> HttpResponse {
+    let representation = req
+        .headers()
+        .get_all("Prefer")
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .any(|x| x.trim().eq_ignore_ascii_case("return=representation"));
 
-            payout_item
-        }
-        PayoutMethodType::Tremendous => {
-            if let Some(email) = user.email {
-                if user.email_verified {
-                    let mut payout_item = crate::database::models::payout_item::Payout {
-                        id: payout_id,
-                        user_id: user.id,
-                        created: Utc::now(),
-                        status: PayoutStatus::InTransit,
-                        amount: transfer,
-                        fee: Some(fee),
-                        method: Some(PayoutMethodType::Tremendous),
-                        method_address: Some(email.clone()),
-                        platform_id: None,
-                    };
-
-                    #[derive(Deserialize)]
-                    struct Reward {
-                        pub id: String,
-                    }
+    let mut response = if representation {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::NoContent()
+    };
+    if let Some(correlation_id) = correlation_id {
+        response.insert_header((CORRELATION_ID_HEADER, correlation_id));
+    }
 
-                    #[derive(Deserialize)]
-                    struct Order {
-                        pub rewards: Vec<Reward>,
-                    }
+    if representation {
+        response
+            .insert_header(("Preference-Applied", "return=representation"))
+            .json(withdrawal)
+    } else {
+        response.finish()
+    }
+}
 
-                    #[derive(Deserialize)]
-                    struct TremendousResponse {
-                        pub order: Order,
-                    }
+/// The withdrawal already made with `idempotency_key`, if any, in which case `withdrawal` is not
+/// made again. Fails with [`ApiError::DuplicatePayout`] if the key was used for a withdrawal of
+/// another amount or method.
+async fn replayed_withdrawal(
+    user_id: crate::database::models::UserId,
+    idempotency_key: &str,
+    withdrawal: &WithdrawalRequest,
+    conn: &mut sqlx::PgConnection,
+) -> Result<Option<WithdrawalResponse>, ApiError> {
+    let Some(id) = crate::database::models::payout_item::Payout::get_id_by_idempotency_key(
+        user_id,
+        idempotency_key,
+        &mut *conn,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
 
-                    let res: TremendousResponse = payouts_queue
-                        .make_tremendous_request(
-                            Method::POST,
-                            "orders",
-                            Some(json! ({
-                                "payment": {
-                                    "funding_source_id": "BALANCE",
-                                },
-                                "rewards": [{
-                                    "value": {
-                                        "denomination": transfer
-                                    },
-                                    "delivery": {
-                                        "method": "EMAIL"
-                                    },
-                                    "recipient": {
-                                        "name": user.username,
-                                        "email": email
-                                    },
-                                    "products": [
-                                        &body.method_id,
-                                    ],
-                                    "campaign_id": dotenvy::var("TREMENDOUS_CAMPAIGN_ID")?,
-                                }]
-                            })),
-                        )
-                        .await?;
-
-                    if let Some(reward) = res.order.rewards.first() {
-                        payout_item.platform_id = Some(reward.id.clone())
-                    }
+    let existing = crate::database::models::payout_item::Payout::get(id, &mut *conn)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+    let gross = existing.amount
+        + existing.fee.unwrap_or(Decimal::ZERO)
+        + existing.withheld.unwrap_or(Decimal::ZERO);
+
+    if gross != withdrawal.amount
+        || existing.method != Some(withdrawal.method)
+        || existing.method_id.as_deref() != Some(withdrawal.method_id.as_str())
+    {
+        return Err(ApiError::DuplicatePayout(format!(
+            "idempotency_key: {idempotency_key} was already used for a different withdrawal"
+        )));
+    }
 
-                    payout_item
-                } else {
-                    return Err(ApiError::InvalidInput(
-                        "You must verify your account email to proceed!".to_string(),
-                    ));
-                }
-            } else {
-                return Err(ApiError::InvalidInput(
-                    "You must add an email to your account to proceed!".to_string(),
-                ));
-            }
-        }
-        PayoutMethodType::Unknown => {
-            return Err(ApiError::Payments(
-                "Invalid payment method specified!".to_string(),
-            ))
-        }
-    };
+    Ok(Some(WithdrawalResponse::from(
+        crate::models::payouts::Payout::from(existing),
+    )))
+}
 
-    sqlx::query!(
-        "
-        UPDATE users
-        SET balance = balance - $1
-        WHERE id = $2
-        ",
-        body.amount,
-        user.id as crate::database::models::ids::UserId
+#[get("destinations")]
+pub async fn user_payout_destinations(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
     )
-    .execute(&mut *transaction)
-    .await?;
-    payout_item.insert(&mut transaction).await?;
+    .await?
+    .1;
 
-    transaction.commit().await?;
-    crate::database::models::User::clear_caches(&[(user.id, None)], &redis).await?;
+    let destinations =
+        crate::database::models::payout_preference_item::PayoutPreferences::get_destinations(
+            user.id.into(),
+            &**pool,
+        )
+        .await?;
 
-    Ok(HttpResponse::NoContent().finish())
+    Ok(HttpResponse::Ok().json(destinations))
 }
 
-#[delete("{id}")]
-pub async fn cancel_payout(
-    info: web::Path<(PayoutId,)>,
+/// Sets where the user's split withdrawals are sent, replacing their previous destinations
+#[put("destinations")]
+pub async fn set_payout_destinations(
     req: HttpRequest,
     pool: web::Data<PgPool>,
     redis: web::Data<RedisPool>,
-    payouts: web::Data<PayoutsQueue>,
     session_queue: web::Data<AuthQueue>,
+    payouts_queue: web::Data<PayoutsQueue>,
+    body: web::Json<Vec<PayoutDestination>>,
 ) -> Result<HttpResponse, ApiError> {
     let user = get_user_from_headers(
         &req,
@@ -642,14 +665,375 @@ pub async fn cancel_payout(
     .await?
     .1;
 
-    let id = info.into_inner().0;
-    let payout = crate::database::models::payout_item::Payout::get(id.into(), &**pool).await?;
+    validate_payout_destinations(&body)?;
 
-    if let Some(payout) = payout {
-        if payout.user_id != user.id.into() && !user.role.is_admin() {
+    let methods = payouts_queue.get_payout_methods().await?;
+    if !body.iter().all(|x| {
+        methods
+            .iter()
+            .any(|m| m.id == x.method_id && m.type_ == x.method)
+    }) {
+        return Err(ApiError::InvalidInput(
+            "Invalid payment method specified!".to_string(),
+        ));
+    }
+
+    let mut transaction = pool.begin().await?;
+    crate::database::models::payout_preference_item::PayoutPreferences::set_destinations(
+        user.id.into(),
+        &body,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Deserialize)]
+pub struct SplitWithdrawal {
+    #[serde(with = "crate::models::payouts::money")]
+    amount: Decimal,
+    /// A note for the user's records, kept on every part
+    #[serde(default)]
+    note: Option<String>,
+}
+
+/// Withdraws an amount split across the user's payout destinations, creating a payout for each
+/// one. Either every part is sent or none are.
+#[post("split")]
+pub async fn create_split_payout(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    body: web::Json<SplitWithdrawal>,
+    session_queue: web::Data<AuthQueue>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    validate_withdrawal_amount(body.amount).map_err(ApiError::InvalidInput)?;
+
+    let (scopes, user) =
+        get_user_record_from_bearer_token(&req, None, &**pool, &redis, &session_queue)
+            .await?
+            .ok_or_else(|| ApiError::Authentication(AuthenticationError::InvalidCredentials))?;
+
+    if !scopes.contains(Scopes::PAYOUTS_WRITE) {
+        return Err(ApiError::Authentication(
+            AuthenticationError::InvalidCredentials,
+        ));
+    }
+
+    let mtx = payouts_queue.lock_user_payouts(user.id.into());
+    let _guard = mtx.lock().await;
+
+    // Checked once the user's lock is held, so shutdown waits for any withdrawal which gets past
+    // it, see `PayoutsQueue::drain`
+    check_withdrawals_allowed(&payouts_queue, &user, &**pool).await?;
+
+    WithdrawalRateLimit::from_env()
+        .check(&redis, user.id.into())
+        .await?;
+
+    if user.balance < body.amount {
+        return Err(ApiError::InvalidInput(
+            "You do not have enough funds to make this payout!".to_string(),
+        ));
+    }
+
+    let destinations =
+        crate::database::models::payout_preference_item::PayoutPreferences::get_destinations(
+            user.id, &**pool,
+        )
+        .await?;
+    if destinations.is_empty() {
+        return Err(ApiError::InvalidInput(
+            "You have not set up any payout destinations!".to_string(),
+        ));
+    }
+
+    let methods = payouts_queue.get_payout_methods().await?;
+    let rounding = PayoutRoundingMode::from_env();
+    let withholding = PayoutWithholding::from_env();
+    let fee_caps = PayPalFeeCaps::from_env();
+    let fx_rates = FxRates::from_env();
+
+    let mut transaction = pool.begin().await?;
+    withdraw_balance(user.id, body.amount, &mut transaction).await?;
+    let correlation_id = new_correlation_id();
+    let note = body.note.as_deref().and_then(sanitize_payout_note);
+
+    let mut requests = Vec::with_capacity(destinations.len());
+    let mut payout_items = Vec::with_capacity(destinations.len());
+    for (index, (destination, amount)) in destinations
+        .iter()
+        .zip(split_withdrawal(body.amount, &destinations))
+        .enumerate()
+    {
+        let payout_method = methods
+            .iter()
+            .find(|x| x.id == destination.method_id)
+            .ok_or_else(|| {
+                ApiError::InvalidInput("Invalid payment method specified!".to_string())
+            })?;
+        // Each part is sent as a withdrawal of its own
+        check_withdrawal_method(payout_method, amount)?;
+
+        let amounts = WithdrawalAmounts::calculate(
+            payout_method,
+            Gross::new(amount),
+            user.paypal_country.as_deref(),
+            rounding,
+            &withholding,
+            &fee_caps,
+        )?;
+        let tremendous_value =
+            TremendousOrderValue::for_method(payout_method, amounts.transfer.amount(), &fx_rates)?;
+        let (address, display_address) =
+            payout_address(&user, destination.method, &destination.method_id)?;
+        let payout_id = generate_payout_id(&mut transaction).await?;
+
+        requests.push(PayoutRequest {
+            id: payout_id.into(),
+            method: destination.method,
+            method_id: destination.method_id.clone(),
+            amount: amounts.transfer.amount(),
+            address,
+            recipient_name: user.username.clone(),
+            // PayPal treats requests sharing an id as retries, so each part needs its own
+            correlation_id: format!("{correlation_id}-{index}"),
+            note: note.clone(),
+            tremendous_value,
+        });
+        payout_items.push(crate::database::models::payout_item::Payout {
+            id: payout_id,
+            user_id: user.id,
+            created: Utc::now(),
+            status: PayoutStatus::InTransit,
+            amount: amounts.transfer.amount(),
+            fee: Some(amounts.fee.amount()),
+            withheld: Some(amounts.withheld.amount()),
+            method: Some(destination.method),
+            method_id: Some(destination.method_id.clone()),
+            method_address: Some(display_address),
+            platform_id: None,
+            note: note.clone(),
+            idempotency_key: None,
+        });
+    }
+
+    let results = payouts_queue
+        .submit_split_payout(&requests, review_threshold())
+        .await?;
+
+    for (mut payout_item, (status, platform_id)) in payout_items.into_iter().zip(results) {
+        payout_item.status = status;
+        payout_item.platform_id = platform_id;
+        payout_item.insert(&mut transaction).await?;
+    }
+
+    transaction.commit().await?;
+    crate::database::models::User::clear_caches(&[(user.id, None)], &redis).await?;
+
+    Ok(HttpResponse::NoContent()
+        .insert_header((CORRELATION_ID_HEADER, correlation_id))
+        .finish())
+}
+
+/// Takes `amount` out of the user's balance, failing if they don't have enough. This is checked
+/// by the database rather than the user lock, as other instances don't share the lock.
+async fn withdraw_balance(
+    user_id: crate::database::models::UserId,
+    amount: Decimal,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<(), ApiError> {
+    if !crate::database::models::User::withdraw_balance(user_id, amount, transaction).await? {
+        return Err(ApiError::InvalidInput(
+            "You do not have enough funds to make this payout!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Locks a withdrawal held for review for the rest of the transaction, so it can only be approved,
+/// denied or cancelled once. The caller must already hold the user's payout lock, see
+/// [`PayoutsQueue::lock_user_payouts`], which is always taken before the row's.
+pub(crate) async fn lock_pending_review_payout(
+    id: PayoutId,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<crate::database::models::payout_item::Payout, ApiError> {
+    sqlx::query!(
+        "
+        SELECT id FROM payouts
+        WHERE id = $1
+        FOR UPDATE
+        ",
+        id.0 as i64
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    .ok_or(ApiError::NotFound)?;
+
+    let payout = crate::database::models::payout_item::Payout::get(id.into(), &mut **transaction)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    if payout.status != PayoutStatus::PendingReview {
+        return Err(ApiError::InvalidInput(
+            "Payout is not pending review!".to_string(),
+        ));
+    }
+
+    Ok(payout)
+}
+
+/// Cancels a withdrawal held for review, locked by [`lock_pending_review_payout`], returning its
+/// amount, fee and withholding to the user's balance. Returns the amount refunded.
+pub(crate) async fn refund_pending_review_payout(
+    payout: &crate::database::models::payout_item::Payout,
+    transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+) -> Result<Decimal, ApiError> {
+    sqlx::query!(
+        "
+        UPDATE payouts
+        SET status = $1
+        WHERE id = $2
+        ",
+        PayoutStatus::Cancelled.as_str(),
+        payout.id.0
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    let refunded = payout.amount
+        + payout.fee.unwrap_or(Decimal::ZERO)
+        + payout.withheld.unwrap_or(Decimal::ZERO);
+    sqlx::query!(
+        "
+        UPDATE users
+        SET balance = balance + $1
+        WHERE id = $2
+        ",
+        refunded,
+        payout.user_id.0
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(refunded)
+}
+
+/// Where a withdrawal with `method` is sent for `user`, and how that address is shown to them
+pub(crate) fn payout_address(
+    user: &crate::database::models::User,
+    method: PayoutMethodType,
+    method_id: &str,
+) -> Result<(String, String), ApiError> {
+    let address = match method {
+        PayoutMethodType::Venmo => {
+            if let Some(venmo) = user.venmo_handle.clone() {
+                (venmo.clone(), venmo)
+            } else {
+                return Err(ApiError::InvalidInput(
+                    "Venmo address has not been set for account!".to_string(),
+                ));
+            }
+        }
+        PayoutMethodType::PayPal => {
+            if let Some(paypal_id) = user.paypal_id.clone() {
+                if let Some(paypal_country) = &user.paypal_country {
+                    if &**paypal_country == "US" && method_id != "paypal_us" {
+                        return Err(ApiError::InvalidInput(
+                            "Please use the US PayPal transfer option!".to_string(),
+                        ));
+                    } else if &**paypal_country != "US" && method_id == "paypal_us" {
+                        return Err(ApiError::InvalidInput(
+                            "Please use the International PayPal transfer option!".to_string(),
+                        ));
+                    }
+
+                    (
+                        paypal_id.clone(),
+                        user.paypal_email.clone().unwrap_or(paypal_id),
+                    )
+                } else {
+                    return Err(ApiError::InvalidInput(
+                        "Please re-link your PayPal account!".to_string(),
+                    ));
+                }
+            } else {
+                return Err(ApiError::InvalidInput(
+                    "You have not linked a PayPal account!".to_string(),
+                ));
+            }
+        }
+        PayoutMethodType::Tremendous => {
+            if let Some(email) = user.email.clone() {
+                if user.email_verified {
+                    (email.clone(), email)
+                } else {
+                    return Err(ApiError::InvalidInput(
+                        "You must verify your account email to proceed!".to_string(),
+                    ));
+                }
+            } else {
+                return Err(ApiError::InvalidInput(
+                    "You must add an email to your account to proceed!".to_string(),
+                ));
+            }
+        }
+        PayoutMethodType::Unknown => {
+            return Err(ApiError::Payments(
+                "Invalid payment method specified!".to_string(),
+            ))
+        }
+    };
+
+    Ok(address)
+}
+
+#[delete("{id}")]
+pub async fn cancel_payout(
+    info: web::Path<(PayoutId,)>,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    payouts: web::Data<PayoutsQueue>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_WRITE]),
+    )
+    .await?
+    .1;
+
+    let id = info.into_inner().0;
+    let payout = crate::database::models::payout_item::Payout::get(id.into(), &**pool).await?;
+
+    if let Some(payout) = payout {
+        if payout.user_id != user.id.into() && !user.role.is_admin() {
             return Ok(HttpResponse::NotFound().finish());
         }
 
+        // Withdrawals held for review were never sent, so their funds are returned straight away
+        if payout.status == PayoutStatus::PendingReview {
+            let mtx = payouts.lock_user_payouts(payout.user_id.into());
+            let _guard = mtx.lock().await;
+
+            let mut transaction = pool.begin().await?;
+            let payout = lock_pending_review_payout(id, &mut transaction).await?;
+            refund_pending_review_payout(&payout, &mut transaction).await?;
+            transaction.commit().await?;
+
+            crate::database::models::User::clear_caches(&[(payout.user_id, None)], &redis).await?;
+
+            return Ok(HttpResponse::NoContent().finish());
+        }
+
         if let Some(platform_id) = payout.platform_id {
             if let Some(method) = payout.method {
                 if payout.status != PayoutStatus::InTransit {
@@ -658,34 +1042,14 @@ pub async fn cancel_payout(
                     ));
                 }
 
-                match method {
-                    PayoutMethodType::Venmo | PayoutMethodType::PayPal => {
-                        payouts
-                            .make_paypal_request::<(), ()>(
-                                Method::POST,
-                                &format!("payments/payouts-item/{}/cancel", platform_id),
-                                None,
-                                None,
-                                None,
-                            )
-                            .await?;
-                    }
-                    PayoutMethodType::Tremendous => {
-                        payouts
-                            .make_tremendous_request::<(), ()>(
-                                Method::POST,
-                                &format!("rewards/{}/cancel", platform_id),
-                                None,
-                            )
-                            .await?;
-                    }
-                    PayoutMethodType::Unknown => {
-                        return Err(ApiError::InvalidInput(
-                            "Payout cannot be cancelled!".to_string(),
-                        ))
-                    }
+                if method == PayoutMethodType::Unknown {
+                    return Err(ApiError::InvalidInput(
+                        "Payout cannot be cancelled!".to_string(),
+                    ));
                 }
 
+                payouts.cancel_payout(method, &platform_id).await?;
+
                 let mut transaction = pool.begin().await?;
                 sqlx::query!(
                     "
@@ -716,9 +1080,281 @@ pub async fn cancel_payout(
     }
 }
 
+/// The authenticated user's withdrawals which are still on their way, or which failed and can
+/// be retried with `POST /v3/payout/{id}/retry`, newest first
+#[get("attempts")]
+pub async fn user_payout_attempts(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?
+    .1;
+
+    let payout_ids = crate::database::models::payout_item::Payout::get_unsettled_for_user(
+        user.id.into(),
+        &**pool,
+    )
+    .await?;
+    let mut payouts =
+        crate::database::models::payout_item::Payout::get_many(&payout_ids, &**pool).await?;
+    payouts.sort_by(|a, b| b.created.cmp(&a.created));
+
+    Ok(HttpResponse::Ok().json(
+        payouts
+            .into_iter()
+            .map(crate::models::payouts::Payout::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Sends one of the authenticated user's failed withdrawals again, to their current address for
+/// its method or with another method. Its funds were returned when it failed, so they are taken
+/// from the balance once more.
+#[post("{id}/retry")]
+pub async fn retry_payout(
+    info: web::Path<(PayoutId,)>,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    body: web::Json<WithdrawalRetryRequest>,
+    session_queue: web::Data<AuthQueue>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate().map_err(ApiError::InvalidInput)?;
+
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_WRITE]),
+    )
+    .await?
+    .1;
+
+    let id = info.into_inner().0;
+    let payout = crate::database::models::payout_item::Payout::get(id.into(), &**pool)
+        .await?
+        .filter(|x| x.user_id == user.id.into())
+        .ok_or(ApiError::NotFound)?;
+
+    // Whether the user can withdraw with the method is checked by `retry_failed_payout`
+    WithdrawalRateLimit::from_env()
+        .check(&redis, user.id)
+        .await?;
+
+    let method = match (body.method, &body.method_id) {
+        (Some(method), Some(method_id)) => {
+            let payout_method = payouts_queue
+                .get_payout_methods()
+                .await?
+                .into_iter()
+                .find(|x| &x.id == method_id)
+                .ok_or_else(|| {
+                    ApiError::InvalidInput("Invalid payment method specified!".to_string())
+                })?;
+            if payout_method.type_ != method {
+                return Err(ApiError::InvalidInput(format!(
+                    "method_id: {method_id} is not a {method} method"
+                )));
+            }
+
+            Some(payout_method)
+        }
+        _ => None,
+    };
+
+    let retry_id = retry_failed_payout(
+        &pool,
+        &redis,
+        &payouts_queue,
+        payout.id,
+        &PayoutRetry {
+            method,
+            idempotency_key: body.idempotency_key.clone(),
+        },
+    )
+    .await?
+    .ok_or_else(|| {
+        ApiError::InvalidInput(
+            "Payout cannot be retried, or you do not have enough funds to retry it!".to_string(),
+        )
+    })?;
+
+    let retried = crate::database::models::payout_item::Payout::get(retry_id, &**pool)
+        .await?
+        .ok_or(ApiError::NotFound)?;
+
+    Ok(HttpResponse::Ok().json(WithdrawalResponse::from(
+        crate::models::payouts::Payout::from(retried),
+    )))
+}
+
 #[derive(Deserialize)]
 pub struct MethodFilter {
     pub country: Option<String>,
+    /// Only lists methods which can withdraw this amount in US dollars, ex: leaving out gift
+    /// cards whose smallest value is more than it
+    pub amount: Option<Decimal>,
+    /// Only lists the first this many methods as ranked. PayPal and Venmo are ranked ahead of
+    /// every other method and are always listed, even past the limit.
+    pub limit: Option<usize>,
+}
+
+/// The most the user can withdraw with each payout method at once, and what would be sent
+#[get("max")]
+pub async fn user_max_withdrawals(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?
+    .1;
+    let (balance, country) = user
+        .payout_data
+        .map(|x| (x.balance, x.paypal_country))
+        .unwrap_or_default();
+
+    let methods = payouts_queue.get_payout_methods().await?;
+
+    Ok(HttpResponse::Ok().json(max_withdrawals(
+        &methods,
+        balance,
+        country.as_deref(),
+        PayoutRoundingMode::from_env(),
+        &PayoutWithholding::from_env(),
+        &PayPalFeeCaps::from_env(),
+    )))
+}
+
+/// Whether the user has set up everything needed to withdraw, for deciding whether to offer
+/// withdrawals or walk them through setup
+#[get("onboarding")]
+pub async fn user_payout_onboarding(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (scopes, user) =
+        get_user_record_from_bearer_token(&req, None, &**pool, &redis, &session_queue)
+            .await?
+            .ok_or_else(|| ApiError::Authentication(AuthenticationError::InvalidCredentials))?;
+
+    if !scopes.contains(Scopes::PAYOUTS_READ) {
+        return Err(ApiError::Authentication(
+            AuthenticationError::InvalidCredentials,
+        ));
+    }
+
+    let on_hold =
+        crate::database::models::payout_hold_item::PayoutHold::get_active(user.id, &**pool)
+            .await?
+            .is_some();
+
+    Ok(HttpResponse::Ok().json(payouts_queue.get_payout_onboarding(&user, on_hold).await?))
+}
+
+/// Lists which payout methods the user can withdraw with, and why the others are excluded, with
+/// their preferred method first while they can withdraw with it
+#[get("eligibility")]
+pub async fn user_payout_eligibility(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let (scopes, user) =
+        get_user_record_from_bearer_token(&req, None, &**pool, &redis, &session_queue)
+            .await?
+            .ok_or_else(|| ApiError::Authentication(AuthenticationError::InvalidCredentials))?;
+
+    if !scopes.contains(Scopes::PAYOUTS_READ) {
+        return Err(ApiError::Authentication(
+            AuthenticationError::InvalidCredentials,
+        ));
+    }
+
+    let on_hold =
+        crate::database::models::payout_hold_item::PayoutHold::get_active(user.id, &**pool)
+            .await?
+            .is_some();
+    let preferred_method =
+        crate::database::models::payout_preference_item::PayoutPreferences::get_preferred_method(
+            user.id, &**pool,
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(
+        payouts_queue
+            .get_payout_eligibility(&user, on_hold, preferred_method.as_deref())
+            .await?,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct PreferredPayoutMethod {
+    /// Clears the preference when `None`
+    pub method_id: Option<String>,
+}
+
+/// Sets the payout method the user prefers to withdraw with
+#[put("preferred-method")]
+pub async fn set_preferred_payout_method(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    payouts_queue: web::Data<PayoutsQueue>,
+    body: web::Json<PreferredPayoutMethod>,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_WRITE]),
+    )
+    .await?
+    .1;
+
+    if let Some(method_id) = &body.method_id {
+        let methods = payouts_queue.get_payout_methods().await?;
+        if !methods.iter().any(|x| &x.id == method_id) {
+            return Err(ApiError::InvalidInput(
+                "Invalid payment method specified!".to_string(),
+            ));
+        }
+    }
+
+    crate::database::models::payout_preference_item::PayoutPreferences::set_preferred_method(
+        user.id.into(),
+        body.method_id.as_deref(),
+        &**pool,
+    )
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[get("methods")]
@@ -726,7 +1362,7 @@ pub async fn payment_methods(
     payouts_queue: web::Data<PayoutsQueue>,
     filter: web::Query<MethodFilter>,
 ) -> Result<HttpResponse, ApiError> {
-    let methods = payouts_queue
+    let mut methods = payouts_queue
         .get_payout_methods()
         .await?
         .into_iter()
@@ -737,9 +1373,99 @@ pub async fn payment_methods(
                 val &= x.supported_countries.contains(country);
             }
 
+            if let Some(amount) = filter.amount {
+                val &= x.balance_interval().accommodates(amount);
+            }
+
             val
         })
         .collect::<Vec<_>>();
 
+    if let Some(limit) = filter.limit {
+        let mut remaining = limit;
+        methods.retain(|x| {
+            let always = matches!(x.type_, PayoutMethodType::PayPal | PayoutMethodType::Venmo);
+            if always || remaining > 0 {
+                remaining = remaining.saturating_sub(1);
+                true
+            } else {
+                false
+            }
+        });
+    }
+
     Ok(HttpResponse::Ok().json(methods))
 }
+
+#[derive(Deserialize)]
+pub struct FeeComparisonQuery {
+    pub amount: Decimal,
+    pub country: Option<String>,
+}
+
+/// Compares the fee and net payout of each method that can send `amount`, best first
+#[get("fees")]
+pub async fn payment_fees(
+    payouts_queue: web::Data<PayoutsQueue>,
+    query: web::Query<FeeComparisonQuery>,
+) -> Result<HttpResponse, ApiError> {
+    if query.amount <= Decimal::ZERO {
+        return Err(ApiError::InvalidInput(
+            "Amount must be positive!".to_string(),
+        ));
+    }
+
+    let methods = payouts_queue.get_payout_methods().await?;
+
+    Ok(HttpResponse::Ok().json(compare_payout_fees(
+        &methods,
+        Gross::new(query.amount),
+        query.country.as_deref(),
+        PayoutRoundingMode::from_env(),
+        &PayPalFeeCaps::from_env(),
+    )))
+}
+
+/// The fee schedule of every payout method, for disclosing fees without picking an amount
+#[get("fees/schedule")]
+pub async fn payment_fee_schedules(
+    payouts_queue: web::Data<PayoutsQueue>,
+) -> Result<HttpResponse, ApiError> {
+    let methods = payouts_queue.get_payout_methods().await?;
+
+    Ok(HttpResponse::Ok().json(payout_fee_schedules(&methods)))
+}
+
+/// The budget, weekend bonus and view and download weights payouts are currently computed with
+#[get("constants")]
+pub async fn payout_constants() -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(PayoutConstants::current()))
+}
+
+#[derive(Deserialize)]
+pub struct DestinationQuery {
+    pub method: PayoutMethodType,
+    pub address: String,
+}
+
+/// Checks whether a withdrawal could be delivered to an address before it is made, catching
+/// mistyped emails, phone numbers and usernames
+#[post("validate")]
+pub async fn validate_destination(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    body: web::Json<DestinationQuery>,
+) -> Result<HttpResponse, ApiError> {
+    get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PAYOUTS_READ]),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(DestinationValidation::check(body.method, &body.address)))
+}
@@ -55,6 +55,8 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("{id}/gallery", web::delete().to(delete_gallery_item))
             .route("{id}/follow", web::post().to(project_follow))
             .route("{id}/follow", web::delete().to(project_unfollow))
+            .route("{id}/payouts", web::post().to(project_payouts_enable))
+            .route("{id}/payouts", web::delete().to(project_payouts_disable))
             .route("{id}/organization", web::get().to(project_get_organization))
             .service(
                 web::scope("{project_id}")
@@ -818,7 +820,11 @@ pub async fn project_edit(
                 sqlx::query!(
                     "
                     UPDATE mods
-                    SET monetization_status = $1
+                    SET monetization_status = $1,
+                        monetization_status_updated = CASE
+                            WHEN monetization_status = $1 THEN monetization_status_updated
+                            ELSE NOW()
+                        END
                     WHERE (id = $2)
                     ",
                     monetization_status.as_str(),
@@ -2263,3 +2269,89 @@ pub async fn project_get_organization(
         Err(ApiError::NotFound)
     }
 }
+
+/// Opts a project back in to payouts
+pub async fn project_payouts_enable(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    set_project_payouts_enabled(req, info, pool, redis, session_queue, true).await
+}
+
+/// Opts a project out of payouts while its owners stay monetized. Its share of each day's budget
+/// goes to other projects instead.
+pub async fn project_payouts_disable(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+) -> Result<HttpResponse, ApiError> {
+    set_project_payouts_enabled(req, info, pool, redis, session_queue, false).await
+}
+
+async fn set_project_payouts_enabled(
+    req: HttpRequest,
+    info: web::Path<(String,)>,
+    pool: web::Data<PgPool>,
+    redis: web::Data<RedisPool>,
+    session_queue: web::Data<AuthQueue>,
+    enabled: bool,
+) -> Result<HttpResponse, ApiError> {
+    let user = get_user_from_headers(
+        &req,
+        &**pool,
+        &redis,
+        &session_queue,
+        Some(&[Scopes::PROJECT_WRITE]),
+    )
+    .await?
+    .1;
+    let string = info.into_inner().0;
+
+    let project_item = db_models::Project::get(&string, &**pool, &redis)
+        .await?
+        .ok_or_else(|| {
+            ApiError::InvalidInput("The specified project does not exist!".to_string())
+        })?;
+
+    if !user.role.is_mod() {
+        let (team_member, organization_team_member) =
+            db_models::TeamMember::get_for_project_permissions(
+                &project_item.inner,
+                user.id.into(),
+                &**pool,
+            )
+            .await?;
+
+        // Hide the project
+        if team_member.is_none() && organization_team_member.is_none() {
+            return Err(ApiError::CustomAuthentication(
+                "The specified project does not exist!".to_string(),
+            ));
+        }
+        let permissions = ProjectPermissions::get_permissions_by_role(
+            &user.role,
+            &team_member,
+            &organization_team_member,
+        )
+        .unwrap_or_default();
+
+        if !permissions.contains(ProjectPermissions::EDIT_DETAILS) {
+            return Err(ApiError::CustomAuthentication(
+                "You do not have the permissions to edit the monetization status of this project!"
+                    .to_string(),
+            ));
+        }
+    }
+
+    let mut transaction = pool.begin().await?;
+    db_models::Project::set_payouts_enabled(project_item.inner.id, enabled, &mut transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(HttpResponse::NoContent().body(""))
+}
@@ -0,0 +1,170 @@
+use crate::database::redis::RedisPool;
+use crate::models::payouts::{
+    currency_decimal_places, PayoutMethod, PayoutUsdEquivalent, BALANCE_CURRENCY,
+};
+use crate::routes::ApiError;
+use crate::util::env::{parse_code_decimals, parse_var};
+use async_trait::async_trait;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Redis namespace fetched exchange rates are shared between instances under
+pub const FX_RATES_NAMESPACE: &str = "fx_rates";
+
+/// How many seconds fetched rates are used for, unless `FX_RATES_TTL` is set
+const DEFAULT_FX_RATES_TTL: i64 = 60 * 60;
+
+/// Where exchange rates are fetched from
+#[async_trait]
+pub trait RatesProvider: Send + Sync {
+    /// The US dollars per unit of each currency, keyed by ISO 4217 code
+    async fn fetch_rates(&self) -> Result<HashMap<String, Decimal>, ApiError>;
+}
+
+/// Fetches exchange rates from `FX_RATES_API_URL`, which responds with units per US dollar as
+/// `{"rates": {"EUR": 0.92, "JPY": 149.5}}`
+pub struct HttpRatesProvider;
+
+#[async_trait]
+impl RatesProvider for HttpRatesProvider {
+    async fn fetch_rates(&self) -> Result<HashMap<String, Decimal>, ApiError> {
+        #[derive(Deserialize)]
+        struct RatesResponse {
+            rates: HashMap<String, Decimal>,
+        }
+
+        let res: RatesResponse = reqwest::Client::new()
+            .get(dotenvy::var("FX_RATES_API_URL")?)
+            .send()
+            .await
+            .and_then(|x| x.error_for_status())
+            .map_err(|err| ApiError::Payments(format!("could not fetch exchange rates: {err}")))?
+            .json()
+            .await
+            .map_err(|err| ApiError::Payments(format!("could not read exchange rates: {err}")))?;
+
+        Ok(res
+            .rates
+            .into_iter()
+            .filter(|(_, per_usd)| *per_usd > Decimal::ZERO)
+            .map(|(currency, per_usd)| (currency, Decimal::ONE / per_usd))
+            .collect())
+    }
+}
+
+/// Exchange rates as the US dollars one unit of each currency is worth, ex: `EUR` at `1.09`.
+/// This is the only convention rates are kept in. They are either configured by
+/// `PAYOUTS_FX_RATES` as comma separated `CURRENCY:RATE` pairs, ex: `EUR:1.09,GBP:1.27`, or
+/// fetched from a [`RatesProvider`] with [`FxRates::fetch`].
+#[derive(Clone, Debug, Default)]
+pub struct FxRates {
+    rates: HashMap<String, Decimal>,
+}
+
+impl FxRates {
+    pub fn new(rates: HashMap<String, Decimal>) -> Self {
+        FxRates {
+            rates: rates
+                .into_iter()
+                .map(|(currency, rate)| (currency.to_uppercase(), rate))
+                .collect(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        FxRates {
+            rates: parse_code_decimals("PAYOUTS_FX_RATES", |rate| rate > Decimal::ZERO),
+        }
+    }
+
+    /// The current rates from `provider`, fetched only if no instance has fetched them within
+    /// `FX_RATES_TTL` seconds. They are kept in Redis, so every instance shares one fetch.
+    pub async fn fetch(provider: &dyn RatesProvider, redis: &RedisPool) -> Result<Self, ApiError> {
+        let mut redis = redis.connect().await?;
+
+        if let Some(rates) = redis
+            .get_deserialized_from_json::<HashMap<String, Decimal>>(
+                FX_RATES_NAMESPACE,
+                "dollars_per_unit",
+            )
+            .await?
+        {
+            return Ok(FxRates { rates });
+        }
+
+        let rates = FxRates::new(
+            provider
+                .fetch_rates()
+                .await?
+                .into_iter()
+                .filter(|(_, rate)| *rate > Decimal::ZERO)
+                .collect(),
+        );
+
+        redis
+            .set_serialized_to_json(
+                FX_RATES_NAMESPACE,
+                "dollars_per_unit",
+                &rates.rates,
+                Some(parse_var("FX_RATES_TTL").unwrap_or(DEFAULT_FX_RATES_TTL)),
+            )
+            .await?;
+
+        Ok(rates)
+    }
+
+    /// The dollars per unit of `currency`, if known
+    pub fn rate(&self, currency: &str) -> Option<Decimal> {
+        if currency.eq_ignore_ascii_case("USD") {
+            return Some(Decimal::ONE);
+        }
+
+        self.rates.get(&currency.to_uppercase()).copied()
+    }
+
+    /// How many units of `to` one unit of `from` is worth. Fails with
+    /// [`ApiError::InvalidInput`] if either currency has no rate.
+    pub fn get_rate(&self, from: &str, to: &str) -> Result<Decimal, ApiError> {
+        let rate = |currency: &str| {
+            self.rate(currency)
+                .ok_or_else(|| ApiError::InvalidInput(format!("Unknown currency: {currency}")))
+        };
+
+        Ok(rate(from)? / rate(to)?)
+    }
+
+    /// `amount` of `from` converted to `to`, rounded to the decimal places of `to`
+    pub fn convert(&self, amount: Decimal, from: &str, to: &str) -> Result<Decimal, ApiError> {
+        let rate = self.get_rate(from, to)?;
+
+        Ok((amount * rate).round_dp(currency_decimal_places(to)))
+    }
+
+    /// The method's interval and fee in US dollars, or `None` if its currency has no known rate
+    pub fn usd_equivalent(&self, method: &PayoutMethod) -> Option<PayoutUsdEquivalent> {
+        let rate = self.rate(method.currency.as_deref()?)?;
+
+        Some(PayoutUsdEquivalent {
+            interval: method.interval.convert(rate),
+            fee: method.fee.convert(rate),
+        })
+    }
+
+    /// The least that can be withdrawn with `method` in its own currency: the smallest amount of
+    /// its interval, raised to `threshold` US dollars converted to its currency if given. The
+    /// converted threshold is rounded up, so it is never worth less than `threshold`. `None` if
+    /// the method's currency has no known rate.
+    pub fn minimum(&self, method: &PayoutMethod, threshold: Option<Decimal>) -> Option<Decimal> {
+        let min = method.interval.min();
+        let Some(threshold) = threshold else {
+            return Some(min);
+        };
+
+        let rate = self.rate(method.currency.as_deref().unwrap_or(BALANCE_CURRENCY))?;
+        let converted = (threshold / rate)
+            .round_dp_with_strategy(method.decimal_places, RoundingStrategy::AwayFromZero);
+
+        Some(min.max(converted))
+    }
+}
@@ -1,10 +1,45 @@
+use rust_decimal::Decimal;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 pub fn parse_var<T: FromStr>(var: &'static str) -> Option<T> {
-    dotenvy::var(var).ok().and_then(|i| i.parse().ok())
+    parse_var_with(env_var, var)
 }
+
+/// Like [`parse_var`], reading variables with `var` instead of from the environment
+pub fn parse_var_with<T: FromStr>(var: impl Fn(&str) -> Option<String>, name: &str) -> Option<T> {
+    var(name).and_then(|i| i.parse().ok())
+}
+
+/// Reads a variable from the environment, for the `_with` forms of config readers
+pub fn env_var(var: &str) -> Option<String> {
+    dotenvy::var(var).ok()
+}
+
 pub fn parse_strings_from_var(var: &'static str) -> Option<Vec<String>> {
     dotenvy::var(var)
         .ok()
         .and_then(|s| serde_json::from_str::<Vec<String>>(&s).ok())
 }
+
+/// Reads comma separated `CODE:VALUE` pairs, ex: country or currency codes, from `var`, skipping and warning about entries
+/// which are malformed or whose value is not `valid`
+pub fn parse_code_decimals(var: &str, valid: impl Fn(Decimal) -> bool) -> HashMap<String, Decimal> {
+    dotenvy::var(var)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|x| !x.trim().is_empty())
+        .filter_map(|x| {
+            let entry = x.split_once(':').and_then(|(code, value)| {
+                let value: Decimal = value.trim().parse().ok()?;
+                valid(value).then(|| (code.trim().to_uppercase(), value))
+            });
+
+            if entry.is_none() {
+                log::warn!("Ignoring invalid {var} entry: {x}");
+            }
+
+            entry
+        })
+        .collect()
+}
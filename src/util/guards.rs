@@ -1,6 +1,9 @@
 use actix_web::guard::GuardContext;
 
 pub const ADMIN_KEY_HEADER: &str = "Modrinth-Admin";
+/// Names who is making an admin request. It is not verified, so the payout audit log only keeps
+/// it as the claimed actor.
+pub const ADMIN_ACTOR_HEADER: &str = "Modrinth-Admin-Actor";
 pub fn admin_key_guard(ctx: &GuardContext) -> bool {
     let admin_key = std::env::var("LABRINTH_ADMIN_KEY")
         .expect("No admin key provided, this should have been caught by check_env_vars");
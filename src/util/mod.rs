@@ -2,6 +2,7 @@ pub mod actix;
 pub mod bitflag;
 pub mod captcha;
 pub mod cors;
+pub mod currency;
 pub mod date;
 pub mod env;
 pub mod ext;
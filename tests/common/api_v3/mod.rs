@@ -13,6 +13,7 @@ pub mod collections;
 pub mod oauth;
 pub mod oauth_clients;
 pub mod organization;
+pub mod payouts;
 pub mod project;
 pub mod request_data;
 pub mod tags;
@@ -28,7 +29,10 @@ pub struct ApiV3 {
 #[async_trait(?Send)]
 impl ApiBuildable for ApiV3 {
     async fn build(labrinth_config: LabrinthConfig) -> Self {
-        let app = App::new().configure(|cfg| labrinth::app_config(cfg, labrinth_config.clone()));
+        // Compressed like the app-wide middleware in main.rs does
+        let app = App::new()
+            .wrap(actix_web::middleware::Compress::default())
+            .configure(|cfg| labrinth::app_config(cfg, labrinth_config.clone()));
         let test_app: Rc<dyn LocalService> = Rc::new(test::init_service(app).await);
 
         Self { test_app }
@@ -0,0 +1,356 @@
+use actix_web::{dev::ServiceResponse, test};
+use chrono::{DateTime, SecondsFormat, Utc};
+use labrinth::{
+    models::payouts::PayoutMethod,
+    queue::payouts::{PayoutConstants, PayoutFeeComparison, PayoutFeeSchedule},
+};
+use rust_decimal::Decimal;
+
+use crate::common::api_common::{Api, AppendsOptionalPat};
+
+use super::ApiV3;
+
+impl ApiV3 {
+    /// Sends `req` to the internal admin routes, authenticated with the admin key
+    async fn admin_request(&self, req: test::TestRequest) -> ServiceResponse {
+        let req = req
+            .append_header((
+                "Modrinth-Admin",
+                dotenvy::var("LABRINTH_ADMIN_KEY").unwrap(),
+            ))
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn create_payout(
+        &self,
+        withdrawal: serde_json::Value,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri("/v3/payout")
+            .append_pat(pat)
+            .set_json(withdrawal)
+            .to_request();
+        self.call(req).await
+    }
+
+    /// Like [`create_payout`](Self::create_payout), asking for the withdrawal to be returned
+    pub async fn create_payout_returning(
+        &self,
+        withdrawal: serde_json::Value,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri("/v3/payout")
+            .append_pat(pat)
+            .insert_header(("Prefer", "return=representation"))
+            .set_json(withdrawal)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_user_payouts(&self, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri("/v3/payout")
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_payout_history(&self, query: &str, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri(&format!("/v3/payout/history?{query}"))
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_user_balance(&self, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri("/v3/payout/balance")
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_payout_onboarding(&self, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri("/v3/payout/onboarding")
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_own_payout_eligibility(&self, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri("/v3/payout/eligibility")
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn set_preferred_payout_method(
+        &self,
+        method_id: Option<&str>,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::put()
+            .uri("/v3/payout/preferred-method")
+            .append_pat(pat)
+            .set_json(serde_json::json!({ "method_id": method_id }))
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_payout_attempts(&self, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri("/v3/payout/attempts")
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn retry_payout(
+        &self,
+        payout_id: &str,
+        retry: serde_json::Value,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri(&format!("/v3/payout/{payout_id}/retry"))
+            .append_pat(pat)
+            .set_json(retry)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn cancel_payout(&self, payout_id: &str, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/v3/payout/{payout_id}"))
+            .append_pat(pat)
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn create_payout_raw(&self, body: &str, pat: Option<&str>) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri("/v3/payout")
+            .append_pat(pat)
+            .insert_header(("Content-Type", "application/json"))
+            .set_payload(body.to_string())
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn recompute_balance(&self, user_id: &str, correct: bool) -> ServiceResponse {
+        let req = test::TestRequest::post().uri(&format!(
+            "/_internal/admin/_recompute-balance/{user_id}?correct={correct}"
+        ));
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_eligibility(&self, user_id: &str) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri(&format!("/_internal/admin/_payout-eligibility/{user_id}"));
+        self.admin_request(req).await
+    }
+
+    pub async fn set_payout_hold(&self, project_id: &str, hold: bool) -> ServiceResponse {
+        let req = test::TestRequest::post().uri(&format!(
+            "/_internal/admin/_payout-hold/{project_id}?hold={hold}"
+        ));
+        self.admin_request(req).await
+    }
+
+    pub async fn place_user_payout_hold(
+        &self,
+        user_id: &str,
+        reason: &str,
+        duration_days: i64,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri(&format!("/_internal/admin/_user-payout-hold/{user_id}"))
+            .set_json(serde_json::json!({
+                "reason": reason,
+                "duration_days": duration_days,
+            }));
+        self.admin_request(req).await
+    }
+
+    pub async fn renew_user_payout_hold(
+        &self,
+        user_id: &str,
+        duration_days: i64,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/_internal/admin/_user-payout-hold/{user_id}/renew"
+            ))
+            .set_json(serde_json::json!({ "duration_days": duration_days }));
+        self.admin_request(req).await
+    }
+
+    pub async fn lift_user_payout_hold(&self, user_id: &str) -> ServiceResponse {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/_internal/admin/_user-payout-hold/{user_id}"));
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_totals(
+        &self,
+        granularity: &str,
+        start: &str,
+        end: &str,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::get().uri(&format!(
+            "/_internal/admin/_payout-totals?granularity={granularity}&start={start}&end={end}"
+        ));
+        self.admin_request(req).await
+    }
+
+    pub async fn review_payout(&self, payout_id: &str, action: &str) -> ServiceResponse {
+        let req = test::TestRequest::post().uri(&format!(
+            "/_internal/admin/_payout-review/{payout_id}/{action}"
+        ));
+        self.admin_request(req).await
+    }
+
+    pub async fn review_payout_as(
+        &self,
+        payout_id: &str,
+        action: &str,
+        actor: &str,
+    ) -> ServiceResponse {
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/_internal/admin/_payout-review/{payout_id}/{action}"
+            ))
+            .append_header(("Modrinth-Admin-Actor", actor));
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_audit_log(&self, query: &str) -> ServiceResponse {
+        let req =
+            test::TestRequest::get().uri(&format!("/_internal/admin/_payout-audit-log?{query}"));
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_method_snapshot(&self, at: DateTime<Utc>) -> ServiceResponse {
+        let req = test::TestRequest::get().uri(&format!(
+            "/_internal/admin/_payout-method-snapshot?at={}",
+            at.to_rfc3339_opts(SecondsFormat::Micros, true)
+        ));
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_runs(&self) -> ServiceResponse {
+        let req = test::TestRequest::get().uri("/_internal/admin/_payout-runs");
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_self_test(&self) -> ServiceResponse {
+        let req = test::TestRequest::get().uri("/_internal/admin/_payout-self-test");
+        self.admin_request(req).await
+    }
+
+    pub async fn tremendous_webhook(&self, body: &str, signature: Option<&str>) -> ServiceResponse {
+        let mut req = test::TestRequest::post()
+            .uri("/v3/payout/_tremendous")
+            .set_payload(body.to_string());
+        if let Some(signature) = signature {
+            req = req.append_header(("Tremendous-Webhook-Signature", signature));
+        }
+        self.call(req.to_request()).await
+    }
+
+    pub async fn export_payouts(&self, day: &str) -> ServiceResponse {
+        let req =
+            test::TestRequest::get().uri(&format!("/_internal/admin/_payout-export?day={day}"));
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_by_reference(&self, provider: &str, id: &str) -> ServiceResponse {
+        let req = test::TestRequest::get().uri(&format!(
+            "/_internal/admin/_payout-by-reference/{provider}/{id}"
+        ));
+        self.admin_request(req).await
+    }
+
+    pub async fn get_payout_methods(&self, country: Option<&str>) -> ServiceResponse {
+        let uri = match country {
+            Some(country) => format!("/v3/payout/methods?country={country}"),
+            None => "/v3/payout/methods".to_string(),
+        };
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_payout_methods_for_amount(&self, amount: &str) -> Vec<PayoutMethod> {
+        let req = test::TestRequest::get()
+            .uri(&format!("/v3/payout/methods?amount={amount}"))
+            .to_request();
+        let resp = self.call(req).await;
+        assert_eq!(resp.status(), 200);
+        test::read_body_json(resp).await
+    }
+
+    pub async fn get_payout_methods_with_limit(&self, limit: usize) -> Vec<PayoutMethod> {
+        let req = test::TestRequest::get()
+            .uri(&format!("/v3/payout/methods?limit={limit}"))
+            .to_request();
+        let resp = self.call(req).await;
+        assert_eq!(resp.status(), 200);
+        test::read_body_json(resp).await
+    }
+
+    pub async fn get_payout_methods_deserialized(
+        &self,
+        country: Option<&str>,
+    ) -> Vec<PayoutMethod> {
+        let resp = self.get_payout_methods(country).await;
+        assert_eq!(resp.status(), 200);
+        test::read_body_json(resp).await
+    }
+
+    pub async fn get_payout_fees(&self, amount: Decimal, country: Option<&str>) -> ServiceResponse {
+        let mut uri = format!("/v3/payout/fees?amount={amount}");
+        if let Some(country) = country {
+            uri.push_str(&format!("&country={country}"));
+        }
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_payout_fees_deserialized(
+        &self,
+        amount: Decimal,
+        country: Option<&str>,
+    ) -> Vec<PayoutFeeComparison> {
+        let resp = self.get_payout_fees(amount, country).await;
+        assert_eq!(resp.status(), 200);
+        test::read_body_json(resp).await
+    }
+
+    pub async fn get_payout_fee_schedules(&self) -> Vec<PayoutFeeSchedule> {
+        let req = test::TestRequest::get()
+            .uri("/v3/payout/fees/schedule")
+            .to_request();
+        let resp = self.call(req).await;
+        assert_eq!(resp.status(), 200);
+        test::read_body_json(resp).await
+    }
+
+    pub async fn get_payout_constants(&self) -> ServiceResponse {
+        let req = test::TestRequest::get()
+            .uri("/v3/payout/constants")
+            .to_request();
+        self.call(req).await
+    }
+
+    pub async fn get_payout_constants_deserialized(&self) -> PayoutConstants {
+        let resp = self.get_payout_constants().await;
+        assert_eq!(resp.status(), 200);
+        test::read_body_json(resp).await
+    }
+}
@@ -497,6 +497,24 @@ impl ApiV3 {
         self.call(req).await
     }
 
+    pub async fn set_project_payouts_enabled(
+        &self,
+        id_or_slug: &str,
+        enabled: bool,
+        pat: Option<&str>,
+    ) -> ServiceResponse {
+        let req = if enabled {
+            test::TestRequest::post()
+        } else {
+            test::TestRequest::delete()
+        }
+        .uri(&format!("/v3/project/{id_or_slug}/payouts"))
+        .append_pat(pat)
+        .to_request();
+
+        self.call(req).await
+    }
+
     pub async fn get_project_organization_deserialized(
         &self,
         id_or_slug: &str,
@@ -2,7 +2,10 @@
 
 use crate::common::get_json_val_str;
 use itertools::Itertools;
+use labrinth::models::payouts::{Gross, PayoutMethod};
 use labrinth::models::v3::projects::Version;
+use labrinth::queue::payouts::PayoutRoundingMode;
+use rust_decimal::Decimal;
 
 use super::api_common::models::CommonVersion;
 
@@ -32,3 +35,30 @@ pub fn assert_any_status_except(
 ) {
     assert_ne!(response.status(), status, "{:#?}", response.response());
 }
+
+/// Asserts a payout method with `id` is listed, returning it
+pub fn assert_method_present<'a>(methods: &'a [PayoutMethod], id: &str) -> &'a PayoutMethod {
+    methods.iter().find(|x| x.id == id).unwrap_or_else(|| {
+        panic!(
+            "payout method {id} not listed, got {:?}",
+            methods.iter().map(|x| &x.id).collect_vec()
+        )
+    })
+}
+
+/// Asserts that withdrawing `amount` with the method `id` sends `expected_net` after its fee
+pub fn assert_fee_for(methods: &[PayoutMethod], id: &str, amount: Decimal, expected_net: Decimal) {
+    let method = assert_method_present(methods, id);
+    let rounding = PayoutRoundingMode::from_env();
+    let gross = Gross::new(amount);
+    let fee = method
+        .fee
+        .calculate(gross)
+        .round_to_cents(rounding.strategy());
+    let net = (gross - fee).round_to_cents(rounding.strategy());
+    assert_eq!(
+        net.amount(),
+        expected_net,
+        "net payout of {amount} with {id}"
+    );
+}
@@ -12,6 +12,15 @@ use crate::common::setup;
 use actix_http::StatusCode;
 use actix_web::dev::ServiceResponse;
 use futures::Future;
+use lazy_static::lazy_static;
+use tokio::sync::RwLock;
+
+lazy_static! {
+    // Environment variables are shared by every test in a binary. Tests which override them hold
+    // this exclusively, and every other test holds it shared, so no test runs with another's
+    // overrides.
+    static ref ENV_LOCK: RwLock<()> = RwLock::new(());
+}
 
 pub async fn with_test_environment<Fut, A>(
     max_connections: Option<u32>,
@@ -20,12 +29,66 @@ pub async fn with_test_environment<Fut, A>(
     Fut: Future<Output = ()>,
     A: ApiBuildable + 'static,
 {
+    let _env = ENV_LOCK.read().await;
+    let test_env: TestEnvironment<A> = TestEnvironment::build(max_connections).await;
+    let db = test_env.db.clone();
+    f(test_env).await;
+    db.cleanup().await;
+}
+
+/// Like [`with_test_environment`], with the environment variables in `vars` set, or removed if
+/// `None`, for the whole test. No other test runs meanwhile, and the variables are restored
+/// afterwards, even if the test fails.
+pub async fn with_test_environment_vars<Fut, A>(
+    vars: &[(&'static str, Option<&str>)],
+    max_connections: Option<u32>,
+    f: impl FnOnce(TestEnvironment<A>) -> Fut,
+) where
+    Fut: Future<Output = ()>,
+    A: ApiBuildable + 'static,
+{
+    let _env = ENV_LOCK.write().await;
+    let _vars = EnvOverrides::set(vars);
     let test_env: TestEnvironment<A> = TestEnvironment::build(max_connections).await;
     let db = test_env.db.clone();
     f(test_env).await;
     db.cleanup().await;
 }
 
+/// Restores overridden environment variables to their previous values when dropped
+struct EnvOverrides {
+    previous: Vec<(&'static str, Option<String>)>,
+}
+
+impl EnvOverrides {
+    fn set(vars: &[(&'static str, Option<&str>)]) -> Self {
+        let previous = vars
+            .iter()
+            .map(|(var, value)| {
+                let previous = std::env::var(var).ok();
+                match value {
+                    Some(value) => std::env::set_var(var, value),
+                    None => std::env::remove_var(var),
+                }
+                (*var, previous)
+            })
+            .collect();
+
+        EnvOverrides { previous }
+    }
+}
+
+impl Drop for EnvOverrides {
+    fn drop(&mut self) {
+        for (var, previous) in self.previous.drain(..).rev() {
+            match previous {
+                Some(value) => std::env::set_var(var, value),
+                None => std::env::remove_var(var),
+            }
+        }
+    }
+}
+
 // TODO: This needs to be slightly redesigned in order to do both V2 and v3 tests.
 // TODO: Most tests, since they use API functions, can be applied to both. The ones that weren't are in v2/, but
 // all tests that can be applied to both should use both v2 and v3 (extract api to a trait  with all the API functions and call both).
@@ -34,6 +97,7 @@ where
     Fut: Future<Output = ()>,
     F: Fn(TestEnvironment<GenericApi>) -> Fut,
 {
+    let _env = ENV_LOCK.read().await;
     println!("Test environment: API v3");
     let test_env_api_v3 = TestEnvironment::<ApiV3>::build(max_connections).await;
     let test_env_api_v3 = TestEnvironment {
@@ -146,14 +210,15 @@ pub trait LocalService {
         Box<dyn std::future::Future<Output = Result<ServiceResponse, actix_web::Error>>>,
     >;
 }
-impl<S> LocalService for S
+impl<S, B> LocalService for S
 where
     S: actix_web::dev::Service<
         actix_http::Request,
-        Response = ServiceResponse,
+        Response = ServiceResponse<B>,
         Error = actix_web::Error,
     >,
     S::Future: 'static,
+    B: actix_web::body::MessageBody + 'static,
 {
     fn call(
         &self,
@@ -161,6 +226,7 @@ where
     ) -> std::pin::Pin<
         Box<dyn std::future::Future<Output = Result<ServiceResponse, actix_web::Error>>>,
     > {
-        Box::pin(self.call(req))
+        let res = self.call(req);
+        Box::pin(async move { res.await.map(|x| x.map_into_boxed_body()) })
     }
 }
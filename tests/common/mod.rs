@@ -10,7 +10,9 @@ pub mod database;
 pub mod dummy_data;
 pub mod environment;
 pub mod pats;
+pub mod payouts;
 pub mod permissions;
+pub mod provider_mock;
 pub mod scopes;
 pub mod search;
 
@@ -0,0 +1,59 @@
+#![allow(dead_code)]
+use chrono::{Duration, Utc};
+use labrinth::database::redis::RedisPool;
+use labrinth::models::payouts::{PayoutInterval, PayoutMethod, PayoutMethodFee, PayoutMethodType};
+use labrinth::queue::payouts::PAYOUT_METHODS_NAMESPACE;
+use rust_decimal::Decimal;
+use serde_json::json;
+
+/// A payout method offered in the US, which sends 1 to 100 without a fee. Tests override the
+/// fields they care about, ex: `PayoutMethod { interval, ..test_method("paypal_us", PayPal) }`
+pub fn test_method(id: &str, type_: PayoutMethodType) -> PayoutMethod {
+    PayoutMethod {
+        id: id.to_string(),
+        type_,
+        name: id.to_string(),
+        supported_countries: vec!["US".to_string()],
+        supported_currencies: vec!["USD".to_string()],
+        image_url: None,
+        interval: PayoutInterval::Standard {
+            min: Decimal::ONE,
+            max: Decimal::from(100),
+        },
+        fee: PayoutMethodFee {
+            percentage: Decimal::ZERO,
+            min: Decimal::ZERO,
+            max: None,
+        },
+        usd_equivalent: None,
+        order: 0,
+        available: true,
+        currency: None,
+        decimal_places: 2,
+        description: None,
+        disclosure: None,
+        maintenance: None,
+        minimum: None,
+    }
+}
+
+/// Shares `methods` in Redis as if another instance had just fetched them, so they are listed
+/// instead of any fetched from the providers
+pub async fn seed_methods(redis: &RedisPool, methods: &[PayoutMethod]) {
+    redis
+        .connect()
+        .await
+        .unwrap()
+        .set_serialized_to_json(
+            PAYOUT_METHODS_NAMESPACE,
+            "all",
+            json!({
+                "options": methods,
+                "tremendous": true,
+                "expires": Utc::now() + Duration::hours(1),
+            }),
+            Some(3600),
+        )
+        .await
+        .unwrap();
+}
@@ -0,0 +1,108 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use actix_http::{Method, StatusCode};
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer};
+
+/// A request received by a [`ProviderMock`]
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+struct MockState {
+    responses: HashMap<(Method, String), (StatusCode, serde_json::Value)>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+/// A local stand-in for the payout providers' HTTP APIs. Each route answers with a canned JSON
+/// response, and every request is recorded so tests can assert on what was sent. Point a
+/// `PayoutsQueue` at it with `with_api_urls(mock.url("paypal/"), mock.url("tremendous/"))`.
+pub struct ProviderMock {
+    address: String,
+    state: Arc<MockState>,
+    handle: ServerHandle,
+}
+
+impl ProviderMock {
+    /// Starts a mock answering each `(method, path, status, body)` route. Requests to any other
+    /// route get a 404 with an empty JSON object.
+    pub fn start(routes: Vec<(Method, &str, StatusCode, serde_json::Value)>) -> Self {
+        let state = Arc::new(MockState {
+            responses: routes
+                .into_iter()
+                .map(|(method, path, status, body)| ((method, path.to_string()), (status, body)))
+                .collect(),
+            requests: Mutex::new(Vec::new()),
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .default_service(web::to(respond))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        let handle = server.handle();
+        actix_rt::spawn(server);
+
+        ProviderMock {
+            address,
+            state,
+            handle,
+        }
+    }
+
+    /// The URL of `path` on the mock, ex: `mock.url("paypal/")`
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.address)
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// The requests received for `path`, oldest first
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|x| x.path == path)
+            .collect()
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(false).await;
+    }
+}
+
+async fn respond(req: HttpRequest, body: String, state: web::Data<MockState>) -> HttpResponse {
+    let path = req.path().trim_start_matches('/').to_string();
+
+    state.requests.lock().unwrap().push(RecordedRequest {
+        method: req.method().clone(),
+        path: path.clone(),
+        headers: req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body,
+    });
+
+    match state.responses.get(&(req.method().clone(), path)) {
+        Some((status, body)) => HttpResponse::build(*status).json(body),
+        None => HttpResponse::NotFound().json(serde_json::json!({})),
+    }
+}
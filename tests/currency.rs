@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use common::{
+    api_v3::ApiV3,
+    environment::{with_test_environment, TestEnvironment},
+};
+use labrinth::routes::ApiError;
+use labrinth::util::currency::{FxRates, RatesProvider};
+use rust_decimal::Decimal;
+
+mod common;
+
+/// Serves fixed rates, counting how often they are fetched
+#[derive(Default)]
+struct MockRatesProvider {
+    fetches: AtomicUsize,
+}
+
+#[async_trait]
+impl RatesProvider for MockRatesProvider {
+    async fn fetch_rates(&self) -> Result<HashMap<String, Decimal>, ApiError> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+
+        Ok(HashMap::from([
+            ("EUR".to_string(), Decimal::new(125, 2)),
+            ("jpy".to_string(), Decimal::new(64, 4)),
+        ]))
+    }
+}
+
+/// Fails every fetch, as an unreachable upstream would
+struct UnreachableRatesProvider;
+
+#[async_trait]
+impl RatesProvider for UnreachableRatesProvider {
+    async fn fetch_rates(&self) -> Result<HashMap<String, Decimal>, ApiError> {
+        Err(ApiError::Payments("rates are unreachable".to_string()))
+    }
+}
+
+#[actix_rt::test]
+pub async fn amounts_are_converted_between_currencies() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let redis = test_env.db.redis_pool.clone();
+        let rates = FxRates::fetch(&MockRatesProvider::default(), &redis)
+            .await
+            .unwrap();
+
+        assert_eq!(rates.get_rate("USD", "EUR").unwrap(), Decimal::new(8, 1));
+        assert_eq!(
+            rates.convert(Decimal::from(10), "eur", "USD").unwrap(),
+            Decimal::new(1250, 2)
+        );
+        // Yen have no decimal places
+        assert_eq!(
+            rates.convert(Decimal::new(999, 2), "EUR", "JPY").unwrap(),
+            Decimal::from(1951)
+        );
+
+        assert!(matches!(
+            rates.convert(Decimal::ONE, "USD", "XYZ"),
+            Err(ApiError::InvalidInput(_))
+        ));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn rates_are_fetched_once_and_shared() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let redis = test_env.db.redis_pool.clone();
+        let provider = MockRatesProvider::default();
+
+        FxRates::fetch(&provider, &redis).await.unwrap();
+        FxRates::fetch(&provider, &redis).await.unwrap();
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+
+        // Another instance uses the shared rates rather than fetching its own
+        let other = FxRates::fetch(&UnreachableRatesProvider, &redis)
+            .await
+            .unwrap();
+        assert_eq!(other.rate("JPY"), Some(Decimal::new(64, 4)));
+    })
+    .await;
+}
@@ -0,0 +1,464 @@
+use actix_http::{Method, StatusCode};
+use actix_web::test;
+use base64::Engine;
+use chrono::Utc;
+use common::api_v3::ApiV3;
+use common::asserts::assert_status;
+use common::database::{USER_USER_ID_PARSED, USER_USER_PAT};
+use common::environment::{with_test_environment, TestEnvironment};
+use common::payouts::seed_methods;
+use common::provider_mock::ProviderMock;
+use labrinth::database::models::payout_item;
+use labrinth::models::payouts::{Payout, PayoutMethodFee, PayoutMethodType, PayoutStatus};
+use labrinth::queue::payouts::{self, PayoutRetry, PayoutsQueue};
+use labrinth::routes::ApiError;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+
+mod common;
+
+fn queue_for(mock: &ProviderMock) -> PayoutsQueue {
+    dotenvy::dotenv().ok();
+
+    PayoutsQueue::new().with_api_urls(mock.url("paypal/"), mock.url("tremendous/"))
+}
+
+fn paypal_token_route() -> (Method, &'static str, StatusCode, Value) {
+    (
+        Method::POST,
+        "paypal/oauth2/token",
+        StatusCode::OK,
+        json!({
+            "access_token": "mock-token",
+            "token_type": "Bearer",
+            "expires_in": 3600,
+        }),
+    )
+}
+
+fn tremendous_products_route() -> (Method, &'static str, StatusCode, Value) {
+    (
+        Method::GET,
+        "tremendous/products",
+        StatusCode::OK,
+        json!({
+            "products": [{
+                "id": "TESTPRODUCT1",
+                "category": "visa",
+                "name": "Test Visa",
+                "description": "",
+                "disclosure": "",
+                "skus": [{ "min": 5, "max": 500 }],
+                "currency_codes": ["USD"],
+                "countries": [{ "abbr": "US" }],
+                "images": [],
+            }],
+        }),
+    )
+}
+
+#[actix_rt::test]
+pub async fn paypal_requests_are_authenticated_with_a_fetched_token() {
+    let mock = ProviderMock::start(vec![
+        paypal_token_route(),
+        (
+            Method::GET,
+            "paypal/payments/payouts-item/ITEM1",
+            StatusCode::OK,
+            json!({ "transaction_status": "SUCCESS" }),
+        ),
+    ]);
+    let queue = queue_for(&mock);
+
+    for _ in 0..2 {
+        let res: Value = queue
+            .make_paypal_request::<(), _>(
+                Method::GET,
+                "payments/payouts-item/ITEM1",
+                None,
+                None,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(res["transaction_status"], "SUCCESS");
+    }
+
+    // The token is fetched with the client credentials, once, and reused for both calls
+    let token_requests = mock.requests_to("paypal/oauth2/token");
+    assert_eq!(token_requests.len(), 1);
+    let credentials = format!(
+        "{}:{}",
+        dotenvy::var("PAYPAL_CLIENT_ID").unwrap(),
+        dotenvy::var("PAYPAL_CLIENT_SECRET").unwrap()
+    );
+    assert_eq!(
+        token_requests[0].headers["authorization"],
+        format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        )
+    );
+    assert_eq!(token_requests[0].body, "grant_type=client_credentials");
+
+    let api_requests = mock.requests_to("paypal/payments/payouts-item/ITEM1");
+    assert_eq!(api_requests.len(), 2);
+    for request in api_requests {
+        assert_eq!(request.headers["authorization"], "Bearer mock-token");
+    }
+
+    mock.stop().await;
+}
+
+#[actix_rt::test]
+pub async fn paypal_auth_failure_is_reported() {
+    let mock = ProviderMock::start(vec![(
+        Method::POST,
+        "paypal/oauth2/token",
+        StatusCode::UNAUTHORIZED,
+        json!({
+            "error": "invalid_client",
+            "error_description": "Client Authentication failed",
+        }),
+    )]);
+    let queue = queue_for(&mock);
+
+    let res = queue
+        .make_paypal_request::<(), Value>(
+            Method::GET,
+            "payments/payouts-item/ITEM1",
+            None,
+            None,
+            None,
+            None,
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(ApiError::Payments(message)) if message == "Error while authenticating with PayPal"
+    ));
+
+    // Nothing is sent to the API without a token
+    assert!(mock
+        .requests_to("paypal/payments/payouts-item/ITEM1")
+        .is_empty());
+
+    mock.stop().await;
+}
+
+#[actix_rt::test]
+pub async fn paypal_error_body_is_surfaced() {
+    let mock = ProviderMock::start(vec![
+        paypal_token_route(),
+        (
+            Method::POST,
+            "paypal/payments/payouts",
+            StatusCode::UNPROCESSABLE_ENTITY,
+            json!({
+                "name": "INSUFFICIENT_FUNDS",
+                "message": "Sender does not have sufficient funds.",
+                "debug_id": "abc123",
+            }),
+        ),
+    ]);
+    let queue = queue_for(&mock);
+
+    let res = queue
+        .make_paypal_request::<_, Value>(
+            Method::POST,
+            "payments/payouts",
+            Some(json!({ "items": [] })),
+            None,
+            None,
+            None,
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(ApiError::Payments(message))
+            if message == "error name: INSUFFICIENT_FUNDS, message: Sender does not have sufficient funds."
+    ));
+
+    let requests = mock.requests_to("paypal/payments/payouts");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        serde_json::from_str::<Value>(&requests[0].body).unwrap(),
+        json!({ "items": [] })
+    );
+
+    mock.stop().await;
+}
+
+#[actix_rt::test]
+pub async fn paypal_error_details_are_surfaced() {
+    let mock = ProviderMock::start(vec![
+        paypal_token_route(),
+        (
+            Method::POST,
+            "paypal/payments/payouts",
+            StatusCode::BAD_REQUEST,
+            json!({
+                "name": "VALIDATION_ERROR",
+                "message": "Invalid request - see details.",
+                "debug_id": "abc123",
+                "details": [
+                    {
+                        "field": "items[0].receiver",
+                        "location": "body",
+                        "issue": "INVALID_EMAIL",
+                        "description": "Receiver is not a valid email address."
+                    },
+                    {
+                        "field": "items[0].amount.currency",
+                        "issue": "CURRENCY_NOT_SUPPORTED"
+                    }
+                ],
+            }),
+        ),
+    ]);
+    let queue = queue_for(&mock);
+
+    let res = queue
+        .make_paypal_request::<_, Value>(
+            Method::POST,
+            "payments/payouts",
+            Some(json!({ "items": [] })),
+            None,
+            None,
+            None,
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(ApiError::Payments(message))
+            if message == "error name: VALIDATION_ERROR, message: Invalid request - see details., details: items[0].receiver: INVALID_EMAIL (Receiver is not a valid email address.); items[0].amount.currency: CURRENCY_NOT_SUPPORTED"
+    ));
+
+    mock.stop().await;
+}
+
+#[actix_rt::test]
+pub async fn tremendous_requests_use_the_api_key() {
+    let mock = ProviderMock::start(vec![(
+        Method::GET,
+        "tremendous/rewards/REWARD1",
+        StatusCode::OK,
+        json!({ "reward": { "delivery": { "status": "SUCCEEDED" } } }),
+    )]);
+    let queue = queue_for(&mock);
+
+    let res: Value = queue
+        .make_tremendous_request::<(), _>(Method::GET, "rewards/REWARD1", None, None)
+        .await
+        .unwrap();
+    assert_eq!(res["reward"]["delivery"]["status"], "SUCCEEDED");
+
+    let requests = mock.requests_to("tremendous/rewards/REWARD1");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].headers["authorization"],
+        format!("Bearer {}", dotenvy::var("TREMENDOUS_API_KEY").unwrap())
+    );
+
+    mock.stop().await;
+}
+
+#[actix_rt::test]
+pub async fn tremendous_error_body_is_surfaced() {
+    let mock = ProviderMock::start(vec![(
+        Method::POST,
+        "tremendous/orders",
+        StatusCode::BAD_REQUEST,
+        json!({ "errors": { "message": "Invalid product" } }),
+    )]);
+    let queue = queue_for(&mock);
+
+    let res = queue
+        .make_tremendous_request::<_, Value>(
+            Method::POST,
+            "orders",
+            Some(json!({ "rewards": [] })),
+            None,
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(ApiError::Payments(message)) if message == "Invalid product"
+    ));
+
+    mock.stop().await;
+}
+
+#[actix_rt::test]
+pub async fn retrying_a_failed_payout_sends_it_to_the_corrected_email() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+
+        let mock = ProviderMock::start(vec![
+            tremendous_products_route(),
+            (
+                Method::POST,
+                "tremendous/orders",
+                StatusCode::OK,
+                json!({ "order": { "rewards": [{ "id": "REWARD2" }] } }),
+            ),
+        ]);
+        let queue = queue_for(&mock);
+
+        // The withdrawal failed because of a typo in the email, which the user has since fixed.
+        // Its funds were returned to the balance when it failed.
+        sqlx::query(
+            "UPDATE users SET email = 'fixed@modrinth.com', email_verified = TRUE, balance = 100 WHERE id = $1",
+        )
+        .bind(USER_USER_ID_PARSED)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let payout_id = labrinth::models::ids::PayoutId(5678);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::Failed,
+            amount: Decimal::from(10),
+            fee: Some(Decimal::ZERO),
+            withheld: Some(Decimal::ZERO),
+            method: Some(PayoutMethodType::Tremendous),
+            method_id: Some("TESTPRODUCT1".to_string()),
+            method_address: Some("fixd@modrinth.com".to_string()),
+            platform_id: Some("REWARD1".to_string()),
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let resp = api.get_payout_attempts(USER_USER_PAT).await;
+        assert_status(&resp, StatusCode::OK);
+        let attempts: Vec<Payout> = test::read_body_json(resp).await;
+        assert_eq!(
+            attempts.iter().map(|x| x.id).collect::<Vec<_>>(),
+            vec![payout_id]
+        );
+
+        let retry = PayoutRetry {
+            method: None,
+            idempotency_key: Some("retry-5678".to_string()),
+        };
+        let retry_id =
+            payouts::retry_failed_payout(&pool, &redis, &queue, payout_id.into(), &retry)
+                .await
+                .unwrap()
+                .expect("the failed payout is retried");
+
+        // A new order is placed, to the corrected email
+        let requests = mock.requests_to("tremendous/orders");
+        assert_eq!(requests.len(), 1);
+        let order: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(
+            order["rewards"][0]["recipient"]["email"],
+            "fixed@modrinth.com"
+        );
+        assert_eq!(order["rewards"][0]["value"]["currency_code"], "USD");
+
+        let retried = payout_item::Payout::get(retry_id, &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(retried.platform_id.as_deref(), Some("REWARD2"));
+        assert_eq!(retried.method_address.as_deref(), Some("fixed@modrinth.com"));
+        let retry_of: Option<i64> = sqlx::query_scalar("SELECT retry_of FROM payouts WHERE id = $1")
+            .bind(retry_id.0)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(retry_of, Some(payout_id.0 as i64));
+
+        // Retrying again with the same key returns the same attempt without placing another order
+        let again = payouts::retry_failed_payout(&pool, &redis, &queue, payout_id.into(), &retry)
+            .await
+            .unwrap();
+        assert_eq!(again, Some(retry_id));
+        assert_eq!(mock.requests_to("tremendous/orders").len(), 1);
+
+        // The failed payout is settled by its retry, which is now the one on its way
+        let resp = api.get_payout_attempts(USER_USER_PAT).await;
+        let attempts: Vec<Payout> = test::read_body_json(resp).await;
+        assert_eq!(
+            attempts.iter().map(|x| x.id).collect::<Vec<_>>(),
+            vec![labrinth::models::ids::PayoutId::from(retry_id)]
+        );
+
+        mock.stop().await;
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn fee_schedules_are_listed_for_every_method() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+
+        let mock = ProviderMock::start(vec![(
+            Method::GET,
+            "tremendous/products",
+            StatusCode::OK,
+            json!({
+                "products": [{
+                    "id": "TESTACH00001",
+                    "category": "ach",
+                    "name": "ACH Transfer",
+                    "description": "",
+                    "disclosure": "",
+                    "skus": [{ "min": 1, "max": 10000 }],
+                    "currency_codes": ["USD"],
+                    "countries": [{ "abbr": "US" }],
+                    "images": [],
+                }],
+            }),
+        )]);
+
+        // The methods as fetched from the providers, shared with the app's queue through Redis
+        let methods = queue_for(&mock).get_payout_methods().await.unwrap();
+        seed_methods(&test_env.db.redis_pool, &methods).await;
+
+        let schedules = api.get_payout_fee_schedules().await;
+        let fee_of = |id: &str| {
+            schedules
+                .iter()
+                .find(|x| x.method_id == id)
+                .unwrap_or_else(|| panic!("{id} has a fee schedule"))
+                .fee
+                .clone()
+        };
+
+        assert_eq!(
+            fee_of("TESTACH00001"),
+            PayoutMethodFee {
+                percentage: Decimal::new(4, 2),
+                min: Decimal::new(25, 2),
+                max: None,
+            }
+        );
+        assert_eq!(
+            fee_of("paypal_us"),
+            PayoutMethodFee {
+                percentage: Decimal::new(2, 2),
+                min: Decimal::new(25, 2),
+                max: Some(Decimal::ONE),
+            }
+        );
+        assert!(schedules.iter().all(|x| x.currency == "USD"));
+        assert_eq!(schedules.len(), methods.len());
+
+        mock.stop().await;
+    })
+    .await;
+}
@@ -0,0 +1,3424 @@
+use std::collections::HashMap;
+
+use actix_http::StatusCode;
+use actix_web::test;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use common::{
+    api_v3::ApiV3,
+    asserts::{assert_fee_for, assert_method_present, assert_status},
+    database::*,
+    environment::{with_test_environment, with_test_environment_vars, TestEnvironment},
+    payouts::{seed_methods, test_method},
+};
+use hex::ToHex;
+use hmac::{Hmac, Mac, NewMac};
+use labrinth::database::models::payout_item;
+use labrinth::models::ids::base62_impl::parse_base62;
+use labrinth::models::ids::UserId;
+use labrinth::models::payouts::{
+    PayoutAuditAction, PayoutAuditEntry, PayoutBalance, PayoutDecimal, PayoutHistoryPage,
+    PayoutIneligibility, PayoutInterval, PayoutMethod, PayoutMethodEligibility, PayoutMethodFee,
+    PayoutMethodSnapshot, PayoutMethodType, PayoutOnboarding, PayoutRun, PayoutStatus,
+    PayoutUsdEquivalent, UserPayoutHold, WithdrawalResponse,
+};
+use labrinth::models::projects::MonetizationStatus;
+use labrinth::queue::payouts::{
+    self, PayoutAnalytics, PayoutDayOutcome, PayoutMultipliers, PayoutPeriod, PayoutSelfTest,
+    CORRELATION_ID_HEADER,
+};
+use labrinth::routes::ApiError;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sha2::Sha256;
+
+mod common;
+
+/// The start of today, in UTC
+fn today() -> DateTime<Utc> {
+    Utc::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+}
+
+/// The start of yesterday, the most recent day which can be paid out
+fn yesterday() -> DateTime<Utc> {
+    today() - Duration::days(1)
+}
+
+async fn get_balance(pool: &sqlx::PgPool, user_id: i64) -> Decimal {
+    sqlx::query_scalar("SELECT balance FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+async fn get_cached_balance(
+    pool: &sqlx::PgPool,
+    redis: &labrinth::database::redis::RedisPool,
+    user_id: i64,
+) -> Decimal {
+    labrinth::database::models::User::get_id(
+        labrinth::database::models::UserId(user_id),
+        pool,
+        redis,
+    )
+    .await
+    .unwrap()
+    .unwrap()
+    .balance
+}
+
+#[actix_rt::test]
+pub async fn disabled_withdrawals_still_accrue_balance() {
+    with_test_environment_vars(
+        &[("PAYOUTS_WITHDRAWALS_ENABLED", Some("false"))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+            let redis = test_env.db.redis_pool.clone();
+
+            let resp = api
+                .create_payout(
+                    json!({
+                        "amount": 1.0,
+                        "method": "paypal",
+                        "method_id": "paypal_us",
+                    }),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status(&resp, StatusCode::SERVICE_UNAVAILABLE);
+
+            let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+            let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+            let start = yesterday();
+            payouts::distribute_payouts(
+                &pool,
+                &redis,
+                &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100)),
+                start,
+                PayoutMultipliers {
+                    sum: 100,
+                    values: HashMap::from([(alpha_project_id, 100)]),
+                },
+                &[MonetizationStatus::Monetized],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+            let after = get_balance(&pool, USER_USER_ID_PARSED).await;
+            assert!(after > before);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn paid_out_users_are_cleared_from_the_cache() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let cache = payouts::NoopPayoutCache::default();
+
+        let start = yesterday();
+        payouts::distribute_payouts(
+            &pool,
+            &cache,
+            &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100)),
+            start,
+            PayoutMultipliers {
+                sum: 100,
+                values: HashMap::from([(alpha_project_id, 100)]),
+            },
+            &[MonetizationStatus::Monetized],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            cache.cleared(),
+            vec![labrinth::database::models::UserId(USER_USER_ID_PARSED)]
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn undistributed_budget_is_swept_into_the_reserve() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let before = get_balance(&pool, FRIEND_USER_ID_PARSED).await;
+
+        let start = yesterday();
+        // The project was only monetized for the second half of the day, so half the budget is
+        // left over
+        sqlx::query("UPDATE mods SET monetization_status_updated = $1 WHERE id = $2")
+            .bind(start + Duration::hours(12))
+            .bind(alpha_project_id as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+        payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100)),
+            start,
+            PayoutMultipliers {
+                sum: 100,
+                values: HashMap::from([(alpha_project_id, 100)]),
+            },
+            &[MonetizationStatus::Monetized],
+            Some(labrinth::database::models::UserId(FRIEND_USER_ID_PARSED)),
+            false,
+        )
+        .await
+        .unwrap();
+
+        let reserved: Decimal = sqlx::query_scalar(
+            "SELECT amount FROM payouts_values WHERE user_id = $1 AND mod_id IS NULL AND created = $2",
+        )
+        .bind(FRIEND_USER_ID_PARSED)
+        .bind(start)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(reserved, Decimal::from(50));
+        assert_eq!(
+            get_balance(&pool, FRIEND_USER_ID_PARSED).await - before,
+            Decimal::from(50)
+        );
+
+        let total: Decimal =
+            sqlx::query_scalar("SELECT SUM(amount) FROM payouts_values WHERE created = $1")
+                .bind(start)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(total, Decimal::from(100));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_self_test_passes() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let resp = test_env.api.get_payout_self_test().await;
+        assert_status(&resp, StatusCode::OK);
+        let result: PayoutSelfTest = test::read_body_json(resp).await;
+        assert!(result.passed);
+        assert!(result.diffs.is_empty());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn completed_runs_record_a_summary() {
+    // The user is capped at 60 of the day's 100, and the friend is the reserve
+    with_test_environment_vars(
+        &[
+            ("PAYOUTS_ANALYTICS_SOURCE", Some("postgres")),
+            ("PAYOUTS_MAX_DAILY_USER_PAYOUT", Some("60")),
+            ("PAYOUTS_RESERVE_USER", Some(FRIEND_USER_ID)),
+        ],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let pool = test_env.db.pool.clone();
+            let redis = test_env.db.redis_pool.clone();
+            let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+            let start = yesterday();
+            sqlx::query(
+                "INSERT INTO analytics_views (recorded, project_id, user_id) VALUES ($1, $2, 0)",
+            )
+            .bind(start + Duration::hours(1))
+            .bind(alpha_project_id as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let period = PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100));
+            payouts::process_payout(
+                &pool,
+                &redis,
+                &clickhouse::Client::default(),
+                &period,
+                false,
+                None,
+            )
+            .await
+            .unwrap();
+
+            let resp = test_env.api.get_payout_runs().await;
+            assert_status(&resp, StatusCode::OK);
+            let runs: Vec<PayoutRun> = test::read_body_json(resp).await;
+            assert_eq!(runs.len(), 1);
+            assert_eq!(runs[0].day, start);
+            assert_eq!(runs[0].budget, Decimal::from(100));
+            assert_eq!(runs[0].distributed, Decimal::from(60));
+            assert_eq!(runs[0].projects, 1);
+            assert_eq!(runs[0].users, 1);
+            assert_eq!(runs[0].outcome, "paid");
+            assert!(!runs[0].forced);
+
+            // The rest was swept into the reserve, but isn't counted as distributed
+            let reserve: Decimal = sqlx::query_scalar(
+                "SELECT SUM(amount) FROM payouts_values WHERE created = $1 AND user_id = $2",
+            )
+            .bind(start)
+            .bind(FRIEND_USER_ID_PARSED)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            assert_eq!(reserve, Decimal::from(40));
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn simulated_payouts_scale_with_the_budget() {
+    // Activity is read from Postgres, and every day of a period is budgeted the same
+    with_test_environment_vars(
+        &[
+            ("PAYOUTS_ANALYTICS_SOURCE", Some("postgres")),
+            ("PAYOUTS_WEEKEND_BONUS", Some("1")),
+        ],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let pool = test_env.db.pool.clone();
+            let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+            let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+            let end = today();
+            let start = end - Duration::days(2);
+            // Two days of views, each shared with a project which doesn't exist and so is never
+            // paid
+            let at = |day: i64, hour: i64| {
+                (start + Duration::days(day) + Duration::hours(hour)).timestamp_millis() * 10
+            };
+            let mut transaction = pool.begin().await.unwrap();
+            payouts::record_postgres_events(
+                &mut transaction,
+                payouts::AnalyticsEvent::Views,
+                [
+                    (at(0, 1), alpha_project_id, 1),
+                    (at(0, 2), alpha_project_id, 1),
+                    (at(0, 3), 4_242_424_242, 1),
+                    (at(1, 1), alpha_project_id, 1),
+                    (at(1, 2), 4_242_424_242, 1),
+                    (at(1, 3), 4_242_424_242, 1),
+                ],
+            )
+            .await
+            .unwrap();
+            transaction.commit().await.unwrap();
+
+            let clickhouse = clickhouse::Client::default();
+            let simulate = |budget: u64| {
+                let (pool, clickhouse) = (&pool, &clickhouse);
+                async move {
+                    payouts::simulate_payouts(pool, clickhouse, start, end, Decimal::from(budget))
+                        .await
+                        .unwrap()
+                }
+            };
+
+            // Each day is its 28 day period's share of the budget, so 2800 budgets 100 a day.
+            // Alpha is the only project which can be paid, so it earns both days' budgets in full.
+            let user_id = UserId(USER_USER_ID_PARSED as u64);
+            let simulated = simulate(2800).await;
+            assert_eq!(simulated.len(), 1);
+            assert_eq!(simulated[&user_id], Decimal::from(200));
+
+            let tripled = simulate(8400).await;
+            assert_eq!(tripled[&user_id], simulated[&user_id] * Decimal::from(3));
+
+            // Nothing is paid out
+            let payouts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM payouts_values")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(payouts, 0);
+            assert_eq!(get_balance(&pool, USER_USER_ID_PARSED).await, before);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn forced_reprocessing_replaces_previous_payouts() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+        let start = yesterday();
+        let period = PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100));
+        let multipliers = || PayoutMultipliers {
+            sum: 100,
+            values: HashMap::from([(alpha_project_id, 100)]),
+        };
+
+        // The first run overpays because of a bad budget, then the day is forcibly reprocessed
+        payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(1000)),
+            start,
+            multipliers(),
+            &[MonetizationStatus::Monetized],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+        payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &period,
+            start,
+            multipliers(),
+            &[MonetizationStatus::Monetized],
+            None,
+            true,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await - before,
+            Decimal::from(100)
+        );
+
+        let rows: Vec<Decimal> = sqlx::query_scalar(
+            "SELECT amount FROM payouts_values WHERE user_id = $1 AND created = $2",
+        )
+        .bind(USER_USER_ID_PARSED)
+        .bind(start)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(rows, vec![Decimal::from(100)]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn forced_reprocessing_never_leaves_a_negative_balance() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let start = yesterday();
+        let distribute = |budget: i64, replace: bool| {
+            let (pool, redis) = (pool.clone(), redis.clone());
+            async move {
+                payouts::distribute_payouts(
+                    &pool,
+                    &redis,
+                    &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(budget)),
+                    start,
+                    PayoutMultipliers {
+                        sum: 100,
+                        values: HashMap::from([(alpha_project_id, 100)]),
+                    },
+                    &[MonetizationStatus::Monetized],
+                    None,
+                    replace,
+                )
+                .await
+                .unwrap();
+            }
+        };
+
+        // The overpayment is withdrawn before the day is reprocessed
+        distribute(1000, false).await;
+        sqlx::query("UPDATE users SET balance = 0 WHERE id = $1")
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+        distribute(100, true).await;
+
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await,
+            Decimal::from(100)
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn held_project_share_is_redistributed() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let beta_project_id = parse_base62(&test_env.dummy.project_beta.project_id).unwrap();
+
+        let resp = api
+            .set_payout_hold(&test_env.dummy.project_beta.project_id, true)
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        let start = yesterday();
+        payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100)),
+            start,
+            PayoutMultipliers {
+                sum: 100,
+                values: HashMap::from([(alpha_project_id, 50), (beta_project_id, 50)]),
+            },
+            &[MonetizationStatus::Monetized],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+
+        let paid: Vec<(i64, Decimal)> =
+            sqlx::query_as("SELECT mod_id, amount FROM payouts_values WHERE created = $1")
+                .bind(start)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(paid, vec![(alpha_project_id as i64, Decimal::from(100))]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn recompute_balance_corrects_drift() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let mut transaction = pool.begin().await.unwrap();
+        payouts::insert_payouts(
+            vec![USER_USER_ID_PARSED],
+            vec![alpha_project_id as i64],
+            vec![Decimal::from(10)],
+            vec![Utc::now()],
+            &mut transaction,
+        )
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        sqlx::query("UPDATE users SET balance = 25 WHERE id = $1")
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let resp = api.recompute_balance(USER_USER_ID, true).await;
+        assert_status(&resp, StatusCode::OK);
+        let recomputed: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(recomputed["previous"], json!(25.0));
+        assert_eq!(recomputed["ledger"], json!(10.0));
+        assert_eq!(recomputed["corrected"], json!(true));
+
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await,
+            Decimal::from(10)
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_totals_are_bucketed() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let days = [
+            ("2023-01-02T00:00:00Z", 10),
+            ("2023-01-04T00:00:00Z", 5),
+            ("2023-01-09T00:00:00Z", 7),
+            ("2023-02-01T00:00:00Z", 3),
+        ];
+        let mut transaction = pool.begin().await.unwrap();
+        payouts::insert_payouts(
+            vec![USER_USER_ID_PARSED; days.len()],
+            vec![alpha_project_id as i64; days.len()],
+            days.iter().map(|x| Decimal::from(x.1)).collect(),
+            days.iter().map(|x| x.0.parse().unwrap()).collect(),
+            &mut transaction,
+        )
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let totals = |granularity: &'static str| async move {
+            let resp = api
+                .get_payout_totals(granularity, "2023-01-01T00:00:00Z", "2023-03-01T00:00:00Z")
+                .await;
+            assert_status(&resp, StatusCode::OK);
+            let totals: serde_json::Value = test::read_body_json(resp).await;
+            totals
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|x| x["total"].as_str().unwrap().parse::<f64>().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(totals("day").await, vec![10.0, 5.0, 7.0, 3.0]);
+        // Weeks start on Monday, and 2023-01-02 is one
+        assert_eq!(totals("week").await, vec![15.0, 7.0, 3.0]);
+        assert_eq!(totals("month").await, vec![22.0, 3.0]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_totals_are_serialized_as_strings() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let mut transaction = pool.begin().await.unwrap();
+        payouts::insert_payouts(
+            vec![USER_USER_ID_PARSED; 2],
+            vec![alpha_project_id as i64; 2],
+            vec![Decimal::new(25, 2), Decimal::from(100000)],
+            vec![
+                "2023-01-02T00:00:00Z".parse().unwrap(),
+                "2023-01-03T00:00:00Z".parse().unwrap(),
+            ],
+            &mut transaction,
+        )
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let resp = api
+            .get_payout_totals("day", "2023-01-01T00:00:00Z", "2023-02-01T00:00:00Z")
+            .await;
+        assert_status(&resp, StatusCode::OK);
+        let totals: serde_json::Value = test::read_body_json(resp).await;
+        let totals = totals
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|x| x["total"].clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(totals, vec![json!("0.25"), json!("100000")]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn denied_review_payout_is_refunded() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+        let payout_id = labrinth::models::ids::PayoutId(1234);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::PendingReview,
+            amount: Decimal::from(900),
+            fee: Some(Decimal::from(50)),
+            withheld: Some(Decimal::from(50)),
+            method: Some(PayoutMethodType::Tremendous),
+            method_id: Some("TESTPRODUCT1".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: None,
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let resp = api.review_payout(&payout_id.to_string(), "deny").await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await - before,
+            Decimal::from(1000)
+        );
+        let payout = payout_item::Payout::get(payout_id.into(), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(payout.status, PayoutStatus::Cancelled);
+
+        // A reviewed payout can't be approved or denied again
+        let resp = api.review_payout(&payout_id.to_string(), "approve").await;
+        assert_status(&resp, StatusCode::BAD_REQUEST);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn users_can_cancel_payouts_held_for_review() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+        let payout_id = labrinth::models::ids::PayoutId(1357);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::PendingReview,
+            amount: Decimal::from(900),
+            fee: Some(Decimal::from(50)),
+            withheld: Some(Decimal::from(50)),
+            method: Some(PayoutMethodType::Tremendous),
+            method_id: Some("TESTPRODUCT1".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: None,
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        // Only the user who made it can cancel it
+        let resp = api
+            .cancel_payout(&payout_id.to_string(), FRIEND_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::NOT_FOUND);
+
+        let resp = api
+            .cancel_payout(&payout_id.to_string(), USER_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await - before,
+            Decimal::from(1000)
+        );
+        let payout = payout_item::Payout::get(payout_id.into(), &pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(payout.status, PayoutStatus::Cancelled);
+
+        // Nor can a cancelled payout be approved afterwards
+        let resp = api.review_payout(&payout_id.to_string(), "approve").await;
+        assert_status(&resp, StatusCode::BAD_REQUEST);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn denying_a_payout_writes_an_audit_entry() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+
+        let payout_id = labrinth::models::ids::PayoutId(2468);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::PendingReview,
+            amount: Decimal::from(900),
+            fee: Some(Decimal::from(50)),
+            withheld: Some(Decimal::from(50)),
+            method: Some(PayoutMethodType::Tremendous),
+            method_id: Some("TESTPRODUCT1".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: None,
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let resp = api
+            .review_payout_as(&payout_id.to_string(), "deny", "support@modrinth.com")
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        let resp = api
+            .get_payout_audit_log(&format!("target={payout_id}"))
+            .await;
+        assert_status(&resp, StatusCode::OK);
+        let entries: Vec<PayoutAuditEntry> = test::read_body_json(resp).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, PayoutAuditAction::DenyPayout);
+        // The name the request gave is kept, but only as a claim, as the admin key is shared
+        assert_eq!(entries[0].actor, "admin");
+        assert_eq!(
+            entries[0].claimed_actor.as_deref(),
+            Some("support@modrinth.com")
+        );
+        assert_eq!(entries[0].target, Some(payout_id.to_string()));
+        assert!(entries[0].details.get("refunded").is_some());
+
+        let resp = api
+            .get_payout_audit_log(&format!("target={payout_id}&claimed_actor=someone-else"))
+            .await;
+        assert_status(&resp, StatusCode::OK);
+        let entries: Vec<PayoutAuditEntry> = test::read_body_json(resp).await;
+        assert!(entries.is_empty());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn caught_up_days_are_audited_with_their_payouts() {
+    with_test_environment_vars(
+        &[("PAYOUTS_ANALYTICS_SOURCE", Some("postgres"))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+            let redis = test_env.db.redis_pool.clone();
+            let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+            let yesterday = yesterday();
+            sqlx::query(
+                "INSERT INTO analytics_views (recorded, project_id, user_id) VALUES ($1, $2, 0)",
+            )
+            .bind(yesterday + Duration::hours(1))
+            .bind(alpha_project_id as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let entry = PayoutAuditEntry {
+                created: Utc::now(),
+                actor: "admin".to_string(),
+                claimed_actor: Some("support@modrinth.com".to_string()),
+                action: PayoutAuditAction::CatchUpPayouts,
+                target: None,
+                details: json!({}),
+            };
+            let clickhouse = clickhouse::Client::default();
+            let catch_up = || payouts::catch_up_payouts(&pool, &redis, &clickhouse, Some(&entry));
+            assert_eq!(catch_up().await.unwrap(), vec![yesterday]);
+
+            // The day is audited along with its payouts
+            let day = yesterday.date_naive().to_string();
+            let resp = api.get_payout_audit_log(&format!("target={day}")).await;
+            assert_status(&resp, StatusCode::OK);
+            let entries: Vec<PayoutAuditEntry> = test::read_body_json(resp).await;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].action, PayoutAuditAction::CatchUpPayouts);
+            assert_eq!(entries[0].actor, "admin");
+
+            // With nothing left to catch up on, only the attempt is audited
+            assert!(catch_up().await.unwrap().is_empty());
+            let resp = api
+                .get_payout_audit_log("claimed_actor=support@modrinth.com")
+                .await;
+            assert_status(&resp, StatusCode::OK);
+            let entries: Vec<PayoutAuditEntry> = test::read_body_json(resp).await;
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].target, None);
+        },
+    )
+    .await;
+}
+
+async fn get_payout_status(
+    pool: &sqlx::PgPool,
+    payout_id: labrinth::models::ids::PayoutId,
+) -> PayoutStatus {
+    payout_item::Payout::get(payout_id.into(), pool)
+        .await
+        .unwrap()
+        .unwrap()
+        .status
+}
+
+fn sign_tremendous_webhook(body: &str) -> String {
+    let mut mac: Hmac<Sha256> =
+        Hmac::new_from_slice(dotenvy::var("TREMENDOUS_PRIVATE_KEY").unwrap().as_bytes()).unwrap();
+    mac.update(body.as_bytes());
+    format!(
+        "sha256={}",
+        mac.finalize().into_bytes().encode_hex::<String>()
+    )
+}
+
+#[actix_rt::test]
+pub async fn signed_tremendous_delivery_updates_payout() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+
+        let payout_id = labrinth::models::ids::PayoutId(4321);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::InTransit,
+            amount: Decimal::from(10),
+            fee: Some(Decimal::ZERO),
+            withheld: Some(Decimal::ZERO),
+            method: Some(PayoutMethodType::Tremendous),
+            method_id: Some("TESTPRODUCT1".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: Some("REWARD1".to_string()),
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let body = json!({
+            "event": "REWARDS.DELIVERY.SUCCEEDED",
+            "payload": { "resource": { "id": "REWARD1" } },
+        })
+        .to_string();
+
+        // Unsigned and wrongly signed payloads are rejected
+        let resp = api.tremendous_webhook(&body, None).await;
+        assert_status(&resp, StatusCode::BAD_REQUEST);
+        let resp = api
+            .tremendous_webhook(&body, Some(&sign_tremendous_webhook("{}")))
+            .await;
+        assert_status(&resp, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            get_payout_status(&pool, payout_id).await,
+            PayoutStatus::InTransit
+        );
+
+        let resp = api
+            .tremendous_webhook(&body, Some(&sign_tremendous_webhook(&body)))
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+        assert_eq!(
+            get_payout_status(&pool, payout_id).await,
+            PayoutStatus::Success
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn late_failure_webhooks_do_not_return_funds_twice() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let queue = payouts::PayoutsQueue::new();
+        let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+        let payout_id = labrinth::models::ids::PayoutId(4322);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::InTransit,
+            amount: Decimal::from(10),
+            fee: Some(Decimal::ONE),
+            withheld: Some(Decimal::ONE),
+            method: Some(PayoutMethodType::Tremendous),
+            method_id: Some("TESTPRODUCT1".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: Some("REWARD2".to_string()),
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        // Reconciliation finds the failure before the provider's webhook arrives
+        assert!(payouts::return_payout_funds(
+            &pool,
+            &redis,
+            &queue,
+            payout_id.into(),
+            PayoutStatus::Failed
+        )
+        .await
+        .unwrap());
+
+        for event in ["REWARDS.DELIVERY.FAILED", "REWARDS.DELIVERY.SUCCEEDED"] {
+            let body = json!({
+                "event": event,
+                "payload": { "resource": { "id": "REWARD2" } },
+            })
+            .to_string();
+            let resp = api
+                .tremendous_webhook(&body, Some(&sign_tremendous_webhook(&body)))
+                .await;
+            assert_status(&resp, StatusCode::NO_CONTENT);
+        }
+
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await - before,
+            Decimal::from(12)
+        );
+        assert_eq!(
+            get_payout_status(&pool, payout_id).await,
+            PayoutStatus::Failed
+        );
+    })
+    .await;
+}
+
+#[test]
+fn standard_payout_interval_json_is_stable() {
+    let fixture = json!({
+        "standard": {
+            "min": 0.01,
+            "max": 5000.0,
+        }
+    });
+    let interval = PayoutInterval::Standard {
+        min: Decimal::new(1, 2),
+        max: Decimal::from(5000),
+    };
+
+    assert_eq!(serde_json::to_value(&interval).unwrap(), fixture);
+    assert_eq!(
+        serde_json::from_value::<PayoutInterval>(fixture).unwrap(),
+        interval
+    );
+}
+
+#[test]
+fn fixed_payout_interval_json_is_stable() {
+    let fixture = json!({
+        "fixed": {
+            "values": [5.0, 10.0, 25.0],
+        }
+    });
+    let interval = PayoutInterval::Fixed {
+        values: vec![
+            PayoutDecimal(Decimal::from(5)),
+            PayoutDecimal(Decimal::from(10)),
+            PayoutDecimal(Decimal::from(25)),
+        ],
+    };
+
+    assert_eq!(serde_json::to_value(&interval).unwrap(), fixture);
+    assert_eq!(
+        serde_json::from_value::<PayoutInterval>(fixture).unwrap(),
+        interval
+    );
+}
+
+#[actix_rt::test]
+pub async fn reconciliation_clears_caches_after_interrupted_payout() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let before = get_cached_balance(&pool, &redis, USER_USER_ID_PARSED).await;
+
+        // A payout run which credited the user and committed, but crashed before clearing caches
+        let start = yesterday();
+        sqlx::query(
+            "INSERT INTO payouts_values (user_id, mod_id, amount, created) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(USER_USER_ID_PARSED)
+        .bind(alpha_project_id as i64)
+        .bind(Decimal::from(10))
+        .bind(start)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("UPDATE users SET balance = balance + $1 WHERE id = $2")
+            .bind(Decimal::from(10))
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_cached_balance(&pool, &redis, USER_USER_ID_PARSED).await,
+            before
+        );
+
+        let cleared = payouts::reconcile_payout_caches(&pool, &redis, start)
+            .await
+            .unwrap();
+        assert_eq!(cleared, 1);
+        assert_eq!(
+            get_cached_balance(&pool, &redis, USER_USER_ID_PARSED).await,
+            before + Decimal::from(10)
+        );
+
+        // Running it again does no harm
+        payouts::reconcile_payout_caches(&pool, &redis, start)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_cached_balance(&pool, &redis, USER_USER_ID_PARSED).await,
+            before + Decimal::from(10)
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn concurrent_withdrawals_cannot_overdraw() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let user_id = labrinth::database::models::UserId(USER_USER_ID_PARSED);
+
+        sqlx::query("UPDATE users SET balance = 100 WHERE id = $1")
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Both withdrawals hold their transaction open as if sending the payout, so the second
+        // has to wait on the first's row lock
+        let pool_ref = &pool;
+        let withdraw = || async move {
+            let mut transaction = pool_ref.begin().await.unwrap();
+            let withdrawn = labrinth::database::models::User::withdraw_balance(
+                user_id,
+                Decimal::from(60),
+                &mut transaction,
+            )
+            .await
+            .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            transaction.commit().await.unwrap();
+            withdrawn
+        };
+        let (first, second) = futures::join!(withdraw(), withdraw());
+
+        assert!(first ^ second);
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await,
+            Decimal::from(40)
+        );
+    })
+    .await;
+}
+
+/// Checks `method` serializes to exactly `fixture`, and that the fixture deserializes back to it.
+/// These fixtures are the stability contract for `PayoutMethod`: fields may be added to them but
+/// never removed or renamed.
+fn assert_payout_method_json(method: PayoutMethod, fixture: serde_json::Value) {
+    assert_eq!(serde_json::to_value(&method).unwrap(), fixture);
+    assert_eq!(
+        serde_json::from_value::<PayoutMethod>(fixture).unwrap(),
+        method
+    );
+}
+
+#[test]
+fn paypal_payout_method_json_is_stable() {
+    assert_payout_method_json(
+        PayoutMethod {
+            id: "paypal_us".to_string(),
+            type_: PayoutMethodType::PayPal,
+            name: "PayPal".to_string(),
+            supported_countries: vec!["US".to_string()],
+            supported_currencies: vec!["USD".to_string()],
+            image_url: None,
+            interval: PayoutInterval::Standard {
+                min: Decimal::new(25, 2),
+                max: Decimal::from(100_000),
+            },
+            fee: PayoutMethodFee {
+                percentage: Decimal::new(2, 2),
+                min: Decimal::new(25, 2),
+                max: Some(Decimal::ONE),
+            },
+            usd_equivalent: None,
+            order: 0,
+            available: true,
+            currency: Some("USD".to_string()),
+            decimal_places: 2,
+            description: None,
+            disclosure: None,
+            maintenance: None,
+            minimum: None,
+        },
+        json!({
+            "id": "paypal_us",
+            "type": "paypal",
+            "name": "PayPal",
+            "supported_countries": ["US"],
+            "supported_currencies": ["USD"],
+            "image_url": null,
+            "interval": { "standard": { "min": 0.25, "max": 100000.0 } },
+            "fee": { "percentage": 0.02, "min": 0.25, "max": 1.0 },
+            "usd_equivalent": null,
+            "order": 0,
+            "available": true,
+            "currency": "USD",
+            "decimal_places": 2,
+            "description": null,
+            "disclosure": null,
+            "maintenance": null,
+            "minimum": null,
+        }),
+    );
+}
+
+#[test]
+fn venmo_payout_method_json_is_stable() {
+    assert_payout_method_json(
+        PayoutMethod {
+            id: "venmo".to_string(),
+            type_: PayoutMethodType::Venmo,
+            name: "Venmo".to_string(),
+            supported_countries: vec!["US".to_string()],
+            supported_currencies: vec!["USD".to_string()],
+            image_url: None,
+            interval: PayoutInterval::Standard {
+                min: Decimal::new(25, 2),
+                max: Decimal::from(100_000),
+            },
+            fee: PayoutMethodFee {
+                percentage: Decimal::new(2, 2),
+                min: Decimal::new(25, 2),
+                max: Some(Decimal::ONE),
+            },
+            usd_equivalent: None,
+            order: 1,
+            available: false,
+            currency: Some("USD".to_string()),
+            decimal_places: 2,
+            description: None,
+            disclosure: None,
+            maintenance: None,
+            minimum: None,
+        },
+        json!({
+            "id": "venmo",
+            "type": "venmo",
+            "name": "Venmo",
+            "supported_countries": ["US"],
+            "supported_currencies": ["USD"],
+            "image_url": null,
+            "interval": { "standard": { "min": 0.25, "max": 100000.0 } },
+            "fee": { "percentage": 0.02, "min": 0.25, "max": 1.0 },
+            "usd_equivalent": null,
+            "order": 1,
+            "available": false,
+            "currency": "USD",
+            "decimal_places": 2,
+            "description": null,
+            "disclosure": null,
+            "maintenance": null,
+            "minimum": null,
+        }),
+    );
+}
+
+#[test]
+fn tremendous_payout_method_json_is_stable() {
+    assert_payout_method_json(
+        PayoutMethod {
+            id: "TESTPRODUCT1".to_string(),
+            type_: PayoutMethodType::Tremendous,
+            name: "Test Card".to_string(),
+            supported_countries: vec!["DE".to_string()],
+            supported_currencies: vec!["EUR".to_string()],
+            image_url: Some("https://example.com/card.png".to_string()),
+            interval: PayoutInterval::Standard {
+                min: Decimal::from(5),
+                max: Decimal::from(50),
+            },
+            fee: PayoutMethodFee {
+                percentage: Decimal::ZERO,
+                min: Decimal::ZERO,
+                max: None,
+            },
+            usd_equivalent: Some(PayoutUsdEquivalent {
+                interval: PayoutInterval::Standard {
+                    min: Decimal::new(55, 1),
+                    max: Decimal::from(55),
+                },
+                fee: PayoutMethodFee {
+                    percentage: Decimal::ZERO,
+                    min: Decimal::ZERO,
+                    max: None,
+                },
+            }),
+            order: 3,
+            available: true,
+            currency: Some("EUR".to_string()),
+            decimal_places: 2,
+            description: Some("A test card.".to_string()),
+            disclosure: Some("Test Card is not a sponsor of the rewards.".to_string()),
+            maintenance: None,
+            minimum: None,
+        },
+        json!({
+            "id": "TESTPRODUCT1",
+            "type": "tremendous",
+            "name": "Test Card",
+            "supported_countries": ["DE"],
+            "supported_currencies": ["EUR"],
+            "image_url": "https://example.com/card.png",
+            "interval": { "standard": { "min": 5.0, "max": 50.0 } },
+            "fee": { "percentage": 0.0, "min": 0.0, "max": null },
+            "usd_equivalent": {
+                "interval": { "standard": { "min": 5.5, "max": 55.0 } },
+                "fee": { "percentage": 0.0, "min": 0.0, "max": null },
+            },
+            "order": 3,
+            "available": true,
+            "currency": "EUR",
+            "decimal_places": 2,
+            "description": "A test card.",
+            "disclosure": "Test Card is not a sponsor of the rewards.",
+            "maintenance": null,
+            "minimum": null,
+        }),
+    );
+}
+
+#[test]
+fn payout_method_json_tolerates_added_and_missing_fields() {
+    let method = serde_json::from_value::<PayoutMethod>(json!({
+        "id": "paypal_us",
+        "type": "paypal",
+        "name": "PayPal",
+        "supported_countries": ["US"],
+        "image_url": null,
+        "interval": { "standard": { "min": 0.25, "max": 100000.0 } },
+        "fee": { "percentage": 0.02, "min": 0.25, "max": 1.0 },
+        "some_future_field": "ignored",
+    }))
+    .unwrap();
+
+    assert!(method.supported_currencies.is_empty());
+    assert_eq!(method.usd_equivalent, None);
+    assert_eq!(method.order, 0);
+    assert!(method.available);
+    assert_eq!(method.currency, None);
+    assert_eq!(method.decimal_places, 2);
+    assert_eq!(method.description, None);
+    assert_eq!(method.disclosure, None);
+    assert_eq!(method.maintenance, None);
+    assert_eq!(method.minimum, None);
+}
+
+#[actix_rt::test]
+pub async fn payout_history_pages_through_every_entry_once() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let today = today();
+        for days in 1..=10 {
+            sqlx::query(
+                "INSERT INTO payouts_values (user_id, mod_id, amount, created) VALUES ($1, $2, $3, $4)",
+            )
+            .bind(USER_USER_ID_PARSED)
+            .bind(alpha_project_id as i64)
+            .bind(Decimal::from(days))
+            .bind(today - Duration::days(days))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        // The 7 days from 8 to 2 days ago, 3 at a time
+        let start = (today - Duration::days(8)).to_rfc3339_opts(SecondsFormat::Micros, true);
+        let end = (today - Duration::days(2)).to_rfc3339_opts(SecondsFormat::Micros, true);
+        let mut amounts = Vec::new();
+        let mut offset = 0;
+        loop {
+            let resp = api
+                .get_payout_history(
+                    &format!("start={start}&end={end}&offset={offset}&limit=3"),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status(&resp, StatusCode::OK);
+            let page: PayoutHistoryPage = test::read_body_json(resp).await;
+            assert_eq!(page.total, 7);
+            if page.entries.is_empty() {
+                break;
+            }
+
+            assert!(page.entries.len() <= 3);
+            offset += page.entries.len();
+            amounts.extend(page.entries.into_iter().map(|x| x.amount));
+        }
+        assert_eq!(amounts, (2..=8).map(Decimal::from).collect::<Vec<_>>());
+
+        // Only the friend's own history is listed to them
+        let resp = api
+            .get_payout_history(&format!("start={start}&end={end}"), FRIEND_USER_PAT)
+            .await;
+        let page: PayoutHistoryPage = test::read_body_json(resp).await;
+        assert_eq!(page.total, 0);
+        assert!(page.entries.is_empty());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_export_lists_the_days_payouts() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let start = yesterday();
+        sqlx::query(
+            "INSERT INTO payouts_values (user_id, mod_id, amount, created) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(USER_USER_ID_PARSED)
+        .bind(alpha_project_id as i64)
+        .bind(Decimal::new(1250, 2))
+        .bind(start)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let resp = api.export_payouts(&start.date_naive().to_string()).await;
+        assert_status(&resp, StatusCode::OK);
+        let csv = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("user_id,project_id,amount,created"));
+        assert!(lines.any(|x| x
+            == format!(
+                "{},{},12.5,{}",
+                USER_USER_ID,
+                test_env.dummy.project_alpha.project_id,
+                start.to_rfc3339()
+            )));
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn zero_activity_day_pays_nothing() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+        let start = yesterday();
+        let period = PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100));
+
+        let outcome = payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &period,
+            start,
+            PayoutMultipliers {
+                sum: 0,
+                values: HashMap::from([(alpha_project_id, 0)]),
+            },
+            &[MonetizationStatus::Monetized],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, PayoutDayOutcome::ZeroActivity);
+
+        let paid: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM payouts_values WHERE created = $1)")
+                .bind(start)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert!(!paid);
+        assert_eq!(get_balance(&pool, USER_USER_ID_PARSED).await, before);
+
+        // The day isn't marked as paid, so it can still be paid out once activity shows up
+        let outcome = payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &period,
+            start,
+            PayoutMultipliers {
+                sum: 100,
+                values: HashMap::from([(alpha_project_id, 100)]),
+            },
+            &[MonetizationStatus::Monetized],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, PayoutDayOutcome::Paid);
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await - before,
+            Decimal::from(100)
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn ineligible_projects_do_not_dilute_the_budget() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let beta_project_id = parse_base62(&test_env.dummy.project_beta.project_id).unwrap();
+
+        sqlx::query("UPDATE mods SET monetization_status = $1 WHERE id = $2")
+            .bind(MonetizationStatus::Demonetized.as_str())
+            .bind(beta_project_id as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let start = yesterday();
+        // Only alpha is eligible, so the demonetized project and the one which doesn't exist
+        // must not take any of the budget
+        payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100)),
+            start,
+            PayoutMultipliers {
+                sum: 100,
+                values: HashMap::from([
+                    (alpha_project_id, 25),
+                    (beta_project_id, 50),
+                    (4_242_424_242, 25),
+                ]),
+            },
+            &[MonetizationStatus::Monetized],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+
+        let paid: Vec<(i64, Decimal)> =
+            sqlx::query_as("SELECT mod_id, amount FROM payouts_values WHERE created = $1")
+                .bind(start)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(paid, vec![(alpha_project_id as i64, Decimal::from(100))]);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn additional_eligible_statuses_are_paid_out() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let beta_project_id = parse_base62(&test_env.dummy.project_beta.project_id).unwrap();
+
+        sqlx::query("UPDATE mods SET monetization_status = $1 WHERE id = $2")
+            .bind(MonetizationStatus::Demonetized.as_str())
+            .bind(beta_project_id as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let start = yesterday();
+        payouts::distribute_payouts(
+            &pool,
+            &redis,
+            &PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100)),
+            start,
+            PayoutMultipliers {
+                sum: 100,
+                values: HashMap::from([(alpha_project_id, 50), (beta_project_id, 50)]),
+            },
+            &[
+                MonetizationStatus::Monetized,
+                MonetizationStatus::Demonetized,
+            ],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+
+        let mut paid: Vec<(i64, Decimal)> = sqlx::query_as(
+            "SELECT mod_id, SUM(amount) FROM payouts_values WHERE created = $1 GROUP BY mod_id",
+        )
+        .bind(start)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        paid.sort();
+        let mut expected = vec![
+            (alpha_project_id as i64, Decimal::from(50)),
+            (beta_project_id as i64, Decimal::from(50)),
+        ];
+        expected.sort();
+        assert_eq!(paid, expected);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn paypal_methods_are_listed_with_fees() {
+    // Tremendous can't be reached from tests, but PayPal and Venmo are defined locally
+    with_test_environment_vars(
+        &[("PAYOUTS_TREMENDOUS_ENABLED", Some("false"))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+
+            let methods = api.get_payout_methods_deserialized(Some("US")).await;
+            assert_method_present(&methods, "paypal_us");
+            assert_method_present(&methods, "venmo");
+            assert!(!methods.iter().any(|x| x.id == "paypal_in"));
+
+            // 2% with a $0.25 minimum and $1 maximum
+            assert_fee_for(
+                &methods,
+                "paypal_us",
+                Decimal::from(10),
+                Decimal::new(975, 2),
+            );
+            assert_fee_for(
+                &methods,
+                "paypal_us",
+                Decimal::from(20),
+                Decimal::new(1960, 2),
+            );
+            assert_fee_for(&methods, "venmo", Decimal::from(100), Decimal::from(99));
+
+            let methods = api.get_payout_methods_deserialized(Some("DE")).await;
+            assert_fee_for(
+                &methods,
+                "paypal_in",
+                Decimal::from(2000),
+                Decimal::from(1980),
+            );
+
+            let fees = api
+                .get_payout_fees_deserialized(Decimal::from(100), Some("US"))
+                .await;
+            assert_eq!(fees[0].net, Decimal::from(99));
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn failed_payout_is_returned_to_balance() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let queue = payouts::PayoutsQueue::new();
+
+        // Warm the user cache, which returning the funds must clear
+        let before = get_cached_balance(&pool, &redis, USER_USER_ID_PARSED).await;
+
+        let payout_id = labrinth::models::ids::PayoutId(5678);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::InTransit,
+            amount: Decimal::from(18),
+            fee: Some(Decimal::new(36, 2)),
+            withheld: Some(Decimal::new(164, 2)),
+            method: Some(PayoutMethodType::PayPal),
+            method_id: Some("paypal_us".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: Some("PAYPALITEM1".to_string()),
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        // The provider reports the payout was returned
+        assert!(payouts::return_payout_funds(
+            &pool,
+            &redis,
+            &queue,
+            payout_id.into(),
+            PayoutStatus::Failed
+        )
+        .await
+        .unwrap());
+
+        assert_eq!(
+            get_cached_balance(&pool, &redis, USER_USER_ID_PARSED).await - before,
+            Decimal::from(20)
+        );
+        assert_eq!(
+            get_payout_status(&pool, payout_id).await,
+            PayoutStatus::Failed
+        );
+
+        // A payout which already failed is never returned twice
+        assert!(!payouts::return_payout_funds(
+            &pool,
+            &redis,
+            &queue,
+            payout_id.into(),
+            PayoutStatus::Failed
+        )
+        .await
+        .unwrap());
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await - before,
+            Decimal::from(20)
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn failed_payouts_of_held_users_are_not_retried() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let queue = payouts::PayoutsQueue::new();
+
+        sqlx::query("UPDATE users SET balance = 100 WHERE id = $1")
+            .bind(FRIEND_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let payout_id = labrinth::models::ids::PayoutId(5679);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(FRIEND_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::Failed,
+            amount: Decimal::from(10),
+            fee: None,
+            withheld: None,
+            method: Some(PayoutMethodType::PayPal),
+            method_id: Some("paypal_us".to_string()),
+            method_address: Some("friend@modrinth.com".to_string()),
+            platform_id: Some("PAYPALITEM2".to_string()),
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let resp = api
+            .place_user_payout_hold(FRIEND_USER_ID, "Pending review", 7)
+            .await;
+        assert_status(&resp, StatusCode::OK);
+
+        let result = payouts::retry_failed_payout(
+            &pool,
+            &redis,
+            &queue,
+            payout_id.into(),
+            &payouts::PayoutRetry::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Compliance(_))));
+        assert_eq!(
+            get_balance(&pool, FRIEND_USER_ID_PARSED).await,
+            Decimal::from(100)
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn failed_payouts_are_reconciled_by_one_instance_at_a_time() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+
+        let held = payouts::try_lock_payout_job(&pool, payouts::RECONCILE_JOB_LOCK)
+            .await
+            .unwrap()
+            .expect("the job lock is free");
+        assert!(
+            payouts::try_lock_payout_job(&pool, payouts::RECONCILE_JOB_LOCK)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Ending the job's transaction releases the lock
+        held.rollback().await.unwrap();
+        assert!(
+            payouts::try_lock_payout_job(&pool, payouts::RECONCILE_JOB_LOCK)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn failed_payouts_are_retried_once_per_key() {
+    with_test_environment_vars(
+        &[
+            ("PAYOUTS_TREMENDOUS_ENABLED", Some("false")),
+            ("PAYOUTS_REVIEW_THRESHOLD", Some("1")),
+        ],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+
+            sqlx::query(
+                "UPDATE users SET paypal_id = 'user', paypal_country = 'US', balance = 100 WHERE id = $1",
+            )
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            // A failed withdrawal, and one on its way made with the key "sent"
+            let failed_id = labrinth::models::ids::PayoutId(2468);
+            let sent_id = labrinth::models::ids::PayoutId(2469);
+            let mut transaction = pool.begin().await.unwrap();
+            for (id, status, idempotency_key) in [
+                (failed_id, PayoutStatus::Failed, None),
+                (sent_id, PayoutStatus::InTransit, Some("sent".to_string())),
+            ] {
+                payout_item::Payout {
+                    id: id.into(),
+                    user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+                    created: Utc::now(),
+                    status,
+                    amount: Decimal::from(10),
+                    fee: Some(Decimal::ZERO),
+                    withheld: Some(Decimal::ZERO),
+                    method: Some(PayoutMethodType::PayPal),
+                    method_id: Some("paypal_us".to_string()),
+                    method_address: Some("user".to_string()),
+                    platform_id: None,
+                    note: None,
+                    idempotency_key,
+                }
+                .insert(&mut transaction)
+                .await
+                .unwrap();
+            }
+            transaction.commit().await.unwrap();
+
+            // Only the user who made it can retry it
+            let resp = api
+                .retry_payout(&failed_id.to_string(), json!({}), FRIEND_USER_PAT)
+                .await;
+            assert_status(&resp, StatusCode::NOT_FOUND);
+
+            // A key already used for another withdrawal is rejected, and nothing is taken
+            let resp = api
+                .retry_payout(
+                    &failed_id.to_string(),
+                    json!({ "idempotency_key": "sent" }),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status(&resp, StatusCode::CONFLICT);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(100)
+            );
+
+            // The retry is above the review threshold, so it is held for review like a new
+            // withdrawal would be
+            let resp = api
+                .retry_payout(
+                    &failed_id.to_string(),
+                    json!({ "idempotency_key": "retry" }),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status(&resp, StatusCode::OK);
+            let retried: WithdrawalResponse = test::read_body_json(resp).await;
+            assert_eq!(retried.status, PayoutStatus::PendingReview);
+            assert_eq!(retried.provider_reference, None);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(90)
+            );
+
+            // Replaying the key returns the same retry without taking the funds again
+            let resp = api
+                .retry_payout(
+                    &failed_id.to_string(),
+                    json!({ "idempotency_key": "retry" }),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status(&resp, StatusCode::OK);
+            let again: WithdrawalResponse = test::read_body_json(resp).await;
+            assert_eq!(again.id, retried.id);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(90)
+            );
+
+            // Any other retry of it is refused, as it was already retried
+            let resp = api
+                .retry_payout(
+                    &failed_id.to_string(),
+                    json!({ "idempotency_key": "another" }),
+                    USER_USER_PAT,
+                )
+                .await;
+            assert_status(&resp, StatusCode::BAD_REQUEST);
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_can_be_found_by_provider_reference() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+
+        let payout_id = labrinth::models::ids::PayoutId(8765);
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: payout_id.into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::InTransit,
+            amount: Decimal::from(25),
+            fee: Some(Decimal::ZERO),
+            withheld: Some(Decimal::ZERO),
+            method: Some(PayoutMethodType::Tremendous),
+            method_id: Some("TESTPRODUCT1".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: Some("REWARD2".to_string()),
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let resp = api.get_payout_by_reference("tremendous", "REWARD2").await;
+        assert_status(&resp, StatusCode::OK);
+        let record: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(record["payout"]["id"], json!(payout_id.to_string()));
+        assert_eq!(record["payout"]["user_id"], json!(USER_USER_ID));
+        assert_eq!(record["method_id"], json!("TESTPRODUCT1"));
+
+        // The reference has to belong to the named provider
+        let resp = api.get_payout_by_reference("paypal", "REWARD2").await;
+        assert_status(&resp, StatusCode::NOT_FOUND);
+        let resp = api.get_payout_by_reference("tremendous", "UNKNOWN").await;
+        assert_status(&resp, StatusCode::NOT_FOUND);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_methods_are_shared_between_instances() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let redis = test_env.db.redis_pool.clone();
+
+        // Another instance already fetched the methods and shared them. Nothing this instance
+        // could fetch itself is called `shared_method`.
+        seed_methods(
+            &redis,
+            &[test_method("shared_method", PayoutMethodType::PayPal)],
+        )
+        .await;
+
+        let methods = payouts::PayoutsQueue::new()
+            .with_redis(redis)
+            .get_payout_methods()
+            .await
+            .unwrap();
+        assert_eq!(
+            methods.iter().map(|x| &*x.id).collect::<Vec<_>>(),
+            vec!["shared_method"]
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_method_snapshots_keep_past_methods() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+
+        let method = |fee: Decimal| PayoutMethod {
+            fee: PayoutMethodFee {
+                percentage: Decimal::ZERO,
+                min: fee,
+                max: None,
+            },
+            ..test_method("snapshot_method", PayoutMethodType::Tremendous)
+        };
+
+        // Nothing is cached yet, and methods are never fetched just to snapshot them
+        let queue = payouts::PayoutsQueue::new().with_redis(redis.clone());
+        assert!(payouts::snapshot_payout_methods(&pool, &queue)
+            .await
+            .unwrap()
+            .is_none());
+
+        seed_methods(&redis, &[method(Decimal::ZERO)]).await;
+        let snapshot = payouts::snapshot_payout_methods(&pool, &queue)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.methods[0].fee.min, Decimal::ZERO);
+
+        // Another instance on the same schedule doesn't snapshot them again
+        let other_instance = payouts::PayoutsQueue::new().with_redis(redis.clone());
+        assert!(payouts::snapshot_payout_methods(&pool, &other_instance)
+            .await
+            .unwrap()
+            .is_none());
+
+        seed_methods(&redis, &[method(Decimal::ONE)]).await;
+        let current = api.get_payout_methods_deserialized(None).await;
+        assert_eq!(current[0].fee.min, Decimal::ONE);
+
+        let resp = api.get_payout_method_snapshot(Utc::now()).await;
+        assert_status(&resp, StatusCode::OK);
+        let past: PayoutMethodSnapshot = test::read_body_json(resp).await;
+        assert_eq!(past.methods, snapshot.methods);
+        assert_ne!(past.methods, current);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn malformed_withdrawals_are_rejected() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+        let withdrawal_json = |amount: f64, method: &str, method_id: &str| {
+            json!({
+                "amount": amount,
+                "method": method,
+                "method_id": method_id,
+            })
+        };
+
+        for (withdrawal, field) in [
+            (withdrawal_json(-5.0, "paypal", "paypal_us"), "amount"),
+            (withdrawal_json(0.0, "paypal", "paypal_us"), "amount"),
+            (withdrawal_json(5.0, "unknown", "paypal_us"), "method"),
+            (withdrawal_json(5.0, "paypal", " "), "method_id"),
+        ] {
+            let resp = api.create_payout(withdrawal, USER_USER_PAT).await;
+            assert_status(&resp, StatusCode::BAD_REQUEST);
+            let error: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(error["error"], "invalid_input");
+            assert!(error["description"]
+                .as_str()
+                .unwrap()
+                .contains(&format!("{field}:")));
+        }
+
+        for body in [
+            r#"{"amount": 5.0, "method": "paypal""#.to_string(),
+            r#"{"amount": "lots", "method": "paypal", "method_id": "paypal_us"}"#.to_string(),
+            format!(
+                r#"{{"amount": 5.0, "method": "paypal", "method_id": "{}"}}"#,
+                "a".repeat(32 * 1024)
+            ),
+        ] {
+            let resp = api.create_payout_raw(&body, USER_USER_PAT).await;
+            assert_status(&resp, StatusCode::BAD_REQUEST);
+        }
+
+        assert_eq!(get_balance(&pool, USER_USER_ID_PARSED).await, before);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn payout_constants_reflect_configured_weights() {
+    with_test_environment_vars(
+        &[("PAYOUTS_DOWNLOAD_WEIGHT", Some("3"))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+
+            let constants = api.get_payout_constants_deserialized().await;
+
+            assert_eq!(constants.weights.downloads, 3);
+            assert_eq!(constants.weights.views, 1);
+            assert_eq!(
+                constants.weekend_allocation,
+                constants.weekday_allocation * constants.weekend_bonus
+            );
+            assert_eq!(
+                constants.period_end - constants.period_start,
+                Duration::days(28)
+            );
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn opted_out_project_share_is_redistributed() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let beta_project_id = parse_base62(&test_env.dummy.project_beta.project_id).unwrap();
+        let beta = &test_env.dummy.project_beta.project_id;
+
+        // Only the project's team can opt it out
+        let resp = api
+            .set_project_payouts_enabled(beta, false, ENEMY_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::UNAUTHORIZED);
+        let resp = api
+            .set_project_payouts_enabled(beta, false, USER_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        let start = yesterday();
+        let period = PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100));
+        let distribute = |replace| {
+            payouts::distribute_payouts(
+                &pool,
+                &redis,
+                &period,
+                start,
+                PayoutMultipliers {
+                    sum: 100,
+                    values: HashMap::from([(alpha_project_id, 50), (beta_project_id, 50)]),
+                },
+                &[MonetizationStatus::Monetized],
+                None,
+                replace,
+            )
+        };
+        let paid = || {
+            sqlx::query_as::<_, (i64, Decimal)>(
+                "SELECT mod_id, amount FROM payouts_values WHERE created = $1 ORDER BY mod_id",
+            )
+            .bind(start)
+            .fetch_all(&pool)
+        };
+
+        distribute(false).await.unwrap();
+        assert_eq!(
+            paid().await.unwrap(),
+            vec![(alpha_project_id as i64, Decimal::from(100))]
+        );
+
+        // Opting back in splits the day between both projects again
+        let resp = api
+            .set_project_payouts_enabled(beta, true, USER_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        distribute(true).await.unwrap();
+        let mut expected = vec![
+            (alpha_project_id as i64, Decimal::from(50)),
+            (beta_project_id as i64, Decimal::from(50)),
+        ];
+        expected.sort();
+        assert_eq!(paid().await.unwrap(), expected);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn monetary_amounts_round_trip_as_strings() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+
+        let mut transaction = pool.begin().await.unwrap();
+        payout_item::Payout {
+            id: labrinth::models::ids::PayoutId(9876).into(),
+            user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+            created: Utc::now(),
+            status: PayoutStatus::Success,
+            amount: Decimal::from(100000),
+            fee: Some(Decimal::new(25, 2)),
+            withheld: None,
+            method: Some(PayoutMethodType::PayPal),
+            method_id: Some("paypal_us".to_string()),
+            method_address: Some("user@modrinth.com".to_string()),
+            platform_id: None,
+            note: None,
+            idempotency_key: None,
+        }
+        .insert(&mut transaction)
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let resp = api.get_user_payouts(USER_USER_PAT).await;
+        assert_status(&resp, StatusCode::OK);
+        let payouts: serde_json::Value = test::read_body_json(resp).await;
+        let payout = payouts
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|x| x["fee"] == "0.25")
+            .expect("the payout is listed");
+        assert_eq!(payout["amount"], "100000");
+        assert!(payout["withheld"].is_null());
+
+        let parsed: labrinth::models::payouts::Payout =
+            serde_json::from_value(payout.clone()).unwrap();
+        assert_eq!(parsed.amount, Decimal::from(100000));
+        assert_eq!(parsed.fee, Some(Decimal::new(25, 2)));
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), *payout);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn blocked_country_cannot_withdraw() {
+    with_test_environment_vars(&[("PAYOUTS_BLOCKED_COUNTRIES", Some("KP")), ("PAYOUTS_TREMENDOUS_ENABLED", Some("false"))], None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+
+        sqlx::query(
+            "UPDATE users SET paypal_id = 'friend', paypal_country = 'KP', balance = 100 WHERE id = $1",
+        )
+        .bind(FRIEND_USER_ID_PARSED)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let resp = api.get_payout_eligibility(FRIEND_USER_ID).await;
+        assert_status(&resp, StatusCode::OK);
+        let eligibility: Vec<PayoutMethodEligibility> = test::read_body_json(resp).await;
+        assert!(!eligibility.is_empty());
+        assert!(eligibility.iter().all(|x| !x.eligible
+            && x.reasons.contains(&PayoutIneligibility::CountryBlocked)));
+
+        let resp = api
+            .create_payout(
+                json!({
+                    "amount": 10.0,
+                    "method": "paypal",
+                    "method_id": "paypal_in",
+                }),
+                FRIEND_USER_PAT,
+            )
+            .await;
+        assert_status(&resp, StatusCode::FORBIDDEN);
+        let error: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(error["error"], "compliance_error");
+
+        assert_eq!(
+            get_balance(&pool, FRIEND_USER_ID_PARSED).await,
+            Decimal::from(100)
+        );
+    },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn expired_payout_holds_no_longer_block_withdrawals() {
+    with_test_environment_vars(
+        &[("PAYOUTS_TREMENDOUS_ENABLED", Some("false"))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+
+            sqlx::query(
+                "UPDATE users SET paypal_id = 'friend', paypal_country = 'US', balance = 100 WHERE id = $1",
+            )
+            .bind(FRIEND_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            // The method doesn't exist, so withdrawals which get past the hold are rejected for that
+            // instead
+            let withdraw = || async {
+                let resp = api
+                    .create_payout(
+                        json!({
+                            "amount": 10.0,
+                            "method": "paypal",
+                            "method_id": "paypal_nowhere",
+                        }),
+                        FRIEND_USER_PAT,
+                    )
+                    .await;
+                let status = resp.status();
+                let error: serde_json::Value = test::read_body_json(resp).await;
+                (status, error["error"].as_str().unwrap().to_string())
+            };
+            let held = || async {
+                let resp = api.get_payout_eligibility(FRIEND_USER_ID).await;
+                assert_status(&resp, StatusCode::OK);
+                let eligibility: Vec<PayoutMethodEligibility> = test::read_body_json(resp).await;
+                assert!(!eligibility.is_empty());
+                eligibility
+                    .iter()
+                    .all(|x| x.reasons.contains(&PayoutIneligibility::OnHold))
+            };
+
+            let resp = api
+                .place_user_payout_hold(FRIEND_USER_ID, "Pending review", 7)
+                .await;
+            assert_status(&resp, StatusCode::OK);
+            let hold: UserPayoutHold = test::read_body_json(resp).await;
+            assert_eq!(hold.reason, "Pending review");
+            assert!(hold.expires > Utc::now() + Duration::days(6));
+
+            assert_eq!(
+                withdraw().await,
+                (StatusCode::FORBIDDEN, "compliance_error".to_string())
+            );
+            assert!(held().await);
+
+            let resp = api.renew_user_payout_hold(FRIEND_USER_ID, 30).await;
+            assert_status(&resp, StatusCode::OK);
+            let renewed: UserPayoutHold = test::read_body_json(resp).await;
+            assert!(renewed.expires > hold.expires);
+
+            sqlx::query("UPDATE payout_holds SET expires = NOW() - INTERVAL '1 hour' WHERE user_id = $1")
+                .bind(FRIEND_USER_ID_PARSED)
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                withdraw().await,
+                (StatusCode::BAD_REQUEST, "invalid_input".to_string())
+            );
+            assert!(!held().await);
+
+            // Expired holds can't be renewed or lifted, only placed again
+            let resp = api.renew_user_payout_hold(FRIEND_USER_ID, 30).await;
+            assert_status(&resp, StatusCode::NOT_FOUND);
+            let resp = api.lift_user_payout_hold(FRIEND_USER_ID).await;
+            assert_status(&resp, StatusCode::NOT_FOUND);
+
+            let resp = api
+                .place_user_payout_hold(FRIEND_USER_ID, "Pending review", 7)
+                .await;
+            assert_status(&resp, StatusCode::OK);
+            assert_eq!(
+                withdraw().await,
+                (StatusCode::FORBIDDEN, "compliance_error".to_string())
+            );
+
+            let resp = api.lift_user_payout_hold(FRIEND_USER_ID).await;
+            assert_status(&resp, StatusCode::NO_CONTENT);
+            assert_eq!(
+                withdraw().await,
+                (StatusCode::BAD_REQUEST, "invalid_input".to_string())
+            );
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn balance_summary_is_computed_from_ledger() {
+    // Every day is budgeted 100, and today's activity is read from Postgres
+    with_test_environment_vars(
+        &[
+            ("PAYOUTS_ANALYTICS_SOURCE", Some("postgres")),
+            ("PAYOUTS_BUDGET", Some("2800")),
+            ("PAYOUTS_WEEKEND_BONUS", Some("1")),
+        ],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+            let redis = test_env.db.redis_pool.clone();
+            let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+            for project_id in [alpha_project_id, alpha_project_id, alpha_project_id, 1_000_000] {
+                sqlx::query(
+                    "INSERT INTO analytics_views (recorded, project_id, user_id) VALUES ($1, $2, 0)",
+                )
+                .bind(Utc::now())
+                .bind(project_id as i64)
+                .execute(&pool)
+                .await
+                .unwrap();
+            }
+
+            let before: PayoutBalance =
+                test::read_body_json(api.get_user_balance(USER_USER_PAT).await).await;
+
+            sqlx::query("UPDATE users SET balance = 50 WHERE id = $1")
+                .bind(USER_USER_ID_PARSED)
+                .execute(&pool)
+                .await
+                .unwrap();
+            for (days_ago, amount) in [(2, Decimal::from(30)), (1, Decimal::new(455, 1))] {
+                sqlx::query(
+                    "INSERT INTO payouts_values (user_id, mod_id, amount, created) VALUES ($1, $2, $3, $4)",
+                )
+                .bind(USER_USER_ID_PARSED)
+                .bind(alpha_project_id as i64)
+                .bind(amount)
+                .bind(Utc::now() - Duration::days(days_ago))
+                .execute(&pool)
+                .await
+                .unwrap();
+            }
+
+            // Only withdrawals which are still on their way count as pending
+            let mut transaction = pool.begin().await.unwrap();
+            for (id, status, amount, fee, withheld) in [
+                (9001, PayoutStatus::InTransit, 10, Some(Decimal::new(5, 1)), None),
+                (9002, PayoutStatus::PendingReview, 20, None, Some(Decimal::from(2))),
+                (9003, PayoutStatus::Success, 5, None, None),
+                (9004, PayoutStatus::Failed, 7, Some(Decimal::ONE), None),
+            ] {
+                payout_item::Payout {
+                    id: labrinth::models::ids::PayoutId(id).into(),
+                    user_id: labrinth::database::models::UserId(USER_USER_ID_PARSED),
+                    created: Utc::now(),
+                    status,
+                    amount: Decimal::from(amount),
+                    fee,
+                    withheld,
+                    method: Some(PayoutMethodType::PayPal),
+                    method_id: Some("paypal_us".to_string()),
+                    method_address: Some("user@modrinth.com".to_string()),
+                    platform_id: None,
+                    note: None,
+                    idempotency_key: None,
+                }
+                .insert(&mut transaction)
+                .await
+                .unwrap();
+            }
+            transaction.commit().await.unwrap();
+            labrinth::database::models::User::clear_caches(
+                &[(labrinth::database::models::UserId(USER_USER_ID_PARSED), None)],
+                &redis,
+            )
+            .await
+            .unwrap();
+
+            let resp = api.get_user_balance(USER_USER_PAT).await;
+            assert_status(&resp, StatusCode::OK);
+            let balance: PayoutBalance = test::read_body_json(resp).await;
+            assert_eq!(balance.available, Decimal::from(50));
+            assert_eq!(balance.pending, before.pending + Decimal::new(325, 1));
+            assert_eq!(
+                balance.lifetime_earnings,
+                before.lifetime_earnings + Decimal::new(755, 1)
+            );
+            // Alpha has three of today's four views, so earns three quarters of today's 100
+            assert_eq!(before.next_payout_estimate, Decimal::from(75));
+            assert_eq!(balance.next_payout_estimate, Decimal::from(75));
+
+            // Users only ever see their own balances
+            let resp = api.get_user_balance(FRIEND_USER_PAT).await;
+            assert_status(&resp, StatusCode::OK);
+            let friend: PayoutBalance = test::read_body_json(resp).await;
+            assert_eq!(friend.pending, Decimal::ZERO);
+            assert_eq!(friend.lifetime_earnings, Decimal::ZERO);
+
+                let resp = api.get_user_balance(None).await;
+            assert_status(&resp, StatusCode::UNAUTHORIZED);
+
+            // The estimate is cached for the day, rather than recalculated on every request
+            sqlx::query(
+                "INSERT INTO analytics_views (recorded, project_id, user_id) VALUES ($1, $2, 0)",
+            )
+            .bind(Utc::now())
+            .bind(1_000_000i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+            let balance: PayoutBalance =
+                test::read_body_json(api.get_user_balance(USER_USER_PAT).await).await;
+            assert_eq!(balance.next_payout_estimate, Decimal::from(75));
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn missed_payout_days_are_caught_up_in_order() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+
+        let today = today();
+
+        // Payouts last ran three days ago, then missed the two days since
+        sqlx::query(
+            "INSERT INTO payouts_values (user_id, mod_id, amount, created) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(USER_USER_ID_PARSED)
+        .bind(alpha_project_id as i64)
+        .bind(Decimal::from(10))
+        .bind(today - Duration::days(3))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let (pool_ref, redis_ref) = (&pool, &redis);
+        let mut processed = Vec::new();
+        let days = payouts::catch_up_payouts_with(&pool, today, |day| {
+            processed.push(day);
+            async move {
+                payouts::distribute_payouts(
+                    pool_ref,
+                    redis_ref,
+                    &PayoutPeriod::new(day, day + Duration::days(1), Decimal::from(100)),
+                    day,
+                    PayoutMultipliers {
+                        sum: 100,
+                        values: HashMap::from([(alpha_project_id, 100)]),
+                    },
+                    &[MonetizationStatus::Monetized],
+                    None,
+                    false,
+                )
+                .await
+                .map(|_| ())
+            }
+        })
+        .await
+        .unwrap();
+
+        let missed = vec![today - Duration::days(2), today - Duration::days(1)];
+        assert_eq!(days, missed);
+        assert_eq!(processed, missed);
+
+        let paid_days: Vec<chrono::DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT DISTINCT created FROM payouts_values WHERE created > $1 ORDER BY created",
+        )
+        .bind(today - Duration::days(3))
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(paid_days, missed);
+
+        // Nothing is left to catch up on
+        assert!(payouts::unprocessed_payout_days(&pool, today)
+            .await
+            .unwrap()
+            .is_empty());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn missed_payout_days_are_caught_up_by_one_instance_at_a_time() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let today = today();
+
+        let pool_ref = &pool;
+        let days = payouts::catch_up_payouts_with(&pool, today, |_| async move {
+            // Another instance catching up while this one pays out the day finds nothing to do
+            let others = payouts::catch_up_payouts_with(pool_ref, today, |day| async move {
+                Err::<(), _>(ApiError::InvalidInput(format!(
+                    "{day} is already being paid out"
+                )))
+            })
+            .await
+            .unwrap();
+            assert!(others.is_empty());
+            Ok::<_, ApiError>(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(days, vec![yesterday()]);
+
+        // The lock is released once the catch-up is done
+        assert!(
+            payouts::try_lock_payout_job(&pool, payouts::PAYOUT_JOB_LOCK)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn zero_activity_days_are_not_caught_up_again() {
+    // No views or downloads are recorded in Postgres
+    with_test_environment_vars(
+        &[("PAYOUTS_ANALYTICS_SOURCE", Some("postgres"))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let pool = test_env.db.pool.clone();
+            let redis = test_env.db.redis_pool.clone();
+            let clickhouse = clickhouse::Client::default();
+
+            let today = today();
+            let yesterday = yesterday();
+
+            let days = payouts::catch_up_payouts_with(&pool, today, |day| {
+                let (pool, redis, clickhouse) = (&pool, &redis, &clickhouse);
+                async move {
+                    let period =
+                        PayoutPeriod::new(day, day + Duration::days(1), Decimal::from(100));
+                    payouts::process_payout(pool, redis, clickhouse, &period, false, None).await
+                }
+            })
+            .await
+            .unwrap();
+            assert_eq!(days, vec![yesterday]);
+
+            let resp = test_env.api.get_payout_runs().await;
+            assert_status(&resp, StatusCode::OK);
+            let runs: Vec<PayoutRun> = test::read_body_json(resp).await;
+            assert_eq!(runs.len(), 1);
+            assert_eq!(runs[0].day, yesterday);
+            assert_eq!(runs[0].distributed, Decimal::ZERO);
+            assert_eq!(runs[0].outcome, "zero_activity");
+
+            // The day has no payouts, but its recorded outcome marks it as processed
+            assert!(payouts::unprocessed_payout_days(&pool, today)
+                .await
+                .unwrap()
+                .is_empty());
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn cancelled_payout_run_leaves_no_partial_credits() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+        let alpha_project_id = parse_base62(&test_env.dummy.project_alpha.project_id).unwrap();
+        let before = get_balance(&pool, USER_USER_ID_PARSED).await;
+
+        let start = yesterday();
+        let period = PayoutPeriod::new(start, start + Duration::days(1), Decimal::from(100));
+        let run = || {
+            payouts::distribute_payouts(
+                &pool,
+                &redis,
+                &period,
+                start,
+                PayoutMultipliers {
+                    sum: 100,
+                    values: HashMap::from([(alpha_project_id, 100)]),
+                },
+                &[MonetizationStatus::Monetized],
+                None,
+                false,
+            )
+        };
+
+        // Another transaction holds the user's row, so the run blocks partway through crediting
+        // and is cancelled there, as it would be on shutdown
+        let mut lock = pool.begin().await.unwrap();
+        sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(USER_USER_ID_PARSED)
+            .execute(&mut *lock)
+            .await
+            .unwrap();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(500), run())
+                .await
+                .is_err()
+        );
+        lock.commit().await.unwrap();
+
+        assert_eq!(get_balance(&pool, USER_USER_ID_PARSED).await, before);
+        let paid: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM payouts_values WHERE created = $1")
+                .bind(start)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(paid, 0);
+
+        // The day was not marked as paid out, so it is processed again in full
+        assert_eq!(run().await.unwrap(), PayoutDayOutcome::Paid);
+        assert_eq!(
+            get_balance(&pool, USER_USER_ID_PARSED).await - before,
+            Decimal::from(100)
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn methods_can_be_filtered_by_amount() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let redis = test_env.db.redis_pool.clone();
+
+        let method = |id: &str, type_, interval| PayoutMethod {
+            interval,
+            ..test_method(id, type_)
+        };
+        let methods = vec![
+            method(
+                "paypal_us",
+                PayoutMethodType::PayPal,
+                PayoutInterval::Standard {
+                    min: Decimal::new(25, 2),
+                    max: Decimal::from(100000),
+                },
+            ),
+            method(
+                "large_card",
+                PayoutMethodType::Tremendous,
+                PayoutInterval::Fixed {
+                    values: vec![
+                        PayoutDecimal(Decimal::from(25)),
+                        PayoutDecimal(Decimal::from(50)),
+                    ],
+                },
+            ),
+            method(
+                "small_card",
+                PayoutMethodType::Tremendous,
+                PayoutInterval::Fixed {
+                    values: vec![
+                        PayoutDecimal(Decimal::from(5)),
+                        PayoutDecimal(Decimal::from(10)),
+                    ],
+                },
+            ),
+        ];
+        seed_methods(&redis, &methods).await;
+
+        let ids =
+            |methods: Vec<PayoutMethod>| methods.into_iter().map(|x| x.id).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(api.get_payout_methods_for_amount("7").await),
+            vec!["paypal_us", "small_card"]
+        );
+        assert_eq!(
+            ids(api.get_payout_methods_for_amount("25").await),
+            vec!["paypal_us", "large_card", "small_card"]
+        );
+        assert!(api.get_payout_methods_for_amount("0.1").await.is_empty());
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn methods_can_be_limited() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let redis = test_env.db.redis_pool.clone();
+
+        seed_methods(
+            &redis,
+            &[
+                test_method("paypal_us", PayoutMethodType::PayPal),
+                test_method("venmo", PayoutMethodType::Venmo),
+                test_method("card_a", PayoutMethodType::Tremendous),
+                test_method("card_b", PayoutMethodType::Tremendous),
+                test_method("card_c", PayoutMethodType::Tremendous),
+            ],
+        )
+        .await;
+
+        let ids =
+            |methods: Vec<PayoutMethod>| methods.into_iter().map(|x| x.id).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(api.get_payout_methods_with_limit(3).await),
+            vec!["paypal_us", "venmo", "card_a"]
+        );
+        // PayPal and Venmo are kept even past the limit
+        assert_eq!(
+            ids(api.get_payout_methods_with_limit(1).await),
+            vec!["paypal_us", "venmo"]
+        );
+        assert_eq!(api.get_payout_methods_with_limit(10).await.len(), 5);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+async fn withdrawals_over_the_rate_limit_are_rejected() {
+    // Held for review, so nothing is sent to PayPal
+    with_test_environment_vars(
+        &[
+            ("PAYOUTS_TREMENDOUS_ENABLED", Some("false")),
+            ("PAYOUTS_REVIEW_THRESHOLD", Some("0")),
+            ("PAYOUTS_WITHDRAWAL_RATE_LIMIT", Some("1")),
+            ("PAYOUTS_WITHDRAWAL_RATE_WINDOW", Some("60")),
+        ],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = &test_env.db.pool;
+            let redis = &test_env.db.redis_pool;
+            let user_id = UserId(USER_USER_ID_PARSED as u64);
+            let limit = payouts::WithdrawalRateLimit {
+                max: 2,
+                window_seconds: 1,
+            };
+
+            limit.check(redis, user_id).await.unwrap();
+            limit.check(redis, user_id).await.unwrap();
+            assert!(matches!(
+                limit.check(redis, user_id).await,
+                Err(ApiError::RateLimited(_))
+            ));
+
+            // Other users have their own count
+            limit
+                .check(redis, UserId(FRIEND_USER_ID_PARSED as u64))
+                .await
+                .unwrap();
+
+            // The count resets once the window ends
+            tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+            limit.check(redis, user_id).await.unwrap();
+
+            // Withdrawals which are rejected aren't counted, so they don't use up the one
+            // withdrawal the friend may make
+            sqlx::query(
+                "UPDATE users SET paypal_id = 'friend', paypal_country = 'US', balance = 100 WHERE id = $1",
+            )
+            .bind(FRIEND_USER_ID_PARSED)
+            .execute(pool)
+            .await
+            .unwrap();
+
+            let withdraw = |amount: f64| {
+                api.create_payout(
+                    json!({
+                        "amount": amount,
+                        "method": "paypal",
+                        "method_id": "paypal_us",
+                    }),
+                    FRIEND_USER_PAT,
+                )
+            };
+            for _ in 0..2 {
+                assert_status(&withdraw(1000.0).await, StatusCode::BAD_REQUEST);
+            }
+            assert_status(&withdraw(10.0).await, StatusCode::NO_CONTENT);
+            assert_status(&withdraw(10.0).await, StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(
+                get_balance(pool, FRIEND_USER_ID_PARSED).await,
+                Decimal::from(90)
+            );
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+async fn postgres_analytics_match_clickhouse() {
+    use chrono::TimeZone;
+    use futures::TryStreamExt;
+    use labrinth::models::analytics::{Download, PageView};
+    use std::net::Ipv6Addr;
+
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let pool = &test_env.db.pool;
+        let clickhouse = labrinth::clickhouse::init_client().await.unwrap();
+
+        // A day of its own, so views and downloads other tests and earlier runs recorded in the
+        // shared ClickHouse database are not counted
+        let start = Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap()
+            + Duration::days(rand::random::<u16>() as i64 % 10_000);
+        let end = start + Duration::days(1);
+        let at = |seconds: i64| {
+            (start + Duration::seconds(seconds))
+                .timestamp_nanos_opt()
+                .unwrap()
+                / 100_000
+        };
+
+        // (recorded, project_id, user_id). Views of no project, anonymous downloads and events
+        // outside the day are not counted.
+        let views = vec![
+            (at(10), 1, 0),
+            (at(20), 1, 3),
+            (at(30), 2, 0),
+            (at(40), 0, 3),
+            (at(-10), 2, 0),
+            (at(90_000), 1, 0),
+        ];
+        let downloads = vec![
+            (at(10), 1, 3),
+            (at(20), 2, 3),
+            (at(30), 2, 4),
+            (at(40), 2, 0),
+            (at(90_000), 2, 3),
+        ];
+
+        let mut insert = clickhouse.insert("views").unwrap();
+        for &(recorded, project_id, user_id) in &views {
+            insert
+                .write(&PageView {
+                    recorded,
+                    domain: "modrinth.com".to_string(),
+                    site_path: "/".to_string(),
+                    user_id,
+                    project_id,
+                    ip: Ipv6Addr::LOCALHOST,
+                    country: "US".to_string(),
+                    user_agent: String::new(),
+                    headers: Vec::new(),
+                })
+                .await
+                .unwrap();
+        }
+        insert.end().await.unwrap();
+
+        let mut insert = clickhouse.insert("downloads").unwrap();
+        for &(recorded, project_id, user_id) in &downloads {
+            insert
+                .write(&Download {
+                    recorded,
+                    domain: "cdn.modrinth.com".to_string(),
+                    site_path: "/".to_string(),
+                    user_id,
+                    project_id,
+                    version_id: 1,
+                    ip: Ipv6Addr::LOCALHOST,
+                    country: "US".to_string(),
+                    user_agent: String::new(),
+                    headers: Vec::new(),
+                })
+                .await
+                .unwrap();
+        }
+        insert.end().await.unwrap();
+
+        let mut transaction = pool.begin().await.unwrap();
+        payouts::record_postgres_events(&mut transaction, payouts::AnalyticsEvent::Views, views)
+            .await
+            .unwrap();
+        payouts::record_postgres_events(
+            &mut transaction,
+            payouts::AnalyticsEvent::Downloads,
+            downloads,
+        )
+        .await
+        .unwrap();
+        transaction.commit().await.unwrap();
+
+        let weights = payouts::PayoutWeights {
+            views: 1,
+            downloads: 3,
+        };
+        let from_clickhouse = payouts::ClickHouseAnalytics(&clickhouse);
+        let from_postgres = payouts::PostgresAnalytics(pool);
+
+        for event in [
+            payouts::AnalyticsEvent::Views,
+            payouts::AnalyticsEvent::Downloads,
+        ] {
+            assert_eq!(
+                from_postgres
+                    .project_counts(event, start, end)
+                    .map_ok(|x| (x.project_id, x.page_views))
+                    .try_collect::<HashMap<_, _>>()
+                    .await
+                    .unwrap(),
+                from_clickhouse
+                    .project_counts(event, start, end)
+                    .map_ok(|x| (x.project_id, x.page_views))
+                    .try_collect::<HashMap<_, _>>()
+                    .await
+                    .unwrap(),
+            );
+            assert_eq!(
+                from_postgres.count(event, start, end).await.unwrap(),
+                from_clickhouse.count(event, start, end).await.unwrap(),
+            );
+        }
+
+        let multipliers = payouts::fetch_payout_multipliers(&from_postgres, start, end, weights)
+            .await
+            .unwrap();
+        assert_eq!(
+            multipliers,
+            payouts::fetch_payout_multipliers(&from_clickhouse, start, end, weights)
+                .await
+                .unwrap()
+        );
+        assert_eq!(multipliers.sum, 3 + 3 * 3);
+        assert_eq!(
+            multipliers.values,
+            HashMap::from([(1, 2 + 3), (2, 1 + 2 * 3)])
+        );
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn onboarding_reports_what_is_left_to_set_up() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+
+        sqlx::query(
+            "UPDATE users SET paypal_id = 'user', paypal_country = 'US', balance = 100 WHERE id = $1",
+        )
+        .bind(USER_USER_ID_PARSED)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "UPDATE users SET paypal_id = NULL, paypal_country = NULL, venmo_handle = NULL WHERE id = $1",
+        )
+        .bind(FRIEND_USER_ID_PARSED)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let method = PayoutMethod {
+            interval: PayoutInterval::Standard {
+                min: Decimal::new(25, 2),
+                max: Decimal::from(100),
+            },
+            ..test_method("paypal_us", PayoutMethodType::PayPal)
+        };
+        seed_methods(&redis, &[method]).await;
+        labrinth::database::models::User::clear_caches(
+            &[
+                (
+                    labrinth::database::models::UserId(USER_USER_ID_PARSED),
+                    None,
+                ),
+                (
+                    labrinth::database::models::UserId(FRIEND_USER_ID_PARSED),
+                    None,
+                ),
+            ],
+            &redis,
+        )
+        .await
+        .unwrap();
+
+        let resp = api.get_payout_onboarding(USER_USER_PAT).await;
+        assert_status(&resp, StatusCode::OK);
+        let onboarding: PayoutOnboarding = test::read_body_json(resp).await;
+        assert_eq!(
+            onboarding,
+            PayoutOnboarding {
+                destination_set: true,
+                country_set: true,
+                verification_complete: true,
+                eligible: true,
+            }
+        );
+
+        // Without a PayPal account the friend cannot use the only method
+        let resp = api.get_payout_onboarding(FRIEND_USER_PAT).await;
+        assert_status(&resp, StatusCode::OK);
+        let onboarding: PayoutOnboarding = test::read_body_json(resp).await;
+        assert!(!onboarding.country_set);
+        assert!(!onboarding.verification_complete);
+        assert!(!onboarding.eligible);
+    })
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn partial_withdrawal_leaves_the_rest_of_the_balance() {
+    // Held for review, so nothing is sent to PayPal
+    with_test_environment_vars(
+        &[("PAYOUTS_REVIEW_THRESHOLD", Some("0"))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+            let redis = test_env.db.redis_pool.clone();
+
+            sqlx::query(
+                "UPDATE users SET paypal_id = 'user', paypal_country = 'US', balance = 100 WHERE id = $1",
+            )
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            // Withdrawals of at most 50 at a time, less than the balance
+            let method = PayoutMethod {
+                interval: PayoutInterval::Standard {
+                    min: Decimal::new(25, 2),
+                    max: Decimal::from(50),
+                },
+                fee: PayoutMethodFee {
+                    percentage: Decimal::new(2, 2),
+                    min: Decimal::new(25, 2),
+                    max: Some(Decimal::ONE),
+                },
+                ..test_method("paypal_us", PayoutMethodType::PayPal)
+            };
+            seed_methods(&redis, &[method]).await;
+
+            let withdraw = |amount: f64| {
+                api.create_payout(
+                    json!({
+                        "amount": amount,
+                        "method": "paypal",
+                        "method_id": "paypal_us",
+                    }),
+                    USER_USER_PAT,
+                )
+            };
+
+            // The interval applies to the requested amount, not the balance
+            let resp = withdraw(60.0).await;
+            assert_status(&resp, StatusCode::BAD_REQUEST);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(100)
+            );
+
+            let resp = withdraw(30.0).await;
+            assert_status(&resp, StatusCode::NO_CONTENT);
+            let (status, net, fee): (String, Decimal, Option<Decimal>) =
+                sqlx::query_as("SELECT status, amount, fee FROM payouts WHERE user_id = $1")
+                    .bind(USER_USER_ID_PARSED)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            assert_eq!(PayoutStatus::from_string(&status), PayoutStatus::PendingReview);
+            assert_eq!(fee, Some(Decimal::new(60, 2)));
+            assert_eq!(net, Decimal::new(2940, 2));
+
+            // Only the requested amount, which the fee comes out of, is debited
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(70)
+            );
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn replayed_withdrawals_are_only_made_once() {
+    // Held for review, so nothing is sent to PayPal
+    with_test_environment_vars(
+        &[
+            ("PAYOUTS_TREMENDOUS_ENABLED", Some("false")),
+            ("PAYOUTS_REVIEW_THRESHOLD", Some("0")),
+        ],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+
+            sqlx::query(
+                "UPDATE users SET paypal_id = 'user', paypal_country = 'US', balance = 100 WHERE id = $1",
+            )
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let withdraw = |amount: f64, method_id: &'static str| {
+                api.create_payout(
+                    json!({
+                        "amount": amount,
+                        "method": "paypal",
+                        "method_id": method_id,
+                        "idempotency_key": "withdrawal-1",
+                    }),
+                    USER_USER_PAT,
+                )
+            };
+            let withdrawals = || async {
+                let count: i64 =
+                    sqlx::query_scalar("SELECT COUNT(1) FROM payouts WHERE user_id = $1")
+                        .bind(USER_USER_ID_PARSED)
+                        .fetch_one(&pool)
+                        .await
+                        .unwrap();
+                count
+            };
+
+            let resp = withdraw(10.0, "paypal_us").await;
+            assert_status(&resp, StatusCode::NO_CONTENT);
+            assert_eq!(withdrawals().await, 1);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(90)
+            );
+
+            // Replaying it succeeds without withdrawing again
+            let resp = withdraw(10.0, "paypal_us").await;
+            assert_status(&resp, StatusCode::NO_CONTENT);
+            assert_eq!(withdrawals().await, 1);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(90)
+            );
+
+            // The key can't be reused for another amount or method
+            for resp in [
+                withdraw(20.0, "paypal_us").await,
+                withdraw(10.0, "venmo").await,
+            ] {
+                assert_status(&resp, StatusCode::CONFLICT);
+                let error: serde_json::Value = test::read_body_json(resp).await;
+                assert_eq!(error["error"], "duplicate_payout");
+            }
+            assert_eq!(withdrawals().await, 1);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(90)
+            );
+
+            // Clients which ask for it get the withdrawal back, both when it is made and when it
+            // is replayed
+            let withdraw_returning = || {
+                api.create_payout_returning(
+                    json!({
+                        "amount": 20.0,
+                        "method": "paypal",
+                        "method_id": "paypal_us",
+                        "idempotency_key": "withdrawal-2",
+                    }),
+                    USER_USER_PAT,
+                )
+            };
+            let resp = withdraw_returning().await;
+            assert_status(&resp, StatusCode::OK);
+            assert!(resp.headers().contains_key(CORRELATION_ID_HEADER));
+            let withdrawal: WithdrawalResponse = test::read_body_json(resp).await;
+            assert_eq!(withdrawal.status, PayoutStatus::PendingReview);
+            assert_eq!(withdrawal.net + withdrawal.fee, Decimal::from(20));
+            assert_eq!(withdrawal.provider_reference, None);
+
+            let resp = withdraw_returning().await;
+            assert_status(&resp, StatusCode::OK);
+            let replayed: WithdrawalResponse = test::read_body_json(resp).await;
+            assert_eq!(replayed, withdrawal);
+            assert_eq!(withdrawals().await, 2);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(70)
+            );
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn method_under_maintenance_is_not_eligible() {
+    let until = Utc::now() + Duration::hours(2);
+    let windows = json!([{
+        "method": "maintained_paypal",
+        "start": Utc::now() - Duration::hours(1),
+        "end": until,
+        "reason": "PayPal is upgrading its systems",
+    }])
+    .to_string();
+    with_test_environment_vars(
+        &[("PAYOUTS_MAINTENANCE_WINDOWS", Some(&windows))],
+        None,
+        |test_env: TestEnvironment<ApiV3>| async move {
+            let api = &test_env.api;
+            let pool = test_env.db.pool.clone();
+            let redis = test_env.db.redis_pool.clone();
+
+            sqlx::query(
+                "UPDATE users SET paypal_id = 'user', paypal_country = 'US', balance = 100 WHERE id = $1",
+            )
+            .bind(USER_USER_ID_PARSED)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let method = |id: &str| PayoutMethod {
+                interval: PayoutInterval::Standard {
+                    min: Decimal::new(25, 2),
+                    max: Decimal::from(100_000),
+                },
+                fee: PayoutMethodFee {
+                    percentage: Decimal::new(2, 2),
+                    min: Decimal::new(25, 2),
+                    max: Some(Decimal::ONE),
+                },
+                ..test_method(id, PayoutMethodType::PayPal)
+            };
+            seed_methods(&redis, &[method("paypal_us"), method("maintained_paypal")]).await;
+
+            let resp = api.get_payout_eligibility(USER_USER_ID).await;
+            let withdrawal = api
+                .create_payout(
+                    json!({
+                        "amount": 10.0,
+                        "method": "paypal",
+                        "method_id": "maintained_paypal",
+                    }),
+                    USER_USER_PAT,
+                )
+                .await;
+
+            assert_status(&resp, StatusCode::OK);
+            let eligibility: Vec<PayoutMethodEligibility> = test::read_body_json(resp).await;
+            let maintained = eligibility
+                .iter()
+                .find(|x| x.method_id == "maintained_paypal")
+                .unwrap();
+            assert!(!maintained.eligible);
+            assert_eq!(maintained.reasons, vec![PayoutIneligibility::Maintenance]);
+            let maintenance = maintained.maintenance.as_ref().unwrap();
+            assert_eq!(maintenance.reason, "PayPal is upgrading its systems");
+            assert_eq!(maintenance.until.timestamp(), until.timestamp());
+
+            let unaffected = eligibility
+                .iter()
+                .find(|x| x.method_id == "paypal_us")
+                .unwrap();
+            assert!(!unaffected
+                .reasons
+                .contains(&PayoutIneligibility::Maintenance));
+            assert!(unaffected.maintenance.is_none());
+
+            assert_status(&withdrawal, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(
+                get_balance(&pool, USER_USER_ID_PARSED).await,
+                Decimal::from(100)
+            );
+        },
+    )
+    .await;
+}
+
+#[actix_rt::test]
+pub async fn preferred_method_is_listed_first_while_eligible() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV3>| async move {
+        let api = &test_env.api;
+        let pool = test_env.db.pool.clone();
+        let redis = test_env.db.redis_pool.clone();
+
+        sqlx::query(
+            "UPDATE users SET paypal_id = 'user', paypal_country = 'US', balance = 100 WHERE id = $1",
+        )
+        .bind(USER_USER_ID_PARSED)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let method = |id: &str, min: Decimal| PayoutMethod {
+            interval: PayoutInterval::Standard {
+                min,
+                max: Decimal::from(1000),
+            },
+            ..test_method(id, PayoutMethodType::PayPal)
+        };
+        seed_methods(&redis, &[
+                        method("paypal_us", Decimal::ONE),
+                        method("paypal_alt", Decimal::ONE),
+                        method("paypal_large", Decimal::from(500)),
+                    ]).await;
+        labrinth::database::models::User::clear_caches(
+            &[(
+                labrinth::database::models::UserId(USER_USER_ID_PARSED),
+                None,
+            )],
+            &redis,
+        )
+        .await
+        .unwrap();
+
+        let eligibility = || async {
+            let resp = api.get_own_payout_eligibility(USER_USER_PAT).await;
+            assert_status(&resp, StatusCode::OK);
+            let eligibility: Vec<PayoutMethodEligibility> = test::read_body_json(resp).await;
+            eligibility
+        };
+        let ids = |eligibility: &[PayoutMethodEligibility]| {
+            eligibility
+                .iter()
+                .map(|x| x.method_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let resp = api
+            .set_preferred_payout_method(Some("unknown"), USER_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::BAD_REQUEST);
+
+        let before = eligibility().await;
+        assert!(before.iter().all(|x| !x.preferred));
+
+        let resp = api
+            .set_preferred_payout_method(Some("paypal_alt"), USER_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        let preferred = eligibility().await;
+        assert_eq!(preferred[0].method_id, "paypal_alt");
+        assert!(preferred[0].preferred && preferred[0].eligible);
+        assert_eq!(preferred[0].note, None);
+        assert!(preferred[1..].iter().all(|x| !x.preferred));
+
+        // An ineligible preference keeps its usual place, with its reasons as the note
+        let resp = api
+            .set_preferred_payout_method(Some("paypal_large"), USER_USER_PAT)
+            .await;
+        assert_status(&resp, StatusCode::NO_CONTENT);
+
+        let fallback = eligibility().await;
+        assert_eq!(ids(&fallback), ids(&before));
+        let large = fallback
+            .iter()
+            .find(|x| x.method_id == "paypal_large")
+            .unwrap();
+        assert!(large.preferred && !large.eligible);
+        assert!(large.reasons.contains(&PayoutIneligibility::UnderThreshold));
+        assert!(large.note.is_some());
+    })
+    .await;
+}
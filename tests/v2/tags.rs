@@ -1,9 +1,13 @@
+use actix_web::{http::header::CONTENT_ENCODING, test};
+use flate2::read::GzDecoder;
 use itertools::Itertools;
 use labrinth::routes::v2::tags::DonationPlatformQueryData;
 
 use std::collections::HashSet;
+use std::io::Read;
 
 use crate::common::{
+    api_common::Api,
     api_v2::ApiV2,
     environment::{with_test_environment, TestEnvironment},
 };
@@ -105,3 +109,33 @@ async fn get_donation_platforms() {
     })
     .await;
 }
+
+#[actix_rt::test]
+async fn get_tags_gzip_compressed() {
+    with_test_environment(None, |test_env: TestEnvironment<ApiV2>| async move {
+        let api = &test_env.api;
+
+        let req = test::TestRequest::get()
+            .uri("/v2/tag/game_version")
+            .to_request();
+        let resp = api.call(req).await;
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        let expected: serde_json::Value = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri("/v2/tag/game_version")
+            .append_header(("Accept-Encoding", "gzip"))
+            .to_request();
+        let resp = api.call(req).await;
+        assert_eq!(resp.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+
+        let body = test::read_body(resp).await;
+        let mut json = String::new();
+        GzDecoder::new(&body[..]).read_to_string(&mut json).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            expected
+        );
+    })
+    .await;
+}